@@ -1,5 +1,5 @@
 var goldenLayout;
-let configVersion = 4;
+let configVersion = 6;
 
 function make_config() {
   let editor_width_pct = 100.0 - 61.8;
@@ -50,6 +50,13 @@ function make_config() {
                 isClosable: false,
                 id: "versions",
               },
+              {
+                type: "component",
+                componentName: "Parameters",
+                componentState: {},
+                isClosable: false,
+                id: "params",
+              },
             ],
           },
           {
@@ -76,6 +83,13 @@ function make_config() {
                 isClosable: false,
                 id: "compiler_output",
               },
+              {
+                type: "component",
+                componentName: "Console",
+                componentState: {},
+                isClosable: false,
+                id: "console",
+              },
               {
                 type: "component",
                 componentName: "Leaderboard",
@@ -146,6 +160,12 @@ export function init() {
       container.getElement()[0].id = "compiler-output-window";
     }
   );
+  goldenLayout.registerComponent(
+    "Console",
+    function (container, componentState) {
+      container.getElement()[0].id = "console-window";
+    }
+  );
   goldenLayout.registerComponent(
     "Leaderboard",
     function (container, componentState) {
@@ -160,6 +180,13 @@ export function init() {
       container.getElement()[0].id = "versions-window";
     }
   );
+  goldenLayout.registerComponent(
+    "Parameters",
+    function (container, componentState) {
+      container.getElement()[0].style.overflow = "auto";
+      container.getElement()[0].id = "params-window";
+    }
+  );
   goldenLayout.init();
 
   window.goldenLayout = goldenLayout;
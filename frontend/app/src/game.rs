@@ -5,6 +5,7 @@ use crate::editor_window::EditorWindow;
 use crate::js;
 use crate::leaderboard::Leaderboard;
 use crate::leaderboard_window::LeaderboardWindow;
+use crate::params_window::ParamsWindow;
 use crate::services;
 use crate::simulation_window::SimulationWindow;
 use crate::toolbar::Toolbar;
@@ -62,6 +63,7 @@ pub enum Msg {
     LoadVersion(String),
     SaveVersion(String),
     RefreshVersions,
+    SetScenarioParam(String, Option<f64>),
 }
 
 enum Overlay {
@@ -95,6 +97,7 @@ pub struct Game {
     editor_links: Vec<CodeEditorLink>,
     compilation_cache: HashMap<Code, Code>,
     seed: Option<u32>,
+    scenario_params: scenario::ParamOverrides,
     shortcodes: Vec<Option<String>>,
     versions_update_timestamp: chrono::DateTime<chrono::Utc>,
 }
@@ -150,6 +153,7 @@ impl Component for Game {
             editor_links: vec![CodeEditorLink::default(), CodeEditorLink::default()],
             compilation_cache,
             seed: query_params.seed,
+            scenario_params: scenario::ParamOverrides::new(),
             shortcodes: vec![query_params.player0, query_params.player1],
             versions_update_timestamp: chrono::Utc::now(),
         }
@@ -293,7 +297,7 @@ impl Component for Game {
                                 ticks: (summary.average_time.unwrap_or(0.0)
                                     / simulation::PHYSICS_TICK_LENGTH)
                                     as u32,
-                                code_size: crate::code_size::calculate(&code_to_string(&code)),
+                                code_size: oort_code_size::calculate(&code_to_string(&code)),
                                 success: summary.failed_seeds.is_empty(),
                                 time: summary.average_time,
                             });
@@ -391,6 +395,13 @@ impl Component for Game {
                 self.versions_update_timestamp = chrono::Utc::now();
                 true
             }
+            Msg::SetScenarioParam(name, value) => {
+                match value {
+                    Some(value) => self.scenario_params.insert(name, value),
+                    None => self.scenario_params.remove(&name),
+                };
+                true
+            }
             Msg::SubmitToTournament => {
                 services::send_telemetry(Telemetry::SubmitToTournament {
                     scenario_name: self.scenario_name.clone(),
@@ -475,6 +486,9 @@ impl Component for Game {
         let simulation_window_host = gloo_utils::document()
             .get_element_by_id("simulation-window")
             .expect("a #simulation-window element");
+        let console_window_host = gloo_utils::document()
+            .get_element_by_id("console-window")
+            .expect("a #console-window element");
         let on_simulation_finished = context.link().callback(Msg::SimulationFinished);
         let register_link = context.link().callback(Msg::RegisterSimulationWindowLink);
         let version = context.props().version.clone();
@@ -514,16 +528,25 @@ impl Component for Game {
         let load_cb = context.link().callback(Msg::LoadVersion);
         let save_cb = context.link().callback(Msg::SaveVersion);
 
+        // For ParamsWindow.
+        let params_window_host = gloo_utils::document()
+            .get_element_by_id("params-window")
+            .expect("a #params-window element");
+        let on_param_change = context
+            .link()
+            .callback(|(name, value)| Msg::SetScenarioParam(name, value));
+
         html! {
         <>
             <Toolbar scenario_name={self.scenario_name.clone()} {select_scenario_cb} show_feedback_cb={show_feedback_cb.clone()} />
             <Welcome host={welcome_window_host} show_feedback_cb={show_feedback_cb.clone()} select_scenario_cb={select_scenario_cb2} />
             <EditorWindow host={editor_window0_host} editor_link={editor0_link} on_editor_action={on_editor0_action} team=0 />
             <EditorWindow host={editor_window1_host} editor_link={editor1_link} on_editor_action={on_editor1_action} team=1 />
-            <SimulationWindow host={simulation_window_host} {on_simulation_finished} {register_link} {version} canvas_ref={self.simulation_canvas_ref.clone()} />
+            <SimulationWindow host={simulation_window_host} console_host={console_window_host} {on_simulation_finished} {register_link} {version} canvas_ref={self.simulation_canvas_ref.clone()} />
             <Documentation host={documentation_window_host} {show_feedback_cb} />
             <CompilerOutputWindow host={compiler_output_window_host} {compiler_errors} />
             <LeaderboardWindow host={leaderboard_window_host} scenario_name={self.scenario_name.clone()} />
+            <ParamsWindow host={params_window_host} scenario_name={self.scenario_name.clone()} params={self.scenario_params.clone()} on_change={on_param_change} />
             <VersionsWindow host={versions_window_host} scenario_name={self.scenario_name.clone()} {load_cb} {save_cb} update_timestamp={self.versions_update_timestamp} />
             { self.render_overlay(context) }
         </>
@@ -586,6 +609,7 @@ impl Game {
                         seed,
                         codes: codes.clone(),
                         nonce: self.background_nonce,
+                        params: self.scenario_params.clone(),
                     });
                     self.background_agents.push(sim_agent);
                 }
@@ -740,7 +764,7 @@ impl Game {
             0.0
         };
         let source_code = code_to_string(&self.player_team().running_source_code);
-        let code_size = crate::code_size::calculate(&source_code);
+        let code_size = oort_code_size::calculate(&source_code);
         let leaderboard_eligible = self.leaderboard_eligible();
 
         let next_scenario = scenario::load(&self.scenario_name).next_scenario();
@@ -867,7 +891,7 @@ impl Game {
                     <br />
                     {
                         if leaderboard_eligible { html! { <Leaderboard scenario_name={ self.scenario_name.clone() } submission={leaderboard_submission} /> } }
-                        else { html! { <p>{ "Leaderboard skipped due to modified opponent code" }</p> } }
+                        else { html! { <p>{ "Leaderboard skipped due to modified opponent code or scenario parameters" }</p> } }
                     }
                 </>
             }
@@ -973,6 +997,7 @@ impl Game {
                 scenario_name: self.scenario_name.clone(),
                 seed,
                 codes: codes.to_vec(),
+                params: self.scenario_params.clone(),
             });
         } else {
             log::error!("Missing SimulationWindow");
@@ -984,6 +1009,9 @@ impl Game {
 
     pub fn change_scenario(&mut self, context: &Context<Self>, scenario_name: &str, run: bool) {
         self.save_current_code(context, None);
+        if self.scenario_name != scenario_name {
+            self.scenario_params.clear();
+        }
         self.scenario_name = scenario_name.to_string();
         let codes = crate::codestorage::load(&self.scenario_name);
         let scenario = oort_simulator::scenario::load(&self.scenario_name);
@@ -1068,6 +1096,10 @@ impl Game {
                 return false;
             }
         }
+        if !self.scenario_params.is_empty() {
+            log::info!("Not eligible for leaderboard due to modified scenario parameters");
+            return false;
+        }
         !is_encrypted(&self.player_team().running_source_code)
     }
 
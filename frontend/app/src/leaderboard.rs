@@ -1,13 +1,17 @@
 use crate::services;
 use crate::userid;
-use oort_proto::LeaderboardSubmission;
-use oort_proto::{LeaderboardData, TimeLeaderboardRow};
+use gloo_timers::callback::Timeout;
+use oort_proto::{CodeSizeLeaderboardRow, LeaderboardData, TimeLeaderboardRow};
+use oort_proto::{LeaderboardSubmission, LeaderboardVerification};
 use yew::prelude::*;
 
 #[derive(Debug)]
 pub enum Msg {
     SendRequest,
     ReceiveResponse(Result<LeaderboardData, anyhow::Error>),
+    ReceiveSubmitResponse(Result<(LeaderboardData, bool), anyhow::Error>),
+    PollVerification,
+    ReceiveVerification(Result<LeaderboardVerification, anyhow::Error>),
 }
 
 #[derive(Properties, Clone, PartialEq, Eq)]
@@ -20,6 +24,7 @@ pub struct Leaderboard {
     data: Option<LeaderboardData>,
     error: Option<String>,
     fetching: bool,
+    verification: Option<LeaderboardVerification>,
 }
 
 impl Component for Leaderboard {
@@ -32,6 +37,7 @@ impl Component for Leaderboard {
             data: None,
             error: None,
             fetching: false,
+            verification: None,
         }
     }
 
@@ -40,15 +46,11 @@ impl Component for Leaderboard {
 
         match msg {
             SendRequest => {
-                let callback =
-                    context
-                        .link()
-                        .callback(|response: Result<LeaderboardData, anyhow::Error>| {
-                            Msg::ReceiveResponse(response)
-                        });
                 if let Some(submission) = context.props().submission.as_ref() {
+                    let callback = context.link().callback(Msg::ReceiveSubmitResponse);
                     services::post_leaderboard(submission.clone(), callback);
                 } else {
+                    let callback = context.link().callback(Msg::ReceiveResponse);
                     services::get_leaderboard(&context.props().scenario_name, callback);
                 }
                 self.fetching = true;
@@ -64,6 +66,54 @@ impl Component for Leaderboard {
                 self.fetching = false;
                 true
             }
+            ReceiveSubmitResponse(response) => {
+                if let Ok((_, true)) = &response {
+                    self.verification = Some(LeaderboardVerification::Pending);
+                    context.link().send_message(Msg::PollVerification);
+                }
+                context
+                    .link()
+                    .send_message(Msg::ReceiveResponse(response.map(|(data, _)| data)));
+                false
+            }
+            PollVerification => {
+                if self.verification != Some(LeaderboardVerification::Pending) {
+                    return false;
+                }
+                let callback = context.link().callback(Msg::ReceiveVerification);
+                services::get_leaderboard_verification(
+                    &context.props().scenario_name,
+                    &userid::get_userid(),
+                    callback,
+                );
+                false
+            }
+            ReceiveVerification(response) => {
+                if self.verification != Some(LeaderboardVerification::Pending) {
+                    return false;
+                }
+                match response {
+                    Ok(LeaderboardVerification::Pending) => {
+                        let link = context.link().clone();
+                        Timeout::new(1_000, move || {
+                            link.send_message(Msg::PollVerification);
+                        })
+                        .forget();
+                    }
+                    Ok(verification @ LeaderboardVerification::Accepted { .. }) => {
+                        self.verification = Some(verification);
+                        let callback = context.link().callback(Msg::ReceiveResponse);
+                        services::get_leaderboard(&context.props().scenario_name, callback);
+                    }
+                    Ok(verification) => self.verification = Some(verification),
+                    Err(error) => {
+                        self.verification = Some(LeaderboardVerification::Rejected {
+                            reason: error.to_string(),
+                        })
+                    }
+                }
+                true
+            }
         }
     }
 
@@ -91,13 +141,46 @@ impl Component for Leaderboard {
                 }
             };
 
+            let render_code_size_row = |row: &CodeSizeLeaderboardRow| -> Html {
+                let class = (row.userid == userid).then_some("own-leaderboard-entry");
+                let copy_encrypted_code_cb = {
+                    let text = row.encrypted_code.clone();
+                    move |_| {
+                        crate::js::clipboard::write(&text);
+                    }
+                };
+                html! {
+                    <tr class={classes!(class)}>
+                        <td>{ row.username.clone().unwrap_or_else(|| userid::generate_username(&row.userid)) }</td>
+                        <td>{ row.code_size }</td>
+                        <td><a class="material-symbols-outlined" onclick={copy_encrypted_code_cb}>{ "content_copy" }</a></td>
+                    </tr>
+                }
+            };
+
+            let verification = match self.verification.as_ref() {
+                Some(LeaderboardVerification::Pending) => {
+                    html! { <p>{ "Verifying your submission..." }</p> }
+                }
+                Some(LeaderboardVerification::Rejected { reason }) => {
+                    html! { <p class="error">{ "Leaderboard submission rejected: " }{ reason.clone() }</p> }
+                }
+                _ => html! {},
+            };
+
             html! {
                 <div class="leaderboard">
+                    { verification }
                     <table>
-                        <tr><th colspan=3>{ "Leaderboard" }</th></tr>
+                        <tr><th colspan=3>{ "Lowest Time" }</th></tr>
                         <tr><th>{ "User" }</th><th>{ "Time" }</th><th>{ "Encrypted Code" }</th></tr>
                         <tbody>{ for data.lowest_time.iter().map(render_time_row) }</tbody>
                     </table>
+                    <table>
+                        <tr><th colspan=3>{ "Smallest Code" }</th></tr>
+                        <tr><th>{ "User" }</th><th>{ "Size" }</th><th>{ "Encrypted Code" }</th></tr>
+                        <tbody>{ for data.smallest_code.iter().map(render_code_size_row) }</tbody>
+                    </table>
                 </div>
             }
         } else {
@@ -109,6 +192,7 @@ impl Component for Leaderboard {
         if old_props.scenario_name != context.props().scenario_name {
             self.data = None;
             self.error = None;
+            self.verification = None;
             context.link().send_message(Msg::SendRequest);
             true
         } else {
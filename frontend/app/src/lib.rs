@@ -1,6 +1,5 @@
 mod analyzer_stub;
 pub mod benchmark;
-pub mod code_size;
 pub mod codestorage;
 pub mod compiler_output_window;
 pub mod documentation;
@@ -10,6 +9,7 @@ pub mod game;
 pub mod js;
 pub mod leaderboard;
 pub mod leaderboard_window;
+pub mod params_window;
 pub mod services;
 pub mod simulation_window;
 pub mod toolbar;
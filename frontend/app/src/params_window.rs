@@ -0,0 +1,97 @@
+use oort_simulator::scenario::{self, Param, ParamOverrides, Params};
+use wasm_bindgen::JsCast;
+use web_sys::HtmlInputElement;
+use yew::{create_portal, function_component, html, Callback, Event, Html, Properties};
+
+#[derive(Properties, PartialEq)]
+pub struct Props {
+    pub host: web_sys::Element,
+    pub scenario_name: String,
+    pub params: ParamOverrides,
+    /// Called with a parameter name and its new value, or None to restore the default.
+    pub on_change: Callback<(String, Option<f64>)>,
+}
+
+#[function_component]
+pub fn ParamsWindow(props: &Props) -> Html {
+    let params = scenario::load_safe(&props.scenario_name)
+        .map(|scenario| scenario.params())
+        .unwrap_or_default();
+
+    let contents = if params.is_empty() {
+        html! { <p>{ "This scenario has no parameters." }</p> }
+    } else {
+        let rows = params
+            .iter()
+            .map(|param| render_param(param, props))
+            .collect::<Html>();
+        let reset_cb = {
+            let on_change = props.on_change.clone();
+            let names: Vec<String> = props.params.keys().cloned().collect();
+            Callback::from(move |_| {
+                for name in names.iter() {
+                    on_change.emit((name.clone(), None));
+                }
+            })
+        };
+        html! {
+            <>
+                <p>{ "Changes apply the next time the simulation starts. Ship code can read these values with " }<code>{ "scenario_param(name)" }</code>{ ". Runs with modified parameters are not submitted to the leaderboard." }</p>
+                <table>
+                    <tr><th>{ "Name" }</th><th>{ "Value" }</th><th>{ "Range" }</th><th>{ "Description" }</th></tr>
+                    { rows }
+                </table>
+                <button onclick={reset_cb} disabled={props.params.is_empty()}>{ "Reset to defaults" }</button>
+            </>
+        }
+    };
+
+    create_portal(
+        html! {
+            <div class="params">
+                <h1>{ "Scenario Parameters" }</h1>
+                { contents }
+            </div>
+        },
+        props.host.clone(),
+    )
+}
+
+fn render_param(param: &Param, props: &Props) -> Html {
+    let value = props
+        .params
+        .get(&param.name)
+        .copied()
+        .unwrap_or(param.default);
+    let onchange = {
+        let on_change = props.on_change.clone();
+        let param = param.clone();
+        Callback::from(move |e: Event| {
+            let target: web_sys::EventTarget = e.target().unwrap();
+            let input = target.unchecked_into::<HtmlInputElement>();
+            let value = match input.value().parse::<f64>() {
+                Ok(value) => value,
+                Err(_) => return,
+            };
+            let overrides = ParamOverrides::from([(param.name.clone(), value)]);
+            if let Err(e) = Params::resolve(std::slice::from_ref(&param), &overrides) {
+                log::warn!("{}", e);
+                return;
+            }
+            let value = (value != param.default).then_some(value);
+            on_change.emit((param.name.clone(), value));
+        })
+    };
+    let step = if param.integer { "1" } else { "any" };
+    html! {
+        <tr>
+            <td><code>{ &param.name }</code></td>
+            <td>
+                <input type="number" value={param.format(value)} min={param.format(param.min)}
+                    max={param.format(param.max)} {step} {onchange} />
+            </td>
+            <td>{ format!("{} to {}", param.format(param.min), param.format(param.max)) }</td>
+            <td>{ &param.description }</td>
+        </tr>
+    }
+}
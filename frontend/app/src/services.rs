@@ -2,7 +2,7 @@ use crate::userid;
 use anyhow::anyhow;
 use chrono::Utc;
 use oort_proto::{LeaderboardData, LeaderboardSubmission, TournamentResults};
-use oort_proto::{ShortcodeUpload, TournamentSubmission};
+use oort_proto::{LeaderboardVerification, ShortcodeUpload, TournamentSubmission};
 use oort_proto::{Telemetry, TelemetryMsg};
 use reqwasm::http::{Request, Response};
 
@@ -59,9 +59,11 @@ pub fn get_leaderboard(
     });
 }
 
+/// Posts a leaderboard submission. The callback receives the current leaderboard
+/// and whether the submission is waiting for server-side verification.
 pub fn post_leaderboard(
     msg: LeaderboardSubmission,
-    callback: yew::Callback<Result<LeaderboardData, anyhow::Error>>,
+    callback: yew::Callback<Result<(LeaderboardData, bool), anyhow::Error>>,
 ) {
     wasm_bindgen_futures::spawn_local(async move {
         let url = format!("{}/leaderboard", backend_url());
@@ -75,13 +77,43 @@ pub fn post_leaderboard(
                 callback.emit(Err(e));
             }
             Ok(response) => {
+                let pending = response.status() == 202;
                 let data: Result<LeaderboardData, _> = response.json().await.map_err(|e| e.into());
-                callback.emit(data);
+                callback.emit(data.map(|data| (data, pending)));
             }
         }
     });
 }
 
+pub fn get_leaderboard_verification(
+    scenario_name: &str,
+    userid: &str,
+    callback: yew::Callback<anyhow::Result<LeaderboardVerification>>,
+) {
+    let url = format!(
+        "{}/leaderboard/{}/verification/{}",
+        backend_url(),
+        scenario_name,
+        userid
+    );
+    wasm_bindgen_futures::spawn_local(async move {
+        let response = Request::get(&url).send().await;
+        let result: anyhow::Result<LeaderboardVerification> = match response {
+            // A status that can't be found yet isn't a rejection, so keep polling.
+            Ok(response) if response.status() == 404 => Ok(LeaderboardVerification::Pending),
+            Ok(response) if response.ok() => response.json().await.map_err(|e| e.into()),
+            Ok(response) => Err(anyhow!(
+                "Request to {} failed with status {}: {}",
+                response.url(),
+                response.status(),
+                response.text().await.unwrap_or_else(|e| format!("{e:?}"))
+            )),
+            Err(e) => Err(anyhow!("Request failed: {:?}", e)),
+        };
+        callback.emit(result);
+    });
+}
+
 pub fn send_telemetry(payload: Telemetry) {
     let userid = userid::get_userid();
     let username = userid::get_username();
@@ -1,9 +1,12 @@
-use crate::ui::UI;
+use crate::ui::{ConsoleFilter, UI};
 use gloo_render::{request_animation_frame, AnimationFrame};
 use oort_simulation_worker::SimAgent;
+use oort_simulator::debug::LogLevel;
 use oort_simulator::{scenario, simulation::Code, snapshot::Snapshot};
 use rand::Rng;
 use std::rc::Rc;
+use wasm_bindgen::JsCast;
+use web_sys::HtmlInputElement;
 use yew::html::Scope;
 use yew::prelude::*;
 use yew_agent::{Bridge, Bridged};
@@ -14,6 +17,7 @@ pub enum Msg {
         scenario_name: String,
         seed: u32,
         codes: Vec<Code>,
+        params: scenario::ParamOverrides,
     },
     Render,
     KeyEvent(web_sys::KeyboardEvent),
@@ -21,12 +25,16 @@ pub enum Msg {
     PointerEvent(web_sys::PointerEvent),
     BlurEvent(web_sys::FocusEvent),
     RequestSnapshot,
+    SetConsoleLevel(String),
+    SetConsolePickedOnly(bool),
+    SetLayerVisible(String, bool),
     ReceivedSimAgentResponse(oort_simulation_worker::Response),
 }
 
 #[derive(Properties, Clone, PartialEq)]
 pub struct SimulationWindowProps {
     pub host: web_sys::Element,
+    pub console_host: web_sys::Element,
     pub on_simulation_finished: Callback<Snapshot>,
     pub register_link: Callback<Scope<SimulationWindow>>,
     pub version: String,
@@ -42,6 +50,8 @@ pub struct SimulationWindow {
     canvas_ref: NodeRef,
     status_ref: NodeRef,
     picked_ref: NodeRef,
+    console_ref: NodeRef,
+    console_filter: ConsoleFilter,
 }
 
 impl Component for SimulationWindow {
@@ -70,6 +80,8 @@ impl Component for SimulationWindow {
             canvas_ref: context.props().canvas_ref.clone(),
             status_ref: NodeRef::default(),
             picked_ref: NodeRef::default(),
+            console_ref: NodeRef::default(),
+            console_filter: ConsoleFilter::default(),
         }
     }
 
@@ -79,6 +91,7 @@ impl Component for SimulationWindow {
                 scenario_name,
                 seed,
                 codes,
+                params,
             } => {
                 self.nonce = rand::thread_rng().gen();
                 self.ui = Some(Box::new(UI::new(
@@ -88,6 +101,8 @@ impl Component for SimulationWindow {
                     self.canvas_ref.clone(),
                     self.status_ref.clone(),
                     self.picked_ref.clone(),
+                    self.console_ref.clone(),
+                    self.console_filter,
                 )));
                 self.sim_agent
                     .send(oort_simulation_worker::Request::StartScenario {
@@ -95,14 +110,17 @@ impl Component for SimulationWindow {
                         seed,
                         codes: codes.to_vec(),
                         nonce: self.nonce,
+                        params,
                     });
                 false
             }
             Msg::Render => {
+                let mut layers_changed = false;
                 if let Some(ui) = self.ui.as_mut() {
                     ui.render();
+                    layers_changed = ui.take_layers_changed();
                 }
-                self.check_status(context)
+                self.check_status(context) || layers_changed
             }
             Msg::RequestSnapshot => {
                 self.sim_agent
@@ -112,6 +130,32 @@ impl Component for SimulationWindow {
                     });
                 false
             }
+            Msg::SetConsoleLevel(level) => {
+                self.console_filter.min_level = match level.as_str() {
+                    "debug" => LogLevel::Debug,
+                    "info" => LogLevel::Info,
+                    "warn" => LogLevel::Warn,
+                    "error" => LogLevel::Error,
+                    _ => LogLevel::Trace,
+                };
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.set_console_filter(self.console_filter);
+                }
+                false
+            }
+            Msg::SetConsolePickedOnly(picked_only) => {
+                self.console_filter.picked_only = picked_only;
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.set_console_filter(self.console_filter);
+                }
+                false
+            }
+            Msg::SetLayerVisible(layer, visible) => {
+                if let Some(ui) = self.ui.as_mut() {
+                    ui.set_layer_visible(&layer, visible);
+                }
+                false
+            }
             Msg::KeyEvent(e) => {
                 if let Some(ui) = self.ui.as_mut() {
                     ui.on_key_event(e);
@@ -165,8 +209,16 @@ impl Component for SimulationWindow {
         let wheel_event_cb = context.link().callback(Msg::WheelEvent);
         let pointer_event_cb = context.link().callback(Msg::PointerEvent);
         let blur_event_cb = context.link().callback(Msg::BlurEvent);
+        let console_level_cb = context.link().callback(|e: Event| {
+            let target: web_sys::EventTarget = e.target().unwrap();
+            Msg::SetConsoleLevel(target.unchecked_into::<HtmlInputElement>().value())
+        });
+        let console_picked_only_cb = context.link().callback(|e: Event| {
+            let target: web_sys::EventTarget = e.target().unwrap();
+            Msg::SetConsolePickedOnly(target.unchecked_into::<HtmlInputElement>().checked())
+        });
 
-        create_portal(
+        let simulation = create_portal(
             html! {
                 <>
                     <canvas id="simcanvas" class="glcanvas"
@@ -186,11 +238,81 @@ impl Component for SimulationWindow {
                 </>
             },
             context.props().host.clone(),
-        )
+        );
+
+        let console = create_portal(
+            html! {
+                <div class="console">
+                    <div class="console-controls">
+                        <select onchange={console_level_cb}>
+                            <option value="trace">{ "All levels" }</option>
+                            <option value="debug">{ "Debug and above" }</option>
+                            <option value="info">{ "Info and above" }</option>
+                            <option value="warn">{ "Warnings and errors" }</option>
+                            <option value="error">{ "Errors only" }</option>
+                        </select>
+                        <label>
+                            <input type="checkbox" onchange={console_picked_only_cb} />
+                            { " Selected ship only" }
+                        </label>
+                        { self.render_layer_toggles(context) }
+                    </div>
+                    <div class="console-log">
+                        <pre ref={self.console_ref.clone()}></pre>
+                    </div>
+                </div>
+            },
+            context.props().console_host.clone(),
+        );
+
+        html! {
+            <>
+                { simulation }
+                { console }
+            </>
+        }
     }
 }
 
 impl SimulationWindow {
+    fn render_layer_toggles(&self, context: &Context<Self>) -> Html {
+        let Some(ui) = self.ui.as_ref() else {
+            return html! {};
+        };
+        if ui.layers().is_empty() {
+            return html! {};
+        }
+        let toggles = ui.layers().iter().map(|layer| {
+            let name = if layer.is_empty() {
+                "default".to_string()
+            } else {
+                layer.clone()
+            };
+            let cb = {
+                let layer = layer.clone();
+                context.link().callback(move |e: Event| {
+                    let target: web_sys::EventTarget = e.target().unwrap();
+                    Msg::SetLayerVisible(
+                        layer.clone(),
+                        target.unchecked_into::<HtmlInputElement>().checked(),
+                    )
+                })
+            };
+            html! {
+                <label>
+                    <input type="checkbox" checked={ui.layer_visible(layer)} onchange={cb} />
+                    { format!(" {name}") }
+                </label>
+            }
+        });
+        html! {
+            <span class="console-layers">
+                { " Layers:" }
+                { for toggles }
+            </span>
+        }
+    }
+
     fn check_status(&mut self, context: &Context<Self>) -> bool {
         if let Some(ui) = self.ui.as_ref() {
             let status = ui.status();
@@ -73,7 +73,13 @@ impl Component for Tournament {
             html! {
                 <div id="tournament_results">
                     <h1>{ "Tournament Results" }</h1>
-                    <p>{ "Scenario: " }{ data.scenario_name.clone() }</p>
+                    {
+                        if data.scenarios.is_empty() {
+                            html! { <p>{ "Scenario: " }{ data.scenario_name.clone() }</p> }
+                        } else {
+                            html! { <p>{ "Combined results over all scenarios, weighted as shown below." }</p> }
+                        }
+                    }
                     <p>
                         { "Ratings are calculated with " }
                         <a href="https://en.wikipedia.org/wiki/Glicko_rating_system">{ "Glicko-2" }</a>
@@ -86,6 +92,14 @@ impl Component for Tournament {
                         { "Click a cell to run those AIs against each other." }
                     </p>
                     { make_win_matrix_table(data) }
+                    { for data.scenarios.iter().map(|x| html! {
+                        <>
+                            <h2>{ format!("{} (weight {})", x.results.scenario_name, x.weight) }</h2>
+                            { make_ratings_table(&x.results) }
+                            <br />
+                            { make_win_matrix_table(&x.results) }
+                        </>
+                    }) }
                 </div>
             }
         } else {
@@ -4,11 +4,12 @@ pub mod frame_timer;
 use log::{debug, info};
 use nalgebra::{point, vector, Point2};
 use oort_renderer::Renderer;
+use oort_simulator::debug::{LogEntry, LogLevel};
 use oort_simulator::model;
 use oort_simulator::scenario::Status;
 use oort_simulator::simulation::{self, PHYSICS_TICK_LENGTH};
 use oort_simulator::snapshot::{self, ShipSnapshot, Snapshot};
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
 use std::time::Duration;
 use web_sys::{Element, HtmlCanvasElement};
 use yew::NodeRef;
@@ -19,6 +20,25 @@ const MAX_ZOOM: f32 = 1e-3;
 const INITIAL_ZOOM: f32 = 5e-4;
 const SNAPSHOT_PRELOAD: usize = 5;
 const MAX_SNAPSHOT_REQUESTS_IN_FLIGHT: usize = 10;
+const GAS_HISTORY_LENGTH: usize = 60;
+const MAX_CONSOLE_ENTRIES: usize = 1000;
+
+/// Selects which log entries are shown in the console.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConsoleFilter {
+    pub min_level: LogLevel,
+    /// Only show entries from the picked ship.
+    pub picked_only: bool,
+}
+
+impl Default for ConsoleFilter {
+    fn default() -> Self {
+        Self {
+            min_level: LogLevel::Trace,
+            picked_only: false,
+        }
+    }
+}
 
 pub struct UI {
     version: String,
@@ -41,13 +61,22 @@ pub struct UI {
     physics_time: std::time::Duration,
     fps: fps::FPS,
     debug: bool,
+    layers: BTreeSet<String>,
+    hidden_layers: BTreeSet<String>,
+    solo_layer: Option<String>,
+    layers_changed: bool,
     last_status_msg: String,
     snapshot_requests_in_flight: usize,
     nonce: u32,
     request_snapshot: yew::Callback<()>,
     picked_ship_id: Option<u64>,
+    gas_history: HashMap<u64, VecDeque<u32>>,
     status_ref: NodeRef,
     picked_ref: NodeRef,
+    console_ref: NodeRef,
+    console_entries: VecDeque<LogEntry>,
+    console_filter: ConsoleFilter,
+    console_dirty: bool,
     touches: HashMap<i32, Touch>,
     drag_start: Option<Point2<i32>>,
     needs_render: bool,
@@ -63,6 +92,8 @@ impl UI {
         canvas_ref: NodeRef,
         status_ref: NodeRef,
         picked_ref: NodeRef,
+        console_ref: NodeRef,
+        console_filter: ConsoleFilter,
     ) -> Self {
         if let Some(elem) = status_ref.cast::<Element>() {
             elem.set_text_content(Some("LOADING..."));
@@ -103,19 +134,70 @@ impl UI {
             physics_time: std::time::Duration::ZERO,
             fps: fps::FPS::new(),
             debug: false,
+            layers: BTreeSet::new(),
+            hidden_layers: BTreeSet::new(),
+            solo_layer: None,
+            layers_changed: false,
             last_status_msg: "".to_owned(),
             snapshot_requests_in_flight: 0,
             nonce,
             request_snapshot,
             picked_ship_id: None,
+            gas_history: HashMap::new(),
             status_ref,
             picked_ref,
+            console_ref,
+            console_entries: VecDeque::new(),
+            console_filter,
+            console_dirty: true,
             touches: HashMap::new(),
             drag_start: None,
             needs_render: true,
         }
     }
 
+    // Cycles between showing all layers and showing each named layer alone.
+    // The default layer has no name and is only shown when cycling back to
+    // all layers.
+    fn cycle_solo_layer(&mut self) {
+        let named = self.layers.iter().filter(|x| !x.is_empty());
+        self.solo_layer = match self.solo_layer.take() {
+            None => named.cloned().next(),
+            Some(current) => named.skip_while(|x| **x <= current).cloned().next(),
+        };
+        self.hidden_layers = match self.solo_layer.as_ref() {
+            Some(solo) => self.layers.iter().filter(|x| *x != solo).cloned().collect(),
+            None => BTreeSet::new(),
+        };
+        self.renderer.set_hidden_layers(self.hidden_layers.clone());
+        self.layers_changed = true;
+    }
+
+    /// Debug drawing layers seen so far. The default layer is "".
+    pub fn layers(&self) -> &BTreeSet<String> {
+        &self.layers
+    }
+
+    pub fn layer_visible(&self, layer: &str) -> bool {
+        !self.hidden_layers.contains(layer)
+    }
+
+    pub fn set_layer_visible(&mut self, layer: &str, visible: bool) {
+        if visible {
+            self.hidden_layers.remove(layer);
+        } else {
+            self.hidden_layers.insert(layer.to_string());
+        }
+        self.solo_layer = None;
+        self.renderer.set_hidden_layers(self.hidden_layers.clone());
+        self.layers_changed = true;
+    }
+
+    /// Returns true once after the set of layers or their visibility changed.
+    pub fn take_layers_changed(&mut self) -> bool {
+        std::mem::take(&mut self.layers_changed)
+    }
+
     pub fn render(&mut self) {
         if self.quit {
             return;
@@ -179,6 +261,11 @@ impl UI {
             self.renderer.set_blur(!self.renderer.get_blur());
         }
 
+        if self.keys_down.contains("l") && !self.keys_ignored.contains("l") {
+            self.keys_ignored.insert("l".to_string());
+            self.cycle_solo_layer();
+        }
+
         if !self.paused {
             self.physics_time += elapsed;
         }
@@ -241,6 +328,9 @@ impl UI {
                     (snapshot.time / PHYSICS_TICK_LENGTH).round() as i64
                 ));
             }
+            if let Some(layer) = self.solo_layer.as_ref() {
+                status_msgs.push(format!("LAYER {layer}"));
+            }
         }
 
         if self.frame % 10 == 0 || self.paused || self.status != Status::Running {
@@ -261,6 +351,9 @@ impl UI {
             }
 
             self.update_picked();
+            if self.console_dirty {
+                self.update_console();
+            }
         }
 
         if self.frame == 600 {
@@ -338,6 +431,33 @@ impl UI {
             }
         }
 
+        self.gas_history
+            .retain(|id, _| snapshot.gas_used.contains_key(id));
+        for drawing in snapshot.drawings.values().flatten() {
+            if !self.layers.contains(&drawing.layer) {
+                self.layers.insert(drawing.layer.clone());
+                self.layers_changed = true;
+            }
+        }
+
+        for (id, gas) in snapshot.gas_used.iter() {
+            let history = self.gas_history.entry(*id).or_default();
+            if history.len() >= GAS_HISTORY_LENGTH {
+                history.pop_front();
+            }
+            history.push_back(*gas);
+        }
+
+        if !snapshot.log.is_empty() {
+            for entry in snapshot.log.iter() {
+                if self.console_entries.len() >= MAX_CONSOLE_ENTRIES {
+                    self.console_entries.pop_front();
+                }
+                self.console_entries.push_back(entry.clone());
+            }
+            self.console_dirty = true;
+        }
+
         if !snapshot.errors.is_empty() {
             self.paused = true;
         }
@@ -443,6 +563,9 @@ impl UI {
                             .map(|ship| ship.id)
                     });
                     self.update_picked();
+                    if self.console_filter.picked_only {
+                        self.console_dirty = true;
+                    }
                     self.needs_render = true;
                 }
             }
@@ -504,8 +627,13 @@ impl UI {
                 } else {
                     "".to_string()
                 };
+                let gas_text = self
+                    .gas_history
+                    .get(&self.picked_ship_id.unwrap())
+                    .map(gas_summary)
+                    .unwrap_or_default();
                 elem.set_text_content(Some(&format!(
-                    "{class:?}\nTeam: {team:?}\nHealth: {health:.0}\n{fuel_text}{debug_text}"
+                    "{class:?}\nTeam: {team:?}\nHealth: {health:.0}\n{fuel_text}{gas_text}{debug_text}"
                 )));
             }
         } else if let Some(elem) = self.picked_ref.cast::<Element>() {
@@ -514,6 +642,41 @@ impl UI {
         self.renderer.set_picked_ship(self.picked_ship_id);
     }
 
+    pub fn set_console_filter(&mut self, filter: ConsoleFilter) {
+        self.console_filter = filter;
+        self.console_dirty = true;
+        self.needs_render = true;
+    }
+
+    fn update_console(&mut self) {
+        self.console_dirty = false;
+        let elem = match self.console_ref.cast::<Element>() {
+            Some(elem) => elem,
+            None => return,
+        };
+        let filter = self.console_filter;
+        let mut text = String::new();
+        for entry in self.console_entries.iter() {
+            if entry.level < filter.min_level
+                || (filter.picked_only && Some(entry.ship) != self.picked_ship_id)
+            {
+                continue;
+            }
+            text.push_str(&format!(
+                "[{:>6}] {:<5} ship {} (team {}): {}\n",
+                entry.tick,
+                format!("{:?}", entry.level).to_uppercase(),
+                entry.ship,
+                entry.team,
+                entry.message
+            ));
+        }
+        elem.set_text_content(Some(&text));
+        if let Some(parent) = elem.parent_element() {
+            parent.set_scroll_top(parent.scroll_height());
+        }
+    }
+
     pub fn set_status_message(&self, text: &str) {
         if let Some(elem) = self.status_ref.cast::<Element>() {
             elem.set_text_content(Some(text));
@@ -531,6 +694,23 @@ impl UI {
     }
 }
 
+fn gas_summary(history: &VecDeque<u32>) -> String {
+    const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let limit = oort_simulator::vm::GAS_PER_TICK as f64;
+    let fraction = |gas: u32| (gas as f64 / limit).clamp(0.0, 1.0);
+    let current = history.back().copied().unwrap_or_default();
+    let peak = history.iter().copied().max().unwrap_or_default();
+    let plot: String = history
+        .iter()
+        .map(|&gas| BARS[(fraction(gas) * (BARS.len() - 1) as f64).round() as usize])
+        .collect();
+    format!(
+        "Gas: {:.1}% (peak {:.1}%)\n{plot}\n",
+        fraction(current) * 100.0,
+        fraction(peak) * 100.0
+    )
+}
+
 #[derive(Debug)]
 struct Touch {
     world_position: Point2<f64>,
@@ -51,3 +51,25 @@ pub fn line_loop_mesh(vertices: &[Vector2<f32>], width: f32) -> Vec<Vector2<f32>
     }
     result
 }
+
+/// Number of segments needed for a smooth circle with the given radius in pixels.
+pub fn circle_segments(radius_pixels: f32) -> usize {
+    ((radius_pixels.max(0.0).sqrt() * 4.0) as usize).clamp(12, 256)
+}
+
+/// Points along an arc from start_angle to end_angle, inclusive.
+pub fn arc(
+    center: Point2<f32>,
+    radius: f32,
+    start_angle: f32,
+    end_angle: f32,
+    segments: usize,
+) -> Vec<Point2<f32>> {
+    let segments = segments.max(1);
+    (0..=segments)
+        .map(|i| {
+            let angle = start_angle + (end_angle - start_angle) * (i as f32 / segments as f32);
+            center + vector![angle.cos(), angle.sin()] * radius
+        })
+        .collect()
+}
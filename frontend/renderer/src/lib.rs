@@ -21,10 +21,12 @@ use grid_renderer::GridRenderer;
 use line_renderer::LineRenderer;
 use nalgebra::{point, vector, Matrix4, Point2};
 use oort_api::Text;
+use oort_simulator::debug::Drawing;
 use oort_simulator::simulation::Line;
 use oort_simulator::snapshot::Snapshot;
 use particle_renderer::ParticleRenderer;
 use ship_renderer::ShipRenderer;
+use std::collections::BTreeSet;
 use text_renderer::TextRenderer;
 use trail_renderer::TrailRenderer;
 use wasm_bindgen::prelude::*;
@@ -48,6 +50,7 @@ pub struct Renderer {
     base_line_width: f32,
     debug: bool,
     picked_ship: Option<u64>,
+    hidden_layers: BTreeSet<String>,
     blur_enabled: bool,
 }
 
@@ -87,6 +90,7 @@ impl Renderer {
             base_line_width: 1.0,
             debug: false,
             picked_ship: None,
+            hidden_layers: BTreeSet::new(),
             blur_enabled: true,
         })
     }
@@ -118,6 +122,11 @@ impl Renderer {
         self.picked_ship = id;
     }
 
+    /// Hides drawings on the given layers.
+    pub fn set_hidden_layers(&mut self, layers: BTreeSet<String>) {
+        self.hidden_layers = layers;
+    }
+
     pub fn unproject(&self, x: i32, y: i32) -> Point2<f64> {
         let inverse_matrix = self.projection_matrix.try_inverse().unwrap();
         let dpr = gloo_utils::window().device_pixel_ratio() as f32;
@@ -204,6 +213,42 @@ impl Renderer {
             self.line_renderer.upload(&self.projection_matrix, &lines)
         };
 
+        let (world_drawings, screen_drawings): (Vec<&Drawing>, Vec<&Drawing>) = {
+            let mut drawings: Vec<&Drawing> = Vec::new();
+            for (ship, ship_drawings) in snapshot.drawings.iter() {
+                if self.debug || self.picked_ship == Some(*ship) {
+                    drawings.extend(ship_drawings.iter());
+                }
+            }
+            drawings
+                .into_iter()
+                .filter(|x| !self.hidden_layers.contains(&x.layer))
+                .partition(|x| !x.screen)
+        };
+        let world_drawing_drawset = {
+            let pixel_size = (self.unproject(1, 0) - self.unproject(0, 0)).x as f32;
+            self.line_renderer
+                .upload_drawings(&self.projection_matrix, pixel_size, &world_drawings)
+        };
+        let world_text_drawset = self
+            .text_renderer
+            .upload_drawings(&self.projection_matrix, &world_drawings);
+        let screen_drawing_drawset = {
+            let screen_projection_matrix = Matrix4::new_orthographic(
+                0.0,
+                screen_width as f32,
+                screen_height as f32,
+                0.0,
+                -1.0,
+                1.0,
+            );
+            self.line_renderer
+                .upload_drawings(&screen_projection_matrix, 1.0, &screen_drawings)
+        };
+        let screen_text_drawset = self
+            .text_renderer
+            .upload_drawings(&self.projection_matrix, &screen_drawings);
+
         self.context.viewport(0, 0, screen_width, screen_height);
 
         if self.blur_enabled {
@@ -239,8 +284,12 @@ impl Renderer {
             self.bullet_renderer.draw(&bullet_drawset);
             self.particle_renderer.draw(&particle_drawset, 1.0);
             self.line_renderer.draw(&line_drawset);
+            self.line_renderer.draw(&world_drawing_drawset);
             self.ship_renderer.draw(&ship_drawset);
             self.text_renderer.draw(&text_drawset);
+            self.text_renderer.draw(&world_text_drawset);
+            self.line_renderer.draw(&screen_drawing_drawset);
+            self.text_renderer.draw(&screen_text_drawset);
         }
     }
 
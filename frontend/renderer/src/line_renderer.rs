@@ -1,7 +1,9 @@
-use super::{buffer_arena, glutil};
+use super::{buffer_arena, geometry, glutil};
 use glutil::VertexAttribBuilder;
-use nalgebra::{vector, Matrix4, Vector4};
+use nalgebra::{vector, Matrix4, Point2, Vector4};
+use oort_simulator::debug::{Drawing, Shape};
 use oort_simulator::simulation::Line;
+use std::f32::consts::TAU;
 use wasm_bindgen::prelude::*;
 use web_sys::{WebGl2RenderingContext, WebGlProgram, WebGlUniformLocation, WebGlVertexArrayObject};
 use WebGl2RenderingContext as gl;
@@ -20,6 +22,7 @@ pub struct DrawSet {
 }
 
 pub struct Draw {
+    mode: u32,
     num_vertices: usize,
     attribs_token: buffer_arena::Token,
 }
@@ -100,6 +103,7 @@ void main() {
 
             let attribs_token = self.buffer_arena.write(&attribs);
             draws.push(Draw {
+                mode: gl::LINES,
                 num_vertices: attribs.len(),
                 attribs_token,
             });
@@ -110,6 +114,93 @@ void main() {
         }
     }
 
+    /// Tessellates shapes drawn by ship code. `pixel_size` is the size of a
+    /// pixel in the projection's units and controls the number of segments.
+    pub fn upload_drawings(
+        &mut self,
+        projection_matrix: &Matrix4<f32>,
+        pixel_size: f32,
+        drawings: &[&Drawing],
+    ) -> DrawSet {
+        let mut lines = vec![];
+        let mut triangles = vec![];
+        for drawing in drawings {
+            let color = drawing.color;
+            let (center, points) = match &drawing.shape {
+                Shape::Line { a, b } => {
+                    lines.push(attribs(a.cast(), color));
+                    lines.push(attribs(b.cast(), color));
+                    continue;
+                }
+                Shape::Circle { center, radius } => {
+                    let radius = *radius as f32;
+                    let segments = geometry::circle_segments(radius / pixel_size);
+                    let center = center.cast();
+                    (center, geometry::arc(center, radius, 0.0, TAU, segments))
+                }
+                Shape::Arc {
+                    center,
+                    radius,
+                    start_angle,
+                    end_angle,
+                } => {
+                    let radius = *radius as f32;
+                    let (start_angle, end_angle) = (*start_angle as f32, *end_angle as f32);
+                    let fraction = ((end_angle - start_angle).abs() / TAU).min(1.0);
+                    let segments = (geometry::circle_segments(radius / pixel_size) as f32
+                        * fraction)
+                        .ceil() as usize;
+                    let center = center.cast();
+                    (
+                        center,
+                        geometry::arc(center, radius, start_angle, end_angle, segments),
+                    )
+                }
+                Shape::Polygon {
+                    center,
+                    radius,
+                    sides,
+                    angle,
+                } => {
+                    let angle = *angle as f32;
+                    let center = center.cast();
+                    (
+                        center,
+                        geometry::arc(center, *radius as f32, angle, angle + TAU, *sides as usize),
+                    )
+                }
+                Shape::Text { .. } => continue,
+            };
+            for w in points.windows(2) {
+                if drawing.filled {
+                    triangles.push(attribs(center, color));
+                    triangles.push(attribs(w[0], color));
+                    triangles.push(attribs(w[1], color));
+                } else {
+                    lines.push(attribs(w[0], color));
+                    lines.push(attribs(w[1], color));
+                }
+            }
+        }
+
+        let mut draws = vec![];
+        for (mode, attribs) in [(gl::TRIANGLES, triangles), (gl::LINES, lines)] {
+            // Multiple of both 2 and 3 so chunks don't split a primitive.
+            for attribs in attribs.chunks(6000) {
+                let attribs_token = self.buffer_arena.write(attribs);
+                draws.push(Draw {
+                    mode,
+                    num_vertices: attribs.len(),
+                    attribs_token,
+                });
+            }
+        }
+        DrawSet {
+            projection_matrix: *projection_matrix,
+            draws,
+        }
+    }
+
     pub fn draw(&mut self, drawset: &DrawSet) {
         if drawset.draws.is_empty() {
             return;
@@ -138,9 +229,16 @@ void main() {
                 .build();
 
             self.context
-                .draw_arrays(gl::LINES, 0, draw.num_vertices as i32);
+                .draw_arrays(draw.mode, 0, draw.num_vertices as i32);
         }
 
         self.context.bind_vertex_array(None);
     }
 }
+
+fn attribs(p: Point2<f32>, color: Vector4<f32>) -> Attribs {
+    Attribs {
+        vertex: vector![p.x, p.y, 0.0, 1.0],
+        color,
+    }
+}
@@ -6,6 +6,7 @@ use image::EncodableLayout;
 use nalgebra::{point, vector, Matrix4, Vector2, Vector4};
 use oort_api::Text;
 use oort_simulator::color;
+use oort_simulator::debug::{Drawing, Shape};
 use wasm_bindgen::prelude::*;
 use web_sys::{
     WebGl2RenderingContext, WebGlProgram, WebGlTexture, WebGlUniformLocation,
@@ -17,6 +18,7 @@ const FONT_PNG: &[u8] = include_bytes!("../../../assets/null_terminator.png");
 const FONT_ROWS: usize = 12;
 const FONT_COLS: usize = 8;
 const FONT_GLYPH_SIZE: usize = 8;
+const SCALE: f32 = 2.0;
 
 pub struct TextRenderer {
     context: WebGl2RenderingContext,
@@ -158,6 +160,59 @@ void main() {
     }
 
     pub fn upload(&mut self, world_projection_matrix: &Matrix4<f32>, texts: &[Text]) -> DrawSet {
+        let runs: Vec<_> = texts
+            .iter()
+            .map(|text| {
+                (
+                    self.project(world_projection_matrix, text.x, text.y),
+                    color::from_u24(text.color),
+                    &text.text[..text.length as usize],
+                )
+            })
+            .collect();
+        self.upload_runs(&runs)
+    }
+
+    /// Uploads text drawn with the richer debug drawing API. Screen-space
+    /// positions are pixels from the top left of the canvas.
+    pub fn upload_drawings(
+        &mut self,
+        world_projection_matrix: &Matrix4<f32>,
+        drawings: &[&Drawing],
+    ) -> DrawSet {
+        let screen_height = self.context.drawing_buffer_height() as f32;
+        let half_glyph = (FONT_GLYPH_SIZE - 1) as f32 * SCALE / 2.0;
+        let runs: Vec<_> = drawings
+            .iter()
+            .filter_map(|drawing| match &drawing.shape {
+                Shape::Text { topleft, text } => {
+                    let pos = if drawing.screen {
+                        vector![
+                            topleft.x as f32 + half_glyph,
+                            screen_height - topleft.y as f32 - half_glyph
+                        ]
+                    } else {
+                        self.project(world_projection_matrix, topleft.x, topleft.y)
+                    };
+                    Some((pos, drawing.color, text.as_bytes()))
+                }
+                _ => None,
+            })
+            .collect();
+        self.upload_runs(&runs)
+    }
+
+    fn project(&self, world_projection_matrix: &Matrix4<f32>, x: f64, y: f64) -> Vector2<f32> {
+        let screen_width = self.context.drawing_buffer_width() as f32;
+        let screen_height = self.context.drawing_buffer_height() as f32;
+        let projected = world_projection_matrix.transform_point(&point![x as f32, y as f32, 0.0]);
+        vector![
+            (projected.x + 1.0) * screen_width / 2.0,
+            (projected.y + 1.0) * screen_height / 2.0
+        ]
+    }
+
+    fn upload_runs(&mut self, runs: &[(Vector2<f32>, Vector4<f32>, &[u8])]) -> DrawSet {
         let screen_width = self.context.drawing_buffer_width() as f32;
         let screen_height = self.context.drawing_buffer_height() as f32;
 
@@ -174,9 +229,8 @@ void main() {
         let quad_vertices = geometry::triquad();
         let vertices_token = self.buffer_arena.write(&quad_vertices);
 
-        let num_glyphs: usize = texts.iter().map(|x| x.length as usize).sum();
-        let scale = 2.0;
-        let screen_glyph_size = (FONT_GLYPH_SIZE - 1) as f32 * scale;
+        let num_glyphs: usize = runs.iter().map(|x| x.2.len()).sum();
+        let screen_glyph_size = (FONT_GLYPH_SIZE - 1) as f32 * SCALE;
         let font_glyph_width = 1.0 / FONT_COLS as f32;
         let font_glyph_height = 1.0 / FONT_ROWS as f32;
         let font_pixel_width = 1.0 / (FONT_COLS * FONT_GLYPH_SIZE) as f32;
@@ -184,18 +238,12 @@ void main() {
 
         let mut attribs = vec![];
         attribs.reserve(num_glyphs);
-        for text in texts {
-            let worldpos = vector![text.x as f32, text.y as f32];
-            let projected =
-                world_projection_matrix.transform_point(&point![worldpos.x, worldpos.y, 0.0]);
-            let projected_pixels = vector![
-                (projected.x + 1.0) * screen_width / 2.0,
-                (projected.y + 1.0) * screen_height / 2.0
-            ];
-            let mut pos = vector![projected_pixels.x.floor(), projected_pixels.y.floor()];
-            let color = color::from_u24(text.color);
-            for i in 0..text.length {
-                let idx = (text.text[i as usize] as usize - 32).clamp(0, FONT_ROWS * FONT_COLS - 1);
+        for &(pixels, color, text) in runs {
+            let mut pos = vector![pixels.x.floor(), pixels.y.floor()];
+            for &c in text {
+                let idx = (c as usize)
+                    .saturating_sub(32)
+                    .min(FONT_ROWS * FONT_COLS - 1);
                 let row = FONT_ROWS - idx / FONT_COLS - 1;
                 let col = idx % FONT_COLS;
 
@@ -216,7 +264,7 @@ void main() {
                     color,
                 });
 
-                pos.x += (FONT_GLYPH_SIZE as f32 + 1.0) * scale;
+                pos.x += (FONT_GLYPH_SIZE as f32 + 1.0) * SCALE;
             }
         }
         let attribs_token = self.buffer_arena.write(&attribs);
@@ -1,7 +1,8 @@
-use oort_simulator::scenario::{Status, MAX_TICKS};
+use oort_simulator::scenario::{ParamOverrides, Status, MAX_TICKS};
 use oort_simulator::simulation::Code;
 use oort_simulator::simulation::Simulation;
 use oort_simulator::snapshot::Snapshot;
+use oort_simulator::vm;
 use serde::{Deserialize, Serialize};
 use yew_agent::{HandlerId, Private, WorkerLink};
 
@@ -12,6 +13,7 @@ pub enum Request {
         seed: u32,
         codes: Vec<Code>,
         nonce: u32,
+        params: ParamOverrides,
     },
     Snapshot {
         ticks: u32,
@@ -53,9 +55,25 @@ impl yew_agent::Worker for SimAgent {
                 seed,
                 codes,
                 nonce,
+                params,
             } => {
-                self.sim = Some(Simulation::new(&scenario_name, seed, &codes));
-                let snapshot = self.sim().snapshot(nonce);
+                let (sim, error) = match Simulation::new_with_params(
+                    &scenario_name,
+                    seed,
+                    &codes,
+                    vm::Backend::default(),
+                    &params,
+                ) {
+                    Ok(sim) => (sim, None),
+                    Err(msg) => (
+                        Simulation::new(&scenario_name, seed, &codes),
+                        Some(vm::Error { msg }),
+                    ),
+                };
+                self.sim = Some(sim);
+                let mut snapshot = self.sim().snapshot(nonce);
+                // Reported like a ship error, which stops the simulation.
+                snapshot.errors.extend(error);
                 self.errored = !snapshot.errors.is_empty();
                 self.link.respond(who, Response::Snapshot { snapshot });
             }
@@ -6,6 +6,6 @@ done
 
 
 (cd frontend && cargo check -q --target wasm32-unknown-unknown)
-(cd shared && cargo test -q && cargo check -q --target wasm32-unknown-unknown --no-default-features --features js)
+(cd shared && cargo test -q && cargo test -q -p oort_simulator --no-default-features --features interpreter && cargo check -q --target wasm32-unknown-unknown --no-default-features --features js)
 (cd tools && cargo check -q)
 (cd services && cargo check -q)
@@ -0,0 +1,379 @@
+//! Continuous ranked ladder for tournament scenarios.
+//!
+//! Each tournament submission is stored as a job and played against the
+//! opponents with the closest ratings, once on each side. Ratings are updated
+//! with Glicko-2 after every game, so standings change as matches finish rather
+//! than when a tournament is run by hand.
+use crate::verify::Compiler;
+use crate::{error, Error};
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use chrono::Utc;
+use oort_proto::{
+    LadderData, LadderJob, LadderMatch, LadderRating, TournamentCompetitor, TournamentSubmission,
+};
+use oort_simulator::scenario::{self, Status};
+use oort_simulator::simulation::{Code, Simulation};
+use oort_storage::{submission_docid, Storage};
+use sha2::{Digest, Sha256};
+use skillratings::glicko2::{glicko2, Glicko2Config, Glicko2Rating};
+use skillratings::Outcomes;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Number of nearest-rated opponents each submission plays.
+pub const NUM_OPPONENTS: usize = 4;
+
+const RECENT_MATCHES: usize = 20;
+const MAX_CACHED_CODES: usize = 64;
+
+/// How long a claimed job can run before another instance retries it.
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(1800);
+
+/// How often each instance checks for jobs it wasn't notified about.
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Plays ladder matches one submission at a time in a background task.
+///
+/// Pending submissions are kept in storage, so they survive restarts and
+/// are picked up by whichever instance polls next.
+pub struct Ladder {
+    storage: Arc<dyn Storage>,
+    notify: Arc<Notify>,
+}
+
+impl Ladder {
+    pub fn new(storage: Arc<dyn Storage>, compiler: Arc<dyn Compiler>) -> Self {
+        let notify = Arc::new(Notify::new());
+        {
+            let storage = storage.clone();
+            let notify = notify.clone();
+            tokio::spawn(async move {
+                let mut cache = CodeCache::default();
+                loop {
+                    if let Err(e) =
+                        run_pending(storage.as_ref(), compiler.as_ref(), &mut cache).await
+                    {
+                        log::error!("Failed to run ladder jobs: {}", e);
+                    }
+                    tokio::select! {
+                        _ = notify.notified() => {}
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                    }
+                }
+            });
+        }
+        Self { storage, notify }
+    }
+
+    /// Adds a submission to the ladder and stores its matches to be played.
+    ///
+    /// The rating is written before any matches are played, so the submission
+    /// can be picked as an opponent straight away. A resubmission keeps the
+    /// user's rating but resets its deviation, so the new code can move
+    /// quickly to where it belongs.
+    pub async fn submit(&self, obj: TournamentSubmission) -> anyhow::Result<()> {
+        let docid = submission_docid(&obj.scenario_name, &obj.userid);
+        let rating = match self.storage.get_ladder(&docid).await? {
+            Some(mut rating) => {
+                rating.username = obj.username.clone();
+                rating.deviation = Glicko2Rating::new().deviation;
+                rating
+            }
+            None => new_rating(&obj),
+        };
+        self.storage.put_ladder(&docid, &rating).await?;
+        let job = LadderJob {
+            submission: obj,
+            claimed: None,
+        };
+        self.storage.put_ladder_job(&docid, &job).await?;
+        self.notify.notify_one();
+        Ok(())
+    }
+}
+
+/// Plays unclaimed jobs until none are left.
+///
+/// Claims aren't transactional, so two instances can occasionally play the
+/// same submission. That only adds extra rated games.
+async fn run_pending(
+    storage: &dyn Storage,
+    compiler: &dyn Compiler,
+    cache: &mut CodeCache,
+) -> anyhow::Result<()> {
+    let claim_timeout = chrono::Duration::from_std(CLAIM_TIMEOUT).unwrap();
+    loop {
+        let now = Utc::now();
+        let Some((docid, mut job)) = storage
+            .list_ladder_jobs()
+            .await?
+            .into_iter()
+            .find(|(_, job)| job.claimed.map_or(true, |t| now - t > claim_timeout))
+        else {
+            return Ok(());
+        };
+        job.claimed = Some(now);
+        storage.put_ladder_job(&docid, &job).await?;
+
+        let obj = &job.submission;
+        if let Err(e) = play_submission(storage, compiler, cache, obj).await {
+            log::warn!(
+                "Failed to play ladder matches for {} in scenario {}: {}",
+                obj.username,
+                obj.scenario_name,
+                e
+            );
+        }
+
+        // Keep a resubmission that arrived while this one was playing.
+        match storage.get_ladder_job(&docid).await? {
+            Some(current) if current.submission.timestamp == obj.timestamp => {
+                storage.delete_ladder_job(&docid).await?
+            }
+            _ => {}
+        }
+    }
+}
+
+pub async fn get(
+    State(storage): State<Arc<dyn Storage>>,
+    Path(scenario_name): Path<String>,
+) -> Result<Json<LadderData>, Error> {
+    if !is_ladder_scenario(&scenario_name) {
+        return Err(error(
+            StatusCode::NOT_FOUND,
+            format!("no ladder for scenario {scenario_name}"),
+        ));
+    }
+    let standings = storage
+        .list_ladder(&scenario_name)
+        .await?
+        .into_iter()
+        .map(|x| TournamentCompetitor {
+            shortcode: format!("tournament:{}:{}", x.username, x.scenario_name),
+            username: x.username,
+            rating: x.rating,
+        })
+        .collect();
+    let recent_matches = storage
+        .list_ladder_matches(&scenario_name, RECENT_MATCHES)
+        .await?;
+    Ok(Json(LadderData {
+        scenario_name,
+        standings,
+        recent_matches,
+    }))
+}
+
+pub fn is_ladder_scenario(scenario_name: &str) -> bool {
+    scenario::load_safe(scenario_name)
+        .map(|x| x.is_tournament())
+        .unwrap_or(false)
+}
+
+/// Compiled code keyed by source, so opponents aren't recompiled for every match.
+#[derive(Default)]
+struct CodeCache {
+    map: HashMap<String, Code>,
+}
+
+impl CodeCache {
+    async fn compile(&mut self, compiler: &dyn Compiler, source: &str) -> anyhow::Result<Code> {
+        if let Some(code) = self.map.get(source) {
+            return Ok(code.clone());
+        }
+        let code = compiler.compile(source).await?;
+        if self.map.len() >= MAX_CACHED_CODES {
+            self.map.clear();
+        }
+        self.map.insert(source.to_string(), code.clone());
+        Ok(code)
+    }
+}
+
+fn new_rating(obj: &TournamentSubmission) -> LadderRating {
+    let initial = Glicko2Rating::new();
+    LadderRating {
+        scenario_name: obj.scenario_name.clone(),
+        userid: obj.userid.clone(),
+        username: obj.username.clone(),
+        timestamp: Utc::now(),
+        rating: initial.rating,
+        deviation: initial.deviation,
+        volatility: initial.volatility,
+        wins: 0,
+        losses: 0,
+        draws: 0,
+    }
+}
+
+fn to_glicko2(rating: &LadderRating) -> Glicko2Rating {
+    Glicko2Rating {
+        rating: rating.rating,
+        deviation: rating.deviation,
+        volatility: rating.volatility,
+    }
+}
+
+fn update(rating: &mut LadderRating, new: Glicko2Rating, outcome: Outcomes) {
+    rating.rating = new.rating;
+    rating.deviation = new.deviation;
+    rating.volatility = new.volatility;
+    rating.timestamp = Utc::now();
+    match outcome {
+        Outcomes::WIN => rating.wins += 1,
+        Outcomes::LOSS => rating.losses += 1,
+        Outcomes::DRAW => rating.draws += 1,
+    }
+}
+
+fn reverse(outcome: Outcomes) -> Outcomes {
+    match outcome {
+        Outcomes::WIN => Outcomes::LOSS,
+        Outcomes::LOSS => Outcomes::WIN,
+        Outcomes::DRAW => Outcomes::DRAW,
+    }
+}
+
+/// Runs a single game and returns the outcome for team 0.
+pub fn run_match(scenario_name: &str, seed: u32, codes: &[Code]) -> Outcomes {
+    let mut sim = Simulation::new(scenario_name, seed, codes);
+    while sim.status() == Status::Running && sim.tick() < scenario::MAX_TICKS {
+        sim.step();
+    }
+    match sim.status() {
+        Status::Victory { team: 0 } => Outcomes::WIN,
+        Status::Victory { team: 1 } => Outcomes::LOSS,
+        _ => Outcomes::DRAW,
+    }
+}
+
+/// Returns the seed for the games between a submission and an opponent.
+///
+/// Derived from both document IDs so the games can be reproduced.
+pub fn match_seed(docid: &str, opponent_docid: &str) -> u32 {
+    let digest = Sha256::new()
+        .chain_update(docid)
+        .chain_update([0u8])
+        .chain_update(opponent_docid)
+        .finalize();
+    u32::from_le_bytes(digest[..4].try_into().unwrap())
+}
+
+/// Plays a submission against its nearest-rated opponents.
+async fn play_submission(
+    storage: &dyn Storage,
+    compiler: &dyn Compiler,
+    cache: &mut CodeCache,
+    obj: &TournamentSubmission,
+) -> anyhow::Result<()> {
+    let scenario_name = &obj.scenario_name;
+    let docid = submission_docid(scenario_name, &obj.userid);
+    let mut rating = match storage.get_ladder(&docid).await? {
+        Some(rating) => rating,
+        None => new_rating(obj),
+    };
+    let code = cache.compile(compiler, &obj.code).await?;
+
+    let submissions: HashMap<String, TournamentSubmission> = storage
+        .list_tournament_submissions(scenario_name)
+        .await?
+        .into_iter()
+        .map(|x| (x.userid.clone(), x))
+        .collect();
+    let mut opponents: Vec<LadderRating> = storage
+        .list_ladder(scenario_name)
+        .await?
+        .into_iter()
+        .filter(|x| x.userid != obj.userid && submissions.contains_key(&x.userid))
+        .collect();
+    opponents.sort_by(|a, b| {
+        let da = (a.rating - rating.rating).abs();
+        let db = (b.rating - rating.rating).abs();
+        da.total_cmp(&db)
+    });
+    opponents.truncate(NUM_OPPONENTS);
+
+    let config = Glicko2Config::new();
+    for mut opponent in opponents {
+        let opponent_code = match cache
+            .compile(compiler, &submissions[&opponent.userid].code)
+            .await
+        {
+            Ok(code) => code,
+            Err(e) => {
+                log::warn!(
+                    "Failed to compile ladder opponent {}: {}",
+                    opponent.username,
+                    e
+                );
+                continue;
+            }
+        };
+
+        let opponent_docid = submission_docid(scenario_name, &opponent.userid);
+        let seed = match_seed(&docid, &opponent_docid);
+        for swap in [false, true] {
+            let (codes, usernames) = if swap {
+                (
+                    vec![opponent_code.clone(), code.clone()],
+                    vec![opponent.username.clone(), rating.username.clone()],
+                )
+            } else {
+                (
+                    vec![code.clone(), opponent_code.clone()],
+                    vec![rating.username.clone(), opponent.username.clone()],
+                )
+            };
+            let outcome = {
+                let scenario_name = scenario_name.clone();
+                tokio::task::spawn_blocking(move || run_match(&scenario_name, seed, &codes)).await?
+            };
+            let outcome = if swap { reverse(outcome) } else { outcome };
+
+            let (r0, r1) = glicko2(
+                &to_glicko2(&rating),
+                &to_glicko2(&opponent),
+                &outcome,
+                &config,
+            );
+            update(&mut rating, r0, outcome);
+            update(&mut opponent, r1, reverse(outcome));
+
+            let winner = match outcome {
+                Outcomes::WIN => Some(rating.username.clone()),
+                Outcomes::LOSS => Some(opponent.username.clone()),
+                Outcomes::DRAW => None,
+            };
+            log::info!(
+                "Ladder match in {}: {} vs {} seed {}: winner {:?}",
+                scenario_name,
+                usernames[0],
+                usernames[1],
+                seed,
+                winner
+            );
+            let id = format!("{}.{:016x}", scenario_name, rand::random::<u64>());
+            storage
+                .create_ladder_match(
+                    &id,
+                    &LadderMatch {
+                        scenario_name: scenario_name.clone(),
+                        timestamp: Utc::now(),
+                        seed,
+                        usernames,
+                        winner,
+                    },
+                )
+                .await?;
+        }
+
+        storage.put_ladder(&opponent_docid, &opponent).await?;
+        storage.put_ladder(&docid, &rating).await?;
+    }
+
+    Ok(())
+}
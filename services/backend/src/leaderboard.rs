@@ -1,126 +1,212 @@
-use crate::{discord, error, project_id, Error};
-use axum::extract::Path;
+use crate::verify::Verifier;
+use crate::{discord, error, Error};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
 use axum::Json;
 use bytes::Bytes;
 use chrono::Utc;
-use firestore::*;
-use gcloud_sdk::google::firestore::v1::Document;
-use oort_proto::{LeaderboardData, LeaderboardSubmission, TimeLeaderboardRow};
+use oort_proto::{CodeSizeLeaderboardRow, LeaderboardData, TimeLeaderboardRow};
+use oort_proto::{LeaderboardSubmission, LeaderboardVerification};
+use oort_simulator::scenario;
+use oort_storage::{submission_docid, Storage};
+use std::sync::Arc;
 
 async fn fetch_leaderboard(
-    db: &FirestoreDb,
+    storage: &dyn Storage,
     scenario_name: &str,
 ) -> anyhow::Result<LeaderboardData> {
-    let docs: Vec<Document> = db
-        .query_doc(
-            FirestoreQueryParams::new("leaderboard".into())
-                .with_filter(FirestoreQueryFilter::Composite(
-                    FirestoreQueryFilterComposite::new(vec![FirestoreQueryFilter::Compare(Some(
-                        FirestoreQueryFilterCompare::Equal(
-                            "scenario_name".into(),
-                            scenario_name.into(),
-                        ),
-                    ))]),
-                ))
-                .with_order_by(vec![
-                    FirestoreQueryOrder::new("time".to_owned(), FirestoreQueryDirection::Ascending),
-                    FirestoreQueryOrder::new(
-                        "timestamp".to_owned(),
-                        FirestoreQueryDirection::Ascending,
-                    ),
-                ])
-                .with_limit(10),
-        )
-        .await?;
+    let docs = storage.list_leaderboard(scenario_name, Some(10)).await?;
 
     let mut leaderboard = LeaderboardData::default();
 
-    for doc in &docs {
-        if let Ok(msg) = FirestoreDb::deserialize_doc_to::<LeaderboardSubmission>(doc) {
-            leaderboard.lowest_time.push(TimeLeaderboardRow {
-                userid: msg.userid.clone(),
-                username: Some(msg.username.clone()),
-                time: format!("{:.2}s", msg.time),
-                encrypted_code: oort_code_encryption::encrypt(&msg.code)?,
-            });
-        } else {
-            log::error!("Failed to deserialize doc {}", doc.name);
-        }
+    for (_, msg) in &docs {
+        leaderboard.lowest_time.push(TimeLeaderboardRow {
+            userid: msg.userid.clone(),
+            username: Some(msg.username.clone()),
+            time: format!("{:.2}s", msg.time),
+            encrypted_code: oort_code_encryption::encrypt(&msg.code)?,
+        });
+    }
+
+    let docs = storage
+        .list_code_size_leaderboard(scenario_name, Some(10))
+        .await?;
+    for (_, msg) in &docs {
+        leaderboard.smallest_code.push(CodeSizeLeaderboardRow {
+            userid: msg.userid.clone(),
+            username: Some(msg.username.clone()),
+            code_size: msg.code_size,
+            encrypted_code: oort_code_encryption::encrypt(&msg.code)?,
+        });
     }
 
     Ok(leaderboard)
 }
 
-pub async fn get(Path(scenario_name): Path<String>) -> Result<Json<LeaderboardData>, Error> {
-    let db = FirestoreDb::new(&project_id()).await?;
-    let data: LeaderboardData = fetch_leaderboard(&db, &scenario_name).await?;
+pub async fn get(
+    State(storage): State<Arc<dyn Storage>>,
+    Path(scenario_name): Path<String>,
+) -> Result<Json<LeaderboardData>, Error> {
+    let data: LeaderboardData = fetch_leaderboard(storage.as_ref(), &scenario_name).await?;
     Ok(Json(data))
 }
 
-pub async fn post(payload: Bytes) -> Result<Json<LeaderboardData>, Error> {
-    let db = FirestoreDb::new(&project_id()).await?;
-
+/// Checks a submission and stores it for verification.
+///
+/// Responds with 202 Accepted and the current leaderboard once stored. The
+/// client polls [`get_verification`] to learn whether the time was accepted.
+pub async fn post(
+    State(storage): State<Arc<dyn Storage>>,
+    State(verifier): State<Arc<Verifier>>,
+    payload: Bytes,
+) -> Result<(StatusCode, Json<LeaderboardData>), Error> {
     let payload = match oort_envelope::remove(payload.as_ref()) {
         Some(x) => x,
         None => {
-            return Err(error(
-                axum::http::StatusCode::BAD_REQUEST,
-                "invalid envelope".into(),
-            ));
+            return Err(error(StatusCode::BAD_REQUEST, "invalid envelope".into()));
         }
     };
     let mut obj: LeaderboardSubmission = serde_json::from_slice(&payload)?;
 
     obj.timestamp = Utc::now();
-    let path = format!("{}.{}", obj.scenario_name, obj.userid);
+    if scenario::load_safe(&obj.scenario_name).is_none() {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            format!("unknown scenario {}", obj.scenario_name),
+        ));
+    }
+    if !obj.time.is_finite() || obj.time <= 0.0 {
+        return Err(error(
+            StatusCode::BAD_REQUEST,
+            format!("invalid time {}", obj.time),
+        ));
+    }
+
+    obj.code_size = oort_code_size::calculate(&obj.code);
 
-    let old_leaderboard = fetch_leaderboard(&db, &obj.scenario_name).await?;
+    let leaderboard = fetch_leaderboard(storage.as_ref(), &obj.scenario_name).await?;
 
-    if let Ok(existing_obj) = db
-        .get_obj::<LeaderboardSubmission>("leaderboard", &path)
-        .await
+    if !improves_time(storage.as_ref(), &obj).await?
+        && !improves_code_size(storage.as_ref(), &obj).await?
     {
-        log::debug!("Got existing obj {:?}", existing_obj);
-        if existing_obj.time <= obj.time {
-            log::debug!("Ignoring slower time");
-            return Ok(Json(old_leaderboard));
-        }
+        log::debug!("Ignoring submission that is neither faster nor smaller");
+        return Ok((StatusCode::OK, Json(leaderboard)));
     }
 
-    db.update_obj("leaderboard", &path, &obj, None).await?;
+    if let Err(e) = verifier.submit(obj).await {
+        return Err(error(StatusCode::SERVICE_UNAVAILABLE, e.to_string()));
+    }
 
-    let new_leaderboard = fetch_leaderboard(&db, &obj.scenario_name).await?;
+    Ok((StatusCode::ACCEPTED, Json(leaderboard)))
+}
 
-    let get_rank = |leaderboard: &LeaderboardData, userid: &str| -> Option<usize> {
-        leaderboard
-            .lowest_time
-            .iter()
-            .enumerate()
-            .find(|(_, entry)| entry.userid == userid)
-            .map(|(i, _)| i + 1)
-    };
+pub async fn get_verification(
+    State(verifier): State<Arc<Verifier>>,
+    Path((scenario_name, userid)): Path<(String, String)>,
+) -> Result<Json<LeaderboardVerification>, Error> {
+    match verifier.status(&scenario_name, &userid).await? {
+        Some(status) => Ok(Json(status)),
+        None => Err(error(
+            StatusCode::NOT_FOUND,
+            "no recent submission found".into(),
+        )),
+    }
+}
 
-    let old_rank = get_rank(&old_leaderboard, &obj.userid);
-    let new_rank = get_rank(&new_leaderboard, &obj.userid);
+async fn improves_time(storage: &dyn Storage, obj: &LeaderboardSubmission) -> anyhow::Result<bool> {
+    let path = submission_docid(&obj.scenario_name, &obj.userid);
+    Ok(match storage.get_leaderboard(&path).await? {
+        Some(existing_obj) => obj.time < existing_obj.time,
+        None => true,
+    })
+}
+
+async fn improves_code_size(
+    storage: &dyn Storage,
+    obj: &LeaderboardSubmission,
+) -> anyhow::Result<bool> {
+    let path = submission_docid(&obj.scenario_name, &obj.userid);
+    Ok(match storage.get_code_size_leaderboard(&path).await? {
+        Some(existing_obj) => obj.code_size < existing_obj.code_size,
+        None => true,
+    })
+}
 
-    let rank_improved = match (old_rank, new_rank) {
+fn rank_improved(old_rank: Option<usize>, new_rank: Option<usize>) -> bool {
+    match (old_rank, new_rank) {
         (Some(old_rank), Some(new_rank)) if old_rank > new_rank => true,
         (None, Some(_)) => true,
         _ => false,
+    }
+}
+
+/// Adds a verified submission to each leaderboard where it beats the user's existing entry.
+pub(crate) async fn record(
+    storage: &dyn Storage,
+    obj: &LeaderboardSubmission,
+) -> anyhow::Result<()> {
+    let path = submission_docid(&obj.scenario_name, &obj.userid);
+    let improves_time = improves_time(storage, obj).await?;
+    let improves_code_size = improves_code_size(storage, obj).await?;
+    if !improves_time && !improves_code_size {
+        log::debug!("Ignoring submission that is neither faster nor smaller");
+        return Ok(());
+    }
+
+    let old_leaderboard = fetch_leaderboard(storage, &obj.scenario_name).await?;
+
+    if improves_time {
+        storage.put_leaderboard(&path, obj).await?;
+    }
+    if improves_code_size {
+        storage.put_code_size_leaderboard(&path, obj).await?;
+    }
+
+    let new_leaderboard = fetch_leaderboard(storage, &obj.scenario_name).await?;
+
+    let time_rank = |leaderboard: &LeaderboardData| -> Option<usize> {
+        leaderboard
+            .lowest_time
+            .iter()
+            .position(|entry| entry.userid == obj.userid)
+            .map(|i| i + 1)
+    };
+    let code_size_rank = |leaderboard: &LeaderboardData| -> Option<usize> {
+        leaderboard
+            .smallest_code
+            .iter()
+            .position(|entry| entry.userid == obj.userid)
+            .map(|i| i + 1)
     };
 
-    if rank_improved {
+    if rank_improved(time_rank(&old_leaderboard), time_rank(&new_leaderboard)) {
         discord::send_message(
             discord::Channel::Leaderboard,
             format!(
                 "{} achieved leaderboard rank {} on scenario {} with time {:.2}s",
                 obj.username,
-                new_rank.unwrap(),
+                time_rank(&new_leaderboard).unwrap(),
                 obj.scenario_name,
                 obj.time
             ),
         );
     }
 
-    Ok(Json(new_leaderboard))
+    if rank_improved(
+        code_size_rank(&old_leaderboard),
+        code_size_rank(&new_leaderboard),
+    ) {
+        discord::send_message(
+            discord::Channel::Leaderboard,
+            format!(
+                "{} achieved code size leaderboard rank {} on scenario {} with {} bytes",
+                obj.username,
+                code_size_rank(&new_leaderboard).unwrap(),
+                obj.scenario_name,
+                obj.code_size
+            ),
+        );
+    }
+
+    Ok(())
 }
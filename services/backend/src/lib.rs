@@ -1,16 +1,77 @@
 pub mod discord;
+pub mod ladder;
 pub mod leaderboard;
 pub mod shortcode;
 pub mod telemetry;
 pub mod tournament;
+pub mod verify;
 
+use axum::extract::FromRef;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use axum::Router;
+use ladder::Ladder;
+use oort_storage::Storage;
+use std::sync::Arc;
+use verify::Verifier;
 
 pub fn project_id() -> String {
     std::env::var("PROJECT_ID").expect("missing PROJECT_ID environment variable")
 }
 
+/// Returns the storage to use: `STORAGE` if set (e.g. `local:oort.db`),
+/// otherwise the Firestore project in `PROJECT_ID`.
+pub fn storage_spec() -> String {
+    std::env::var("STORAGE").unwrap_or_else(|_| project_id())
+}
+
+pub fn compiler_url() -> String {
+    std::env::var("COMPILER_URL").unwrap_or_else(|_| "http://localhost:8081".to_string())
+}
+
+#[derive(Clone)]
+pub struct AppState {
+    pub storage: Arc<dyn Storage>,
+    pub verifier: Arc<Verifier>,
+    pub ladder: Arc<Ladder>,
+}
+
+impl FromRef<AppState> for Arc<dyn Storage> {
+    fn from_ref(state: &AppState) -> Self {
+        state.storage.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Verifier> {
+    fn from_ref(state: &AppState) -> Self {
+        state.verifier.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Ladder> {
+    fn from_ref(state: &AppState) -> Self {
+        state.ladder.clone()
+    }
+}
+
+pub fn router(state: AppState) -> Router {
+    use axum::routing::{get, post};
+    Router::new()
+        .route("/shortcode/:id", get(shortcode::get))
+        .route("/shortcode", post(shortcode::post))
+        .route("/telemetry", post(telemetry::post))
+        .route("/tournament/submit", post(tournament::submit))
+        .route("/tournament/results/:id", get(tournament::get_results))
+        .route("/ladder/:scenario_name", get(ladder::get))
+        .route("/leaderboard/:scenario_name", get(leaderboard::get))
+        .route("/leaderboard", post(leaderboard::post))
+        .route(
+            "/leaderboard/:scenario_name/verification/:userid",
+            get(leaderboard::get_verification),
+        )
+        .with_state(state)
+}
+
 pub fn error(status_code: StatusCode, msg: String) -> Error {
     Error {
         status_code,
@@ -18,6 +79,7 @@ pub fn error(status_code: StatusCode, msg: String) -> Error {
     }
 }
 
+#[derive(Debug)]
 pub struct Error {
     status_code: StatusCode,
     err: anyhow::Error,
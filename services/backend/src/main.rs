@@ -1,6 +1,8 @@
-use oort_backend_service::{leaderboard, project_id, shortcode, telemetry, tournament};
+use oort_backend_service::ladder::Ladder;
+use oort_backend_service::verify::{Compiler, CompilerService, Verifier};
+use oort_backend_service::{compiler_url, router, storage_spec, AppState};
+use std::sync::Arc;
 
-use axum::Router;
 use http::Method;
 use tower_http::cors::{Any, CorsLayer};
 
@@ -22,7 +24,15 @@ async fn main() {
     };
 
     log::info!("Starting oort_backend_service");
-    log::info!("Using project ID {}", project_id());
+    let storage_spec = storage_spec();
+    log::info!("Using storage {}", storage_spec);
+    let storage = oort_storage::open(&storage_spec)
+        .await
+        .expect("failed to open storage");
+    log::info!("Using compiler {}", compiler_url());
+    let compiler: Arc<dyn Compiler> = Arc::new(CompilerService::new(&compiler_url()));
+    let verifier = Verifier::new(storage.clone(), compiler.clone());
+    let ladder = Ladder::new(storage.clone(), compiler);
     log::info!(
         "hashed envelope secret: {:?}",
         &oort_envelope::hashed_secret()
@@ -33,19 +43,13 @@ async fn main() {
         .allow_origin(Any)
         .allow_headers(Any);
 
-    let router = {
-        use axum::routing::{get, post};
-        Router::new()
-            .route("/shortcode/:id", get(shortcode::get))
-            .route("/shortcode", post(shortcode::post))
-            .route("/telemetry", post(telemetry::post))
-            .route("/tournament/submit", post(tournament::submit))
-            .route("/tournament/results/:id", get(tournament::get_results))
-            .route("/leaderboard/:scenario_name", get(leaderboard::get))
-            .route("/leaderboard", post(leaderboard::post))
-            .layer(cors)
-            .layer(tower_http::trace::TraceLayer::new_for_http())
-    };
+    let router = router(AppState {
+        storage,
+        verifier: Arc::new(verifier),
+        ladder: Arc::new(ladder),
+    })
+    .layer(cors)
+    .layer(tower_http::trace::TraceLayer::new_for_http());
 
     axum::Server::bind(&format!("0.0.0.0:{port}").parse().unwrap())
         .serve(router.into_make_service())
@@ -1,11 +1,12 @@
-use crate::{project_id, Error};
+use crate::{error, Error};
 use anyhow::bail;
-use axum::extract::{Json, Path};
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
 use chrono::Utc;
-use firestore::*;
-use gcloud_sdk::google::firestore::v1::Document;
-use oort_proto::{LeaderboardSubmission, ShortcodeUpload, TournamentSubmission};
+use oort_proto::ShortcodeUpload;
+use oort_storage::Storage;
 use regex::Regex;
+use std::sync::Arc;
 
 #[derive(Clone, Debug)]
 enum Shortcode {
@@ -48,101 +49,35 @@ fn parse_id(id: &str) -> anyhow::Result<Shortcode> {
     }
 }
 
-async fn fetch_leaderboard(
-    db: &FirestoreDb,
-    scenario_name: &str,
-    username: &str,
-) -> anyhow::Result<String> {
-    let docs: Vec<Document> = db
-        .query_doc(
-            FirestoreQueryParams::new("leaderboard".into())
-                .with_filter(FirestoreQueryFilter::Composite(
-                    FirestoreQueryFilterComposite::new(vec![
-                        FirestoreQueryFilter::Compare(Some(FirestoreQueryFilterCompare::Equal(
-                            "scenario_name".into(),
-                            scenario_name.into(),
-                        ))),
-                        FirestoreQueryFilter::Compare(Some(FirestoreQueryFilterCompare::Equal(
-                            "username".into(),
-                            username.into(),
-                        ))),
-                    ]),
-                ))
-                .with_order_by(vec![
-                    FirestoreQueryOrder::new("time".to_owned(), FirestoreQueryDirection::Ascending),
-                    FirestoreQueryOrder::new(
-                        "timestamp".to_owned(),
-                        FirestoreQueryDirection::Ascending,
-                    ),
-                ])
-                .with_limit(1),
-        )
-        .await?;
-
-    for doc in &docs {
-        if let Ok(msg) = FirestoreDb::deserialize_doc_to::<LeaderboardSubmission>(doc) {
-            return oort_code_encryption::encrypt(&msg.code);
-        }
-    }
-
-    bail!("no matching leaderboard entry found");
-}
-
-async fn fetch_tournament(
-    db: &FirestoreDb,
-    scenario_name: &str,
-    username: &str,
-) -> anyhow::Result<String> {
-    let docs: Vec<Document> = db
-        .query_doc(
-            FirestoreQueryParams::new("tournament".into())
-                .with_filter(FirestoreQueryFilter::Composite(
-                    FirestoreQueryFilterComposite::new(vec![
-                        FirestoreQueryFilter::Compare(Some(FirestoreQueryFilterCompare::Equal(
-                            "scenario_name".into(),
-                            scenario_name.into(),
-                        ))),
-                        FirestoreQueryFilter::Compare(Some(FirestoreQueryFilterCompare::Equal(
-                            "username".into(),
-                            username.into(),
-                        ))),
-                    ]),
-                ))
-                .with_order_by(vec![FirestoreQueryOrder::new(
-                    "timestamp".to_owned(),
-                    FirestoreQueryDirection::Ascending,
-                )])
-                .with_limit(1),
-        )
-        .await?;
-
-    for doc in &docs {
-        if let Ok(msg) = FirestoreDb::deserialize_doc_to::<TournamentSubmission>(doc) {
-            return oort_code_encryption::encrypt(&msg.code);
-        }
-    }
-
-    bail!("no matching tournament entry found");
-}
-
-pub async fn get(Path(id): Path<String>) -> Result<String, Error> {
-    let db = FirestoreDb::new(&project_id()).await?;
+pub async fn get(
+    State(storage): State<Arc<dyn Storage>>,
+    Path(id): Path<String>,
+) -> Result<String, Error> {
     let code = match parse_id(&id)? {
         Shortcode::Leaderboard {
             username,
             scenario_name,
-        } => fetch_leaderboard(&db, &scenario_name, &username).await?,
+        } => storage
+            .find_leaderboard_by_username(&scenario_name, &username)
+            .await?
+            .map(|obj| obj.code),
         Shortcode::Tournament {
             username,
             scenario_name,
-        } => fetch_tournament(&db, &scenario_name, &username).await?,
-        Shortcode::Uploaded { docid } => {
-            let obj = db.get_obj::<ShortcodeUpload>("shortcode", &docid).await?;
-            oort_code_encryption::encrypt(&obj.code)?
-        }
+        } => storage
+            .find_tournament_by_username(&scenario_name, &username)
+            .await?
+            .map(|obj| obj.code),
+        Shortcode::Uploaded { docid } => storage.get_shortcode(&docid).await?.map(|obj| obj.code),
     };
 
-    Ok(code)
+    match code {
+        Some(code) => Ok(oort_code_encryption::encrypt(&code)?),
+        None => Err(error(
+            StatusCode::NOT_FOUND,
+            format!("shortcode {id} not found"),
+        )),
+    }
 }
 
 fn generate_docid() -> String {
@@ -160,10 +95,12 @@ fn generate_docid() -> String {
         .collect()
 }
 
-pub async fn post(Json(mut obj): Json<ShortcodeUpload>) -> Result<String, Error> {
-    let db = FirestoreDb::new(&project_id()).await?;
+pub async fn post(
+    State(storage): State<Arc<dyn Storage>>,
+    Json(mut obj): Json<ShortcodeUpload>,
+) -> Result<String, Error> {
     obj.timestamp = Utc::now();
     let docid = generate_docid();
-    db.create_obj("shortcode", &docid, &obj).await?;
+    storage.create_shortcode(&docid, &obj).await?;
     Ok(docid)
 }
@@ -1,8 +1,9 @@
-use crate::{discord, project_id, Error};
-use axum::extract::Json;
+use crate::{discord, Error};
+use axum::extract::{Json, State};
 use chrono::prelude::*;
-use firestore::*;
 use oort_proto::{Telemetry, TelemetryMsg};
+use oort_storage::Storage;
+use std::sync::Arc;
 
 fn generate_docid() -> String {
     use rand::Rng;
@@ -19,12 +20,14 @@ fn generate_docid() -> String {
         .collect()
 }
 
-pub async fn post(Json(mut obj): Json<TelemetryMsg>) -> Result<(), Error> {
-    let db = FirestoreDb::new(&project_id()).await?;
+pub async fn post(
+    State(storage): State<Arc<dyn Storage>>,
+    Json(mut obj): Json<TelemetryMsg>,
+) -> Result<(), Error> {
     obj.timestamp = Utc::now();
     log::debug!("Got request obj {:?}", obj);
     let docid = generate_docid();
-    db.create_obj("telemetry", &docid, &obj).await?;
+    storage.create_telemetry(&docid, &obj).await?;
     match obj.payload {
         Telemetry::Crash { msg } => {
             discord::send_message(
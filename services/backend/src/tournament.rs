@@ -1,23 +1,36 @@
-use crate::{project_id, Error};
-use axum::extract::{Json, Path};
+use crate::ladder::{self, Ladder};
+use crate::{error, Error};
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
 use chrono::Utc;
-use firestore::*;
 use oort_proto::{TournamentResults, TournamentSubmission};
+use oort_storage::{submission_docid, Storage};
+use std::sync::Arc;
 
-pub async fn submit(Json(mut obj): Json<TournamentSubmission>) -> Result<String, Error> {
-    let db = FirestoreDb::new(&project_id()).await?;
+/// Stores a tournament submission and adds it to the ladder.
+pub async fn submit(
+    State(storage): State<Arc<dyn Storage>>,
+    State(ladder): State<Arc<Ladder>>,
+    Json(mut obj): Json<TournamentSubmission>,
+) -> Result<String, Error> {
     obj.timestamp = Utc::now();
-    let docid = format!("{}.{}", obj.scenario_name, obj.userid);
-    db.update_obj("tournament", &docid, &obj, None).await?;
+    let docid = submission_docid(&obj.scenario_name, &obj.userid);
+    storage.put_tournament_submission(&docid, &obj).await?;
+    if ladder::is_ladder_scenario(&obj.scenario_name) {
+        ladder.submit(obj).await?;
+    }
     Ok(docid)
 }
 
 pub async fn get_results(
+    State(storage): State<Arc<dyn Storage>>,
     Path(id): Path<String>,
 ) -> Result<axum::response::Json<TournamentResults>, Error> {
-    let db = FirestoreDb::new(&project_id()).await?;
-    let tournament_results = db
-        .get_obj::<TournamentResults>("tournament_results", &id)
-        .await?;
-    Ok(Json(tournament_results))
+    match storage.get_tournament_results(&id).await? {
+        Some(tournament_results) => Ok(Json(tournament_results)),
+        None => Err(error(
+            StatusCode::NOT_FOUND,
+            format!("tournament {id} not found"),
+        )),
+    }
 }
@@ -0,0 +1,242 @@
+//! Re-simulates leaderboard submissions before accepting them.
+//!
+//! The client reports its own time, so the backend compiles the submitted code,
+//! runs the scenario over the same seeds the client uses, and only records the
+//! entry if every seed is a victory and the average time matches. The code size
+//! is also recomputed here rather than trusted.
+use crate::leaderboard;
+use async_trait::async_trait;
+use chrono::Utc;
+use oort_proto::{LeaderboardSubmission, LeaderboardVerification, LeaderboardVerificationJob};
+use oort_simulator::scenario::{self, Status};
+use oort_simulator::simulation::{Code, Simulation};
+use oort_storage::{submission_docid, Storage};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+
+/// Matches the number of background simulations run by the frontend.
+pub const NUM_SEEDS: u32 = 10;
+
+/// Largest accepted difference between the submitted and verified time, in seconds.
+pub const TIME_TOLERANCE: f64 = 0.01;
+
+/// Largest number of submissions waiting for verification across all instances.
+const MAX_PENDING: usize = 16;
+
+/// How long a claimed job can run before another instance retries it.
+const CLAIM_TIMEOUT: Duration = Duration::from_secs(600);
+
+/// How often each instance checks for jobs it wasn't notified about.
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+#[async_trait]
+pub trait Compiler: Send + Sync {
+    async fn compile(&self, code: &str) -> anyhow::Result<Code>;
+}
+
+/// Compiles code with the compiler service.
+pub struct CompilerService {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl CompilerService {
+    pub fn new(url: &str) -> Self {
+        Self {
+            url: url.to_string(),
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+#[async_trait]
+impl Compiler for CompilerService {
+    async fn compile(&self, code: &str) -> anyhow::Result<Code> {
+        let response = self
+            .client
+            .post(format!("{}/compile", self.url))
+            .body(code.to_string())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("{}", response.text().await?);
+        }
+        Ok(Code::Wasm(response.bytes().await?.to_vec()))
+    }
+}
+
+/// Runs the scenario on every seed and returns the average time.
+pub fn simulate(scenario_name: &str, code: Code) -> Result<f64, String> {
+    let mut total_time = 0.0;
+    for seed in 0..NUM_SEEDS {
+        let scenario = scenario::load(scenario_name);
+        let mut codes = scenario.initial_code();
+        codes[0] = code.clone();
+        let mut sim = Simulation::new(scenario_name, seed, &codes);
+        while sim.status() == Status::Running && sim.tick() < scenario::MAX_TICKS {
+            sim.step();
+        }
+        match sim.status() {
+            Status::Victory { team: 0 } => total_time += sim.score_time(),
+            _ => return Err(format!("Simulation failed on seed {seed}")),
+        }
+    }
+    Ok(total_time / NUM_SEEDS as f64)
+}
+
+/// Returns the verified time for a submission or the reason it was rejected.
+pub async fn verify(compiler: &dyn Compiler, obj: &LeaderboardSubmission) -> Result<f64, String> {
+    let code = compiler
+        .compile(&obj.code)
+        .await
+        .map_err(|e| format!("Compilation failed: {e}"))?;
+    let scenario_name = obj.scenario_name.clone();
+    let time = tokio::task::spawn_blocking(move || simulate(&scenario_name, code))
+        .await
+        .map_err(|e| format!("Simulation crashed: {e}"))??;
+    if (time - obj.time).abs() > TIME_TOLERANCE {
+        return Err(format!(
+            "Submitted time {:.2}s does not match verified time {:.2}s",
+            obj.time, time
+        ));
+    }
+    Ok(time)
+}
+
+/// Verifies a submission and records it on the leaderboards if it is accepted.
+async fn verify_and_record(
+    storage: &dyn Storage,
+    compiler: &dyn Compiler,
+    mut obj: LeaderboardSubmission,
+) -> LeaderboardVerification {
+    match verify(compiler, &obj).await {
+        Ok(time) => {
+            obj.time = time;
+            obj.code_size = oort_code_size::calculate(&obj.code);
+            match leaderboard::record(storage, &obj).await {
+                Ok(()) => LeaderboardVerification::Accepted {
+                    time,
+                    code_size: obj.code_size,
+                },
+                Err(e) => {
+                    log::error!("Failed to record leaderboard entry: {}", e);
+                    LeaderboardVerification::Rejected {
+                        reason: "Failed to update leaderboard".to_string(),
+                    }
+                }
+            }
+        }
+        Err(reason) => {
+            log::info!(
+                "Rejected leaderboard submission from {} for scenario {}: {}",
+                obj.username,
+                obj.scenario_name,
+                reason
+            );
+            LeaderboardVerification::Rejected { reason }
+        }
+    }
+}
+
+/// Verifies pending jobs until none are left that another instance hasn't claimed.
+///
+/// Claims aren't transactional, so two instances can occasionally verify the
+/// same submission. That only repeats work: recording is idempotent.
+async fn run_pending(storage: &dyn Storage, compiler: &dyn Compiler) -> anyhow::Result<()> {
+    loop {
+        let now = Utc::now();
+        let Some((docid, mut job)) = storage
+            .list_pending_leaderboard_verifications()
+            .await?
+            .into_iter()
+            .find(|(_, job)| job.claimed.map_or(true, |t| now - t > claim_timeout()))
+        else {
+            return Ok(());
+        };
+        job.claimed = Some(now);
+        storage.put_leaderboard_verification(&docid, &job).await?;
+
+        let status = verify_and_record(storage, compiler, job.submission.clone()).await;
+
+        // Don't overwrite a resubmission that arrived while this one was running.
+        match storage.get_leaderboard_verification(&docid).await? {
+            Some(current) if current.submission.timestamp == job.submission.timestamp => {}
+            _ => continue,
+        }
+        job.status = status;
+        storage.put_leaderboard_verification(&docid, &job).await?;
+    }
+}
+
+fn claim_timeout() -> chrono::Duration {
+    chrono::Duration::from_std(CLAIM_TIMEOUT).unwrap()
+}
+
+/// Verifies submissions one at a time in a background task.
+///
+/// Jobs and their statuses are kept in storage, so any instance can report
+/// the status and jobs survive restarts. Each instance's worker wakes when a
+/// submission arrives and also polls, which picks up jobs left behind by an
+/// instance that was shut down.
+pub struct Verifier {
+    storage: Arc<dyn Storage>,
+    notify: Arc<Notify>,
+}
+
+impl Verifier {
+    pub fn new(storage: Arc<dyn Storage>, compiler: Arc<dyn Compiler>) -> Self {
+        let notify = Arc::new(Notify::new());
+        {
+            let storage = storage.clone();
+            let notify = notify.clone();
+            tokio::spawn(async move {
+                loop {
+                    if let Err(e) = run_pending(storage.as_ref(), compiler.as_ref()).await {
+                        log::error!("Failed to verify leaderboard submissions: {}", e);
+                    }
+                    tokio::select! {
+                        _ = notify.notified() => {}
+                        _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                    }
+                }
+            });
+        }
+        Self { storage, notify }
+    }
+
+    /// Stores a submission for verification, failing if too many are waiting.
+    pub async fn submit(&self, obj: LeaderboardSubmission) -> anyhow::Result<()> {
+        let pending = self
+            .storage
+            .list_pending_leaderboard_verifications()
+            .await?;
+        if pending.len() >= MAX_PENDING {
+            anyhow::bail!("Too many leaderboard submissions waiting for verification");
+        }
+        let docid = submission_docid(&obj.scenario_name, &obj.userid);
+        let job = LeaderboardVerificationJob {
+            submission: obj,
+            status: LeaderboardVerification::Pending,
+            claimed: None,
+        };
+        self.storage
+            .put_leaderboard_verification(&docid, &job)
+            .await?;
+        self.notify.notify_one();
+        Ok(())
+    }
+
+    /// Returns the status of the user's most recent submission.
+    pub async fn status(
+        &self,
+        scenario_name: &str,
+        userid: &str,
+    ) -> anyhow::Result<Option<LeaderboardVerification>> {
+        Ok(self
+            .storage
+            .get_leaderboard_verification(&submission_docid(scenario_name, userid))
+            .await?
+            .map(|job| job.status))
+    }
+}
@@ -0,0 +1,392 @@
+use async_trait::async_trait;
+use axum::extract::{Json, Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use chrono::Utc;
+use oort_backend_service::ladder::{self, Ladder};
+use oort_backend_service::verify::{self, Compiler, Verifier};
+use oort_backend_service::{leaderboard, shortcode, tournament};
+use oort_proto::{
+    LeaderboardSubmission, LeaderboardVerification, ShortcodeUpload, TournamentSubmission,
+};
+use oort_simulator::scenario;
+use oort_simulator::simulation::Code;
+use oort_storage::{LocalStorage, Storage};
+use std::sync::Arc;
+
+const SCENARIO: &str = "tutorial_guns";
+
+fn storage() -> Arc<dyn Storage> {
+    Arc::new(LocalStorage::in_memory().unwrap())
+}
+
+/// Compiles "solution" to the scenario's reference solution, "reference" and
+/// "empty" to the builtin AIs, and rejects anything else.
+struct FakeCompiler;
+
+#[async_trait]
+impl Compiler for FakeCompiler {
+    async fn compile(&self, code: &str) -> anyhow::Result<Code> {
+        match code {
+            "solution" => Ok(scenario::load(SCENARIO).solution()),
+            "reference" => Ok(scenario::reference_ai()),
+            "empty" => Ok(scenario::empty_ai()),
+            _ => anyhow::bail!("expected item"),
+        }
+    }
+}
+
+fn verifier(storage: &Arc<dyn Storage>) -> Arc<Verifier> {
+    Arc::new(Verifier::new(storage.clone(), Arc::new(FakeCompiler)))
+}
+
+fn ladder(storage: &Arc<dyn Storage>) -> Arc<Ladder> {
+    Arc::new(Ladder::new(storage.clone(), Arc::new(FakeCompiler)))
+}
+
+fn solution_time() -> f64 {
+    verify::simulate(SCENARIO, scenario::load(SCENARIO).solution()).unwrap()
+}
+
+fn decrypt(code: &str) -> String {
+    oort_code_encryption::decrypt(code).unwrap()
+}
+
+fn submission(username: &str, time: f64, code: &str) -> LeaderboardSubmission {
+    LeaderboardSubmission {
+        scenario_name: SCENARIO.to_string(),
+        userid: format!("id-{username}"),
+        username: username.to_string(),
+        timestamp: Utc::now(),
+        time,
+        code_size: 0,
+        code: code.to_string(),
+    }
+}
+
+/// Posts a submission and waits for it to be verified.
+async fn submit_leaderboard(
+    storage: &Arc<dyn Storage>,
+    verifier: &Arc<Verifier>,
+    obj: LeaderboardSubmission,
+) -> LeaderboardVerification {
+    let payload = oort_envelope::add(&serde_json::to_vec(&obj).unwrap());
+    let (status, _) = leaderboard::post(
+        State(storage.clone()),
+        State(verifier.clone()),
+        payload.into(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(status, StatusCode::ACCEPTED);
+    wait_for_verification(verifier, &obj).await
+}
+
+async fn wait_for_verification(
+    verifier: &Arc<Verifier>,
+    obj: &LeaderboardSubmission,
+) -> LeaderboardVerification {
+    loop {
+        let Json(verification) = leaderboard::get_verification(
+            State(verifier.clone()),
+            Path((obj.scenario_name.clone(), obj.userid.clone())),
+        )
+        .await
+        .unwrap();
+        if verification != LeaderboardVerification::Pending {
+            return verification;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+}
+
+async fn leaderboard_times(storage: &Arc<dyn Storage>) -> Vec<(String, String)> {
+    let Json(data) = leaderboard::get(State(storage.clone()), Path(SCENARIO.to_string()))
+        .await
+        .unwrap();
+    data.lowest_time
+        .into_iter()
+        .map(|row| (row.username.unwrap(), row.time))
+        .collect()
+}
+
+async fn leaderboard_code_sizes(storage: &Arc<dyn Storage>) -> Vec<(String, usize)> {
+    let Json(data) = leaderboard::get(State(storage.clone()), Path(SCENARIO.to_string()))
+        .await
+        .unwrap();
+    data.smallest_code
+        .into_iter()
+        .map(|row| (row.username.unwrap(), row.code_size))
+        .collect()
+}
+
+#[tokio::test]
+async fn test_leaderboard() {
+    let storage = storage();
+    let verifier = verifier(&storage);
+    let time = solution_time();
+
+    assert_eq!(
+        submit_leaderboard(&storage, &verifier, submission("alice", time, "solution")).await,
+        LeaderboardVerification::Accepted {
+            time,
+            code_size: oort_code_size::calculate("solution"),
+        }
+    );
+    assert_eq!(
+        leaderboard_times(&storage).await,
+        vec![("alice".to_string(), format!("{time:.2}s"))]
+    );
+    assert_eq!(
+        leaderboard_code_sizes(&storage).await,
+        vec![("alice".to_string(), oort_code_size::calculate("solution"))]
+    );
+
+    // Another instance sharing the storage reports the same status.
+    let Json(verification) = leaderboard::get_verification(
+        State(verifier(&storage)),
+        Path((SCENARIO.to_string(), "id-alice".to_string())),
+    )
+    .await
+    .unwrap();
+    assert!(matches!(
+        verification,
+        LeaderboardVerification::Accepted { .. }
+    ));
+
+    let code = shortcode::get(
+        State(storage.clone()),
+        Path(format!("leaderboard:alice:{SCENARIO}")),
+    )
+    .await
+    .unwrap();
+    assert_eq!(decrypt(&code), "solution");
+}
+
+#[tokio::test]
+async fn test_leaderboard_rejected() {
+    let storage = storage();
+    let verifier = verifier(&storage);
+    let time = solution_time();
+
+    match submit_leaderboard(
+        &storage,
+        &verifier,
+        submission("alice", time / 2.0, "solution"),
+    )
+    .await
+    {
+        LeaderboardVerification::Rejected { reason } => {
+            assert!(reason.contains("does not match"), "{reason}")
+        }
+        status => panic!("unexpected status {status:?}"),
+    }
+    match submit_leaderboard(&storage, &verifier, submission("bob", time, "garbage")).await {
+        LeaderboardVerification::Rejected { reason } => {
+            assert!(reason.contains("Compilation failed"), "{reason}")
+        }
+        status => panic!("unexpected status {status:?}"),
+    }
+    assert!(leaderboard_times(&storage).await.is_empty());
+    assert!(leaderboard_code_sizes(&storage).await.is_empty());
+}
+
+#[tokio::test]
+async fn test_leaderboard_ignores_slower_time() {
+    let storage = storage();
+    let verifier = verifier(&storage);
+    let mut obj = submission("alice", 1.0, "solution");
+    obj.code_size = oort_code_size::calculate(&obj.code);
+    let docid = oort_storage::submission_docid(SCENARIO, &obj.userid);
+    storage.put_leaderboard(&docid, &obj).await.unwrap();
+    storage
+        .put_code_size_leaderboard(&docid, &obj)
+        .await
+        .unwrap();
+
+    let payload =
+        oort_envelope::add(&serde_json::to_vec(&submission("alice", 2.0, "solution")).unwrap());
+    let (status, Json(data)) =
+        leaderboard::post(State(storage.clone()), State(verifier), payload.into())
+            .await
+            .unwrap();
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(data.lowest_time[0].time, "1.00s");
+    assert_eq!(data.smallest_code[0].code_size, obj.code_size);
+}
+
+#[tokio::test]
+async fn test_leaderboard_slower_time() {
+    let storage = storage();
+    let verifier = verifier(&storage);
+    let time = solution_time();
+    let mut obj = submission("alice", time / 2.0, "solution");
+    obj.code_size = oort_code_size::calculate(&obj.code);
+    let docid = oort_storage::submission_docid(SCENARIO, &obj.userid);
+    storage.put_leaderboard(&docid, &obj).await.unwrap();
+    storage
+        .put_code_size_leaderboard(&docid, &obj)
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        submit_leaderboard(&storage, &verifier, submission("bob", time, "solution")).await,
+        LeaderboardVerification::Accepted { .. }
+    ));
+
+    // A slower time doesn't replace the existing entry, even once verified.
+    let obj = submission("alice", time, "solution");
+    verifier.submit(obj.clone()).await.unwrap();
+    assert!(matches!(
+        wait_for_verification(&verifier, &obj).await,
+        LeaderboardVerification::Accepted { .. }
+    ));
+    assert_eq!(
+        leaderboard_times(&storage).await,
+        vec![
+            ("alice".to_string(), format!("{:.2}s", time / 2.0)),
+            ("bob".to_string(), format!("{time:.2}s"))
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_leaderboard_smaller_code() {
+    let storage = storage();
+    let verifier = verifier(&storage);
+    let time = solution_time();
+    let mut obj = submission("alice", time / 2.0, "solution");
+    obj.code_size = oort_code_size::calculate(&obj.code) + 100;
+    let docid = oort_storage::submission_docid(SCENARIO, &obj.userid);
+    storage.put_leaderboard(&docid, &obj).await.unwrap();
+    storage
+        .put_code_size_leaderboard(&docid, &obj)
+        .await
+        .unwrap();
+
+    // Slower than the existing entry, but smaller.
+    assert_eq!(
+        submit_leaderboard(&storage, &verifier, submission("alice", time, "solution")).await,
+        LeaderboardVerification::Accepted {
+            time,
+            code_size: oort_code_size::calculate("solution"),
+        }
+    );
+    assert_eq!(
+        leaderboard_times(&storage).await,
+        vec![("alice".to_string(), format!("{:.2}s", time / 2.0))]
+    );
+    assert_eq!(
+        leaderboard_code_sizes(&storage).await,
+        vec![("alice".to_string(), oort_code_size::calculate("solution"))]
+    );
+}
+
+#[tokio::test]
+async fn test_invalid_envelope() {
+    let storage = storage();
+    let verifier = verifier(&storage);
+    let err = leaderboard::post(State(storage), State(verifier), b"garbage".to_vec().into())
+        .await
+        .unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_shortcode() {
+    let storage = storage();
+    let obj = ShortcodeUpload {
+        userid: "id".to_string(),
+        username: "alice".to_string(),
+        timestamp: Utc::now(),
+        code: "// uploaded".to_string(),
+    };
+    let docid = shortcode::post(State(storage.clone()), Json(obj))
+        .await
+        .unwrap();
+    let code = shortcode::get(State(storage.clone()), Path(docid))
+        .await
+        .unwrap();
+    assert_eq!(decrypt(&code), "// uploaded");
+
+    let err = shortcode::get(State(storage.clone()), Path("missing".to_string()))
+        .await
+        .unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_tournament() {
+    let storage = storage();
+    let obj = TournamentSubmission {
+        scenario_name: "fighter_duel".to_string(),
+        userid: "id".to_string(),
+        username: "alice".to_string(),
+        timestamp: Utc::now(),
+        code: "// tournament".to_string(),
+    };
+    let docid = tournament::submit(State(storage.clone()), State(ladder(&storage)), Json(obj))
+        .await
+        .unwrap();
+    assert_eq!(docid, "fighter_duel.id");
+
+    let code = shortcode::get(
+        State(storage.clone()),
+        Path("tournament:alice:fighter_duel".to_string()),
+    )
+    .await
+    .unwrap();
+    assert_eq!(decrypt(&code), "// tournament");
+
+    let err = tournament::get_results(State(storage.clone()), Path("missing".to_string()))
+        .await
+        .unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_ladder() {
+    let storage = storage();
+    let ladder = ladder(&storage);
+    let submit = |username: &str, code: &str| TournamentSubmission {
+        scenario_name: "primitive_duel".to_string(),
+        userid: format!("id-{username}"),
+        username: username.to_string(),
+        timestamp: Utc::now(),
+        code: code.to_string(),
+    };
+
+    for obj in [submit("alice", "empty"), submit("bob", "reference")] {
+        let docid = oort_storage::submission_docid(&obj.scenario_name, &obj.userid);
+        tournament::submit(State(storage.clone()), State(ladder.clone()), Json(obj))
+            .await
+            .unwrap();
+        // The rating is written before any matches are played.
+        assert!(storage.get_ladder(&docid).await.unwrap().is_some());
+        while !storage.list_ladder_jobs().await.unwrap().is_empty() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+
+    // Bob plays Alice once on each side.
+    let Json(data) = ladder::get(State(storage.clone()), Path("primitive_duel".to_string()))
+        .await
+        .unwrap();
+    let usernames: Vec<_> = data.standings.iter().map(|x| x.username.as_str()).collect();
+    assert_eq!(usernames, vec!["bob", "alice"]);
+    assert!(data.standings[0].rating > data.standings[1].rating);
+    assert_eq!(data.standings[0].shortcode, "tournament:bob:primitive_duel");
+    assert_eq!(data.recent_matches.len(), 2);
+    for game in &data.recent_matches {
+        assert_eq!(game.winner.as_deref(), Some("bob"));
+        assert_eq!(
+            game.seed,
+            ladder::match_seed("primitive_duel.id-bob", "primitive_duel.id-alice")
+        );
+    }
+
+    let err = ladder::get(State(storage.clone()), Path(SCENARIO.to_string()))
+        .await
+        .unwrap_err();
+    assert_eq!(err.into_response().status(), StatusCode::NOT_FOUND);
+}
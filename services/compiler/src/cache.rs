@@ -0,0 +1,424 @@
+//! Content-addressed cache of compiler output.
+//!
+//! Entries are keyed by a SHA-256 hash of the normalized source code, the compile
+//! mode, the artifact kind and a version string covering the toolchain and
+//! oort itself, so upgrading either never serves stale output. Recently used
+//! entries are kept in memory and optionally in a size-limited directory on
+//! disk, which survives restarts.
+use bytes::Bytes;
+use oort_compiler::Mode;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tempfile::NamedTempFile;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Artifact {
+    /// WebAssembly from the compiler.
+    Wasm,
+    /// Native code from `oort_simulator::vm::precompile`.
+    Precompiled,
+}
+
+impl Artifact {
+    fn extension(self) -> &'static str {
+        match self {
+            Artifact::Wasm => "wasm",
+            Artifact::Precompiled => "precompiled",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Key {
+    hash: [u8; 32],
+    artifact: Artifact,
+}
+
+impl Key {
+    fn filename(&self) -> String {
+        let hex: String = self.hash.iter().map(|b| format!("{b:02x}")).collect();
+        format!("{}.{}", hex, self.artifact.extension())
+    }
+}
+
+#[derive(Default, Debug)]
+pub struct Stats {
+    pub memory_hits: AtomicU64,
+    pub disk_hits: AtomicU64,
+    pub misses: AtomicU64,
+}
+
+pub struct Cache {
+    version: String,
+    memory: Mutex<MemoryCache>,
+    disk: Option<Mutex<DiskCache>>,
+    stats: Stats,
+}
+
+impl Cache {
+    /// Creates an in-memory cache holding up to `memory_limit` bytes.
+    ///
+    /// `version` should identify everything besides the source code that
+    /// affects compiler output.
+    pub fn new(version: &str, memory_limit: usize) -> Self {
+        Self {
+            version: version.to_string(),
+            memory: Mutex::new(MemoryCache {
+                entries: HashMap::new(),
+                size: 0,
+                limit: memory_limit,
+                clock: 0,
+            }),
+            disk: None,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Also stores entries in `dir`, evicting the least recently used files
+    /// when they exceed `limit` bytes.
+    pub fn with_disk(mut self, dir: &Path, limit: u64) -> std::io::Result<Self> {
+        self.disk = Some(Mutex::new(DiskCache::open(dir, limit)?));
+        Ok(self)
+    }
+
+    pub fn key(&self, code: &str, mode: Mode, artifact: Artifact) -> Key {
+        let mut hasher = Sha256::new();
+        // Length prefixes keep the boundaries between parts unambiguous.
+        for part in [
+            self.version.as_str(),
+            &format!("{mode:?}"),
+            artifact.extension(),
+            &normalize(code),
+        ] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+        Key {
+            hash: hasher.finalize().into(),
+            artifact,
+        }
+    }
+
+    pub async fn get(&self, key: &Key) -> Option<Bytes> {
+        if let Some(value) = self.memory.lock().unwrap().get(key) {
+            self.stats.memory_hits.fetch_add(1, Ordering::Relaxed);
+            return Some(value);
+        }
+        if let Some(disk) = self.disk.as_ref() {
+            let filename = key.filename();
+            let path = disk.lock().unwrap().touch(&filename);
+            if let Some(path) = path {
+                match blocking(move || std::fs::read(path)).await {
+                    Ok(value) => {
+                        let value = Bytes::from(value);
+                        self.stats.disk_hits.fetch_add(1, Ordering::Relaxed);
+                        self.memory.lock().unwrap().insert(*key, value.clone());
+                        return Some(value);
+                    }
+                    Err(e) => {
+                        log::warn!("Failed to read {} from cache: {}", filename, e);
+                        // Forget the entry so it is rewritten instead of failing again.
+                        disk.lock().unwrap().remove(&filename);
+                    }
+                }
+            }
+        }
+        self.stats.misses.fetch_add(1, Ordering::Relaxed);
+        None
+    }
+
+    pub async fn insert(&self, key: Key, value: Bytes) {
+        if let Some(disk) = self.disk.as_ref() {
+            let filename = key.filename();
+            let path = disk.lock().unwrap().dir.join(&filename);
+            let contents = value.clone();
+            match blocking(move || write_atomically(&path, &contents)).await {
+                Ok(()) => {
+                    let evicted = disk.lock().unwrap().record(filename, value.len() as u64);
+                    if let Err(e) = blocking(move || remove_files(&evicted)).await {
+                        log::warn!("Failed to evict from cache: {}", e);
+                    }
+                }
+                Err(e) => log::warn!("Failed to write {} to cache: {}", filename, e),
+            }
+        }
+        self.memory.lock().unwrap().insert(key, value);
+    }
+
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    /// Renders hit counts and sizes as one "name value" pair per line.
+    pub fn render_stats(&self) -> String {
+        let (memory_entries, memory_bytes) = {
+            let memory = self.memory.lock().unwrap();
+            (memory.entries.len(), memory.size)
+        };
+        let (disk_entries, disk_bytes) = match self.disk.as_ref() {
+            Some(disk) => {
+                let disk = disk.lock().unwrap();
+                (disk.entries.len(), disk.size)
+            }
+            None => (0, 0),
+        };
+        format!(
+            "memory_hits {}\ndisk_hits {}\nmisses {}\nmemory_entries {}\nmemory_bytes {}\ndisk_entries {}\ndisk_bytes {}\n",
+            self.stats.memory_hits.load(Ordering::Relaxed),
+            self.stats.disk_hits.load(Ordering::Relaxed),
+            self.stats.misses.load(Ordering::Relaxed),
+            memory_entries,
+            memory_bytes,
+            disk_entries,
+            disk_bytes,
+        )
+    }
+}
+
+/// Runs blocking file I/O off the async runtime.
+async fn blocking<T, F>(f: F) -> std::io::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> std::io::Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await?
+}
+
+/// Writes to a temporary file first so readers never see a partial entry.
+fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut tmpfile = NamedTempFile::new_in(path.parent().unwrap())?;
+    tmpfile.write_all(contents)?;
+    tmpfile.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+fn remove_files(paths: &[PathBuf]) -> std::io::Result<()> {
+    for path in paths {
+        std::fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Removes differences that can't change the compiled output: a byte order
+/// mark, Windows line endings and trailing whitespace at the end of the file.
+fn normalize(code: &str) -> String {
+    code.trim_start_matches('\u{feff}')
+        .replace("\r\n", "\n")
+        .trim_end()
+        .to_string()
+}
+
+struct MemoryCache {
+    /// Value and the clock at last use.
+    entries: HashMap<Key, (Bytes, u64)>,
+    size: usize,
+    limit: usize,
+    clock: u64,
+}
+
+impl MemoryCache {
+    fn get(&mut self, key: &Key) -> Option<Bytes> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|(value, last_used)| {
+            *last_used = clock;
+            value.clone()
+        })
+    }
+
+    fn insert(&mut self, key: Key, value: Bytes) {
+        if value.len() > self.limit {
+            return;
+        }
+        self.clock += 1;
+        if let Some((old, _)) = self.entries.insert(key, (value.clone(), self.clock)) {
+            self.size -= old.len();
+        }
+        self.size += value.len();
+        while self.size > self.limit {
+            let oldest = *self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .unwrap()
+                .0;
+            let (value, _) = self.entries.remove(&oldest).unwrap();
+            self.size -= value.len();
+        }
+    }
+}
+
+struct DiskCache {
+    dir: PathBuf,
+    /// Size and the clock at last use, keyed by filename.
+    entries: HashMap<String, (u64, u64)>,
+    size: u64,
+    limit: u64,
+    clock: u64,
+}
+
+impl DiskCache {
+    /// Indexes existing entries, treating the least recently modified as the
+    /// least recently used.
+    fn open(dir: &Path, limit: u64) -> std::io::Result<Self> {
+        std::fs::create_dir_all(dir)?;
+        let mut files = vec![];
+        for entry in std::fs::read_dir(dir)? {
+            let entry = entry?;
+            let filename = entry.file_name().to_string_lossy().to_string();
+            let metadata = entry.metadata()?;
+            if filename.starts_with(".tmp") {
+                // Left behind by an interrupted write.
+                std::fs::remove_file(entry.path())?;
+            } else if metadata.is_file() {
+                files.push((metadata.modified()?, filename, metadata.len()));
+            }
+        }
+        files.sort();
+
+        let mut cache = Self {
+            dir: dir.to_path_buf(),
+            entries: HashMap::new(),
+            size: 0,
+            limit,
+            clock: 0,
+        };
+        for (_, filename, size) in files {
+            remove_files(&cache.record(filename, size))?;
+        }
+        log::info!(
+            "Opened compile cache in {} with {} entries ({} bytes)",
+            dir.display(),
+            cache.entries.len(),
+            cache.size
+        );
+        Ok(cache)
+    }
+
+    /// Marks an entry as used and returns its path, if it exists.
+    fn touch(&mut self, filename: &str) -> Option<PathBuf> {
+        self.clock += 1;
+        let (_, last_used) = self.entries.get_mut(filename)?;
+        *last_used = self.clock;
+        Some(self.dir.join(filename))
+    }
+
+    fn remove(&mut self, filename: &str) {
+        if let Some((size, _)) = self.entries.remove(filename) {
+            self.size -= size;
+        }
+    }
+
+    /// Records a file written to the cache directory and returns the paths
+    /// of files to evict.
+    fn record(&mut self, filename: String, size: u64) -> Vec<PathBuf> {
+        self.clock += 1;
+        if let Some((old_size, _)) = self.entries.insert(filename, (size, self.clock)) {
+            self.size -= old_size;
+        }
+        self.size += size;
+        let mut evicted = vec![];
+        while self.size > self.limit {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .unwrap()
+                .0
+                .clone();
+            self.remove(&oldest);
+            evicted.push(self.dir.join(oldest));
+        }
+        evicted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        let cache = Cache::new("v1", 1 << 20);
+        let key = cache.key("fn main() {}\n", Mode::Std, Artifact::Wasm);
+        assert_eq!(
+            key,
+            cache.key("\u{feff}fn main() {}\r\n\r\n", Mode::Std, Artifact::Wasm)
+        );
+        assert_ne!(key, cache.key("fn main() { }", Mode::Std, Artifact::Wasm));
+        assert_ne!(key, cache.key("fn main() {}", Mode::NoStd, Artifact::Wasm));
+        assert_ne!(
+            key,
+            cache.key("fn main() {}", Mode::Std, Artifact::Precompiled)
+        );
+        assert_ne!(
+            key,
+            Cache::new("v2", 1 << 20).key("fn main() {}", Mode::Std, Artifact::Wasm)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_memory() {
+        let cache = Cache::new("v1", 10);
+        let keys: Vec<Key> = ["a", "b", "c"]
+            .iter()
+            .map(|code| cache.key(code, Mode::Std, Artifact::Wasm))
+            .collect();
+        assert_eq!(cache.get(&keys[0]).await, None);
+        cache.insert(keys[0], Bytes::from_static(b"aaaa")).await;
+        cache.insert(keys[1], Bytes::from_static(b"bbbb")).await;
+        assert_eq!(cache.get(&keys[0]).await, Some(Bytes::from_static(b"aaaa")));
+
+        // Evicts "b", which was used least recently.
+        cache.insert(keys[2], Bytes::from_static(b"cccc")).await;
+        assert_eq!(cache.get(&keys[1]).await, None);
+        assert!(cache.get(&keys[0]).await.is_some());
+        assert!(cache.get(&keys[2]).await.is_some());
+
+        let stats = cache.stats();
+        assert_eq!(stats.memory_hits.load(Ordering::Relaxed), 3);
+        assert_eq!(stats.misses.load(Ordering::Relaxed), 2);
+    }
+
+    #[tokio::test]
+    async fn test_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new("v1", 1 << 20).with_disk(dir.path(), 10).unwrap();
+        let keys: Vec<Key> = ["a", "b", "c"]
+            .iter()
+            .map(|code| cache.key(code, Mode::Std, Artifact::Wasm))
+            .collect();
+        cache.insert(keys[0], Bytes::from_static(b"aaaa")).await;
+        cache.insert(keys[1], Bytes::from_static(b"bbbb")).await;
+        cache.insert(keys[2], Bytes::from_static(b"cccc")).await;
+        drop(cache);
+
+        // A new cache finds the newest entries on disk.
+        let cache = Cache::new("v1", 1 << 20).with_disk(dir.path(), 10).unwrap();
+        assert_eq!(cache.get(&keys[0]).await, None);
+        assert_eq!(cache.get(&keys[1]).await, Some(Bytes::from_static(b"bbbb")));
+        assert_eq!(cache.get(&keys[2]).await, Some(Bytes::from_static(b"cccc")));
+        assert_eq!(cache.get(&keys[2]).await, Some(Bytes::from_static(b"cccc")));
+        assert_eq!(cache.stats().disk_hits.load(Ordering::Relaxed), 2);
+        assert_eq!(cache.stats().memory_hits.load(Ordering::Relaxed), 1);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_disk_unreadable() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = Cache::new("v1", 1 << 20).with_disk(dir.path(), 10).unwrap();
+        let key = cache.key("a", Mode::Std, Artifact::Wasm);
+        cache.insert(key, Bytes::from_static(b"aaaa")).await;
+        drop(cache);
+
+        let cache = Cache::new("v1", 1 << 20).with_disk(dir.path(), 10).unwrap();
+        std::fs::remove_file(dir.path().join(key.filename())).unwrap();
+        assert_eq!(cache.get(&key).await, None);
+        assert!(cache.render_stats().contains("disk_entries 0\n"));
+    }
+}
@@ -1,3 +1,4 @@
+pub mod cache;
 pub mod sanitizer;
 
 use axum::http::StatusCode;
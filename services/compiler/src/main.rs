@@ -1,12 +1,15 @@
-use axum::extract::State;
+use axum::extract::{FromRef, State};
 use axum::Router;
 use bytes::Bytes;
 use clap::Parser as _;
 use http::{Method, StatusCode};
 use once_cell::sync::Lazy;
-use oort_compiler::Compiler;
+use oort_compiler::{Compiler, Mode};
+use oort_compiler_service::cache::{Artifact, Cache};
 use oort_compiler_service::{error, Error};
+use oort_simulator::simulation::Code;
 use std::io::Write;
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use tempfile::NamedTempFile;
 use tokio::process::Command;
@@ -17,30 +20,116 @@ static FORMAT_LOCK: Lazy<tokio::sync::Mutex<()>> = Lazy::new(|| tokio::sync::Mut
 static SEMAPHORE: Lazy<tokio::sync::Semaphore> =
     Lazy::new(|| tokio::sync::Semaphore::new(MAX_CONCURRENCY));
 
-async fn post_compile(
-    State(compiler): State<Arc<Mutex<Compiler>>>,
+#[derive(Clone)]
+struct AppState {
+    compiler: Arc<Mutex<Compiler>>,
+    cache: Arc<Cache>,
+}
+
+impl FromRef<AppState> for Arc<Mutex<Compiler>> {
+    fn from_ref(state: &AppState) -> Self {
+        state.compiler.clone()
+    }
+}
+
+impl FromRef<AppState> for Arc<Cache> {
+    fn from_ref(state: &AppState) -> Self {
+        state.cache.clone()
+    }
+}
+
+async fn post_compile(State(state): State<AppState>, code: String) -> Result<Bytes, Error> {
+    compile(state, code, Mode::Std).await
+}
+
+async fn post_compile_no_std(State(state): State<AppState>, code: String) -> Result<Bytes, Error> {
+    compile(state, code, Mode::NoStd).await
+}
+
+/// Compiles and then precompiles to native code with
+/// `oort_simulator::vm::precompile`, for consumers running the simulator
+/// natively.
+async fn post_compile_precompiled(
+    State(state): State<AppState>,
     mut code: String,
 ) -> Result<Bytes, Error> {
-    let permit = SEMAPHORE.try_acquire();
-    if permit.is_err() {
-        Err(anyhow::anyhow!("Service overloaded"))?
+    code = decrypt(code)?;
+    let key = state.cache.key(&code, Mode::Std, Artifact::Precompiled);
+    if let Some(precompiled) = state.cache.get(&key).await {
+        log::info!("Precompile cache hit");
+        return Ok(precompiled);
     }
 
+    let wasm = compile(state.clone(), code, Mode::Std).await?;
+    // Precompiling takes as long as compiling, so it counts against the
+    // same concurrency limit.
+    let _permit = SEMAPHORE
+        .try_acquire()
+        .map_err(|_| anyhow::anyhow!("Service overloaded"))?;
+    let start_time = std::time::Instant::now();
+    let result = tokio::runtime::Handle::current()
+        .spawn_blocking(move || oort_simulator::vm::precompile(&wasm))
+        .await?;
+    let elapsed = std::time::Instant::now() - start_time;
+    match result {
+        Ok(Code::Precompiled(precompiled)) => {
+            log::info!("Precompile succeeded in {:?}", elapsed);
+            state.cache.insert(key, precompiled.clone()).await;
+            Ok(precompiled)
+        }
+        Ok(_) => Err(anyhow::anyhow!("Unexpected precompile output").into()),
+        Err(e) => {
+            log::info!("Precompile failed in {:?}", elapsed);
+            Err(error(StatusCode::BAD_REQUEST, e.msg))
+        }
+    }
+}
+
+async fn get_cache_stats(State(cache): State<Arc<Cache>>) -> String {
+    cache.render_stats()
+}
+
+fn decrypt(code: String) -> Result<String, Error> {
     if oort_code_encryption::is_encrypted(&code) {
         log::debug!("Encrypted code: {}", code);
-        code = oort_code_encryption::decrypt(&code)?;
+        return Ok(oort_code_encryption::decrypt(&code)?);
     }
+    Ok(code)
+}
+
+async fn compile(state: AppState, mut code: String, mode: Mode) -> Result<Bytes, Error> {
+    code = decrypt(code)?;
+    // Only successful compiles are cached, so a hit has already passed the
+    // sanitizer. Hits don't count against the concurrency limit.
+    let key = state.cache.key(&code, mode, Artifact::Wasm);
+    if let Some(wasm) = state.cache.get(&key).await {
+        log::info!("Compile cache hit");
+        return Ok(wasm);
+    }
+
+    let permit = SEMAPHORE.try_acquire();
+    if permit.is_err() {
+        Err(anyhow::anyhow!("Service overloaded"))?
+    }
+
     log::debug!("Code: {}", code);
     oort_compiler_service::sanitizer::check(&code)?;
+    let compiler = state.compiler.clone();
     let start_time = std::time::Instant::now();
     let result = tokio::runtime::Handle::current()
-        .spawn_blocking(move || compiler.lock().unwrap().compile(&code))
+        .spawn_blocking(move || {
+            let mut compiler = compiler.lock().unwrap();
+            compiler.set_mode(mode);
+            compiler.compile(&code)
+        })
         .await?;
     let elapsed = std::time::Instant::now() - start_time;
     match result {
         Ok(wasm) => {
             log::info!("Compile succeeded in {:?}", elapsed);
-            Ok(Bytes::copy_from_slice(&wasm))
+            let wasm = Bytes::from(wasm);
+            state.cache.insert(key, wasm.clone()).await;
+            Ok(wasm)
         }
         Err(e) => {
             log::info!("Compile failed in {:?}", elapsed);
@@ -80,6 +169,18 @@ async fn main() {
     struct Arguments {
         #[clap(short, long)]
         prepare: bool,
+
+        /// Memory used to cache compiler output.
+        #[clap(long, default_value_t = 256)]
+        cache_memory_mb: usize,
+
+        /// Directory to also cache compiler output in, across restarts.
+        #[clap(long)]
+        cache_dir: Option<PathBuf>,
+
+        /// Disk space used to cache compiler output in --cache-dir.
+        #[clap(long, default_value_t = 4096)]
+        cache_disk_mb: u64,
     }
     let args = Arguments::parse();
 
@@ -102,27 +203,50 @@ async fn main() {
 
     if args.prepare {
         compiler.enable_online();
-        compiler
-            .compile(include_str!("../../../shared/builtin_ai/src/empty.rs"))
-            .unwrap();
+        for mode in [Mode::Std, Mode::NoStd] {
+            compiler.set_mode(mode);
+            compiler
+                .compile(include_str!("../../../shared/builtin_ai/src/empty.rs"))
+                .unwrap();
+        }
         return;
     }
 
     log::info!("Starting oort_compiler_service v1");
 
+    // The git version is "unknown" when built without the repository, as in
+    // the Docker image, so keep the package version as well.
+    let version = format!(
+        "{} oort {} {}",
+        compiler.rustc_version().unwrap(),
+        env!("CARGO_PKG_VERSION"),
+        oort_version::version()
+    );
+    log::info!("Cache version: {}", version);
+    let mut cache = Cache::new(&version, args.cache_memory_mb << 20);
+    if let Some(dir) = args.cache_dir.as_ref() {
+        cache = cache.with_disk(dir, args.cache_disk_mb << 20).unwrap();
+    }
+
     let cors = CorsLayer::new()
         .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
         .allow_origin(Any)
         .allow_headers(Any);
 
     let router = {
-        use axum::routing::post;
+        use axum::routing::{get, post};
         Router::new()
             .route("/compile", post(post_compile))
+            .route("/compile/no_std", post(post_compile_no_std))
+            .route("/compile/precompiled", post(post_compile_precompiled))
+            .route("/cache_stats", get(get_cache_stats))
             .route("/format", post(post_format))
             .layer(cors)
             .layer(tower_http::trace::TraceLayer::new_for_http())
-            .with_state(Arc::new(Mutex::new(compiler)))
+            .with_state(AppState {
+                compiler: Arc::new(Mutex::new(compiler)),
+                cache: Arc::new(cache),
+            })
     };
 
     axum::Server::bind(&format!("0.0.0.0:{port}").parse().unwrap())
@@ -1,6 +1,14 @@
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
 mod user;
 
-use std::collections::HashMap;
+// Ships are only looked up by key, so the map type doesn't affect behavior.
+// HashMap needs std for its random state.
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap as ShipMap;
+#[cfg(feature = "std")]
+use std::collections::HashMap as ShipMap;
 
 // For compatibility.
 pub use oort_api::prelude;
@@ -10,13 +18,13 @@ struct ShipWrapper {
     rng: oort_api::rng_state::RngState,
 }
 
-static mut SHIPS: Option<HashMap<i32, ShipWrapper>> = None;
+static mut SHIPS: Option<ShipMap<i32, ShipWrapper>> = None;
 
 #[doc(hidden)]
 #[no_mangle]
 fn export_initialize() {
     unsafe {
-        SHIPS = Some(HashMap::new());
+        SHIPS = Some(ShipMap::new());
     }
 }
 
@@ -34,6 +42,7 @@ pub fn export_tick_ship(key: i32) {
             }
         });
         oort_api::rng_state::set(ship.rng.clone());
+        oort_api::dispatch_events!(&mut ship.user_ship);
         ship.user_ship.tick();
         oort_api::dbg::update();
         ship.rng = oort_api::rng_state::get().clone();
@@ -47,3 +56,78 @@ pub fn export_delete_ship(key: i32) {
         SHIPS.as_mut().unwrap().remove(&key);
     }
 }
+
+// Panics trap without a message, which avoids linking the formatting code.
+#[cfg(not(feature = "std"))]
+#[panic_handler]
+fn panic(_: &core::panic::PanicInfo) -> ! {
+    core::arch::wasm32::unreachable()
+}
+
+#[cfg(not(feature = "std"))]
+#[global_allocator]
+static ALLOCATOR: allocator::Allocator = allocator::Allocator;
+
+#[cfg(not(feature = "std"))]
+mod allocator {
+    use core::alloc::{GlobalAlloc, Layout};
+    use core::arch::wasm32;
+    use core::ptr::null_mut;
+
+    const PAGE_SIZE: usize = 65536;
+    const NUM_CLASSES: usize = usize::BITS as usize;
+
+    /// Power-of-two size classes, each with a free list. Freed blocks are
+    /// reused for the same class and memory is never returned to the host.
+    pub struct Allocator;
+
+    static mut FREE_LISTS: [usize; NUM_CLASSES] = [0; NUM_CLASSES];
+    static mut HEAP_NEXT: usize = 0;
+    static mut HEAP_END: usize = 0;
+
+    fn size_class(layout: Layout) -> usize {
+        let size = layout.size().max(layout.align()).max(8);
+        (usize::BITS - (size - 1).leading_zeros()) as usize
+    }
+
+    unsafe impl GlobalAlloc for Allocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let class = size_class(layout);
+            if layout.align() > PAGE_SIZE || class >= NUM_CLASSES {
+                return null_mut();
+            }
+            let head = FREE_LISTS[class];
+            if head != 0 {
+                FREE_LISTS[class] = *(head as *const usize);
+                return head as *mut u8;
+            }
+
+            // Blocks are aligned to their size (up to a page) so a reused
+            // block satisfies any alignment that fits in its class.
+            let size = 1usize << class;
+            let align = size.min(PAGE_SIZE);
+            let mut start = (HEAP_NEXT + align - 1) & !(align - 1);
+            if start + size > HEAP_END {
+                let pages = (size - 1) / PAGE_SIZE + 1;
+                let prev = wasm32::memory_grow(0, pages);
+                if prev == usize::MAX {
+                    return null_mut();
+                }
+                let base = prev * PAGE_SIZE;
+                if base != HEAP_END {
+                    HEAP_NEXT = base;
+                }
+                HEAP_END = base + pages * PAGE_SIZE;
+                start = (HEAP_NEXT + align - 1) & !(align - 1);
+            }
+            HEAP_NEXT = start + size;
+            start as *mut u8
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            let class = size_class(layout);
+            *(ptr as *mut usize) = FREE_LISTS[class];
+            FREE_LISTS[class] = ptr as usize;
+        }
+    }
+}
@@ -0,0 +1,546 @@
+//! Typed radio messages.
+//!
+//! A radio [`Message`] is four `f64`s. This module packs small structs into
+//! them bit by bit, with a version byte and a checksum so that messages from
+//! other senders (or an older version of your own code) are rejected instead
+//! of being misread.
+//!
+//! Simple structs can use the [`radio_message!`](crate::radio_message!) macro:
+//!
+//! ```
+//! use oort_api::prelude::*;
+//!
+//! radio_message! {
+//!     version = 1;
+//!     #[derive(Debug, PartialEq)]
+//!     pub struct TargetReport {
+//!         pub id: u16,
+//!         pub class: Class,
+//!         pub position: Vec2,
+//!         pub engaged: bool,
+//!     }
+//! }
+//!
+//! let report = TargetReport {
+//!     id: 7,
+//!     class: Class::Cruiser,
+//!     position: vec2(1000.0, -500.0),
+//!     engaged: true,
+//! };
+//! let msg = oort_api::codec::encode(&report).unwrap();
+//! assert_eq!(oort_api::codec::decode::<TargetReport>(&msg), Ok(report));
+//! ```
+//!
+//! Anything else can implement [`Field`] by hand, for example to quantize a
+//! value with [`Writer::write_quantized`].
+//!
+//! Each `f64` carries 48 bits as an exactly representable integer, so a
+//! message holds [`MAX_PAYLOAD_BITS`] bits after the header. Payloads that
+//! don't fit can be split across several words with [`encode_words`] and
+//! [`decode_words`].
+#[cfg(not(feature = "std"))]
+use crate::float::F64Ext;
+use crate::vec::Vec2;
+use crate::{Ability, Class, Message};
+use alloc::{vec, vec::Vec};
+
+const BITS_PER_WORD: usize = 48;
+const BYTES_PER_WORD: usize = BITS_PER_WORD / 8;
+// Version byte followed by a 16-bit checksum.
+const HEADER_BYTES: usize = 3;
+
+/// Number of payload bits that fit in one [`Message`].
+pub const MAX_PAYLOAD_BITS: usize = 4 * BITS_PER_WORD - HEADER_BYTES * 8;
+
+/// A value that can be written to and read from a radio message.
+pub trait Field: Sized {
+    #[allow(missing_docs)]
+    fn write(&self, w: &mut Writer);
+    /// Returns `None` if the message ended early.
+    fn read(r: &mut Reader) -> Option<Self>;
+}
+
+/// A top level radio message.
+pub trait RadioMessage: Field {
+    /// Checked when decoding. Bump it when the layout changes.
+    const VERSION: u8 = 0;
+}
+
+/// Reasons a message could not be encoded.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum EncodeError {
+    /// The payload doesn't fit in one message.
+    TooLarge {
+        #[allow(missing_docs)]
+        bits: usize,
+    },
+}
+
+/// Reasons a message could not be decoded.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DecodeError {
+    /// The message wasn't produced by this module, e.g. a raw `send`.
+    NotEncoded,
+    /// The message was corrupted or is a different type.
+    Checksum,
+    /// The message was encoded with another version of the type.
+    Version {
+        #[allow(missing_docs)]
+        expected: u8,
+        #[allow(missing_docs)]
+        found: u8,
+    },
+    /// The message ended before all fields were read.
+    Truncated,
+}
+
+/// Writes fields into a bit buffer.
+#[derive(Default, Clone, Debug)]
+pub struct Writer {
+    bytes: Vec<u8>,
+    bit_len: usize,
+}
+
+impl Writer {
+    #[allow(missing_docs)]
+    pub fn new() -> Writer {
+        Writer::default()
+    }
+
+    /// Writes the low `bits` bits of `value`.
+    pub fn write_bits(&mut self, value: u64, bits: u32) {
+        for i in 0..bits {
+            let shift = self.bit_len & 7;
+            if shift == 0 {
+                self.bytes.push(0);
+            }
+            let bit = ((value >> i) & 1) as u8;
+            *self.bytes.last_mut().unwrap() |= bit << shift;
+            self.bit_len += 1;
+        }
+    }
+
+    /// Writes any [`Field`].
+    pub fn write<T: Field>(&mut self, value: &T) {
+        value.write(self);
+    }
+
+    /// Writes `value` clamped to `[min, max]` using `bits` bits of precision.
+    pub fn write_quantized(&mut self, value: f64, min: f64, max: f64, bits: u32) {
+        let steps = ((1u64 << bits) - 1) as f64;
+        let x = ((value - min) / (max - min)).clamp(0.0, 1.0);
+        self.write_bits((x * steps).round() as u64, bits);
+    }
+
+    /// Number of bits written so far.
+    pub fn bit_len(&self) -> usize {
+        self.bit_len
+    }
+}
+
+/// Reads fields from a bit buffer.
+#[derive(Clone, Debug)]
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    #[allow(missing_docs)]
+    pub fn new(bytes: &'a [u8]) -> Reader<'a> {
+        Reader { bytes, pos: 0 }
+    }
+
+    /// Reads `bits` bits written by [`Writer::write_bits`].
+    pub fn read_bits(&mut self, bits: u32) -> Option<u64> {
+        if self.pos + bits as usize > self.bytes.len() * 8 {
+            return None;
+        }
+        let mut value = 0;
+        for i in 0..bits {
+            let bit = (self.bytes[self.pos / 8] >> (self.pos % 8)) & 1;
+            value |= (bit as u64) << i;
+            self.pos += 1;
+        }
+        Some(value)
+    }
+
+    /// Reads any [`Field`].
+    pub fn read<T: Field>(&mut self) -> Option<T> {
+        T::read(self)
+    }
+
+    /// Reads a value written by [`Writer::write_quantized`].
+    pub fn read_quantized(&mut self, min: f64, max: f64, bits: u32) -> Option<f64> {
+        let steps = ((1u64 << bits) - 1) as f64;
+        Some(min + self.read_bits(bits)? as f64 / steps * (max - min))
+    }
+}
+
+macro_rules! impl_field_uint {
+    ($($ty:ty),*) => {
+        $(
+            impl Field for $ty {
+                fn write(&self, w: &mut Writer) {
+                    w.write_bits(*self as u64, <$ty>::BITS);
+                }
+
+                fn read(r: &mut Reader) -> Option<Self> {
+                    r.read_bits(<$ty>::BITS).map(|v| v as $ty)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_field_int {
+    ($($ty:ty => $uty:ty),*) => {
+        $(
+            impl Field for $ty {
+                fn write(&self, w: &mut Writer) {
+                    (*self as $uty).write(w);
+                }
+
+                fn read(r: &mut Reader) -> Option<Self> {
+                    <$uty>::read(r).map(|v| v as $ty)
+                }
+            }
+        )*
+    };
+}
+
+impl_field_uint!(u8, u16, u32, u64);
+impl_field_int!(i8 => u8, i16 => u16, i32 => u32, i64 => u64);
+
+impl Field for bool {
+    fn write(&self, w: &mut Writer) {
+        w.write_bits(*self as u64, 1);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        r.read_bits(1).map(|v| v != 0)
+    }
+}
+
+impl Field for f32 {
+    fn write(&self, w: &mut Writer) {
+        self.to_bits().write(w);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        u32::read(r).map(f32::from_bits)
+    }
+}
+
+impl Field for f64 {
+    fn write(&self, w: &mut Writer) {
+        self.to_bits().write(w);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        u64::read(r).map(f64::from_bits)
+    }
+}
+
+/// Stored as two `f32`s.
+impl Field for Vec2 {
+    fn write(&self, w: &mut Writer) {
+        (self.x as f32).write(w);
+        (self.y as f32).write(w);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        Some(Vec2::new(f32::read(r)? as f64, f32::read(r)? as f64))
+    }
+}
+
+impl Field for Class {
+    fn write(&self, w: &mut Writer) {
+        w.write_bits(*self as u64, 3);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        r.read_bits(3).map(|v| Class::from_f64(v as f64))
+    }
+}
+
+impl Field for Ability {
+    fn write(&self, w: &mut Writer) {
+        w.write_bits(*self as u64, 3);
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        r.read_bits(3).map(|v| Ability::from_f64(v as f64))
+    }
+}
+
+impl<T: Field> Field for Option<T> {
+    fn write(&self, w: &mut Writer) {
+        self.is_some().write(w);
+        if let Some(v) = self {
+            v.write(w);
+        }
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        if bool::read(r)? {
+            Some(Some(T::read(r)?))
+        } else {
+            Some(None)
+        }
+    }
+}
+
+impl<T: Field, const N: usize> Field for [T; N] {
+    fn write(&self, w: &mut Writer) {
+        for v in self.iter() {
+            v.write(w);
+        }
+    }
+
+    fn read(r: &mut Reader) -> Option<Self> {
+        let mut values = Vec::with_capacity(N);
+        for _ in 0..N {
+            values.push(T::read(r)?);
+        }
+        values.try_into().ok()
+    }
+}
+
+// Adler-style, so that an all-zero message doesn't have a valid checksum.
+fn checksum(bytes: &[u8]) -> u16 {
+    let (mut a, mut b) = (1u16, 0u16);
+    for &x in bytes {
+        a = (a + x as u16) % 255;
+        b = (b + a) % 255;
+    }
+    (b << 8) | a
+}
+
+fn pack(version: u8, payload: Vec<u8>, min_words: usize) -> Vec<f64> {
+    let mut bytes = vec![version, 0, 0];
+    bytes.extend(payload);
+    let num_words = ((bytes.len() - 1) / BYTES_PER_WORD + 1).max(min_words);
+    bytes.resize(num_words * BYTES_PER_WORD, 0);
+    let sum = checksum(&bytes);
+    bytes[1..3].copy_from_slice(&sum.to_le_bytes());
+    bytes
+        .chunks_exact(BYTES_PER_WORD)
+        .map(|chunk| {
+            let mut word = [0u8; 8];
+            word[..BYTES_PER_WORD].copy_from_slice(chunk);
+            u64::from_le_bytes(word) as f64
+        })
+        .collect()
+}
+
+/// Encodes a message into as many words as it needs.
+pub fn encode_words<T: RadioMessage>(msg: &T) -> Vec<f64> {
+    let mut w = Writer::new();
+    msg.write(&mut w);
+    pack(T::VERSION, w.bytes, 1)
+}
+
+/// Decodes words produced by [`encode_words`].
+pub fn decode_words<T: RadioMessage>(words: &[f64]) -> Result<T, DecodeError> {
+    let mut bytes = Vec::with_capacity(words.len() * BYTES_PER_WORD);
+    for &word in words {
+        if !(0.0..(1u64 << BITS_PER_WORD) as f64).contains(&word) || word.fract() != 0.0 {
+            return Err(DecodeError::NotEncoded);
+        }
+        bytes.extend_from_slice(&(word as u64).to_le_bytes()[..BYTES_PER_WORD]);
+    }
+    if bytes.len() < HEADER_BYTES {
+        return Err(DecodeError::Truncated);
+    }
+    let found = u16::from_le_bytes([bytes[1], bytes[2]]);
+    bytes[1..3].fill(0);
+    if checksum(&bytes) != found {
+        return Err(DecodeError::Checksum);
+    }
+    if bytes[0] != T::VERSION {
+        return Err(DecodeError::Version {
+            expected: T::VERSION,
+            found: bytes[0],
+        });
+    }
+    T::read(&mut Reader::new(&bytes[HEADER_BYTES..])).ok_or(DecodeError::Truncated)
+}
+
+/// Encodes a message that fits in a single radio [`Message`].
+pub fn encode<T: RadioMessage>(msg: &T) -> Result<Message, EncodeError> {
+    let mut w = Writer::new();
+    msg.write(&mut w);
+    if w.bit_len() > MAX_PAYLOAD_BITS {
+        return Err(EncodeError::TooLarge { bits: w.bit_len() });
+    }
+    Ok(pack(T::VERSION, w.bytes, 4).try_into().unwrap())
+}
+
+/// Decodes a radio [`Message`] produced by [`encode`].
+pub fn decode<T: RadioMessage>(msg: &Message) -> Result<T, DecodeError> {
+    decode_words(msg)
+}
+
+/// Encodes and sends a message on the selected radio.
+pub fn send_message<T: RadioMessage>(msg: &T) -> Result<(), EncodeError> {
+    crate::api::send(encode(msg)?);
+    Ok(())
+}
+
+/// Receives a message on the selected radio.
+///
+/// Returns `None` if nothing was received or the message didn't decode as `T`.
+pub fn receive_message<T: RadioMessage>() -> Option<T> {
+    decode(&crate::api::receive()?).ok()
+}
+
+/// Defines a struct and implements [`RadioMessage`](codec::RadioMessage) for it.
+///
+/// Every field must implement [`Field`](codec::Field). See the
+/// [`codec`](crate::codec) module for an example.
+#[macro_export]
+macro_rules! radio_message {
+    (
+        version = $version:expr;
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $($(#[$fmeta:meta])* $fvis:vis $field:ident : $ty:ty),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis struct $name {
+            $($(#[$fmeta])* $fvis $field: $ty),*
+        }
+
+        impl $crate::codec::Field for $name {
+            fn write(&self, w: &mut $crate::codec::Writer) {
+                $(w.write(&self.$field);)*
+            }
+
+            fn read(r: &mut $crate::codec::Reader) -> Option<Self> {
+                Some($name {
+                    $($field: r.read()?),*
+                })
+            }
+        }
+
+        impl $crate::codec::RadioMessage for $name {
+            const VERSION: u8 = $version;
+        }
+    };
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vec::vec2;
+
+    radio_message! {
+        version = 3;
+        #[derive(Debug, PartialEq, Clone)]
+        struct Report {
+            id: u16,
+            class: Class,
+            position: Vec2,
+            velocity: Option<Vec2>,
+            flags: [bool; 4],
+            heading: i8,
+        }
+    }
+
+    radio_message! {
+        version = 4;
+        #[derive(Debug, PartialEq)]
+        struct ReportV4 {
+            id: u16,
+        }
+    }
+
+    radio_message! {
+        version = 0;
+        #[derive(Debug, PartialEq)]
+        struct Big {
+            values: [f64; 4],
+        }
+    }
+
+    fn report() -> Report {
+        Report {
+            id: 1234,
+            class: Class::Frigate,
+            position: vec2(1000.5, -2000.25),
+            velocity: Some(vec2(-10.0, 20.0)),
+            flags: [true, false, false, true],
+            heading: -100,
+        }
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let msg = encode(&report()).unwrap();
+        assert_eq!(decode::<Report>(&msg), Ok(report()));
+    }
+
+    #[test]
+    fn test_small_message() {
+        let msg = encode(&ReportV4 { id: 5 }).unwrap();
+        assert_eq!(decode::<ReportV4>(&msg), Ok(ReportV4 { id: 5 }));
+    }
+
+    #[test]
+    fn test_rejects_corruption() {
+        let mut msg = encode(&report()).unwrap();
+        msg[2] += 1.0;
+        assert_eq!(decode::<Report>(&msg), Err(DecodeError::Checksum));
+        msg[2] += 0.5;
+        assert_eq!(decode::<Report>(&msg), Err(DecodeError::NotEncoded));
+    }
+
+    #[test]
+    fn test_rejects_foreign_messages() {
+        assert_eq!(decode::<Report>(&[0.0; 4]), Err(DecodeError::Checksum));
+        assert_eq!(
+            decode::<Report>(&[1.5, -2.0, 3.0, 4.0]),
+            Err(DecodeError::NotEncoded)
+        );
+    }
+
+    #[test]
+    fn test_version() {
+        let msg = encode(&report()).unwrap();
+        assert_eq!(
+            decode::<ReportV4>(&msg),
+            Err(DecodeError::Version {
+                expected: 4,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_large_payload() {
+        let big = Big {
+            values: [1.0, -2.0, f64::MAX, 0.1],
+        };
+        assert_eq!(encode(&big), Err(EncodeError::TooLarge { bits: 256 }));
+        let words = encode_words(&big);
+        assert_eq!(words.len(), 6);
+        assert_eq!(decode_words::<Big>(&words), Ok(big));
+        assert_eq!(decode_words::<Big>(&words[..4]), Err(DecodeError::Checksum));
+    }
+
+    #[test]
+    fn test_quantized() {
+        let mut w = Writer::new();
+        w.write_quantized(0.3, 0.0, 1.0, 10);
+        w.write_quantized(5.0, 0.0, 1.0, 10);
+        assert_eq!(w.bit_len(), 20);
+        let bytes = w.bytes.clone();
+        let mut r = Reader::new(&bytes);
+        assert!((r.read_quantized(0.0, 1.0, 10).unwrap() - 0.3).abs() < 1e-3);
+        assert_eq!(r.read_quantized(0.0, 1.0, 10), Some(1.0));
+        assert_eq!(r.read_bits(4), Some(0));
+        assert_eq!(r.read_bits(1), None);
+    }
+}
@@ -0,0 +1,146 @@
+//! Floating point functions for no_std builds.
+//!
+//! Without `std` the inherent `f64` methods like `sqrt` and `sin` don't exist.
+//! This trait provides them with the same names, implemented with libm, which
+//! is also what `std` uses on Wasm.
+
+/// Math methods on `f64` for no_std builds. See the `f64` docs for details.
+#[allow(missing_docs)]
+pub trait F64Ext {
+    fn abs(self) -> f64;
+    fn signum(self) -> f64;
+    fn floor(self) -> f64;
+    fn ceil(self) -> f64;
+    fn round(self) -> f64;
+    fn trunc(self) -> f64;
+    fn fract(self) -> f64;
+    fn rem_euclid(self, rhs: f64) -> f64;
+    fn sqrt(self) -> f64;
+    fn cbrt(self) -> f64;
+    fn hypot(self, other: f64) -> f64;
+    fn powi(self, n: i32) -> f64;
+    fn powf(self, n: f64) -> f64;
+    fn exp(self) -> f64;
+    fn ln(self) -> f64;
+    fn log10(self) -> f64;
+    fn log2(self) -> f64;
+    fn sin(self) -> f64;
+    fn cos(self) -> f64;
+    fn tan(self) -> f64;
+    fn sin_cos(self) -> (f64, f64);
+    fn asin(self) -> f64;
+    fn acos(self) -> f64;
+    fn atan(self) -> f64;
+    fn atan2(self, other: f64) -> f64;
+}
+
+impl F64Ext for f64 {
+    fn abs(self) -> f64 {
+        libm::fabs(self)
+    }
+
+    fn signum(self) -> f64 {
+        if self.is_nan() {
+            f64::NAN
+        } else {
+            libm::copysign(1.0, self)
+        }
+    }
+
+    fn floor(self) -> f64 {
+        libm::floor(self)
+    }
+
+    fn ceil(self) -> f64 {
+        libm::ceil(self)
+    }
+
+    fn round(self) -> f64 {
+        libm::round(self)
+    }
+
+    fn trunc(self) -> f64 {
+        libm::trunc(self)
+    }
+
+    fn fract(self) -> f64 {
+        self - libm::trunc(self)
+    }
+
+    fn rem_euclid(self, rhs: f64) -> f64 {
+        let r = self % rhs;
+        if r < 0.0 {
+            r + libm::fabs(rhs)
+        } else {
+            r
+        }
+    }
+
+    fn sqrt(self) -> f64 {
+        libm::sqrt(self)
+    }
+
+    fn cbrt(self) -> f64 {
+        libm::cbrt(self)
+    }
+
+    fn hypot(self, other: f64) -> f64 {
+        libm::hypot(self, other)
+    }
+
+    fn powi(self, n: i32) -> f64 {
+        libm::pow(self, n as f64)
+    }
+
+    fn powf(self, n: f64) -> f64 {
+        libm::pow(self, n)
+    }
+
+    fn exp(self) -> f64 {
+        libm::exp(self)
+    }
+
+    fn ln(self) -> f64 {
+        libm::log(self)
+    }
+
+    fn log10(self) -> f64 {
+        libm::log10(self)
+    }
+
+    fn log2(self) -> f64 {
+        libm::log2(self)
+    }
+
+    fn sin(self) -> f64 {
+        libm::sin(self)
+    }
+
+    fn cos(self) -> f64 {
+        libm::cos(self)
+    }
+
+    fn tan(self) -> f64 {
+        libm::tan(self)
+    }
+
+    fn sin_cos(self) -> (f64, f64) {
+        libm::sincos(self)
+    }
+
+    fn asin(self) -> f64 {
+        libm::asin(self)
+    }
+
+    fn acos(self) -> f64 {
+        libm::acos(self)
+    }
+
+    fn atan(self) -> f64 {
+        libm::atan(self)
+    }
+
+    fn atan2(self, other: f64) -> f64 {
+        libm::atan2(self, other)
+    }
+}
@@ -1,5 +1,17 @@
 #![doc = include_str!("../README.md")]
 #![warn(missing_docs)]
+#![cfg_attr(not(feature = "std"), no_std)]
+extern crate alloc;
+
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("no_std builds of oort_api need the libm feature for float math");
+
+pub mod codec;
+#[cfg(not(feature = "std"))]
+mod float;
+pub mod math;
+#[cfg(feature = "mock")]
+pub mod mock;
 mod vec;
 
 #[allow(missing_docs)]
@@ -142,6 +154,22 @@ pub enum SystemState {
 
     Id,
 
+    GasRemaining,
+
+    Events,
+    DamageTaken,
+
+    DebugShapesPointer,
+    DebugShapesLength,
+    DebugLayersPointer,
+    DebugLayersLength,
+
+    DebugLogPointer,
+    DebugLogLength,
+
+    // Bitmask of abilities active last tick, used to generate events.
+    ActiveAbilities,
+
     Size,
     MaxSize = 128,
 }
@@ -149,6 +177,9 @@ pub enum SystemState {
 #[allow(missing_docs)]
 pub const MAX_ENVIRONMENT_SIZE: usize = 1024;
 
+/// Amount of gas (roughly, Wasm instructions) each ship may use per tick.
+pub const GAS_PER_TICK: u32 = 1_000_000;
+
 /// Identifiers for each class of ship.
 #[allow(missing_docs)]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -195,6 +226,19 @@ pub enum Ability {
     Shield,
 }
 
+impl Ability {
+    #[allow(missing_docs)]
+    pub fn from_f64(v: f64) -> Ability {
+        match v as u32 {
+            1 => Ability::Boost,
+            2 => Ability::ShapedCharge,
+            3 => Ability::Decoy,
+            4 => Ability::Shield,
+            _ => Ability::None,
+        }
+    }
+}
+
 /// Electronic Counter Measures (ECM) modes.
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -206,6 +250,36 @@ pub enum EcmMode {
     Noise,
 }
 
+/// Severity of a message logged with [`log!`](crate::log!).
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LogLevel {
+    #[allow(missing_docs)]
+    Trace = 1,
+    #[allow(missing_docs)]
+    Debug,
+    #[allow(missing_docs)]
+    Info,
+    #[allow(missing_docs)]
+    Warn,
+    #[allow(missing_docs)]
+    Error,
+}
+
+impl LogLevel {
+    #[doc(hidden)]
+    pub fn from_u8(x: u8) -> Option<LogLevel> {
+        match x {
+            1 => Some(LogLevel::Trace),
+            2 => Some(LogLevel::Debug),
+            3 => Some(LogLevel::Info),
+            4 => Some(LogLevel::Warn),
+            5 => Some(LogLevel::Error),
+            _ => None,
+        }
+    }
+}
+
 #[doc(hidden)]
 #[derive(Default, Clone)]
 pub struct Line {
@@ -227,6 +301,42 @@ pub struct Text {
     pub text: [u8; 11],
 }
 
+#[doc(hidden)]
+#[repr(C)]
+#[derive(Default, Clone, Debug)]
+pub struct Shape {
+    pub x0: f64,
+    pub y0: f64,
+    pub x1: f64,
+    pub y1: f64,
+    pub angle0: f64,
+    pub angle1: f64,
+    pub color: u32,
+    pub ttl: u32,
+    pub kind: u8,
+    pub flags: u8,
+    pub layer: u8,
+    pub text_length: u8,
+    pub text: [u8; 28],
+}
+
+impl Shape {
+    // Line from (x0, y0) to (x1, y1).
+    pub const LINE: u8 = 0;
+    // Center (x0, y0), radius x1.
+    pub const CIRCLE: u8 = 1;
+    // Center (x0, y0), radius x1, from angle0 to angle1.
+    pub const ARC: u8 = 2;
+    // Center (x0, y0), radius x1, y1 sides, rotated by angle0.
+    pub const POLYGON: u8 = 3;
+    // Top left at (x0, y0).
+    pub const TEXT: u8 = 4;
+
+    pub const FILLED: u8 = 1 << 0;
+    // Coordinates are pixels from the top left of the screen.
+    pub const SCREEN: u8 = 1 << 1;
+}
+
 /// Message sent and received on the radio.
 pub type Message = [f64; 4];
 
@@ -251,6 +361,21 @@ pub mod sys {
         system_state[index as usize] = value;
     }
 
+    /// Description of the ship's weapons and abilities. See `loadout_internal` for the layout.
+    #[no_mangle]
+    pub static mut LOADOUT: [f64; crate::api::loadout_internal::SIZE] =
+        [0.0; crate::api::loadout_internal::SIZE];
+
+    pub fn read_loadout(index: usize) -> f64 {
+        let loadout = unsafe { &LOADOUT };
+        loadout[index]
+    }
+
+    pub fn write_loadout(index: usize, value: f64) {
+        let loadout = unsafe { &mut LOADOUT };
+        loadout[index] = value;
+    }
+
     #[no_mangle]
     pub static mut ENVIRONMENT: [u8; MAX_ENVIRONMENT_SIZE] = [0; MAX_ENVIRONMENT_SIZE];
 
@@ -261,7 +386,15 @@ pub mod sys {
             .iter()
             .position(|&c| c == 0)
             .unwrap_or(environment.len());
-        std::str::from_utf8(&environment[..n]).expect("Failed to convert environment to string")
+        core::str::from_utf8(&environment[..n]).expect("Failed to convert environment to string")
+    }
+
+    /// Returns the amount of gas this ship used during the previous tick.
+    ///
+    /// A ship that runs out of gas ([`GAS_PER_TICK`](crate::GAS_PER_TICK)) explodes.
+    /// Returns 0 if the simulator can't measure gas usage for this AI.
+    pub fn gas_used() -> u32 {
+        crate::GAS_PER_TICK.saturating_sub(read_system_state(SystemState::GasRemaining) as u32)
     }
 
     pub fn getenv(key: &str) -> Option<&'static str> {
@@ -278,22 +411,6 @@ pub mod sys {
     }
 }
 
-mod math {
-    pub use std::f64::consts::{PI, TAU};
-
-    /// Returns the smallest rotation between angles `a` and `b`.
-    ///
-    /// A positive result is a counter-clockwise rotation and negative is clockwise.
-    pub fn angle_diff(a: f64, b: f64) -> f64 {
-        let c = (b - a).rem_euclid(TAU);
-        if c > PI {
-            c - TAU
-        } else {
-            c
-        }
-    }
-}
-
 mod rng {
     fn rng() -> &'static mut oorandom::Rand64 {
         let rng_state = unsafe { super::rng_state::get() };
@@ -337,6 +454,7 @@ mod api {
     use super::sys::{read_system_state, write_system_state};
     use super::{Ability, Class, EcmMode, SystemState};
     use crate::{vec::*, Message};
+    use alloc::vec::Vec;
 
     /// The time between each simulation tick.
     pub const TICK_LENGTH: f64 = 1.0 / 60.0;
@@ -369,6 +487,13 @@ mod api {
             .unwrap_or(0.0)
     }
 
+    /// Returns the value of a scenario parameter (e.g. `"num_targets"`), or
+    /// `None` if the scenario does not declare it.
+    pub fn scenario_param(name: &str) -> Option<f64> {
+        let key = alloc::format!("PARAM_{}", name.to_ascii_uppercase());
+        super::sys::getenv(&key)?.parse().ok()
+    }
+
     /// Returns the current position (in meters).
     pub fn position() -> Vec2 {
         vec2(
@@ -595,7 +720,7 @@ mod api {
             let stride = 7;
             let offset = stride * sel;
             let add_offset =
-                |x| unsafe { ::std::mem::transmute::<u8, SystemState>((x as u8) + offset as u8) };
+                |x| unsafe { ::core::mem::transmute::<u8, SystemState>((x as u8) + offset as u8) };
             RadioIndices {
                 channel: add_offset(SystemState::Radio0Channel),
                 send: add_offset(SystemState::Radio0Send),
@@ -708,6 +833,195 @@ mod api {
         write_system_state(SystemState::ActivateAbility, ability as u32 as f64);
     }
 
+    #[doc(hidden)]
+    pub mod loadout_internal {
+        // Layout of sys::LOADOUT, written by the simulator before each tick.
+        pub const NUM_WEAPONS: usize = 0;
+        pub const NUM_ABILITIES: usize = 1;
+        pub const WEAPONS: usize = 2;
+
+        pub const MAX_WEAPONS: usize = 4;
+        pub const WEAPON_STRIDE: usize = 17;
+
+        pub const KIND: usize = 0;
+        pub const SPEED: usize = 1;
+        pub const OFFSET_X: usize = 2;
+        pub const OFFSET_Y: usize = 3;
+        pub const HEADING: usize = 4;
+        pub const MIN_ANGLE: usize = 5;
+        pub const MAX_ANGLE: usize = 6;
+        pub const INACCURACY: usize = 7;
+        pub const SPEED_ERROR: usize = 8;
+        pub const RELOAD_TICKS: usize = 9;
+        pub const MAGAZINE_SIZE: usize = 10;
+        pub const MAGAZINE_REMAINING: usize = 11;
+        pub const MAGAZINE_RELOAD_TICKS: usize = 12;
+        pub const BURST_SIZE: usize = 13;
+        pub const BULLET_TTL: usize = 14;
+        pub const BULLET_MASS: usize = 15;
+        pub const LAUNCHER_CLASS: usize = 16;
+
+        pub const KIND_GUN: f64 = 1.0;
+        pub const KIND_LAUNCHER: f64 = 2.0;
+
+        pub const ABILITIES: usize = WEAPONS + MAX_WEAPONS * WEAPON_STRIDE;
+        pub const MAX_ABILITIES: usize = 4;
+        pub const ABILITY_STRIDE: usize = 5;
+
+        pub const ABILITY: usize = 0;
+        pub const ACTIVE_TIME: usize = 1;
+        pub const RELOAD_TIME: usize = 2;
+        pub const ACTIVE_TIME_REMAINING: usize = 3;
+        pub const RELOAD_TIME_REMAINING: usize = 4;
+
+        pub const SIZE: usize = ABILITIES + MAX_ABILITIES * ABILITY_STRIDE;
+
+        pub fn weapon_index(weapon: usize, field: usize) -> usize {
+            assert!(weapon < MAX_WEAPONS);
+            WEAPONS + weapon * WEAPON_STRIDE + field
+        }
+
+        pub fn ability_index(ability: usize, field: usize) -> usize {
+            assert!(ability < MAX_ABILITIES);
+            ABILITIES + ability * ABILITY_STRIDE + field
+        }
+    }
+
+    /// A gun, as returned by [`weapons`].
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    pub struct GunInfo {
+        /// Bullet speed relative to the ship (in m/s).
+        pub speed: f64,
+        /// Maximum random error added to the bullet speed (in m/s).
+        pub speed_error: f64,
+        /// Position of the gun relative to the ship, in the ship's frame (in meters).
+        pub offset: Vec2,
+        /// Direction last passed to `aim` (in radians).
+        pub heading: f64,
+        /// Lower limit of the firing direction, relative to the ship's heading (in radians).
+        pub min_angle: f64,
+        /// Upper limit of the firing direction, relative to the ship's heading (in radians).
+        pub max_angle: f64,
+        /// Maximum random error added to the bullet direction (in radians).
+        pub inaccuracy: f64,
+        /// Ticks between shots.
+        pub reload_ticks: u32,
+        /// Number of bullets per magazine.
+        pub magazine_size: u32,
+        /// Bullets left in the current magazine.
+        pub magazine_remaining: u32,
+        /// Extra ticks to reload after emptying a magazine.
+        pub magazine_reload_ticks: u32,
+        /// Bullets fired per shot.
+        pub burst_size: u32,
+        /// Bullet lifetime (in seconds).
+        pub bullet_ttl: f64,
+        /// Bullet mass (in kg).
+        pub bullet_mass: f64,
+    }
+
+    /// A missile or torpedo launcher, as returned by [`weapons`].
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    pub struct LauncherInfo {
+        /// Class of the launched ship.
+        pub class: Class,
+        /// Launch speed relative to the ship (in m/s).
+        pub speed: f64,
+        /// Position of the launcher relative to the ship, in the ship's frame (in meters).
+        pub offset: Vec2,
+        /// Launch direction relative to the ship (in radians).
+        pub heading: f64,
+        /// Ticks between launches.
+        pub reload_ticks: u32,
+    }
+
+    /// A weapon, as returned by [`weapons`].
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    pub enum Weapon {
+        #[allow(missing_docs)]
+        Gun(GunInfo),
+        #[allow(missing_docs)]
+        Launcher(LauncherInfo),
+    }
+
+    /// A special ability, as returned by [`abilities`].
+    #[derive(Copy, Clone, PartialEq, Debug)]
+    pub struct AbilityInfo {
+        #[allow(missing_docs)]
+        pub ability: Ability,
+        /// How long the ability lasts once activated (in seconds).
+        pub active_time: f64,
+        /// Time before the ability can be activated again (in seconds).
+        pub reload_time: f64,
+        /// Time until the active ability ends (in seconds).
+        pub active_time_remaining: f64,
+        /// Time until the ability can be activated again (in seconds).
+        pub reload_time_remaining: f64,
+    }
+
+    /// Returns this ship's weapons.
+    ///
+    /// The position in the list is the index passed to `fire`, `aim` and
+    /// `reload_ticks`.
+    pub fn weapons() -> Vec<Weapon> {
+        use super::sys::read_loadout;
+        use loadout_internal::*;
+        let n = (read_loadout(NUM_WEAPONS) as usize).min(MAX_WEAPONS);
+        (0..n)
+            .filter_map(|i| {
+                let get = |field| read_loadout(weapon_index(i, field));
+                let offset = vec2(get(OFFSET_X), get(OFFSET_Y));
+                if get(KIND) == KIND_GUN {
+                    Some(Weapon::Gun(GunInfo {
+                        speed: get(SPEED),
+                        speed_error: get(SPEED_ERROR),
+                        offset,
+                        heading: get(HEADING),
+                        min_angle: get(MIN_ANGLE),
+                        max_angle: get(MAX_ANGLE),
+                        inaccuracy: get(INACCURACY),
+                        reload_ticks: get(RELOAD_TICKS) as u32,
+                        magazine_size: get(MAGAZINE_SIZE) as u32,
+                        magazine_remaining: get(MAGAZINE_REMAINING) as u32,
+                        magazine_reload_ticks: get(MAGAZINE_RELOAD_TICKS) as u32,
+                        burst_size: get(BURST_SIZE) as u32,
+                        bullet_ttl: get(BULLET_TTL),
+                        bullet_mass: get(BULLET_MASS),
+                    }))
+                } else if get(KIND) == KIND_LAUNCHER {
+                    Some(Weapon::Launcher(LauncherInfo {
+                        class: Class::from_f64(get(LAUNCHER_CLASS)),
+                        speed: get(SPEED),
+                        offset,
+                        heading: get(HEADING),
+                        reload_ticks: get(RELOAD_TICKS) as u32,
+                    }))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /// Returns this ship's special abilities and their cooldowns.
+    pub fn abilities() -> Vec<AbilityInfo> {
+        use super::sys::read_loadout;
+        use loadout_internal::*;
+        let n = (read_loadout(NUM_ABILITIES) as usize).min(MAX_ABILITIES);
+        (0..n)
+            .map(|i| {
+                let get = |field| read_loadout(ability_index(i, field));
+                AbilityInfo {
+                    ability: Ability::from_f64(get(ABILITY)),
+                    active_time: get(ACTIVE_TIME),
+                    reload_time: get(RELOAD_TIME),
+                    active_time_remaining: get(ACTIVE_TIME_REMAINING),
+                    reload_time_remaining: get(RELOAD_TIME_REMAINING),
+                }
+            })
+            .collect()
+    }
+
     /// Returns the position of the target set by the scenario.
     /// Only used in tutorials.
     pub fn target() -> Vec2 {
@@ -730,14 +1044,61 @@ mod api {
 #[doc(hidden)]
 #[macro_use]
 pub mod dbg {
-    use super::{Line, Text};
+    use super::{Line, Shape, Text};
     use crate::sys::write_system_state;
     use crate::vec::*;
-    use std::f64::consts::TAU;
+    use alloc::string::String;
+    use alloc::vec::Vec;
+    use core::f64::consts::TAU;
+
+    /// False if debug output was compiled out by disabling the `dbg` feature.
+    pub const ENABLED: bool = cfg!(feature = "dbg");
 
     static mut TEXT_BUFFER: String = String::new();
     static mut LINE_BUFFER: Vec<Line> = Vec::new();
     static mut DRAWN_TEXT_BUFFER: Vec<Text> = Vec::new();
+    static mut SHAPE_BUFFER: Vec<Shape> = Vec::new();
+    // Newline separated, layer N is line N - 1. Layer 0 is the default layer.
+    static mut LAYER_BUFFER: String = String::new();
+    // Each entry is a level byte followed by the message and a NUL.
+    static mut LOG_BUFFER: String = String::new();
+    static mut CURRENT_LAYER: u8 = 0;
+    static mut CURRENT_TTL: u32 = 1;
+
+    // Old ABI buffers can't carry a layer or TTL, so only use them when
+    // neither is set.
+    fn use_shapes() -> bool {
+        unsafe { CURRENT_LAYER != 0 || CURRENT_TTL != 1 }
+    }
+
+    fn push_shape(mut shape: Shape) {
+        if !ENABLED {
+            return;
+        }
+        unsafe {
+            shape.layer = CURRENT_LAYER;
+            shape.ttl = CURRENT_TTL;
+            SHAPE_BUFFER.push(shape);
+        }
+    }
+
+    fn text_shape(kind_flags: u8, topleft: Vec2, color: u32, text: &str) -> Shape {
+        let mut shape = Shape {
+            kind: Shape::TEXT,
+            flags: kind_flags,
+            x0: topleft.x,
+            y0: topleft.y,
+            color,
+            ..Default::default()
+        };
+        shape
+            .text
+            .iter_mut()
+            .zip(text.bytes())
+            .for_each(|(d, s)| *d = s);
+        shape.text_length = text.len().min(shape.text.len()) as u8;
+        shape
+    }
 
     /// Adds text to be displayed when the ship is selected by clicking on it.
     ///
@@ -745,24 +1106,98 @@ pub mod dbg {
     #[macro_export]
     macro_rules! debug {
         ($($arg:tt)*) => {
-            $crate::dbg::write(std::format_args!($($arg)*))
+            if $crate::dbg::ENABLED {
+                $crate::dbg::write(::core::format_args!($($arg)*))
+            }
         };
     }
 
     #[allow(unused)]
     #[doc(hidden)]
-    pub fn write(args: std::fmt::Arguments) {
-        use std::fmt::Write;
+    pub fn write(args: core::fmt::Arguments) {
         let buf = unsafe { &mut TEXT_BUFFER };
-        let _ = std::fmt::write(buf, args);
+        let _ = core::fmt::write(buf, args);
         buf.push('\n');
     }
 
+    /// Logs a message at the given [`LogLevel`](crate::LogLevel).
+    ///
+    /// Unlike [`debug!`](crate::debug!), log messages from every ship are
+    /// collected with the tick they were logged on and shown together in the
+    /// console. Works like [println!] after the level argument.
+    #[macro_export]
+    macro_rules! log {
+        ($level:expr, $($arg:tt)*) => {
+            if $crate::dbg::ENABLED {
+                $crate::dbg::log_internal($level, ::core::format_args!($($arg)*))
+            }
+        };
+    }
+
+    /// Logs a message at [`LogLevel::Trace`](crate::LogLevel::Trace).
+    #[macro_export]
+    macro_rules! log_trace {
+        ($($arg:tt)*) => { $crate::log!($crate::LogLevel::Trace, $($arg)*) };
+    }
+
+    /// Logs a message at [`LogLevel::Debug`](crate::LogLevel::Debug).
+    #[macro_export]
+    macro_rules! log_debug {
+        ($($arg:tt)*) => { $crate::log!($crate::LogLevel::Debug, $($arg)*) };
+    }
+
+    /// Logs a message at [`LogLevel::Info`](crate::LogLevel::Info).
+    #[macro_export]
+    macro_rules! log_info {
+        ($($arg:tt)*) => { $crate::log!($crate::LogLevel::Info, $($arg)*) };
+    }
+
+    /// Logs a message at [`LogLevel::Warn`](crate::LogLevel::Warn).
+    #[macro_export]
+    macro_rules! log_warn {
+        ($($arg:tt)*) => { $crate::log!($crate::LogLevel::Warn, $($arg)*) };
+    }
+
+    /// Logs a message at [`LogLevel::Error`](crate::LogLevel::Error).
+    #[macro_export]
+    macro_rules! log_error {
+        ($($arg:tt)*) => { $crate::log!($crate::LogLevel::Error, $($arg)*) };
+    }
+
+    #[allow(unused)]
+    #[doc(hidden)]
+    pub fn log_internal(level: super::LogLevel, args: core::fmt::Arguments) {
+        let buf = unsafe { &mut LOG_BUFFER };
+        buf.push(level as u8 as char);
+        let start = buf.len();
+        let _ = core::fmt::write(buf, args);
+        if buf[start..].contains('\0') {
+            let message = buf.split_off(start).replace('\0', " ");
+            buf.push_str(&message);
+        }
+        buf.push('\0');
+    }
+
     /// Draws a line visible in debug mode.
     ///
     /// `a` and `b` are positions in world coordinates.
     /// `color` is 24-bit RGB.
     pub fn draw_line(a: Vec2, b: Vec2, color: u32) {
+        if !ENABLED {
+            return;
+        }
+        if use_shapes() {
+            push_shape(Shape {
+                kind: Shape::LINE,
+                x0: a.x,
+                y0: a.y,
+                x1: b.x,
+                y1: b.y,
+                color,
+                ..Default::default()
+            });
+            return;
+        }
         let buf = unsafe { &mut LINE_BUFFER };
         buf.push(Line {
             x0: a.x,
@@ -851,16 +1286,21 @@ pub mod dbg {
     #[macro_export]
     macro_rules! draw_text {
         ($topleft:expr, $color:expr, $($arg:tt)*) => {
-            $crate::dbg::draw_text_internal($topleft, $color, std::format_args!($($arg)*))
+            if $crate::dbg::ENABLED {
+                $crate::dbg::draw_text_internal($topleft, $color, ::core::format_args!($($arg)*))
+            }
         };
     }
 
     #[allow(unused)]
     #[doc(hidden)]
-    pub fn draw_text_internal(topleft: Vec2, color: u32, args: std::fmt::Arguments) {
-        use std::fmt::Write;
+    pub fn draw_text_internal(topleft: Vec2, color: u32, args: core::fmt::Arguments) {
         let mut text = String::new();
-        let _ = std::fmt::write(&mut text, args);
+        let _ = core::fmt::write(&mut text, args);
+        if use_shapes() {
+            push_shape(text_shape(0, topleft, color, &text));
+            return;
+        }
         let buf = unsafe { &mut DRAWN_TEXT_BUFFER };
         // TODO handle longer text
         let mut text_buf = [0u8; 11];
@@ -877,8 +1317,154 @@ pub mod dbg {
         });
     }
 
+    /// Draws a circle visible in debug mode.
+    ///
+    /// `center` is a position in world coordinates.
+    /// `color` is 24-bit RGB.
+    pub fn draw_circle(center: Vec2, radius: f64, color: u32) {
+        push_shape(Shape {
+            kind: Shape::CIRCLE,
+            x0: center.x,
+            y0: center.y,
+            x1: radius,
+            color,
+            ..Default::default()
+        });
+    }
+
+    /// Draws a filled circle visible in debug mode.
+    ///
+    /// `center` is a position in world coordinates.
+    /// `color` is 24-bit RGB.
+    pub fn fill_circle(center: Vec2, radius: f64, color: u32) {
+        push_shape(Shape {
+            kind: Shape::CIRCLE,
+            flags: Shape::FILLED,
+            x0: center.x,
+            y0: center.y,
+            x1: radius,
+            color,
+            ..Default::default()
+        });
+    }
+
+    /// Draws an arc visible in debug mode.
+    ///
+    /// The arc runs counterclockwise from `start_angle` to `end_angle` (in radians).
+    /// `center` is a position in world coordinates.
+    /// `color` is 24-bit RGB.
+    pub fn draw_arc(center: Vec2, radius: f64, start_angle: f64, end_angle: f64, color: u32) {
+        push_shape(Shape {
+            kind: Shape::ARC,
+            x0: center.x,
+            y0: center.y,
+            x1: radius,
+            angle0: start_angle,
+            angle1: end_angle,
+            color,
+            ..Default::default()
+        });
+    }
+
+    /// Draws a filled circular sector visible in debug mode.
+    ///
+    /// Covers the same angles as [`draw_arc`].
+    pub fn fill_arc(center: Vec2, radius: f64, start_angle: f64, end_angle: f64, color: u32) {
+        push_shape(Shape {
+            kind: Shape::ARC,
+            flags: Shape::FILLED,
+            x0: center.x,
+            y0: center.y,
+            x1: radius,
+            angle0: start_angle,
+            angle1: end_angle,
+            color,
+            ..Default::default()
+        });
+    }
+
+    /// Draws a filled regular polygon visible in debug mode.
+    ///
+    /// Takes the same arguments as [`draw_polygon`].
+    pub fn fill_polygon(center: Vec2, radius: f64, sides: i32, angle: f64, color: u32) {
+        push_shape(Shape {
+            kind: Shape::POLYGON,
+            flags: Shape::FILLED,
+            x0: center.x,
+            y0: center.y,
+            x1: radius,
+            y1: sides as f64,
+            angle0: angle,
+            color,
+            ..Default::default()
+        });
+    }
+
+    /// Puts subsequent drawings in a named layer that can be hidden in the UI.
+    ///
+    /// Lasts until the end of the tick. An empty name selects the default layer.
+    pub fn set_draw_layer(name: &str) {
+        if !ENABLED {
+            return;
+        }
+        let name = name.trim();
+        if name.is_empty() || name.contains('\n') {
+            unsafe { CURRENT_LAYER = 0 };
+            return;
+        }
+        let layers = unsafe { &mut LAYER_BUFFER };
+        let index = match layers.lines().position(|x| x == name) {
+            Some(i) => i + 1,
+            None => {
+                if layers.lines().count() >= u8::MAX as usize {
+                    return;
+                }
+                if !layers.is_empty() {
+                    layers.push('\n');
+                }
+                layers.push_str(name);
+                layers.lines().count()
+            }
+        };
+        unsafe { CURRENT_LAYER = index as u8 };
+    }
+
+    /// Keeps subsequent drawings on screen for this many ticks, instead of one.
+    ///
+    /// Lasts until the end of the tick.
+    pub fn set_draw_ttl(ticks: u32) {
+        if !ENABLED {
+            return;
+        }
+        unsafe { CURRENT_TTL = ticks.max(1) };
+    }
+
+    /// Adds text drawn at a fixed position on the screen, visible in debug mode.
+    ///
+    /// `topleft` is in pixels from the top left corner of the screen.
+    /// Works like [println!].
+    #[macro_export]
+    macro_rules! draw_hud_text {
+        ($topleft:expr, $color:expr, $($arg:tt)*) => {
+            if $crate::dbg::ENABLED {
+                $crate::dbg::draw_hud_text_internal($topleft, $color, ::core::format_args!($($arg)*))
+            }
+        };
+    }
+
+    #[allow(unused)]
+    #[doc(hidden)]
+    pub fn draw_hud_text_internal(topleft: Vec2, color: u32, args: core::fmt::Arguments) {
+        let mut text = String::new();
+        let _ = core::fmt::write(&mut text, args);
+        push_shape(text_shape(Shape::SCREEN, topleft, color, &text));
+    }
+
     #[doc(hidden)]
     pub fn update() {
+        if !ENABLED {
+            return;
+        }
         {
             let slice = unsafe { &mut TEXT_BUFFER }.as_bytes();
             write_system_state(
@@ -912,6 +1498,77 @@ pub mod dbg {
                 slice.len() as u32 as f64,
             );
         }
+        {
+            let slice = unsafe { &mut SHAPE_BUFFER }.as_slice();
+            write_system_state(
+                super::SystemState::DebugShapesPointer,
+                slice.as_ptr() as u32 as f64,
+            );
+            write_system_state(
+                super::SystemState::DebugShapesLength,
+                slice.len() as u32 as f64,
+            );
+        }
+        {
+            let slice = unsafe { &mut LAYER_BUFFER }.as_bytes();
+            write_system_state(
+                super::SystemState::DebugLayersPointer,
+                slice.as_ptr() as u32 as f64,
+            );
+            write_system_state(
+                super::SystemState::DebugLayersLength,
+                slice.len() as u32 as f64,
+            );
+        }
+        {
+            let slice = unsafe { &mut LOG_BUFFER }.as_bytes();
+            write_system_state(
+                super::SystemState::DebugLogPointer,
+                slice.as_ptr() as u32 as f64,
+            );
+            write_system_state(
+                super::SystemState::DebugLogLength,
+                slice.len() as u32 as f64,
+            );
+        }
+    }
+
+    #[cfg(feature = "mock")]
+    pub(crate) fn text_buffer() -> String {
+        unsafe { TEXT_BUFFER.clone() }
+    }
+
+    #[cfg(feature = "mock")]
+    pub(crate) fn line_buffer() -> Vec<Line> {
+        unsafe { LINE_BUFFER.clone() }
+    }
+
+    #[cfg(feature = "mock")]
+    pub(crate) fn drawn_text_buffer() -> Vec<Text> {
+        unsafe { DRAWN_TEXT_BUFFER.clone() }
+    }
+
+    #[cfg(feature = "mock")]
+    pub(crate) fn shape_buffer() -> Vec<Shape> {
+        unsafe { SHAPE_BUFFER.clone() }
+    }
+
+    #[cfg(feature = "mock")]
+    pub(crate) fn layer_names() -> Vec<String> {
+        unsafe { LAYER_BUFFER.lines().map(String::from).collect() }
+    }
+
+    #[cfg(feature = "mock")]
+    pub(crate) fn log_entries() -> Vec<(super::LogLevel, String)> {
+        unsafe {
+            LOG_BUFFER
+                .split_terminator('\0')
+                .filter_map(|x| {
+                    let level = super::LogLevel::from_u8(*x.as_bytes().first()?)?;
+                    Some((level, x[1..].to_string()))
+                })
+                .collect()
+        }
     }
 
     #[doc(hidden)]
@@ -920,6 +1577,11 @@ pub mod dbg {
             TEXT_BUFFER.clear();
             LINE_BUFFER.clear();
             DRAWN_TEXT_BUFFER.clear();
+            SHAPE_BUFFER.clear();
+            LAYER_BUFFER.clear();
+            LOG_BUFFER.clear();
+            CURRENT_LAYER = 0;
+            CURRENT_TTL = 1;
         }
     }
 }
@@ -966,22 +1628,174 @@ mod deprecated {
     }
 }
 
+mod events {
+    use super::sys::read_system_state;
+    use super::{Ability, Message, SystemState};
+
+    /// Optional callbacks for things that happened since the previous tick.
+    ///
+    /// Implement this for your `Ship` to have the callbacks called just before
+    /// `tick()`. Every method has an empty default implementation, so only the
+    /// events you care about need to be handled:
+    ///
+    /// ```ignore
+    /// impl EventHandler for Ship {
+    ///     fn on_damaged(&mut self, amount: f64) {
+    ///         debug!("took {} damage", amount);
+    ///     }
+    /// }
+    /// ```
+    pub trait EventHandler {
+        /// Called when the ship's health dropped since the previous tick.
+        fn on_damaged(&mut self, _amount: f64) {}
+
+        /// Called when the radar had a contact on the previous tick but not on this one.
+        fn on_contact_lost(&mut self) {}
+
+        /// Called for each radio that received a message, with the radio's channel.
+        fn on_radio(&mut self, _channel: usize, _msg: Message) {}
+
+        /// Called when a missile or torpedo was launched, with the index passed to `fire()`.
+        fn on_missile_launched(&mut self, _index: usize) {}
+
+        /// Called when an ability that was active on the previous tick stopped being active.
+        fn on_ability_ended(&mut self, _ability: Ability) {}
+    }
+
+    #[doc(hidden)]
+    pub mod events_internal {
+        use super::*;
+        use crate::api::radio_internal::{radio_indices, MAX_RADIOS};
+        use core::cell::RefCell;
+
+        pub const DAMAGED: u32 = 1 << 0;
+        pub const CONTACT_LOST: u32 = 1 << 1;
+        // One bit per radio, starting here.
+        pub const RADIO_RECEIVED: u32 = 1 << 8;
+        // One bit per weapon index, starting here.
+        pub const MISSILE_LAUNCHED: u32 = 1 << 16;
+        pub const MAX_LAUNCH_EVENTS: usize = 4;
+        // One bit per `Ability` discriminant, starting here.
+        pub const ABILITY_ENDED: u32 = 1 << 20;
+        pub const ABILITIES: [Ability; 4] = [
+            Ability::Boost,
+            Ability::ShapedCharge,
+            Ability::Decoy,
+            Ability::Shield,
+        ];
+
+        pub fn deliver<T: EventHandler>(handler: &mut T) {
+            let events = read_system_state(SystemState::Events) as u32;
+            if events & DAMAGED != 0 {
+                handler.on_damaged(read_system_state(SystemState::DamageTaken));
+            }
+            if events & CONTACT_LOST != 0 {
+                handler.on_contact_lost();
+            }
+            for i in 0..MAX_RADIOS {
+                if events & (RADIO_RECEIVED << i) == 0 {
+                    continue;
+                }
+                let idxs = radio_indices(i);
+                handler.on_radio(
+                    read_system_state(idxs.channel) as usize,
+                    [
+                        read_system_state(idxs.data[0]),
+                        read_system_state(idxs.data[1]),
+                        read_system_state(idxs.data[2]),
+                        read_system_state(idxs.data[3]),
+                    ],
+                );
+            }
+            for i in 0..MAX_LAUNCH_EVENTS {
+                if events & (MISSILE_LAUNCHED << i) != 0 {
+                    handler.on_missile_launched(i);
+                }
+            }
+            for ability in ABILITIES {
+                if events & (ABILITY_ENDED << ability as u32) != 0 {
+                    handler.on_ability_ended(ability);
+                }
+            }
+        }
+
+        // Calls deliver() only if the ship implements EventHandler, using
+        // autoref-based specialization: method lookup tries `&Dispatch<T>`
+        // (which needs the bound) before `Dispatch<T>`.
+        pub struct Dispatch<'a, T>(pub RefCell<&'a mut T>);
+
+        pub trait ViaHandler {
+            fn dispatch(&self);
+        }
+
+        impl<T: EventHandler> ViaHandler for &Dispatch<'_, T> {
+            fn dispatch(&self) {
+                deliver(&mut **self.0.borrow_mut());
+            }
+        }
+
+        pub trait ViaNothing {
+            fn dispatch(&self);
+        }
+
+        impl<T> ViaNothing for Dispatch<'_, T> {
+            fn dispatch(&self) {}
+        }
+    }
+}
+
+#[doc(hidden)]
+pub use events::events_internal;
+
+/// Dispatches pending events to a ship if it implements `EventHandler`.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! dispatch_events {
+    ($ship:expr) => {{
+        #[allow(unused_imports)]
+        use $crate::events_internal::{Dispatch, ViaHandler, ViaNothing};
+        (&&Dispatch(::core::cell::RefCell::new($ship))).dispatch();
+    }};
+}
+
 /// All APIs.
 pub mod prelude {
     #[doc(inline)]
     pub use super::api::*;
     #[doc(inline)]
+    pub use super::codec::{receive_message, send_message, RadioMessage};
+    #[doc(inline)]
     pub use super::dbg::*;
     #[doc(hidden)]
     pub use super::deprecated::*;
     #[doc(inline)]
-    pub use super::math::*;
+    pub use super::events::EventHandler;
+    #[doc(inline)]
+    pub use super::math::{angle_diff, PI, TAU};
     #[doc(inline)]
     pub use super::rng::*;
     #[doc(inline)]
     pub use super::vec::*;
     #[doc(inline)]
-    pub use super::{Ability, Class, EcmMode, Message};
+    pub use super::{Ability, Class, EcmMode, LogLevel, Message};
     #[doc(inline)]
-    pub use crate::{debug, draw_text};
+    pub use crate::{
+        debug, draw_hud_text, draw_text, log, log_debug, log_error, log_info, log_trace, log_warn,
+        radio_message,
+    };
+
+    // Stand in for the std prelude and f64 methods in no_std builds.
+    #[cfg(not(feature = "std"))]
+    #[doc(hidden)]
+    pub use super::float::F64Ext;
+    #[cfg(not(feature = "std"))]
+    #[doc(hidden)]
+    pub use alloc::{
+        borrow::ToOwned,
+        boxed::Box,
+        format,
+        string::{String, ToString},
+        vec,
+        vec::Vec,
+    };
 }
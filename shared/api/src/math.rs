@@ -0,0 +1,367 @@
+//! Targeting and kinematics helpers.
+//!
+//! Only [`angle_diff`], [`PI`] and [`TAU`] are in the prelude. Import the rest
+//! with `use oort_api::math::*`.
+//!
+//! Positions and velocities passed to these functions are relative to the
+//! shooter: `dp` is the target's position minus ours and `dv` is the target's
+//! velocity minus ours. Bullets inherit the velocity of the ship that fired
+//! them and don't accelerate, so no "bullet drop" is needed.
+//!
+//! Everything here is plain floating point arithmetic with no iteration
+//! limits that depend on timing, so results are identical on every run.
+//!
+//! ```
+//! use oort_api::math::*;
+//! use oort_api::prelude::vec2;
+//!
+//! // Target 1 km ahead, crossing at 100 m/s. Bullets travel at 1 km/s.
+//! let t = intercept_time(vec2(1000.0, 0.0), vec2(0.0, 100.0), 1000.0).unwrap();
+//! assert!((t - 1.005).abs() < 1e-3);
+//! ```
+use crate::api::{
+    angular_velocity, heading, max_angular_acceleration, position, velocity, GunInfo,
+};
+#[cfg(not(feature = "std"))]
+use crate::float::F64Ext;
+use crate::vec::Vec2;
+use alloc::{vec, vec::Vec};
+
+pub use core::f64::consts::{PI, TAU};
+
+/// Returns the smallest rotation between angles `a` and `b`.
+///
+/// A positive result is a counter-clockwise rotation and negative is clockwise.
+pub fn angle_diff(a: f64, b: f64) -> f64 {
+    let c = (b - a).rem_euclid(TAU);
+    if c > PI {
+        c - TAU
+    } else {
+        c
+    }
+}
+
+/// Returns the time until a projectile fired now at `speed` can hit a target
+/// at relative position `dp` moving with constant relative velocity `dv`.
+///
+/// Returns `None` if the projectile can never catch the target.
+pub fn intercept_time(dp: Vec2, dv: Vec2, speed: f64) -> Option<f64> {
+    // |dp + dv*t| = speed*t
+    let roots = solve_quadratic(dv.dot(dv) - speed * speed, 2.0 * dp.dot(dv), dp.dot(dp));
+    smallest_positive(&roots)
+}
+
+/// Like [`intercept_time`] but for a target with constant relative
+/// acceleration `da`.
+pub fn intercept_time_accel(dp: Vec2, dv: Vec2, da: Vec2, speed: f64) -> Option<f64> {
+    // |dp + dv*t + da*t^2/2| = speed*t
+    let roots = solve_quartic(
+        0.25 * da.dot(da),
+        da.dot(dv),
+        dv.dot(dv) + da.dot(dp) - speed * speed,
+        2.0 * dp.dot(dv),
+        dp.dot(dp),
+    );
+    smallest_positive(&roots)
+}
+
+/// Returns the relative position where a projectile fired now at `speed` will
+/// meet a target with constant relative velocity `dv`.
+pub fn lead(dp: Vec2, dv: Vec2, speed: f64) -> Option<Vec2> {
+    intercept_time(dp, dv, speed).map(|t| dp + dv * t)
+}
+
+/// Like [`lead`] but for a target with constant relative acceleration `da`.
+pub fn lead_accel(dp: Vec2, dv: Vec2, da: Vec2, speed: f64) -> Option<Vec2> {
+    intercept_time_accel(dp, dv, da, speed).map(|t| dp + dv * t + da * (0.5 * t * t))
+}
+
+/// Returns the heading to fire `gun` at to hit a target with the given
+/// absolute position and velocity.
+///
+/// Accounts for the gun's offset from the center of the ship.
+pub fn gun_lead_heading(
+    gun: &GunInfo,
+    target_position: Vec2,
+    target_velocity: Vec2,
+) -> Option<f64> {
+    let origin = position() + gun.offset.rotate(heading());
+    lead(
+        target_position - origin,
+        target_velocity - velocity(),
+        gun.speed,
+    )
+    .map(|p| p.angle())
+}
+
+/// Returns the lateral acceleration commanded by proportional navigation.
+///
+/// `dp` and `dv` are the target's position and velocity relative to the
+/// missile. Typical values of `navigation_constant` are between 3 and 5. The
+/// result is perpendicular to the line of sight.
+pub fn proportional_navigation(dp: Vec2, dv: Vec2, navigation_constant: f64) -> Vec2 {
+    let range_squared = dp.dot(dp);
+    if range_squared == 0.0 {
+        return Vec2::new(0.0, 0.0);
+    }
+    let range = range_squared.sqrt();
+    let los_rate = dp.cross(dv) / range_squared;
+    let closing_speed = -dp.dot(dv) / range;
+    let normal = Vec2::new(-dp.y, dp.x) / range;
+    normal * (navigation_constant * closing_speed * los_rate)
+}
+
+/// Returns the minimum time to rotate by `angle` and come to rest, starting
+/// with angular velocity `angular_velocity` and accelerating at most
+/// `max_angular_acceleration`.
+pub fn time_to_turn(angle: f64, angular_velocity: f64, max_angular_acceleration: f64) -> f64 {
+    let a = max_angular_acceleration;
+    if a <= 0.0 {
+        return f64::INFINITY;
+    }
+    // Double integrator with position x (distance past the goal) and velocity v.
+    let x = -angle;
+    let v = angular_velocity;
+    let s = x + v * v.abs() / (2.0 * a);
+    if s > 0.0 {
+        (v + 2.0 * (0.5 * v * v + a * x).sqrt()) / a
+    } else if s < 0.0 {
+        (-v + 2.0 * (0.5 * v * v - a * x).sqrt()) / a
+    } else {
+        v.abs() / a
+    }
+}
+
+/// Returns the minimum time for this ship to turn to `target_heading`.
+pub fn time_to_heading(target_heading: f64) -> f64 {
+    time_to_turn(
+        angle_diff(heading(), target_heading),
+        angular_velocity(),
+        max_angular_acceleration(),
+    )
+}
+
+fn smallest_positive(roots: &[f64]) -> Option<f64> {
+    roots.iter().copied().filter(|&t| t > 0.0).reduce(f64::min)
+}
+
+// Real roots of a*x^2 + b*x + c.
+fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a.abs() < 1e-12 * (b.abs() + c.abs()).max(1e-300) {
+        if b == 0.0 {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return vec![];
+    }
+    // Avoids cancellation when b*b >> 4*a*c.
+    let q = -0.5 * (b + b.signum() * disc.sqrt());
+    if q == 0.0 {
+        return vec![0.0];
+    }
+    vec![q / a, c / q]
+}
+
+// Real roots of a*x^3 + b*x^2 + c*x + d.
+fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a.abs() < 1e-12 * (b.abs() + c.abs() + d.abs()).max(1e-300) {
+        return solve_quadratic(b, c, d);
+    }
+    let (b, c, d) = (b / a, c / a, d / a);
+    // Depressed cubic y^3 + p*y + q with x = y - b/3.
+    let shift = b / 3.0;
+    let p = c - b * shift;
+    let q = 2.0 * shift * shift * shift - shift * c + d;
+    let disc = q * q / 4.0 + p * p * p / 27.0;
+    let roots = if disc > 0.0 {
+        let u = (-q / 2.0 + disc.sqrt()).cbrt();
+        let v = (-q / 2.0 - disc.sqrt()).cbrt();
+        vec![u + v]
+    } else if p == 0.0 {
+        vec![0.0]
+    } else {
+        // Three real roots.
+        let r = 2.0 * (-p / 3.0).sqrt();
+        let phi = (3.0 * q / (p * r)).clamp(-1.0, 1.0).acos() / 3.0;
+        (0..3)
+            .map(|k| r * (phi - TAU * k as f64 / 3.0).cos())
+            .collect()
+    };
+    roots.into_iter().map(|y| y - shift).collect()
+}
+
+// Real roots of a*x^4 + b*x^3 + c*x^2 + d*x + e, using Ferrari's method.
+fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a.abs() < 1e-12 * (b.abs() + c.abs() + d.abs() + e.abs()).max(1e-300) {
+        return solve_cubic(b, c, d, e);
+    }
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+    // Depressed quartic y^4 + p*y^2 + q*y + r with x = y - b/4.
+    let shift = b / 4.0;
+    let p = c - 6.0 * shift * shift;
+    let q = d - 2.0 * c * shift + 8.0 * shift * shift * shift;
+    let r = e - d * shift + c * shift * shift - 3.0 * shift * shift * shift * shift;
+    let mut roots = if q.abs() < 1e-12 * (p.abs() + r.abs()).max(1e-300) {
+        // Biquadratic.
+        solve_quadratic(1.0, p, r)
+            .into_iter()
+            .filter(|&z| z >= 0.0)
+            .flat_map(|z| [z.sqrt(), -z.sqrt()])
+            .collect()
+    } else {
+        // Any positive root of the resolvent cubic splits the quartic into
+        // two quadratics.
+        let m = solve_cubic(1.0, p, 0.25 * p * p - r, -0.125 * q * q)
+            .into_iter()
+            .reduce(f64::max)
+            .unwrap_or(0.0);
+        if m <= 0.0 {
+            return vec![];
+        }
+        let s = (2.0 * m).sqrt();
+        let mut roots = solve_quadratic(1.0, -s, 0.5 * p + m + q / (2.0 * s));
+        roots.extend(solve_quadratic(1.0, s, 0.5 * p + m - q / (2.0 * s)));
+        roots
+    };
+    // Polish the roots against the original polynomial.
+    for x in roots.iter_mut() {
+        *x -= shift;
+        for _ in 0..2 {
+            let f = (((*x + b) * *x + c) * *x + d) * *x + e;
+            let df = ((4.0 * *x + 3.0 * b) * *x + 2.0 * c) * *x + d;
+            if df != 0.0 {
+                *x -= f / df;
+            }
+        }
+    }
+    roots
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::vec::vec2;
+
+    fn assert_near(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-6 * b.abs().max(1.0), "{a} != {b}");
+    }
+
+    #[test]
+    fn test_angle_diff() {
+        assert_near(angle_diff(0.0, 1.0), 1.0);
+        assert_near(angle_diff(1.0, 0.0), -1.0);
+        assert_near(angle_diff(0.1, TAU - 0.1), -0.2);
+        assert_near(angle_diff(TAU - 0.1, 0.1), 0.2);
+    }
+
+    #[test]
+    fn test_polynomials() {
+        let mut roots = solve_cubic(1.0, -6.0, 11.0, -6.0);
+        roots.sort_by(f64::total_cmp);
+        assert_eq!(roots.len(), 3);
+        for (x, y) in roots.iter().zip([1.0, 2.0, 3.0]) {
+            assert_near(*x, y);
+        }
+
+        // (x-1)(x-2)(x-3)(x+4)
+        let mut roots = solve_quartic(1.0, -2.0, -13.0, 38.0, -24.0);
+        roots.sort_by(f64::total_cmp);
+        assert_eq!(roots.len(), 4);
+        for (x, y) in roots.iter().zip([-4.0, 1.0, 2.0, 3.0]) {
+            assert_near(*x, y);
+        }
+
+        // (x^2+1)(x-5)(x+0.5)
+        let mut roots = solve_quartic(1.0, -4.5, -1.5, -4.5, -2.5);
+        roots.sort_by(f64::total_cmp);
+        assert_eq!(roots.len(), 2);
+        assert_near(roots[0], -0.5);
+        assert_near(roots[1], 5.0);
+
+        assert!(solve_quartic(1.0, 0.0, 0.0, 0.0, 1.0).is_empty());
+    }
+
+    #[test]
+    fn test_intercept() {
+        // Stationary target.
+        assert_near(
+            intercept_time(vec2(1000.0, 0.0), vec2(0.0, 0.0), 500.0).unwrap(),
+            2.0,
+        );
+
+        // Crossing target.
+        let dp = vec2(1000.0, 0.0);
+        let dv = vec2(0.0, 100.0);
+        let t = intercept_time(dp, dv, 1000.0).unwrap();
+        let p = lead(dp, dv, 1000.0).unwrap();
+        assert_near(p.length(), 1000.0 * t);
+        assert_near(p.y, 100.0 * t);
+
+        // Target outrunning the bullet.
+        assert_eq!(intercept_time(dp, vec2(2000.0, 0.0), 1000.0), None);
+
+        // Closing target faster than the bullet.
+        assert_near(
+            intercept_time(dp, vec2(-2000.0, 0.0), 1000.0).unwrap(),
+            1.0 / 3.0,
+        );
+    }
+
+    #[test]
+    fn test_intercept_accel() {
+        let dp = vec2(1000.0, 500.0);
+        let dv = vec2(-50.0, 80.0);
+        let speed = 1000.0;
+
+        // Zero acceleration matches the constant velocity solution.
+        assert_near(
+            intercept_time_accel(dp, dv, vec2(0.0, 0.0), speed).unwrap(),
+            intercept_time(dp, dv, speed).unwrap(),
+        );
+
+        let da = vec2(30.0, -60.0);
+        let t = intercept_time_accel(dp, dv, da, speed).unwrap();
+        let p = lead_accel(dp, dv, da, speed).unwrap();
+        assert_near(p.length(), speed * t);
+        assert_near(p.x, dp.x + dv.x * t + 0.5 * da.x * t * t);
+
+        // Accelerating away eventually escapes.
+        assert_eq!(
+            intercept_time_accel(dp, vec2(0.0, 0.0), vec2(1e6, 0.0), speed),
+            None
+        );
+    }
+
+    #[test]
+    fn test_proportional_navigation() {
+        // Head-on collision course: no line of sight rotation.
+        let a = proportional_navigation(vec2(1000.0, 0.0), vec2(-100.0, 0.0), 4.0);
+        assert_near(a.length(), 0.0);
+
+        // Target crossing to the left commands a left turn.
+        let a = proportional_navigation(vec2(1000.0, 0.0), vec2(-100.0, 50.0), 4.0);
+        assert_near(a.x, 0.0);
+        assert_near(a.y, 4.0 * 100.0 * 50.0 / 1000.0);
+
+        assert_eq!(
+            proportional_navigation(vec2(0.0, 0.0), vec2(1.0, 0.0), 4.0),
+            vec2(0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn test_time_to_turn() {
+        // From rest: accelerate halfway, decelerate halfway.
+        assert_near(time_to_turn(1.0, 0.0, 1.0), 2.0);
+        assert_near(time_to_turn(-1.0, 0.0, 1.0), 2.0);
+        // Already turning the right way at the ideal rate.
+        assert_near(time_to_turn(0.5, 1.0, 1.0), 1.0);
+        // Turning the wrong way has to stop first.
+        assert!(time_to_turn(1.0, -1.0, 1.0) > time_to_turn(1.0, 0.0, 1.0));
+        assert_near(time_to_turn(0.0, 0.0, 1.0), 0.0);
+        assert_eq!(time_to_turn(1.0, 0.0, 0.0), f64::INFINITY);
+    }
+}
@@ -0,0 +1,747 @@
+//! Native mock of the simulator for unit testing ship code.
+//!
+//! Requires the `mock` feature. A [`Mock`] owns the system state that the
+//! API functions read and write, so a test can set up the inputs for a tick,
+//! call `Ship::tick`, and check the commands it issued:
+//!
+//! ```ignore
+//! use oort_api::mock::Mock;
+//! use oort_api::prelude::*;
+//!
+//! #[test]
+//! fn fires_at_target() {
+//!     let mut mock = Mock::new(Class::Fighter);
+//!     let mut ship = Ship::new();
+//!     mock.set_contact(Some(ScanResult {
+//!         class: Class::Fighter,
+//!         position: vec2(1000.0, 0.0),
+//!         velocity: vec2(0.0, 0.0),
+//!         rssi: -50.0,
+//!         snr: 50.0,
+//!     }));
+//!     mock.tick(|| ship.tick());
+//!     assert!(mock.fired(0));
+//! }
+//! ```
+//!
+//! [`Mock::step`] advances a simple kinematic model (no collisions, bullets
+//! or radar) so that guidance code can be tested over many ticks.
+//!
+//! The API keeps its state in globals, so only one [`Mock`] can exist at a
+//! time. Creating a second one blocks until the first is dropped, which
+//! serializes tests that run in parallel.
+use crate::api::{loadout_internal, radio_internal, AbilityInfo, ScanResult, Weapon, TICK_LENGTH};
+use crate::events::{events_internal, EventHandler};
+use crate::sys::{read_system_state, write_loadout, write_system_state};
+use crate::vec::*;
+use crate::{
+    dbg, rng_state, Ability, Class, Line, LogLevel, Message, Shape, SystemState,
+    MAX_ENVIRONMENT_SIZE,
+};
+use std::collections::BTreeMap;
+use std::f64::consts::TAU;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+const AIM: [SystemState; 4] = [
+    SystemState::Aim0,
+    SystemState::Aim1,
+    SystemState::Aim2,
+    SystemState::Aim3,
+];
+
+const FIRE: [SystemState; 4] = [
+    SystemState::Fire0,
+    SystemState::Fire1,
+    SystemState::Fire2,
+    SystemState::Fire3,
+];
+
+const RELOAD_TICKS: [SystemState; 4] = [
+    SystemState::ReloadTicks0,
+    SystemState::ReloadTicks1,
+    SystemState::ReloadTicks2,
+    SystemState::ReloadTicks3,
+];
+
+/// A single simulated ship.
+pub struct Mock {
+    environment: BTreeMap<String, String>,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl Mock {
+    /// Creates a stationary ship at the origin with the limits of the given class.
+    ///
+    /// Create the mock before calling `Ship::new`, since constructors often
+    /// read the ship class or seed.
+    pub fn new(class: Class) -> Mock {
+        let guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        unsafe {
+            crate::sys::SYSTEM_STATE = [0.0; SystemState::MaxSize as usize];
+            crate::sys::LOADOUT = [0.0; loadout_internal::SIZE];
+        }
+
+        let (health, forward, backward, lateral, angular, fuel) = match class {
+            Class::Fighter => (100.0, 60.0, 30.0, 30.0, TAU, f64::INFINITY),
+            Class::Frigate => (10000.0, 10.0, 5.0, 5.0, TAU / 8.0, f64::INFINITY),
+            Class::Cruiser => (20000.0, 5.0, 2.5, 2.5, TAU / 16.0, f64::INFINITY),
+            Class::Missile => (20.0, 300.0, 0.0, 100.0, 4.0 * TAU, 2000.0),
+            Class::Torpedo => (100.0, 70.0, 0.0, 20.0, 2.0 * TAU, 3000.0),
+            _ => (100.0, 0.0, 0.0, 0.0, 0.0, f64::INFINITY),
+        };
+        write_system_state(SystemState::Class, class as u32 as f64);
+        write_system_state(SystemState::Id, 1.0);
+        write_system_state(SystemState::Health, health);
+        write_system_state(SystemState::MaxForwardAcceleration, forward);
+        write_system_state(SystemState::MaxBackwardAcceleration, backward);
+        write_system_state(SystemState::MaxLateralAcceleration, lateral);
+        write_system_state(SystemState::MaxAngularAcceleration, angular);
+        write_system_state(SystemState::Fuel, fuel);
+        write_system_state(SystemState::RadarWidth, TAU / 16.0);
+        write_system_state(SystemState::RadarMaxDistance, 1e9);
+        write_system_state(SystemState::GasRemaining, crate::GAS_PER_TICK as f64);
+
+        let mut mock = Mock {
+            environment: BTreeMap::new(),
+            _guard: guard,
+        };
+        mock.set_seed(0);
+        mock.update_environment();
+        dbg::reset();
+        mock
+    }
+
+    /// Sets the value returned by `seed()` and reseeds `rand()`.
+    pub fn set_seed(&mut self, seed: u32) {
+        write_system_state(SystemState::Seed, seed as f64);
+        unsafe { rng_state::set(rng_state::RngState::new()) };
+    }
+
+    /// Sets the position (in meters).
+    pub fn set_position(&mut self, position: Vec2) {
+        write_system_state(SystemState::PositionX, position.x);
+        write_system_state(SystemState::PositionY, position.y);
+    }
+
+    /// Sets the velocity (in m/s).
+    pub fn set_velocity(&mut self, velocity: Vec2) {
+        write_system_state(SystemState::VelocityX, velocity.x);
+        write_system_state(SystemState::VelocityY, velocity.y);
+    }
+
+    /// Sets the heading (in radians).
+    pub fn set_heading(&mut self, heading: f64) {
+        write_system_state(SystemState::Heading, heading);
+    }
+
+    /// Sets the angular velocity (in radians/s).
+    pub fn set_angular_velocity(&mut self, angular_velocity: f64) {
+        write_system_state(SystemState::AngularVelocity, angular_velocity);
+    }
+
+    /// Sets the current health.
+    pub fn set_health(&mut self, health: f64) {
+        write_system_state(SystemState::Health, health);
+    }
+
+    /// Sets the remaining fuel (delta-v).
+    pub fn set_fuel(&mut self, fuel: f64) {
+        write_system_state(SystemState::Fuel, fuel);
+    }
+
+    /// Sets the value returned by `current_tick()`.
+    pub fn set_tick(&mut self, tick: u32) {
+        write_system_state(SystemState::CurrentTick, tick as f64);
+    }
+
+    /// Sets the value returned by `reload_ticks(index)`.
+    pub fn set_reload_ticks(&mut self, index: usize, ticks: u32) {
+        write_system_state(RELOAD_TICKS[index], ticks as f64);
+    }
+
+    /// Sets the result of `scan()` for the following ticks.
+    pub fn set_contact(&mut self, contact: Option<ScanResult>) {
+        if let Some(contact) = contact {
+            write_system_state(SystemState::RadarContactFound, 1.0);
+            write_system_state(SystemState::RadarContactClass, contact.class as u32 as f64);
+            write_system_state(SystemState::RadarContactPositionX, contact.position.x);
+            write_system_state(SystemState::RadarContactPositionY, contact.position.y);
+            write_system_state(SystemState::RadarContactVelocityX, contact.velocity.x);
+            write_system_state(SystemState::RadarContactVelocityY, contact.velocity.y);
+            write_system_state(SystemState::RadarContactRssi, contact.rssi);
+            write_system_state(SystemState::RadarContactSnr, contact.snr);
+        } else {
+            write_system_state(SystemState::RadarContactFound, 0.0);
+        }
+    }
+
+    /// Sets the message returned by `receive()` on the given radio.
+    pub fn set_received(&mut self, radio: usize, msg: Option<Message>) {
+        let idxs = radio_internal::radio_indices(radio);
+        if let Some(msg) = msg {
+            write_system_state(idxs.receive, 1.0);
+            for (idx, v) in idxs.data.iter().zip(msg) {
+                write_system_state(*idx, v);
+            }
+        } else {
+            write_system_state(idxs.receive, 0.0);
+        }
+        set_event(events_internal::RADIO_RECEIVED << radio, msg.is_some());
+    }
+
+    /// Sets the damage reported to `EventHandler::on_damaged`, or 0 for none.
+    pub fn set_damage_taken(&mut self, amount: f64) {
+        write_system_state(SystemState::DamageTaken, amount);
+        set_event(events_internal::DAMAGED, amount > 0.0);
+    }
+
+    /// Sets whether `EventHandler::on_contact_lost` is called.
+    pub fn set_contact_lost(&mut self, lost: bool) {
+        set_event(events_internal::CONTACT_LOST, lost);
+    }
+
+    /// Sets whether `EventHandler::on_missile_launched` is called for the weapon `index`.
+    pub fn set_missile_launched(&mut self, index: usize, launched: bool) {
+        set_event(events_internal::MISSILE_LAUNCHED << index, launched);
+    }
+
+    /// Sets whether `EventHandler::on_ability_ended` is called for `ability`.
+    pub fn set_ability_ended(&mut self, ability: Ability, ended: bool) {
+        set_event(events_internal::ABILITY_ENDED << ability as u32, ended);
+    }
+
+    /// Calls the ship's [`EventHandler`] methods for the events set up so far,
+    /// as the game does before each `tick()`.
+    pub fn dispatch_events(&mut self, ship: &mut impl EventHandler) {
+        events_internal::deliver(ship);
+    }
+
+    /// Sets the list returned by `weapons()`.
+    pub fn set_weapons(&mut self, weapons: &[Weapon]) {
+        use loadout_internal::*;
+        for i in 0..MAX_WEAPONS {
+            for field in 0..WEAPON_STRIDE {
+                write_loadout(weapon_index(i, field), 0.0);
+            }
+        }
+        for (i, weapon) in weapons.iter().take(MAX_WEAPONS).enumerate() {
+            let set = |field, value| write_loadout(weapon_index(i, field), value);
+            match weapon {
+                Weapon::Gun(gun) => {
+                    set(KIND, KIND_GUN);
+                    set(SPEED, gun.speed);
+                    set(SPEED_ERROR, gun.speed_error);
+                    set(OFFSET_X, gun.offset.x);
+                    set(OFFSET_Y, gun.offset.y);
+                    set(HEADING, gun.heading);
+                    set(MIN_ANGLE, gun.min_angle);
+                    set(MAX_ANGLE, gun.max_angle);
+                    set(INACCURACY, gun.inaccuracy);
+                    set(RELOAD_TICKS, gun.reload_ticks as f64);
+                    set(MAGAZINE_SIZE, gun.magazine_size as f64);
+                    set(MAGAZINE_REMAINING, gun.magazine_remaining as f64);
+                    set(MAGAZINE_RELOAD_TICKS, gun.magazine_reload_ticks as f64);
+                    set(BURST_SIZE, gun.burst_size as f64);
+                    set(BULLET_TTL, gun.bullet_ttl);
+                    set(BULLET_MASS, gun.bullet_mass);
+                }
+                Weapon::Launcher(launcher) => {
+                    set(KIND, KIND_LAUNCHER);
+                    set(SPEED, launcher.speed);
+                    set(OFFSET_X, launcher.offset.x);
+                    set(OFFSET_Y, launcher.offset.y);
+                    set(HEADING, launcher.heading);
+                    set(RELOAD_TICKS, launcher.reload_ticks as f64);
+                    set(LAUNCHER_CLASS, launcher.class as u32 as f64);
+                }
+            }
+        }
+        write_loadout(NUM_WEAPONS, weapons.len().min(MAX_WEAPONS) as f64);
+    }
+
+    /// Sets the list returned by `abilities()`.
+    pub fn set_abilities(&mut self, abilities: &[AbilityInfo]) {
+        use loadout_internal::*;
+        for (i, ability) in abilities.iter().take(MAX_ABILITIES).enumerate() {
+            let set = |field, value| write_loadout(ability_index(i, field), value);
+            set(ABILITY, ability.ability as u32 as f64);
+            set(ACTIVE_TIME, ability.active_time);
+            set(RELOAD_TIME, ability.reload_time);
+            set(ACTIVE_TIME_REMAINING, ability.active_time_remaining);
+            set(RELOAD_TIME_REMAINING, ability.reload_time_remaining);
+        }
+        write_loadout(NUM_ABILITIES, abilities.len().min(MAX_ABILITIES) as f64);
+    }
+
+    /// Sets a variable returned by the scenario environment (e.g. `SCENARIO_NAME`).
+    pub fn set_env(&mut self, key: &str, value: &str) {
+        self.environment.insert(key.to_string(), value.to_string());
+        self.update_environment();
+    }
+
+    /// Reads a raw system state value.
+    pub fn get_state(&self, index: SystemState) -> f64 {
+        read_system_state(index)
+    }
+
+    /// Writes a raw system state value.
+    pub fn set_state(&mut self, index: SystemState, value: f64) {
+        write_system_state(index, value);
+    }
+
+    /// Runs one tick of ship code, typically `|| ship.tick()`.
+    ///
+    /// Commands from the previous tick and debug output are cleared first.
+    pub fn tick(&mut self, f: impl FnOnce()) {
+        write_system_state(SystemState::AccelerateX, 0.0);
+        write_system_state(SystemState::AccelerateY, 0.0);
+        write_system_state(SystemState::Torque, 0.0);
+        write_system_state(SystemState::Explode, 0.0);
+        for idx in FIRE {
+            write_system_state(idx, 0.0);
+        }
+        for i in 0..radio_internal::MAX_RADIOS {
+            write_system_state(radio_internal::radio_indices(i).send, 0.0);
+        }
+        dbg::reset();
+        f();
+    }
+
+    /// Applies the last tick's commands to a simple kinematic model and
+    /// advances the current tick.
+    ///
+    /// Acceleration and torque are clamped to the ship's limits, as in the game.
+    pub fn step(&mut self) {
+        let heading = read_system_state(SystemState::Heading);
+        let mut acceleration = self.clamped_acceleration().rotate(heading);
+        let fuel = read_system_state(SystemState::Fuel);
+        let delta_v = acceleration.length() * TICK_LENGTH;
+        if delta_v > fuel {
+            acceleration = vec2(0.0, 0.0);
+        } else {
+            write_system_state(SystemState::Fuel, fuel - delta_v);
+        }
+        let velocity = self.velocity() + acceleration * TICK_LENGTH;
+        let position = self.position() + velocity * TICK_LENGTH;
+        self.set_velocity(velocity);
+        self.set_position(position);
+
+        let max_angular_acceleration = read_system_state(SystemState::MaxAngularAcceleration);
+        let torque = self
+            .torque()
+            .clamp(-max_angular_acceleration, max_angular_acceleration);
+        let angular_velocity =
+            read_system_state(SystemState::AngularVelocity) + torque * TICK_LENGTH;
+        self.set_angular_velocity(angular_velocity);
+        self.set_heading((heading + angular_velocity * TICK_LENGTH).rem_euclid(TAU));
+
+        for idx in RELOAD_TICKS {
+            write_system_state(idx, (read_system_state(idx) - 1.0).max(0.0));
+        }
+        let tick = read_system_state(SystemState::CurrentTick);
+        write_system_state(SystemState::CurrentTick, tick + 1.0);
+    }
+
+    /// Calls [`tick`](Mock::tick) and [`step`](Mock::step) `ticks` times.
+    pub fn run(&mut self, ticks: u32, mut f: impl FnMut()) {
+        for _ in 0..ticks {
+            self.tick(&mut f);
+            self.step();
+        }
+    }
+
+    /// Returns the current position (in meters).
+    pub fn position(&self) -> Vec2 {
+        vec2(
+            read_system_state(SystemState::PositionX),
+            read_system_state(SystemState::PositionY),
+        )
+    }
+
+    /// Returns the current velocity (in m/s).
+    pub fn velocity(&self) -> Vec2 {
+        vec2(
+            read_system_state(SystemState::VelocityX),
+            read_system_state(SystemState::VelocityY),
+        )
+    }
+
+    /// Returns the acceleration requested by `accelerate()`, in world coordinates.
+    pub fn acceleration(&self) -> Vec2 {
+        vec2(
+            read_system_state(SystemState::AccelerateX),
+            read_system_state(SystemState::AccelerateY),
+        )
+        .rotate(read_system_state(SystemState::Heading))
+    }
+
+    /// Returns the angular acceleration requested by `torque()` or `turn()`.
+    pub fn torque(&self) -> f64 {
+        read_system_state(SystemState::Torque)
+    }
+
+    /// Returns the heading passed to `aim(index)`.
+    pub fn aim(&self, index: usize) -> f64 {
+        read_system_state(AIM[index])
+    }
+
+    /// Returns whether `fire(index)` was called during the last tick.
+    pub fn fired(&self, index: usize) -> bool {
+        read_system_state(FIRE[index]) > 0.0
+    }
+
+    /// Returns whether `explode()` was called during the last tick.
+    pub fn exploded(&self) -> bool {
+        read_system_state(SystemState::Explode) > 0.0
+    }
+
+    /// Returns the ability passed to `activate_ability()`.
+    pub fn ability(&self) -> Ability {
+        match read_system_state(SystemState::ActivateAbility) as u32 {
+            x if x == Ability::Boost as u32 => Ability::Boost,
+            x if x == Ability::ShapedCharge as u32 => Ability::ShapedCharge,
+            x if x == Ability::Decoy as u32 => Ability::Decoy,
+            x if x == Ability::Shield as u32 => Ability::Shield,
+            _ => Ability::None,
+        }
+    }
+
+    /// Returns the radar heading set by the ship.
+    pub fn radar_heading(&self) -> f64 {
+        read_system_state(SystemState::RadarHeading)
+    }
+
+    /// Returns the radar width set by the ship.
+    pub fn radar_width(&self) -> f64 {
+        read_system_state(SystemState::RadarWidth)
+    }
+
+    /// Returns the channel of the given radio.
+    pub fn radio_channel(&self, radio: usize) -> usize {
+        read_system_state(radio_internal::radio_indices(radio).channel) as usize
+    }
+
+    /// Returns the message sent on the given radio during the last tick.
+    pub fn sent(&self, radio: usize) -> Option<Message> {
+        let idxs = radio_internal::radio_indices(radio);
+        if read_system_state(idxs.send) == 0.0 {
+            return None;
+        }
+        Some(idxs.data.map(read_system_state))
+    }
+
+    /// Returns the text written with `debug!` during the last tick.
+    pub fn debug_text(&self) -> String {
+        dbg::text_buffer()
+    }
+
+    /// Returns the lines drawn during the last tick.
+    pub fn debug_lines(&self) -> Vec<Line> {
+        dbg::line_buffer()
+    }
+
+    /// Returns the strings drawn with `draw_text!` during the last tick.
+    pub fn drawn_text(&self) -> Vec<String> {
+        dbg::drawn_text_buffer()
+            .iter()
+            .map(|t| String::from_utf8_lossy(&t.text[..t.length as usize]).into_owned())
+            .collect()
+    }
+
+    /// Returns the circles, arcs, filled shapes, HUD text, and anything drawn
+    /// with a layer or TTL set during the last tick.
+    pub fn debug_shapes(&self) -> Vec<Shape> {
+        dbg::shape_buffer()
+    }
+
+    /// Returns the layer names used during the last tick. Layer `i` in a
+    /// [`Shape`] is element `i - 1`.
+    pub fn debug_layers(&self) -> Vec<String> {
+        dbg::layer_names()
+    }
+
+    /// Returns the messages logged with [`log!`](crate::log!) during the last tick.
+    pub fn log_entries(&self) -> Vec<(LogLevel, String)> {
+        dbg::log_entries()
+    }
+
+    /// Returns the commanded acceleration in ship coordinates, limited per
+    /// axis to the maximum forward, backward and lateral acceleration.
+    fn clamped_acceleration(&self) -> Vec2 {
+        let forward = read_system_state(SystemState::MaxForwardAcceleration);
+        let backward = read_system_state(SystemState::MaxBackwardAcceleration);
+        let lateral = read_system_state(SystemState::MaxLateralAcceleration);
+        vec2(
+            read_system_state(SystemState::AccelerateX).clamp(-backward, forward),
+            read_system_state(SystemState::AccelerateY).clamp(-lateral, lateral),
+        )
+    }
+
+    fn update_environment(&mut self) {
+        let s = self
+            .environment
+            .iter()
+            .map(|(k, v)| format!("{k}={v}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(s.len() < MAX_ENVIRONMENT_SIZE, "environment too large");
+        let environment = unsafe { &mut crate::sys::ENVIRONMENT };
+        environment.fill(0);
+        environment[..s.len()].copy_from_slice(s.as_bytes());
+    }
+}
+
+fn set_event(bit: u32, value: bool) {
+    let mut events = read_system_state(SystemState::Events) as u32;
+    if value {
+        events |= bit;
+    } else {
+        events &= !bit;
+    }
+    write_system_state(SystemState::Events, events as f64);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::prelude::*;
+
+    #[test]
+    fn test_commands() {
+        let mut mock = Mock::new(Class::Fighter);
+        mock.set_heading(PI / 2.0);
+        mock.tick(|| {
+            accelerate(vec2(0.0, 10.0));
+            fire(0);
+            send([1.0, 2.0, 3.0, 4.0]);
+            debug!("hello");
+            draw_line(vec2(0.0, 0.0), vec2(1.0, 1.0), 0xffffff);
+            draw_text!(vec2(0.0, 0.0), 0xffffff, "text");
+        });
+        assert!((mock.acceleration() - vec2(0.0, 10.0)).length() < 1e-9);
+        assert!(mock.fired(0));
+        assert!(!mock.fired(1));
+        assert_eq!(mock.sent(0), Some([1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(mock.debug_text(), "hello\n");
+        assert_eq!(mock.debug_lines().len(), 1);
+        assert_eq!(mock.drawn_text(), vec!["text".to_string()]);
+
+        mock.tick(|| {});
+        assert!(!mock.fired(0));
+        assert_eq!(mock.sent(0), None);
+        assert_eq!(mock.debug_text(), "");
+    }
+
+    #[test]
+    fn test_inputs() {
+        let mut mock = Mock::new(Class::Cruiser);
+        mock.set_env("SCENARIO_NAME", "test");
+        mock.set_env("PARAM_NUM_TARGETS", "8");
+        mock.set_received(0, Some([5.0, 6.0, 7.0, 8.0]));
+        mock.set_contact(Some(ScanResult {
+            class: Class::Missile,
+            position: vec2(1.0, 2.0),
+            velocity: vec2(3.0, 4.0),
+            rssi: -10.0,
+            snr: 20.0,
+        }));
+        assert_eq!(class(), Class::Cruiser);
+        assert_eq!(max_forward_acceleration(), 5.0);
+        assert_eq!(scenario_name(), "test");
+        assert_eq!(scenario_param("num_targets"), Some(8.0));
+        assert_eq!(scenario_param("missing"), None);
+        assert_eq!(receive(), Some([5.0, 6.0, 7.0, 8.0]));
+        let contact = scan().unwrap();
+        assert_eq!(contact.class, Class::Missile);
+        assert_eq!(contact.position, vec2(1.0, 2.0));
+    }
+
+    #[test]
+    fn test_shapes() {
+        let mut mock = Mock::new(Class::Fighter);
+        mock.tick(|| {
+            draw_line(vec2(0.0, 0.0), vec2(1.0, 1.0), 0xffffff);
+            draw_circle(vec2(1.0, 2.0), 3.0, 0xff0000);
+            set_draw_layer("targets");
+            set_draw_ttl(60);
+            fill_polygon(vec2(0.0, 0.0), 10.0, 6, 0.0, 0x00ff00);
+            draw_line(vec2(0.0, 0.0), vec2(1.0, 1.0), 0xffffff);
+            set_draw_layer("");
+            draw_hud_text!(vec2(10.0, 10.0), 0xffffff, "hud");
+        });
+        assert_eq!(mock.debug_lines().len(), 1);
+        assert_eq!(mock.debug_layers(), vec!["targets".to_string()]);
+        let shapes = mock.debug_shapes();
+        let summary: Vec<_> = shapes
+            .iter()
+            .map(|s| (s.kind, s.flags, s.layer, s.ttl))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Shape::CIRCLE, 0, 0, 1),
+                (Shape::POLYGON, Shape::FILLED, 1, 60),
+                (Shape::LINE, 0, 1, 60),
+                (Shape::TEXT, Shape::SCREEN, 0, 60),
+            ]
+        );
+        assert_eq!(&shapes[3].text[..3], b"hud");
+
+        mock.tick(|| draw_line(vec2(0.0, 0.0), vec2(1.0, 1.0), 0xffffff));
+        assert!(mock.debug_shapes().is_empty());
+        assert_eq!(mock.debug_lines().len(), 1);
+    }
+
+    #[test]
+    fn test_log() {
+        let mut mock = Mock::new(Class::Fighter);
+        mock.tick(|| {
+            log_info!("tick {}", current_tick());
+            log_warn!("low fuel\0");
+            log!(LogLevel::Error, "error");
+        });
+        assert_eq!(
+            mock.log_entries(),
+            vec![
+                (LogLevel::Info, "tick 0".to_string()),
+                (LogLevel::Warn, "low fuel ".to_string()),
+                (LogLevel::Error, "error".to_string()),
+            ]
+        );
+
+        mock.tick(|| {});
+        assert!(mock.log_entries().is_empty());
+    }
+
+    #[test]
+    fn test_step() {
+        let mut mock = Mock::new(Class::Fighter);
+        mock.run(60, || accelerate(vec2(10.0, 0.0)));
+        assert_eq!(current_tick(), 60);
+        assert!((mock.velocity().x - 10.0).abs() < 1e-9);
+        assert!((mock.position().x - 5.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_step_clamps_acceleration() {
+        let mut mock = Mock::new(Class::Fighter);
+        mock.run(60, || accelerate(vec2(1000.0, 0.0)));
+        assert!((mock.velocity().x - max_forward_acceleration()).abs() < 1e-9);
+
+        mock.set_velocity(vec2(0.0, 0.0));
+        mock.run(60, || accelerate(vec2(-1000.0, -1000.0)));
+        assert!((mock.velocity().x + max_backward_acceleration()).abs() < 1e-9);
+        assert!((mock.velocity().y + max_lateral_acceleration()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_step_clamps_torque() {
+        let mut mock = Mock::new(Class::Fighter);
+        mock.run(60, || torque(100.0));
+        assert!((angular_velocity() - max_angular_acceleration()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_events() {
+        #[derive(Default)]
+        struct Ship {
+            damage: f64,
+            contact_lost: bool,
+            messages: Vec<(usize, Message)>,
+            launched: Vec<usize>,
+            abilities_ended: Vec<Ability>,
+        }
+
+        impl EventHandler for Ship {
+            fn on_damaged(&mut self, amount: f64) {
+                self.damage += amount;
+            }
+
+            fn on_contact_lost(&mut self) {
+                self.contact_lost = true;
+            }
+
+            fn on_radio(&mut self, channel: usize, msg: Message) {
+                self.messages.push((channel, msg));
+            }
+
+            fn on_missile_launched(&mut self, index: usize) {
+                self.launched.push(index);
+            }
+
+            fn on_ability_ended(&mut self, ability: Ability) {
+                self.abilities_ended.push(ability);
+            }
+        }
+
+        let mut mock = Mock::new(Class::Fighter);
+        let mut ship = Ship::default();
+        mock.dispatch_events(&mut ship);
+        assert_eq!(ship.damage, 0.0);
+        assert!(!ship.contact_lost);
+        assert!(ship.messages.is_empty());
+
+        mock.set_damage_taken(12.5);
+        mock.set_contact_lost(true);
+        mock.set_state(radio_internal::radio_indices(1).channel, 3.0);
+        mock.set_received(1, Some([1.0, 2.0, 3.0, 4.0]));
+        mock.set_missile_launched(2, true);
+        mock.set_ability_ended(Ability::Shield, true);
+        mock.dispatch_events(&mut ship);
+        assert_eq!(ship.damage, 12.5);
+        assert!(ship.contact_lost);
+        assert_eq!(ship.messages, vec![(3, [1.0, 2.0, 3.0, 4.0])]);
+        assert_eq!(ship.launched, vec![2]);
+        assert_eq!(ship.abilities_ended, vec![Ability::Shield]);
+    }
+
+    #[test]
+    fn test_loadout() {
+        let mut mock = Mock::new(Class::Frigate);
+        assert!(weapons().is_empty());
+        assert!(abilities().is_empty());
+
+        let gun = GunInfo {
+            speed: 1000.0,
+            speed_error: 0.0,
+            offset: vec2(20.0, 0.0),
+            heading: 0.0,
+            min_angle: 0.0,
+            max_angle: TAU,
+            inaccuracy: 0.0,
+            reload_ticks: 60,
+            magazine_size: 10,
+            magazine_remaining: 7,
+            magazine_reload_ticks: 120,
+            burst_size: 1,
+            bullet_ttl: 5.0,
+            bullet_mass: 1.0,
+        };
+        let launcher = LauncherInfo {
+            class: Class::Missile,
+            speed: 100.0,
+            offset: vec2(0.0, 10.0),
+            heading: PI / 2.0,
+            reload_ticks: 300,
+        };
+        let boost = AbilityInfo {
+            ability: Ability::Boost,
+            active_time: 2.0,
+            reload_time: 10.0,
+            active_time_remaining: 0.0,
+            reload_time_remaining: 3.5,
+        };
+        mock.set_weapons(&[Weapon::Gun(gun), Weapon::Launcher(launcher)]);
+        mock.set_abilities(&[boost]);
+        assert_eq!(
+            weapons(),
+            vec![Weapon::Gun(gun), Weapon::Launcher(launcher)]
+        );
+        assert_eq!(abilities(), vec![boost]);
+
+        mock.set_weapons(&[]);
+        assert!(weapons().is_empty());
+    }
+}
@@ -1,4 +1,6 @@
-use std::{
+#[cfg(not(feature = "std"))]
+use crate::float::F64Ext;
+use core::{
     fmt::Display,
     ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
 };
@@ -52,7 +54,7 @@ impl Vec2 {
     pub fn angle(self) -> f64 {
         let mut a = self.y.atan2(self.x);
         if a < 0.0 {
-            a += std::f64::consts::TAU;
+            a += core::f64::consts::TAU;
         }
         a
     }
@@ -69,7 +71,7 @@ impl Vec2 {
 }
 
 impl Display for Vec2 {
-    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         fmt.debug_tuple("").field(&self.x).field(&self.y).finish()
     }
 }
@@ -3,6 +3,7 @@ use flate2::Compression;
 use no_comment::{languages, IntoWithoutComments as _};
 use std::io::Write;
 
+/// Returns the deflate-compressed size of the code with comments removed.
 pub fn calculate(code: &str) -> usize {
     let mut e = DeflateEncoder::new(Vec::new(), Compression::default());
     e.write_all(
@@ -1,11 +1,46 @@
-use anyhow::{bail, Result};
+use anyhow::{anyhow, bail, Result};
 use std::path::{Path, PathBuf};
 
+/// Standard library configuration that ship code is built against.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    /// Full `std` with debug output.
+    #[default]
+    Std,
+    /// `no_std` + `alloc` with debug output compiled out. Produces much
+    /// smaller modules, but ship code can only use `core` and `alloc`.
+    NoStd,
+}
+
+impl Mode {
+    fn target_dir(self) -> &'static str {
+        match self {
+            Mode::Std => "target",
+            Mode::NoStd => "target-no-std",
+        }
+    }
+
+    fn cargo_args(self) -> &'static [&'static str] {
+        match self {
+            Mode::Std => &[],
+            Mode::NoStd => &["--no-default-features", "--features", "libm"],
+        }
+    }
+
+    fn cfgs(self) -> &'static [&'static str] {
+        match self {
+            Mode::Std => &["feature=\"std\"", "feature=\"dbg\""],
+            Mode::NoStd => &[],
+        }
+    }
+}
+
 pub struct Compiler {
     #[allow(dead_code)]
     tmp_dir: Option<tempdir::TempDir>,
     dir: PathBuf,
     offline: bool,
+    mode: Mode,
     rustc: String,
 }
 
@@ -18,6 +53,7 @@ impl Compiler {
             tmp_dir: Some(tmp_dir),
             dir,
             offline: true,
+            mode: Mode::default(),
             rustc: find_rustc(),
         }
     }
@@ -27,6 +63,7 @@ impl Compiler {
             tmp_dir: None,
             dir: dir.to_path_buf(),
             offline: true,
+            mode: Mode::default(),
             rustc: find_rustc(),
         }
     }
@@ -35,13 +72,43 @@ impl Compiler {
         self.offline = false;
     }
 
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Returns the version of the rustc used to compile, e.g. "rustc 1.68.0 (2c8cc3432 2023-03-06)".
+    pub fn rustc_version(&self) -> Result<String> {
+        let output = std::process::Command::new(&self.rustc)
+            .arg("--version")
+            .output()?;
+        if !output.status.success() {
+            bail!(
+                "rustc --version failed: {}",
+                std::str::from_utf8(&output.stderr)?
+            );
+        }
+        Ok(std::str::from_utf8(&output.stdout)?.trim().to_string())
+    }
+
     pub fn compile(&mut self, code: &str) -> Result<Vec<u8> /* wasm */> {
         let tmp_path = &self.dir;
 
-        if std::fs::metadata(tmp_path.join("Cargo.toml")).is_ok() {
-            return self.compile_fast(code);
+        if std::fs::metadata(tmp_path.join("Cargo.toml")).is_err() {
+            self.write_workspace(code)?;
         }
+        if find_rlib(tmp_path, self.mode, "oort_api").is_none() {
+            self.build_workspace()?;
+        }
+
+        self.compile_fast(code)
+    }
 
+    fn write_workspace(&self, code: &str) -> Result<()> {
+        let tmp_path = &self.dir;
         std::fs::write(
             tmp_path.join("Cargo.toml"),
             include_bytes!("../../../Cargo.toml.user"),
@@ -64,6 +131,18 @@ impl Compiler {
             tmp_path.join("api/src/vec.rs"),
             include_bytes!("../../api/src/vec.rs"),
         )?;
+        std::fs::write(
+            tmp_path.join("api/src/codec.rs"),
+            include_bytes!("../../api/src/codec.rs"),
+        )?;
+        std::fs::write(
+            tmp_path.join("api/src/float.rs"),
+            include_bytes!("../../api/src/float.rs"),
+        )?;
+        std::fs::write(
+            tmp_path.join("api/src/math.rs"),
+            include_bytes!("../../api/src/math.rs"),
+        )?;
 
         std::fs::create_dir_all(tmp_path.join("ai/src"))?;
         std::fs::write(
@@ -75,14 +154,24 @@ impl Compiler {
             include_bytes!("../../ai/src/lib.rs"),
         )?;
         std::fs::write(tmp_path.join("ai/src/user.rs"), code.as_bytes())?;
+        Ok(())
+    }
 
+    fn build_workspace(&self) -> Result<()> {
+        let tmp_path = &self.dir;
         let output = std::process::Command::new("cargo")
             .args([
                 "build",
                 "--manifest-path",
                 tmp_path.join("Cargo.toml").as_os_str().to_str().unwrap(),
                 "--target-dir",
-                tmp_path.join("target").as_os_str().to_str().unwrap(),
+                tmp_path
+                    .join(self.mode.target_dir())
+                    .as_os_str()
+                    .to_str()
+                    .unwrap(),
+                "-p",
+                "oort_ai",
                 "-v",
                 "-j1",
                 if self.offline { "--offline" } else { "-v" },
@@ -90,6 +179,7 @@ impl Compiler {
                 "--target",
                 "wasm32-unknown-unknown",
             ])
+            .args(self.mode.cargo_args())
             .env(
                 "RUSTFLAGS",
                 "-C opt-level=s -C link-arg=-zstack-size=16384 -C llvm-args=-rng-seed=42",
@@ -98,14 +188,18 @@ impl Compiler {
         if !output.status.success() {
             bail!("cargo failed: {}", std::str::from_utf8(&output.stderr)?);
         }
-
-        self.compile_fast(code)
+        Ok(())
     }
 
     pub fn compile_fast(&mut self, code: &str) -> Result<Vec<u8> /* wasm */> {
         let tmp_path = &self.dir;
         std::fs::write(tmp_path.join("ai/src/user.rs"), code.as_bytes())?;
         let rustc_bin_dir = Path::new(&self.rustc).parent().unwrap();
+        let release_dir = tmp_path
+            .join(self.mode.target_dir())
+            .join("wasm32-unknown-unknown/release");
+        let api_rlib = find_rlib(tmp_path, self.mode, "oort_api")
+            .ok_or_else(|| anyhow!("oort_api rlib not found"))?;
 
         let output = std::process::Command::new(&self.rustc)
             .env(
@@ -120,8 +214,8 @@ impl Compiler {
                 "--crate-type",
                 "cdylib",
                 "-o",
-                tmp_path
-                    .join("target/wasm32-unknown-unknown/release/oort_ai.wasm")
+                release_dir
+                    .join("oort_ai.wasm")
                     .as_os_str()
                     .to_str()
                     .unwrap(),
@@ -130,22 +224,9 @@ impl Compiler {
                 "-C",
                 "strip=debuginfo",
                 "-L",
-                &format!(
-                    "dependency={}",
-                    tmp_path
-                        .join("target/wasm32-unknown-unknown/release/deps")
-                        .as_os_str()
-                        .to_str()
-                        .unwrap()
-                ),
+                &format!("dependency={}", release_dir.join("deps").display()),
                 "--extern",
-                &format!(
-                    "oort_api={}",
-                    find_rlib(tmp_path, "oort_api")
-                        .as_os_str()
-                        .to_str()
-                        .unwrap()
-                ),
+                &format!("oort_api={}", api_rlib.display()),
                 "-C",
                 "opt-level=s",
                 "-C",
@@ -155,22 +236,22 @@ impl Compiler {
                 "--remap-path-prefix",
                 &format!("{}=/tmp/oort-ai", tmp_path.display()),
             ])
+            .args(self.mode.cfgs().iter().flat_map(|&cfg| ["--cfg", cfg]))
             .output()?;
         if !output.status.success() {
             bail!("rustc failed: {}", std::str::from_utf8(&output.stderr)?);
         }
 
-        Ok(std::fs::read(tmp_path.join(
-            "target/wasm32-unknown-unknown/release/oort_ai.wasm",
-        ))?)
+        Ok(std::fs::read(release_dir.join("oort_ai.wasm"))?)
     }
 }
 
-fn find_rlib(tmp_path: &Path, crate_name: &str) -> PathBuf {
-    if let Some(path) = glob::glob(
+fn find_rlib(tmp_path: &Path, mode: Mode, crate_name: &str) -> Option<PathBuf> {
+    glob::glob(
         tmp_path
+            .join(mode.target_dir())
             .join(format!(
-                "target/wasm32-unknown-unknown/release/deps/lib{crate_name}-*.rlib"
+                "wasm32-unknown-unknown/release/deps/lib{crate_name}-*.rlib"
             ))
             .as_os_str()
             .to_str()
@@ -178,10 +259,7 @@ fn find_rlib(tmp_path: &Path, crate_name: &str) -> PathBuf {
     )
     .unwrap()
     .next()
-    {
-        return path.unwrap();
-    }
-    panic!("{crate_name} rlib not found");
+    .map(|path| path.unwrap())
 }
 
 fn find_rustc() -> String {
@@ -0,0 +1,25 @@
+use oort_compiler::{Compiler, Mode};
+
+const SHIP: &str = include_str!("../../builtin_ai/src/empty.rs");
+
+fn compile(mode: Mode) -> Vec<u8> {
+    let mut compiler = Compiler::new();
+    compiler.set_mode(mode);
+    compiler.compile(SHIP).unwrap()
+}
+
+#[test]
+fn test_std() {
+    let wasm = compile(Mode::Std);
+    assert_eq!(&wasm[..4], b"\0asm");
+}
+
+#[test]
+fn test_no_std() {
+    let wasm = compile(Mode::NoStd);
+    assert_eq!(&wasm[..4], b"\0asm");
+    assert!(
+        wasm.len() < compile(Mode::Std).len(),
+        "no_std build should be smaller"
+    );
+}
@@ -1,6 +1,6 @@
 pub mod analyzer;
 
-use chrono::serde::ts_milliseconds;
+use chrono::serde::{ts_milliseconds, ts_milliseconds_option};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
@@ -46,6 +46,8 @@ pub enum Telemetry {
 #[derive(Serialize, Deserialize, Debug, Default)]
 pub struct LeaderboardData {
     pub lowest_time: Vec<TimeLeaderboardRow>,
+    #[serde(default)]
+    pub smallest_code: Vec<CodeSizeLeaderboardRow>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Default)]
@@ -56,6 +58,14 @@ pub struct TimeLeaderboardRow {
     pub encrypted_code: String,
 }
 
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct CodeSizeLeaderboardRow {
+    pub userid: String,
+    pub username: Option<String>,
+    pub code_size: usize,
+    pub encrypted_code: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct LeaderboardSubmission {
     pub scenario_name: String,
@@ -71,6 +81,26 @@ pub struct LeaderboardSubmission {
 
 impl Eq for LeaderboardSubmission {}
 
+/// Progress of the server re-simulating a leaderboard submission.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "status")]
+pub enum LeaderboardVerification {
+    Pending,
+    Accepted { time: f64, code_size: usize },
+    Rejected { reason: String },
+}
+
+/// A leaderboard submission stored until the backend has verified it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LeaderboardVerificationJob {
+    pub submission: LeaderboardSubmission,
+    pub status: LeaderboardVerification,
+    /// When a backend instance started verifying the submission.
+    #[serde(default)]
+    #[serde(with = "ts_milliseconds_option")]
+    pub claimed: Option<DateTime<Utc>>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct TournamentSubmission {
     pub scenario_name: String,
@@ -97,6 +127,32 @@ pub struct TournamentResults {
     pub scenario_name: String,
     pub competitors: Vec<TournamentCompetitor>,
     pub win_matrix: Vec<f64>,
+    /// Per-scenario breakdown when the tournament was played on a pool of
+    /// scenarios. Competitors are listed in the same order as above.
+    #[serde(default)]
+    pub scenarios: Vec<TournamentScenarioResults>,
+    /// Games that crashed or exceeded a resource limit instead of finishing.
+    #[serde(default)]
+    pub failures: Vec<TournamentFailure>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TournamentScenarioResults {
+    /// Relative weight of this scenario in the combined ratings.
+    pub weight: f64,
+    pub results: TournamentResults,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TournamentFailure {
+    pub scenario_name: String,
+    pub seed: u32,
+    /// Usernames of the players controlling team 0 and team 1.
+    pub usernames: Vec<String>,
+    /// Username of the player who forfeited, or `None` if the game couldn't be
+    /// attributed to either player and was scored as a draw.
+    pub forfeited_by: Option<String>,
+    pub reason: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
@@ -105,3 +161,52 @@ pub struct TournamentCompetitor {
     pub shortcode: String,
     pub rating: f64,
 }
+
+/// A competitor's Glicko-2 rating on the continuous ladder for a tournament scenario.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LadderRating {
+    pub scenario_name: String,
+    pub userid: String,
+    pub username: String,
+    #[serde(default)]
+    #[serde(with = "ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    pub rating: f64,
+    pub deviation: f64,
+    pub volatility: f64,
+    pub wins: u32,
+    pub losses: u32,
+    pub draws: u32,
+}
+
+/// A tournament submission stored until its ladder matches have been played.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LadderJob {
+    pub submission: TournamentSubmission,
+    /// When a backend instance started playing the matches.
+    #[serde(default)]
+    #[serde(with = "ts_milliseconds_option")]
+    pub claimed: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LadderMatch {
+    pub scenario_name: String,
+    #[serde(default)]
+    #[serde(with = "ts_milliseconds")]
+    pub timestamp: DateTime<Utc>,
+    pub seed: u32,
+    /// Usernames of the players controlling team 0 and team 1.
+    pub usernames: Vec<String>,
+    /// Username of the winner, or `None` for a draw.
+    pub winner: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct LadderData {
+    pub scenario_name: String,
+    /// Competitors ordered by rating, highest first.
+    pub standings: Vec<TournamentCompetitor>,
+    /// Most recent matches, newest first.
+    pub recent_matches: Vec<LadderMatch>,
+}
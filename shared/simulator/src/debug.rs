@@ -1,6 +1,7 @@
 use crate::ship::ShipHandle;
 use crate::simulation::Simulation;
 use nalgebra::{vector, Point2, UnitComplex, Vector4};
+pub use oort_api::LogLevel;
 use serde::{Deserialize, Serialize};
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -10,6 +11,56 @@ pub struct Line {
     pub color: Vector4<f32>,
 }
 
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub enum Shape {
+    Line {
+        a: Point2<f64>,
+        b: Point2<f64>,
+    },
+    Circle {
+        center: Point2<f64>,
+        radius: f64,
+    },
+    Arc {
+        center: Point2<f64>,
+        radius: f64,
+        start_angle: f64,
+        end_angle: f64,
+    },
+    Polygon {
+        center: Point2<f64>,
+        radius: f64,
+        sides: u32,
+        angle: f64,
+    },
+    Text {
+        topleft: Point2<f64>,
+        text: String,
+    },
+}
+
+/// A shape drawn by ship code, tessellated by the renderer.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct Drawing {
+    pub shape: Shape,
+    pub color: Vector4<f32>,
+    pub filled: bool,
+    /// Coordinates are pixels from the top left of the screen instead of world coordinates.
+    pub screen: bool,
+    /// Name of the layer, empty for the default layer.
+    pub layer: String,
+}
+
+/// A message logged by ship code.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct LogEntry {
+    pub tick: u32,
+    pub ship: u64,
+    pub team: i32,
+    pub level: LogLevel,
+    pub message: String,
+}
+
 pub fn emit_ship(sim: &mut Simulation, handle: ShipHandle) {
     let mut lines = vec![];
     lines.reserve(3);
@@ -11,4 +11,5 @@ pub mod scenario;
 pub mod ship;
 pub mod simulation;
 pub mod snapshot;
+pub mod trace;
 pub mod vm;
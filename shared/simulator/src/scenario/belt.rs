@@ -57,7 +57,7 @@ impl Scenario for Belt {
         }
 
         let bound = vector![(sim.world_size() / 2.0) * 0.9, (sim.world_size() / 4.0)];
-        for _ in 0..100 {
+        for _ in 0..sim.params().get_int("num_asteroids") {
             let mut data = asteroid(rng.gen_range(0..30));
             data.health = 10000.0;
             ship::create(
@@ -73,6 +73,16 @@ impl Scenario for Belt {
         }
     }
 
+    fn params(&self) -> Vec<Param> {
+        vec![Param::int(
+            "num_asteroids",
+            "Number of asteroids",
+            0,
+            500,
+            100,
+        )]
+    }
+
     fn status(&self, sim: &Simulation) -> Status {
         check_capital_ship_tournament_victory(sim)
     }
@@ -17,24 +17,27 @@ impl Scenario for GunneryScenario {
         ship_data.guns.pop();
         ship_data.missile_launchers.pop();
         ship_data.acceleration = vector![0.0, 0.0];
+        let spawn_distance = sim.params().get("spawn_distance");
         ship::create(
             sim,
-            vector![-9000.0, 0.0],
+            vector![-spawn_distance / 2.0, 0.0],
             vector![0.0, 0.0],
             0.0,
             ship_data,
         );
+        let num_targets = sim.params().get_int("num_targets");
+        let target_speed = sim.params().get("target_speed");
         let mut rng = new_rng(seed);
-        for _ in 0..4 {
+        for _ in 0..num_targets {
             ship::create(
                 sim,
                 vector![
-                    9000.0 + rng.gen_range(-500.0..500.0),
+                    spawn_distance / 2.0 + rng.gen_range(-500.0..500.0),
                     -9000.0 + rng.gen_range(-500.0..500.0)
                 ],
                 vector![
                     0.0 + rng.gen_range(-10.0..10.0),
-                    700.0 + rng.gen_range(-300.0..600.0)
+                    target_speed + rng.gen_range(-300.0..600.0)
                 ],
                 std::f64::consts::PI,
                 target(1),
@@ -50,6 +53,26 @@ impl Scenario for GunneryScenario {
         builtin("gunnery")
     }
 
+    fn params(&self) -> Vec<Param> {
+        vec![
+            Param::int("num_targets", "Number of targets", 1, 32, 4),
+            Param::float(
+                "target_speed",
+                "Average target speed in m/s",
+                300.0,
+                2000.0,
+                700.0,
+            ),
+            Param::float(
+                "spawn_distance",
+                "Horizontal distance from the ship to the targets in meters",
+                2000.0,
+                34000.0,
+                18000.0,
+            ),
+        ]
+    }
+
     fn tick(&mut self, sim: &mut Simulation) {
         let handles = sim.ships.iter().cloned().collect::<Vec<_>>();
         for handle in handles {
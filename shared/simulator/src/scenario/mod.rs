@@ -31,7 +31,7 @@ use crate::simulation::{Code, Line, Simulation};
 use nalgebra::{vector, Vector2};
 use rand::{Rng, RngCore};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 pub mod prelude {
     pub use super::Scenario;
@@ -42,6 +42,7 @@ pub mod prelude {
     };
     pub use super::{fighter_without_missiles, fighter_without_missiles_or_radar, target_asteroid};
     pub use super::{place_teams, Placement};
+    pub use super::{Param, Params};
     pub use super::{DEFAULT_TUTORIAL_MAX_TICKS, TOURNAMENT_MAX_TICKS};
     pub use crate::rng::{new_rng, SeededRng};
     pub use crate::ship::{
@@ -118,6 +119,14 @@ pub trait Scenario {
     fn world_size(&self) -> f64 {
         40000.0
     }
+
+    /// Parameters that can be overridden when creating the simulation.
+    ///
+    /// Values are read with [`Simulation::params`] and are visible to ship
+    /// code through the environment.
+    fn params(&self) -> Vec<Param> {
+        vec![]
+    }
 }
 
 pub fn load_safe(name: &str) -> Option<Box<dyn Scenario>> {
@@ -309,6 +318,129 @@ pub fn target_asteroid(variant: i32) -> ShipData {
     asteroid
 }
 
+/// Overrides for scenario parameters, keyed by name.
+pub type ParamOverrides = BTreeMap<String, f64>;
+
+/// A tunable scenario parameter.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Param {
+    pub name: String,
+    pub description: String,
+    pub min: f64,
+    pub max: f64,
+    pub default: f64,
+    /// Only whole numbers are allowed.
+    pub integer: bool,
+}
+
+impl Param {
+    pub fn int(name: &str, description: &str, min: i64, max: i64, default: i64) -> Param {
+        Param {
+            name: name.to_string(),
+            description: description.to_string(),
+            min: min as f64,
+            max: max as f64,
+            default: default as f64,
+            integer: true,
+        }
+    }
+
+    pub fn float(name: &str, description: &str, min: f64, max: f64, default: f64) -> Param {
+        Param {
+            name: name.to_string(),
+            description: description.to_string(),
+            min,
+            max,
+            default,
+            integer: false,
+        }
+    }
+
+    /// Name of the environment variable holding the value, e.g. `PARAM_NUM_TARGETS`.
+    pub fn env_name(&self) -> String {
+        format!("PARAM_{}", self.name.to_uppercase())
+    }
+
+    pub fn format(&self, value: f64) -> String {
+        if self.integer {
+            format!("{}", value as i64)
+        } else {
+            format!("{value}")
+        }
+    }
+
+    fn check(&self, value: f64) -> Result<(), String> {
+        if !(self.min..=self.max).contains(&value) {
+            Err(format!(
+                "{} must be between {} and {}, got {}",
+                self.name,
+                self.format(self.min),
+                self.format(self.max),
+                value
+            ))
+        } else if self.integer && value.fract() != 0.0 {
+            Err(format!(
+                "{} must be a whole number, got {}",
+                self.name, value
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Values for every parameter declared by a scenario.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Params {
+    values: BTreeMap<String, f64>,
+}
+
+impl Params {
+    /// Applies overrides on top of the defaults, rejecting unknown names and
+    /// out of range values.
+    pub fn resolve(params: &[Param], overrides: &ParamOverrides) -> Result<Params, String> {
+        for name in overrides.keys() {
+            if !params.iter().any(|p| &p.name == name) {
+                return Err(format!("Unknown scenario parameter {name:?}"));
+            }
+        }
+        let mut values = BTreeMap::new();
+        for param in params {
+            let value = overrides.get(&param.name).copied().unwrap_or(param.default);
+            param.check(value)?;
+            values.insert(param.name.clone(), value);
+        }
+        Ok(Params { values })
+    }
+
+    /// Returns the value of a parameter declared by the scenario.
+    pub fn get(&self, name: &str) -> f64 {
+        match self.values.get(name) {
+            Some(&value) => value,
+            None => panic!("Undeclared scenario parameter {name:?}"),
+        }
+    }
+
+    pub fn get_int(&self, name: &str) -> i64 {
+        self.get(name) as i64
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.values.iter().map(|(k, v)| (k.as_str(), *v))
+    }
+}
+
+/// Parses overrides of the form `name=value`.
+pub fn parse_param_override(s: &str) -> Result<(String, f64), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("Expected NAME=VALUE, got {s:?}"))?;
+    let value = value
+        .parse()
+        .map_err(|_| format!("Invalid value for {name}: {value:?}"))?;
+    Ok((name.to_string(), value))
+}
+
 pub struct Placement {
     pub position: Vector2<f64>,
     pub heading: f64,
@@ -328,3 +460,37 @@ pub fn place_teams(rng: &mut dyn RngCore, world_size: f64) -> Vec<Placement> {
         },
     ]
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_params() {
+        let params = vec![
+            Param::int("num_targets", "", 1, 10, 4),
+            Param::float("speed", "", 0.0, 1.0, 0.5),
+        ];
+
+        let defaults = Params::resolve(&params, &ParamOverrides::new()).unwrap();
+        assert_eq!(defaults.get_int("num_targets"), 4);
+        assert_eq!(defaults.get("speed"), 0.5);
+
+        let overrides = ParamOverrides::from([("num_targets".to_string(), 8.0)]);
+        let resolved = Params::resolve(&params, &overrides).unwrap();
+        assert_eq!(resolved.get_int("num_targets"), 8);
+        assert_eq!(resolved.get("speed"), 0.5);
+
+        for (name, value) in [("num_targets", 11.0), ("num_targets", 2.5), ("foo", 1.0)] {
+            let overrides = ParamOverrides::from([(name.to_string(), value)]);
+            assert!(Params::resolve(&params, &overrides).is_err());
+        }
+
+        assert_eq!(
+            parse_param_override("num_targets=8"),
+            Ok(("num_targets".to_string(), 8.0))
+        );
+        assert!(parse_param_override("num_targets").is_err());
+        assert!(parse_param_override("num_targets=x").is_err());
+    }
+}
@@ -8,7 +8,6 @@ pub struct PlanetaryDefense {
 
 impl PlanetaryDefense {
     const PLANET_HEALTH: f64 = 1.5e5;
-    const SPAWN_DURATION: f64 = 60.0;
 
     pub fn new() -> Self {
         Self { rng: new_rng(0) }
@@ -81,13 +80,15 @@ impl Scenario for PlanetaryDefense {
     }
 
     fn tick(&mut self, sim: &mut Simulation) {
-        if sim.time() < Self::SPAWN_DURATION {
+        let spawn_duration = sim.params().get("spawn_duration");
+        if sim.time() < spawn_duration {
             let bound = (sim.world_size() / 2.0) * 0.9;
+            let spawn_rate = sim.params().get("spawn_rate");
             if self
                 .rng
-                .gen_bool(PHYSICS_TICK_LENGTH * (sim.time() / Self::SPAWN_DURATION) * 2.0)
+                .gen_bool(PHYSICS_TICK_LENGTH * (sim.time() / spawn_duration) * spawn_rate)
             {
-                let mut ship_data = if self.rng.gen_bool(0.1) {
+                let mut ship_data = if self.rng.gen_bool(sim.params().get("torpedo_fraction")) {
                     torpedo(1)
                 } else {
                     missile(1)
@@ -121,13 +122,39 @@ impl Scenario for PlanetaryDefense {
             .any(|&handle| sim.ship(handle).data().team == 1);
         if !planet_alive {
             Status::Victory { team: 1 }
-        } else if sim.time() > Self::SPAWN_DURATION && !enemy_alive {
+        } else if sim.time() > sim.params().get("spawn_duration") && !enemy_alive {
             Status::Victory { team: 0 }
         } else {
             Status::Running
         }
     }
 
+    fn params(&self) -> Vec<Param> {
+        vec![
+            Param::float(
+                "spawn_duration",
+                "Seconds during which enemies spawn",
+                10.0,
+                300.0,
+                60.0,
+            ),
+            Param::float(
+                "spawn_rate",
+                "Peak enemies spawned per second",
+                0.5,
+                20.0,
+                2.0,
+            ),
+            Param::float(
+                "torpedo_fraction",
+                "Fraction of enemies that are torpedoes",
+                0.0,
+                1.0,
+                0.1,
+            ),
+        ]
+    }
+
     fn initial_code(&self) -> Vec<Code> {
         vec![empty_ai(), builtin("planetary_defense_enemy")]
     }
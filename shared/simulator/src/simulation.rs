@@ -9,6 +9,7 @@ use crate::scenario;
 use crate::scenario::Scenario;
 use crate::ship::{ShipAccessor, ShipAccessorMut, ShipData, ShipHandle, Target};
 use crate::snapshot::*;
+use crate::trace::TickHash;
 use crate::vm;
 use crate::vm::TeamController;
 use crossbeam::channel::Sender;
@@ -24,6 +25,10 @@ use std::collections::{BTreeMap, HashMap};
 use std::rc::Rc;
 
 pub const MAX_WORLD_SIZE: f64 = 200000.0;
+/// Maximum ticks a drawing can persist for.
+pub const MAX_DRAWING_TTL: u32 = 3600;
+/// Maximum number of persistent drawings per ship.
+pub const MAX_PERSISTENT_DRAWINGS: usize = 1024;
 pub const PHYSICS_TICK_LENGTH: f64 = 1.0 / 60.0;
 
 #[derive(Clone, Serialize, Deserialize, Debug, Eq, Hash, PartialEq)]
@@ -63,12 +68,55 @@ pub struct Simulation {
     timing: Timing,
     pub(crate) rng: ChaCha8Rng,
     world_size: f64,
+    vm_backend: vm::Backend,
+    hash_trace: Option<Vec<TickHash>>,
+    // (ship, expiry tick, drawing) for drawings with a TTL.
+    persistent_drawings: Vec<(u64, u32, debug::Drawing)>,
+    params: scenario::Params,
 }
 
 impl Simulation {
     pub fn new(scenario_name: &str, seed: u32, codes: &[Code]) -> Box<Simulation> {
-        let mut scenario = scenario::load(scenario_name);
+        Self::new_with_backend(scenario_name, seed, codes, vm::Backend::default())
+    }
+
+    pub fn new_with_backend(
+        scenario_name: &str,
+        seed: u32,
+        codes: &[Code],
+        vm_backend: vm::Backend,
+    ) -> Box<Simulation> {
+        Self::new_with_params(
+            scenario_name,
+            seed,
+            codes,
+            vm_backend,
+            &scenario::ParamOverrides::new(),
+        )
+        .expect("invalid default scenario parameters")
+    }
 
+    /// Creates a simulation with some scenario parameters changed from their
+    /// defaults. Fails if an override is unknown or out of range.
+    pub fn new_with_params(
+        scenario_name: &str,
+        seed: u32,
+        codes: &[Code],
+        vm_backend: vm::Backend,
+        overrides: &scenario::ParamOverrides,
+    ) -> Result<Box<Simulation>, String> {
+        let scenario = scenario::load(scenario_name);
+        let params = scenario::Params::resolve(&scenario.params(), overrides)?;
+        Ok(Self::create(scenario, seed, codes, vm_backend, params))
+    }
+
+    fn create(
+        mut scenario: Box<dyn Scenario>,
+        seed: u32,
+        codes: &[Code],
+        vm_backend: vm::Backend,
+        params: scenario::Params,
+    ) -> Box<Simulation> {
         log::debug!("seed {seed}");
         let (contact_send, contact_recv) = crossbeam::channel::unbounded();
         let mut sim = Box::new(Simulation {
@@ -102,6 +150,10 @@ impl Simulation {
             timing: Default::default(),
             rng: crate::rng::new_rng(seed),
             world_size: scenario.world_size(),
+            vm_backend,
+            hash_trace: None,
+            persistent_drawings: Vec::new(),
+            params,
         });
 
         for (team, code) in codes.iter().enumerate() {
@@ -142,6 +194,10 @@ impl Simulation {
         self.world_size
     }
 
+    pub fn params(&self) -> &scenario::Params {
+        &self.params
+    }
+
     pub fn status(&self) -> scenario::Status {
         self.scenario.as_ref().unwrap().status(self)
     }
@@ -165,6 +221,17 @@ impl Simulation {
         self.events.clear();
         self.timing = Default::default();
 
+        let tick = self.tick;
+        self.persistent_drawings
+            .retain(|(_, expiry, _)| *expiry > tick);
+        for (ship, _, drawing) in self.persistent_drawings.iter() {
+            self.events
+                .drawings
+                .entry(*ship)
+                .or_default()
+                .push(drawing.clone());
+        }
+
         let new_ships = std::mem::take(&mut self.new_ships);
         for (team, handle) in new_ships.iter() {
             if let Some(team_ctrl) = self.get_team_controller(*team) {
@@ -216,9 +283,11 @@ impl Simulation {
             .collect();
         teams.sort_by_key(|(k, _)| *k);
 
-        for (_, team_controller) in teams.iter() {
+        for (team, team_controller) in teams.iter() {
+            vm::set_executing_team(Some(*team));
             team_controller.borrow_mut().tick(self);
         }
+        vm::set_executing_team(None);
         self.timing.vm += vm_timer.elapsed();
 
         let ship_timer = Timer::new();
@@ -240,10 +309,18 @@ impl Simulation {
         self.timing.scenario += scenario_timer.elapsed();
 
         self.tick += 1;
+
+        if self.hash_trace.is_some() {
+            let tick_hash = TickHash::compute(self);
+            self.hash_trace.as_mut().unwrap().push(tick_hash);
+        }
     }
 
     pub fn upload_code(&mut self, team: i32, code: &Code) {
-        match vm::new_team_controller(code) {
+        vm::set_executing_team(Some(team));
+        let result = vm::new_team_controller_with_backend(code, self.vm_backend);
+        vm::set_executing_team(None);
+        match result {
             Ok(team_ctrl) => {
                 self.team_controllers
                     .insert(team, Rc::new(RefCell::new(team_ctrl)));
@@ -287,6 +364,43 @@ impl Simulation {
             .extend(texts.iter().cloned());
     }
 
+    /// Emits drawings along with the number of ticks each should stay visible.
+    pub fn emit_drawings(&mut self, ship: ShipHandle, drawings: Vec<(u32, debug::Drawing)>) {
+        let ship: u64 = ship.into();
+        let mut num_persistent = self
+            .persistent_drawings
+            .iter()
+            .filter(|(x, _, _)| *x == ship)
+            .count();
+        for (ttl, drawing) in drawings {
+            let ttl = ttl.min(MAX_DRAWING_TTL);
+            if ttl > 1 && num_persistent < MAX_PERSISTENT_DRAWINGS {
+                self.persistent_drawings
+                    .push((ship, self.tick + ttl, drawing.clone()));
+                num_persistent += 1;
+            }
+            self.events.drawings.entry(ship).or_default().push(drawing);
+        }
+    }
+
+    pub fn emit_log(&mut self, ship: ShipHandle, entries: Vec<(oort_api::LogLevel, String)>) {
+        let team = self.ship(ship).data().team;
+        for (level, message) in entries {
+            let entry = debug::LogEntry {
+                tick: self.tick,
+                ship: ship.into(),
+                team,
+                level,
+                message,
+            };
+            self.events.log.push(entry);
+        }
+    }
+
+    pub fn emit_gas_used(&mut self, ship: ShipHandle, gas: u32) {
+        self.events.gas_used.insert(ship.into(), gas);
+    }
+
     pub fn write_target(&mut self, ship: ShipHandle, p: Vector2<f64>, v: Vector2<f64>) {
         self.ship_mut(ship).data_mut().target = Some(Box::new(Target {
             position: p,
@@ -317,6 +431,17 @@ impl Simulation {
         s.finish()
     }
 
+    /// Starts recording a [`TickHash`] after every step.
+    pub fn enable_hash_trace(&mut self) {
+        if self.hash_trace.is_none() {
+            self.hash_trace = Some(vec![TickHash::compute(self)]);
+        }
+    }
+
+    pub fn hash_trace(&self) -> Option<&[TickHash]> {
+        self.hash_trace.as_deref()
+    }
+
     pub fn snapshot(&self, nonce: u32) -> Snapshot {
         let mut snapshot = Snapshot {
             nonce,
@@ -329,6 +454,9 @@ impl Simulation {
             debug_lines: self.events.debug_lines.clone(),
             debug_text: self.events.debug_text.clone(),
             drawn_text: self.events.drawn_text.clone(),
+            drawings: self.events.drawings.clone(),
+            log: self.events.log.clone(),
+            gas_used: self.events.gas_used.clone(),
             particles: self.events.particles.clone(),
             errors: self.events.errors.clone(),
             cheats: self.cheats,
@@ -384,6 +512,10 @@ impl Simulation {
             self.scenario.as_ref().unwrap().name(),
         );
         environment.insert("WORLD_SIZE".to_string(), format!("{}", self.world_size));
+        for param in self.scenario.as_ref().unwrap().params() {
+            let value = self.params.get(&param.name);
+            environment.insert(param.env_name(), param.format(value));
+        }
         if let Some(team_ctrl) = self.get_team_controller(team) {
             team_ctrl
                 .borrow_mut()
@@ -442,6 +574,9 @@ pub struct SimEvents {
     pub debug_lines: Vec<(u64, Vec<Line>)>,
     pub debug_text: BTreeMap<u64, String>,
     pub drawn_text: BTreeMap<u64, Vec<Text>>,
+    pub drawings: BTreeMap<u64, Vec<debug::Drawing>>,
+    pub log: Vec<debug::LogEntry>,
+    pub gas_used: BTreeMap<u64, u32>,
 }
 
 impl SimEvents {
@@ -452,6 +587,9 @@ impl SimEvents {
             debug_lines: Vec::new(),
             debug_text: BTreeMap::new(),
             drawn_text: BTreeMap::new(),
+            drawings: BTreeMap::new(),
+            log: Vec::new(),
+            gas_used: BTreeMap::new(),
         }
     }
 
@@ -461,6 +599,9 @@ impl SimEvents {
         self.debug_lines.clear();
         self.debug_text.clear();
         self.drawn_text.clear();
+        self.drawings.clear();
+        self.log.clear();
+        self.gas_used.clear();
     }
 }
 
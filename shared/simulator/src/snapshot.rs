@@ -1,3 +1,4 @@
+use crate::debug::{Drawing, LogEntry};
 use crate::scenario::Status;
 use crate::ship::ShipClass;
 use crate::simulation::{Line, Particle};
@@ -22,6 +23,10 @@ pub struct Snapshot {
     pub debug_lines: Vec<(u64, Vec<Line>)>,
     pub debug_text: BTreeMap<u64, String>,
     pub drawn_text: BTreeMap<u64, Vec<Text>>,
+    pub drawings: BTreeMap<u64, Vec<Drawing>>,
+    /// Messages logged by ships during this tick.
+    pub log: Vec<LogEntry>,
+    pub gas_used: BTreeMap<u64, u32>,
     pub timing: Timing,
     pub world_size: f64,
 }
@@ -0,0 +1,175 @@
+use crate::bullet;
+use crate::ship::ShipHandle;
+use crate::simulation::Simulation;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+/// Hashes of each subsystem's state at the start of a tick.
+///
+/// Comparing these between two runs narrows a determinism bug down to the
+/// first tick and subsystem that differ.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TickHash {
+    pub tick: u32,
+    pub ships: u64,
+    pub bullets: u64,
+    pub radar: u64,
+    pub radio: u64,
+    pub rng: u64,
+}
+
+impl TickHash {
+    pub fn compute(sim: &Simulation) -> TickHash {
+        TickHash {
+            tick: sim.tick(),
+            ships: hash_ships(sim),
+            bullets: hash_bullets(sim),
+            radar: hash_radar(sim),
+            radio: hash_radio(sim),
+            rng: hash_rng(sim),
+        }
+    }
+
+    /// Returns the names of the components that differ from `other`.
+    pub fn diff(&self, other: &TickHash) -> Vec<&'static str> {
+        let mut result = vec![];
+        if self.ships != other.ships {
+            result.push("ships");
+        }
+        if self.bullets != other.bullets {
+            result.push("bullets");
+        }
+        if self.radar != other.radar {
+            result.push("radar");
+        }
+        if self.radio != other.radio {
+            result.push("radio");
+        }
+        if self.rng != other.rng {
+            result.push("rng");
+        }
+        result
+    }
+}
+
+/// Returns a human readable dump of a ship's state, one field per line.
+///
+/// Lines from two simulations can be compared to produce a field-level diff.
+pub fn describe_ship(sim: &Simulation, handle: ShipHandle) -> Vec<String> {
+    let ship = sim.ship(handle);
+    let mut lines = vec![
+        format!(
+            "position: [{:?}, {:?}]",
+            ship.position().x,
+            ship.position().y
+        ),
+        format!(
+            "velocity: [{:?}, {:?}]",
+            ship.velocity().x,
+            ship.velocity().y
+        ),
+        format!("heading: {:?}", ship.heading()),
+        format!("angular_velocity: {:?}", ship.angular_velocity()),
+    ];
+    lines.extend(format!("{:#?}", ship.data()).lines().map(|x| x.to_string()));
+    lines
+}
+
+fn fixedpoint(v: f64) -> i64 {
+    (v * 1e9) as i64
+}
+
+fn write_handle(s: &mut DefaultHasher, handle: ShipHandle) {
+    let (index, generation) = handle.0.into_raw_parts();
+    s.write_u32(index);
+    s.write_u32(generation);
+}
+
+fn hash_ships(sim: &Simulation) -> u64 {
+    let mut s = DefaultHasher::new();
+    for &handle in sim.ships.iter() {
+        let ship = sim.ship(handle);
+        let data = ship.data();
+        write_handle(&mut s, handle);
+        s.write_i64(fixedpoint(ship.position().x));
+        s.write_i64(fixedpoint(ship.position().y));
+        s.write_i64(fixedpoint(ship.heading()));
+        s.write_i64(fixedpoint(ship.velocity().x));
+        s.write_i64(fixedpoint(ship.velocity().y));
+        s.write_i64(fixedpoint(ship.angular_velocity()));
+        s.write_i64(fixedpoint(data.health));
+        s.write_i64(fixedpoint(data.fuel.unwrap_or(-1.0)));
+        s.write_i64(fixedpoint(data.acceleration.x));
+        s.write_i64(fixedpoint(data.acceleration.y));
+        s.write_i64(fixedpoint(data.angular_acceleration));
+        s.write_u8(data.destroyed as u8);
+    }
+    s.finish()
+}
+
+fn hash_bullets(sim: &Simulation) -> u64 {
+    let mut s = DefaultHasher::new();
+    for &handle in sim.bullets.iter() {
+        let body = bullet::body(sim, handle);
+        s.write_i64(fixedpoint(body.translation().x));
+        s.write_i64(fixedpoint(body.translation().y));
+        s.write_i64(fixedpoint(body.linvel().x));
+        s.write_i64(fixedpoint(body.linvel().y));
+    }
+    s.finish()
+}
+
+fn hash_radar(sim: &Simulation) -> u64 {
+    let mut s = DefaultHasher::new();
+    for &handle in sim.ships.iter() {
+        let Some(radar) = sim.ship(handle).data().radar.as_ref() else {
+            continue;
+        };
+        write_handle(&mut s, handle);
+        s.write_i64(fixedpoint(radar.heading));
+        s.write_i64(fixedpoint(radar.width));
+        s.write_i64(fixedpoint(radar.min_distance));
+        s.write_i64(fixedpoint(radar.max_distance));
+        s.write_u32(radar.ecm_mode as u32);
+        if let Some(result) = radar.result.as_ref() {
+            s.write_i64(fixedpoint(result.position.x));
+            s.write_i64(fixedpoint(result.position.y));
+            s.write_i64(fixedpoint(result.velocity.x));
+            s.write_i64(fixedpoint(result.velocity.y));
+            s.write_i64(fixedpoint(result.rssi));
+            s.write_i64(fixedpoint(result.snr));
+        }
+    }
+    s.finish()
+}
+
+fn hash_radio(sim: &Simulation) -> u64 {
+    let mut s = DefaultHasher::new();
+    for &handle in sim.ships.iter() {
+        let radios = &sim.ship(handle).data().radios;
+        if radios.is_empty() {
+            continue;
+        }
+        write_handle(&mut s, handle);
+        for radio in radios.iter() {
+            s.write_usize(radio.channel);
+            for msg in [radio.sent, radio.received] {
+                if let Some(msg) = msg {
+                    for v in msg {
+                        s.write_i64(fixedpoint(v));
+                    }
+                } else {
+                    s.write_u8(0);
+                }
+            }
+        }
+    }
+    s.finish()
+}
+
+fn hash_rng(sim: &Simulation) -> u64 {
+    let mut s = DefaultHasher::new();
+    s.write_u128(sim.rng.get_word_pos());
+    s.finish()
+}
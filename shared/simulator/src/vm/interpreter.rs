@@ -0,0 +1,155 @@
+// Pure-Rust backend built on the wasmi interpreter.
+//
+// Slower than wasmer but runs anywhere Rust does, and is useful for checking
+// that the wasmer backends are deterministic. Gas metering is done by the
+// same limiter rewrite, so both backends see identical gas usage.
+use super::{limiter, Error, Limits};
+use crate::simulation::Code;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasmi::{Engine, Linker, Memory, Module, Store, TypedFunc};
+
+#[derive(Clone)]
+pub struct InterpreterVm {
+    store: Rc<RefCell<Store<()>>>,
+    memory: Memory,
+    system_state_offset: u32,
+    environment_offset: u32,
+    loadout_offset: Option<u32>,
+    tick_ship: TypedFunc<i32, ()>,
+    delete_ship: TypedFunc<i32, ()>,
+    reset_gas: TypedFunc<i32, ()>,
+    get_gas: Option<TypedFunc<(), i32>>,
+}
+
+impl InterpreterVm {
+    pub fn create(code: &Code, limits: &Limits) -> Result<InterpreterVm, Error> {
+        let wasm = match code {
+            Code::Wasm(wasm) => limiter::rewrite(wasm, limits)?,
+            _ => {
+                return Err(Error {
+                    msg: "The interpreter backend only supports Wasm code".to_string(),
+                })
+            }
+        };
+
+        let engine = Engine::default();
+        let module = translate_error(Module::new(&engine, &wasm[..]))?;
+        let mut store = Store::new(&engine, ());
+        let linker = <Linker<()>>::new(&engine);
+        let instance = translate_error(
+            linker
+                .instantiate(&mut store, &module)
+                .and_then(|pre| pre.start(&mut store)),
+        )?;
+
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or_else(|| missing_export("memory"))?;
+        let get_offset = |name: &str| -> Result<u32, Error> {
+            match instance.get_global(&store, name).map(|g| g.get(&store)) {
+                Some(wasmi::Value::I32(v)) => Ok(v as u32),
+                _ => Err(missing_export(name)),
+            }
+        };
+        let system_state_offset = get_offset("SYSTEM_STATE")?;
+        let environment_offset = get_offset("ENVIRONMENT")?;
+        // Optional, missing from AIs built against older versions of oort_api.
+        let loadout_offset = get_offset("LOADOUT").ok();
+
+        let initialize =
+            translate_error(instance.get_typed_func::<(), ()>(&store, "export_initialize"))?;
+        let tick_ship =
+            translate_error(instance.get_typed_func::<i32, ()>(&store, "export_tick_ship"))?;
+        let delete_ship =
+            translate_error(instance.get_typed_func::<i32, ()>(&store, "export_delete_ship"))?;
+        let reset_gas = translate_error(instance.get_typed_func::<i32, ()>(&store, "reset_gas"))?;
+        let get_gas = instance.get_typed_func::<(), i32>(&store, "get_gas").ok();
+
+        translate_runtime_error(reset_gas.call(&mut store, super::GAS_PER_TICK))?;
+        translate_runtime_error(initialize.call(&mut store, ()))?;
+
+        Ok(InterpreterVm {
+            store: Rc::new(RefCell::new(store)),
+            memory,
+            system_state_offset,
+            environment_offset,
+            loadout_offset,
+            tick_ship,
+            delete_ship,
+            reset_gas,
+            get_gas,
+        })
+    }
+
+    pub fn system_state_offset(&self) -> u32 {
+        self.system_state_offset
+    }
+
+    pub fn environment_offset(&self) -> u32 {
+        self.environment_offset
+    }
+
+    pub fn loadout_offset(&self) -> Option<u32> {
+        self.loadout_offset
+    }
+
+    pub fn reset_gas(&self, gas: i32) -> Result<(), Error> {
+        translate_runtime_error(self.reset_gas.call(&mut *self.store.borrow_mut(), gas))
+    }
+
+    pub fn gas_remaining(&self) -> Result<Option<i32>, Error> {
+        self.get_gas
+            .as_ref()
+            .map(|f| translate_runtime_error(f.call(&mut *self.store.borrow_mut(), ())))
+            .transpose()
+    }
+
+    pub fn tick_ship(&self, index: i32) -> Result<(), Error> {
+        translate_runtime_error(self.tick_ship.call(&mut *self.store.borrow_mut(), index))
+    }
+
+    pub fn delete_ship(&self, index: i32) -> Result<(), Error> {
+        translate_runtime_error(self.delete_ship.call(&mut *self.store.borrow_mut(), index))
+    }
+
+    pub fn read_memory(&self, offset: u32, buf: &mut [u8]) -> Option<()> {
+        let store = self.store.borrow();
+        self.memory.read(&*store, offset as usize, buf).ok()
+    }
+
+    pub fn write_memory(&self, offset: u32, data: &[u8]) -> Option<()> {
+        let mut store = self.store.borrow_mut();
+        self.memory.write(&mut *store, offset as usize, data).ok()
+    }
+}
+
+fn missing_export(name: &str) -> Error {
+    Error {
+        msg: format!("Interpreter error: missing export {name:?}"),
+    }
+}
+
+fn translate_error<T, U>(err: Result<T, U>) -> Result<T, Error>
+where
+    U: std::fmt::Debug,
+{
+    match err {
+        Ok(val) => Ok(val),
+        Err(err) => Err(Error {
+            msg: format!("Interpreter error: {err:?}"),
+        }),
+    }
+}
+
+fn translate_runtime_error<T, U>(err: Result<T, U>) -> Result<T, Error>
+where
+    U: std::fmt::Display,
+{
+    match err {
+        Ok(val) => Ok(val),
+        Err(err) => Err(Error {
+            msg: format!("Ship runtime error: {err}"),
+        }),
+    }
+}
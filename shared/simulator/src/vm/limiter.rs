@@ -1,7 +1,9 @@
 // Based on https://github.com/scrtlabs/SecretNetwork/blob/621d3899babc4741ef1ba596152c097677d246db/cosmwasm/enclaves/shared/contract-engine/src/wasm3/gas.rs
+use super::Limits;
 use walrus::{ir::*, FunctionBuilder, GlobalId, InitExpr, LocalFunction, ValType};
 
-pub fn rewrite(wasm: &[u8]) -> Result<Vec<u8>, super::Error> {
+pub fn rewrite(wasm: &[u8], limits: &Limits) -> Result<Vec<u8>, super::Error> {
+    limits.check_size(wasm)?;
     let mut module = match walrus::Module::from_buffer(wasm) {
         Ok(m) => m,
         Err(e) => {
@@ -10,6 +12,8 @@ pub fn rewrite(wasm: &[u8]) -> Result<Vec<u8>, super::Error> {
             })
         }
     };
+    limits.check(&module)?;
+    limits.cap(&mut module);
 
     let gas_global = module
         .globals
@@ -29,6 +33,14 @@ pub fn rewrite(wasm: &[u8]) -> Result<Vec<u8>, super::Error> {
         module.exports.add("reset_gas", reset_gas);
     }
 
+    // Create a get_gas() -> remaining function.
+    {
+        let mut func = FunctionBuilder::new(&mut module.types, &[], &[ValType::I32]);
+        func.func_body().global_get(gas_global);
+        let get_gas = func.finish(vec![], &mut module.funcs);
+        module.exports.add("get_gas", get_gas);
+    }
+
     Ok(module.emit_wasm())
 }
 
@@ -51,14 +63,17 @@ fn rewrite_block(func: &mut LocalFunction, block_id: InstrSeqId, gas_global: Glo
     let builder = func.builder_mut();
     let mut builder = builder.dangling_instr_seq(None);
     let seq = builder
-        // if unsigned(globals[gas]) < unsigned(block_cost) { throw(); }
+        // if unsigned(globals[gas]) < unsigned(block_cost) { globals[gas] = 0; throw(); }
+        //
+        // Zeroing the gas before trapping lets the host tell an out-of-gas trap
+        // apart from other runtime errors and charge the full allowance.
         .global_get(gas_global)
         .i32_const(block_cost)
         .binop(BinaryOp::I32LtU)
         .if_else(
             None,
             |then| {
-                then.unreachable();
+                then.i32_const(0).global_set(gas_global).unreachable();
             },
             |_else| {},
         )
@@ -124,7 +139,7 @@ mod tests {
 )
 ",
         );
-        let new_wasm = rewrite(&wasm).unwrap();
+        let new_wasm = rewrite(&wasm, &Limits::default()).unwrap();
         check_wat(
             &new_wasm,
             "
@@ -136,6 +151,8 @@ mod tests {
     i32.const 2
     i32.lt_u
     if  ;; label = @1
+      i32.const 0
+      global.set 0
       unreachable
     end
     global.get 0
@@ -147,6 +164,8 @@ mod tests {
       i32.const 1
       i32.lt_u
       if  ;; label = @2
+        i32.const 0
+        global.set 0
         unreachable
       end
       global.get 0
@@ -159,8 +178,11 @@ mod tests {
   (func (;1;) (type 1) (param i32)
     local.get 0
     global.set 0)
+  (func (;2;) (type 0) (result i32)
+    global.get 0)
   (global (;0;) (mut i32) (i32.const 0))
-  (export \"reset_gas\" (func 1)))
+  (export \"reset_gas\" (func 1))
+  (export \"get_gas\" (func 2)))
 ",
         );
     }
@@ -0,0 +1,338 @@
+use super::Error;
+use serde::{Deserialize, Serialize};
+
+/// Resource limits enforced on AI modules.
+///
+/// Modules are checked before instantiation and their memories are capped so
+/// that `memory.grow` fails instead of exhausting the host.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    pub max_initial_memory_pages: u32,
+    pub max_memory_pages: u32,
+    pub max_module_size: usize,
+    pub max_functions: usize,
+    pub max_table_size: u32,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_initial_memory_pages: 64,
+            max_memory_pages: 1024,
+            max_module_size: 8 << 20,
+            max_functions: 20000,
+            max_table_size: 10000,
+        }
+    }
+}
+
+impl Limits {
+    pub fn check_size(&self, wasm: &[u8]) -> Result<(), Error> {
+        if wasm.len() > self.max_module_size {
+            return Err(Error {
+                msg: format!(
+                    "Wasm module is {} bytes, more than the limit of {} bytes",
+                    wasm.len(),
+                    self.max_module_size
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    pub fn check(&self, module: &walrus::Module) -> Result<(), Error> {
+        let num_functions = module.funcs.iter().count();
+        if num_functions > self.max_functions {
+            return Err(Error {
+                msg: format!(
+                    "Wasm module has {} functions, more than the limit of {}",
+                    num_functions, self.max_functions
+                ),
+            });
+        }
+
+        for memory in module.memories.iter() {
+            if memory.initial > self.max_initial_memory_pages {
+                return Err(Error {
+                    msg: format!(
+                        "Wasm module requests {} initial memory pages, more than the limit of {}",
+                        memory.initial, self.max_initial_memory_pages
+                    ),
+                });
+            }
+            if let Some(maximum) = memory.maximum {
+                if maximum > self.max_memory_pages {
+                    return Err(Error {
+                        msg: format!(
+                            "Wasm module requests {} maximum memory pages, more than the limit of {}",
+                            maximum, self.max_memory_pages
+                        ),
+                    });
+                }
+            }
+        }
+
+        for table in module.tables.iter() {
+            let size = table.maximum.unwrap_or(table.initial);
+            if table.initial > self.max_table_size || size > self.max_table_size {
+                return Err(Error {
+                    msg: format!(
+                        "Wasm module requests a table of {} elements, more than the limit of {}",
+                        size, self.max_table_size
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sets the maximum size of each memory and table so growth is bounded at runtime.
+    pub fn cap(&self, module: &mut walrus::Module) {
+        for memory in module.memories.iter_mut() {
+            memory.maximum = Some(
+                memory
+                    .maximum
+                    .unwrap_or(self.max_memory_pages)
+                    .min(self.max_memory_pages),
+            );
+        }
+        for table in module.tables.iter_mut() {
+            table.maximum = Some(
+                table
+                    .maximum
+                    .unwrap_or(self.max_table_size)
+                    .min(self.max_table_size),
+            );
+        }
+    }
+}
+
+#[cfg(feature = "sys")]
+pub use tunables::LimitingTunables;
+
+#[cfg(feature = "sys")]
+mod tunables {
+    // Based on https://github.com/wasmerio/wasmer/blob/master/examples/tunables_limit_memory.rs
+    use super::Limits;
+    use std::ptr::NonNull;
+    use wasmer::vm::{
+        self, MemoryError, MemoryStyle, TableStyle, VMMemoryDefinition, VMTableDefinition,
+    };
+    use wasmer::{MemoryType, Pages, TableType, Tunables};
+
+    /// Applies [`Limits`] to memories and tables created by the store.
+    ///
+    /// This covers precompiled modules, which skip the static checks.
+    pub struct LimitingTunables<T: Tunables> {
+        limits: Limits,
+        base: T,
+    }
+
+    impl<T: Tunables> LimitingTunables<T> {
+        pub fn new(base: T, limits: Limits) -> Self {
+            Self { limits, base }
+        }
+
+        fn adjust_memory(&self, requested: &MemoryType) -> MemoryType {
+            let mut adjusted = *requested;
+            let limit = Pages(self.limits.max_memory_pages);
+            adjusted.maximum = Some(requested.maximum.unwrap_or(limit).min(limit));
+            adjusted
+        }
+
+        fn validate_memory(&self, ty: &MemoryType) -> Result<(), MemoryError> {
+            if ty.minimum > Pages(self.limits.max_initial_memory_pages) {
+                return Err(MemoryError::Generic(format!(
+                    "Wasm module requests {} initial memory pages, more than the limit of {}",
+                    ty.minimum.0, self.limits.max_initial_memory_pages
+                )));
+            }
+            if let Some(maximum) = ty.maximum {
+                if ty.minimum > maximum {
+                    return Err(MemoryError::InvalidMemory {
+                        reason: "Minimum exceeds the allowed memory limit".to_string(),
+                    });
+                }
+            }
+            Ok(())
+        }
+
+        fn adjust_table(&self, requested: &TableType) -> TableType {
+            let mut adjusted = *requested;
+            let limit = self.limits.max_table_size;
+            adjusted.maximum = Some(requested.maximum.unwrap_or(limit).min(limit));
+            adjusted
+        }
+
+        fn validate_table(&self, ty: &TableType) -> Result<(), String> {
+            if ty.minimum > self.limits.max_table_size {
+                return Err(format!(
+                    "Wasm module requests a table of {} elements, more than the limit of {}",
+                    ty.minimum, self.limits.max_table_size
+                ));
+            }
+            Ok(())
+        }
+    }
+
+    impl<T: Tunables> Tunables for LimitingTunables<T> {
+        fn memory_style(&self, memory: &MemoryType) -> MemoryStyle {
+            let adjusted = self.adjust_memory(memory);
+            self.base.memory_style(&adjusted)
+        }
+
+        fn table_style(&self, table: &TableType) -> TableStyle {
+            self.base.table_style(table)
+        }
+
+        fn create_host_memory(
+            &self,
+            ty: &MemoryType,
+            style: &MemoryStyle,
+        ) -> Result<vm::VMMemory, MemoryError> {
+            let adjusted = self.adjust_memory(ty);
+            self.validate_memory(&adjusted)?;
+            self.base.create_host_memory(&adjusted, style)
+        }
+
+        unsafe fn create_vm_memory(
+            &self,
+            ty: &MemoryType,
+            style: &MemoryStyle,
+            vm_definition_location: NonNull<VMMemoryDefinition>,
+        ) -> Result<vm::VMMemory, MemoryError> {
+            let adjusted = self.adjust_memory(ty);
+            self.validate_memory(&adjusted)?;
+            self.base
+                .create_vm_memory(&adjusted, style, vm_definition_location)
+        }
+
+        fn create_host_table(
+            &self,
+            ty: &TableType,
+            style: &TableStyle,
+        ) -> Result<vm::VMTable, String> {
+            let adjusted = self.adjust_table(ty);
+            self.validate_table(&adjusted)?;
+            self.base.create_host_table(&adjusted, style)
+        }
+
+        unsafe fn create_vm_table(
+            &self,
+            ty: &TableType,
+            style: &TableStyle,
+            vm_definition_location: NonNull<VMTableDefinition>,
+        ) -> Result<vm::VMTable, String> {
+            let adjusted = self.adjust_table(ty);
+            self.validate_table(&adjusted)?;
+            self.base
+                .create_vm_table(&adjusted, style, vm_definition_location)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wat2wasm(wat: &str) -> Vec<u8> {
+        wabt::Wat2Wasm::new()
+            .convert(wat)
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    fn check(wat: &str, limits: &Limits) -> Result<(), Error> {
+        let wasm = wat2wasm(wat);
+        limits.check_size(&wasm)?;
+        limits.check(&walrus::Module::from_buffer(&wasm).unwrap())
+    }
+
+    #[test]
+    fn test_accepts_small_module() {
+        check(
+            "(module (memory 17) (table 1 funcref) (func (result i32) i32.const 1))",
+            &Limits::default(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn test_rejects_initial_memory() {
+        let err = check("(module (memory 10000))", &Limits::default()).unwrap_err();
+        assert!(err.msg.contains("initial memory pages"), "{}", err.msg);
+    }
+
+    #[test]
+    fn test_rejects_maximum_memory() {
+        let err = check("(module (memory 1 65536))", &Limits::default()).unwrap_err();
+        assert!(err.msg.contains("maximum memory pages"), "{}", err.msg);
+    }
+
+    #[test]
+    fn test_rejects_table() {
+        let err = check("(module (table 1000000 funcref))", &Limits::default()).unwrap_err();
+        assert!(err.msg.contains("table"), "{}", err.msg);
+    }
+
+    #[test]
+    fn test_rejects_functions() {
+        let limits = Limits {
+            max_functions: 2,
+            ..Default::default()
+        };
+        let err = check("(module (func) (func) (func))", &limits).unwrap_err();
+        assert!(err.msg.contains("3 functions"), "{}", err.msg);
+    }
+
+    #[test]
+    fn test_rejects_module_size() {
+        let limits = Limits {
+            max_module_size: 16,
+            ..Default::default()
+        };
+        let err = check("(module (func) (func) (func))", &limits).unwrap_err();
+        assert!(err.msg.contains("bytes"), "{}", err.msg);
+    }
+
+    #[test]
+    fn test_cap() {
+        let limits = Limits::default();
+        let mut module = walrus::Module::from_buffer(&wat2wasm("(module (memory 1))")).unwrap();
+        limits.cap(&mut module);
+        assert_eq!(
+            module.memories.iter().next().unwrap().maximum,
+            Some(limits.max_memory_pages)
+        );
+    }
+
+    #[cfg(feature = "sys")]
+    #[test]
+    fn test_memory_grow_fails_past_limit() {
+        use wasmer::{imports, Instance, Module};
+        let limits = Limits {
+            max_memory_pages: 4,
+            ..Default::default()
+        };
+        let mut store = super::super::wasmer_backend::new_store(&limits);
+        let wasm = wat2wasm(
+            r#"
+(module
+    (memory 1)
+    (func (export "grow") (param i32) (result i32)
+        local.get 0
+        memory.grow)
+)
+"#,
+        );
+        let module = Module::new(&store, wasm).unwrap();
+        let instance = Instance::new(&mut store, &module, &imports! {}).unwrap();
+        let grow = instance.exports.get_function("grow").unwrap();
+        let result = grow.call(&mut store, &[100.into()]).unwrap();
+        assert_eq!(result[0].i32(), Some(-1));
+        let result = grow.call(&mut store, &[3.into()]).unwrap();
+        assert_eq!(result[0].i32(), Some(1));
+    }
+}
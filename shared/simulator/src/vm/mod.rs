@@ -1,24 +1,59 @@
 pub mod builtin;
+#[cfg(feature = "interpreter")]
+mod interpreter;
 mod limiter;
+mod limits;
+pub mod validator;
+#[cfg(any(feature = "sys", feature = "js"))]
+mod wasmer_backend;
+
+#[cfg(not(any(feature = "sys", feature = "js", feature = "interpreter")))]
+compile_error!("One of the \"sys\", \"js\" or \"interpreter\" features must be enabled");
 
 use crate::color;
 use crate::debug;
 use crate::ship::{ShipClass, ShipHandle};
 use crate::simulation::{Code, Simulation};
 use nalgebra::point;
-use oort_api::{Ability, Class, EcmMode, Line, SystemState, Text};
+use oort_api::prelude::loadout_internal;
+use oort_api::{Ability, Class, EcmMode, Line, Shape, SystemState, Text};
 use serde::{Deserialize, Serialize};
-use std::cell::{Ref, RefCell, RefMut};
+use std::cell::Cell;
 use std::collections::BTreeMap;
 use std::collections::HashMap;
-use std::ops::{Deref, DerefMut};
-use std::rc::Rc;
-use wasmer::{imports, Instance, MemoryView, Module, Store, WasmPtr};
+
+pub use limits::Limits;
+#[cfg(feature = "precompile")]
+pub use wasmer_backend::{precompile, precompile_with_limits};
 
 pub type Vec2 = nalgebra::Vector2<f64>;
 pub type Environment = BTreeMap<String, String>;
 
-const GAS_PER_TICK: i32 = 1_000_000;
+pub const GAS_PER_TICK: i32 = oort_api::GAS_PER_TICK as i32;
+/// Maximum size of the log buffer read from a ship each tick.
+pub const MAX_LOG_BYTES: u32 = 16384;
+/// Maximum number of log entries accepted from a ship each tick.
+pub const MAX_LOG_ENTRIES_PER_TICK: usize = 32;
+/// Log messages longer than this are truncated.
+pub const MAX_LOG_MESSAGE_LENGTH: usize = 256;
+
+thread_local! {
+    /// Team whose AI this thread is compiling or running.
+    static EXECUTING_TEAM: Cell<Option<i32>> = const { Cell::new(None) };
+}
+
+/// Returns the team whose AI the current thread is compiling or running.
+///
+/// Each thread tracks its own simulation, so concurrent simulations don't
+/// interfere. Safe to call from a signal handler, so a child process that is
+/// killed for exceeding a resource limit can report which AI was responsible.
+pub fn executing_team() -> Option<i32> {
+    EXECUTING_TEAM.with(Cell::get)
+}
+
+pub(crate) fn set_executing_team(team: Option<i32>) {
+    EXECUTING_TEAM.with(|x| x.set(team));
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Error {
@@ -34,20 +69,43 @@ impl From<wasm_bindgen::JsValue> for Error {
     }
 }
 
-impl From<wasmer::InstantiationError> for Error {
-    fn from(err: wasmer::InstantiationError) -> Self {
-        Self {
-            msg: format!("Wasmer instantiation error: {err:?}"),
+/// Which implementation executes AI code.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// wasmer, using singlepass natively or the browser's engine on the web.
+    Wasmer,
+    /// The wasmi interpreter. Requires the "interpreter" feature.
+    Interpreter,
+}
+
+impl Default for Backend {
+    fn default() -> Self {
+        if cfg!(any(feature = "sys", feature = "js")) {
+            Backend::Wasmer
+        } else {
+            Backend::Interpreter
         }
     }
 }
+
 pub fn new_team_controller(code: &Code) -> Result<Box<TeamController>, Error> {
+    new_team_controller_with_backend(code, Backend::default())
+}
+
+pub fn new_team_controller_with_backend(
+    code: &Code,
+    backend: Backend,
+) -> Result<Box<TeamController>, Error> {
+    let limits = Limits::default();
     match code {
-        Code::Wasm(_) => TeamController::create(code),
+        Code::Wasm(wasm) => {
+            validator::validate(wasm).into_result()?;
+            TeamController::create_with_backend(code, &limits, backend)
+        }
         #[cfg(feature = "precompile")]
-        Code::Precompiled(_) => TeamController::create(code),
+        Code::Precompiled(_) => TeamController::create_with_backend(code, &limits, backend),
         Code::Builtin(name) => match builtin::load_compiled(name) {
-            Ok(code) => new_team_controller(&code),
+            Ok(code) => new_team_controller_with_backend(&code, backend),
             Err(e) => Err(Error { msg: e }),
         },
         _ => unreachable!(),
@@ -58,14 +116,30 @@ pub struct TeamController {
     vm: WasmVm,
     states: HashMap<ShipHandle, LocalSystemState>,
     next_id: u32,
+    // Contents of the AI's loadout buffer, which is shared by all ships on
+    // the team. Only rewritten when the next ship's loadout differs.
+    loadout: Option<[f64; loadout_internal::SIZE]>,
 }
 
 impl TeamController {
     pub fn create(code: &Code) -> Result<Box<TeamController>, Error> {
+        Self::create_with_limits(code, &Limits::default())
+    }
+
+    pub fn create_with_limits(code: &Code, limits: &Limits) -> Result<Box<TeamController>, Error> {
+        Self::create_with_backend(code, limits, Backend::default())
+    }
+
+    pub fn create_with_backend(
+        code: &Code,
+        limits: &Limits,
+        backend: Backend,
+    ) -> Result<Box<TeamController>, Error> {
         Ok(Box::new(TeamController {
-            vm: WasmVm::create(code)?,
+            vm: WasmVm::create_with_backend(code, limits, backend)?,
             states: HashMap::new(),
             next_id: 1,
+            loadout: None,
         }))
     }
 
@@ -78,6 +152,7 @@ impl TeamController {
         );
         state.set(SystemState::Id, self.next_id as f64);
         self.next_id += 1;
+        state.set(SystemState::GasRemaining, GAS_PER_TICK as f64);
         if let Some(radar) = sim.ship(handle).data().radar.as_ref() {
             state.set(SystemState::RadarHeading, radar.heading);
             state.set(SystemState::RadarWidth, radar.width);
@@ -94,11 +169,7 @@ impl TeamController {
         self.states.remove(&handle);
         let (index, _) = handle.0.into_raw_parts();
         let index = index as i32;
-        if let Err(e) = translate_runtime_error(
-            self.vm
-                .delete_ship
-                .call(self.vm.store_mut().deref_mut(), &[index.into()]),
-        ) {
+        if let Err(e) = self.vm.delete_ship(index) {
             log::warn!("Failed to delete ship: {:?}", e);
         }
     }
@@ -116,49 +187,52 @@ impl TeamController {
     }
 
     fn tick_ship(&mut self, sim: &mut Simulation, handle: ShipHandle) -> Result<(), Error> {
-        let vm = &mut self.vm;
+        let vm = &self.vm;
         let state = self.states.get_mut(&handle).unwrap();
 
         {
-            translate_runtime_error(
-                vm.reset_gas
-                    .call(vm.store_mut().deref_mut(), &[GAS_PER_TICK.into()]),
-            )?;
+            vm.reset_gas(GAS_PER_TICK)?;
 
             generate_system_state(sim, handle, state);
 
-            let store = vm.store();
-            let memory_view = vm.memory.view(store.deref());
-            let slice = vm
-                .system_state_ptr
-                .slice(&memory_view, SystemState::Size as u32)
+            vm.write_system_state(&state.state)
                 .expect("system state write");
-            slice.write_slice(&state.state).expect("system state write");
+
+            let loadout = generate_loadout(sim, handle);
+            if self.loadout != Some(loadout) {
+                vm.write_loadout(&loadout).ok_or_else(|| Error {
+                    msg: "failed to write loadout".to_string(),
+                })?;
+                self.loadout = Some(loadout);
+            }
         }
 
         let (index, _) = handle.0.into_raw_parts();
         let index = index as i32;
-        translate_runtime_error(
-            vm.tick_ship
-                .call(vm.store_mut().deref_mut(), &[index.into()]),
-        )?;
+        if let Err(e) = vm.tick_ship(index) {
+            // The limiter zeroes the gas before trapping, so a ship that ran
+            // out of gas is charged the full allowance.
+            if let Ok(Some(gas_remaining)) = vm.gas_remaining() {
+                sim.emit_gas_used(handle, (GAS_PER_TICK - gas_remaining) as u32);
+            }
+            return Err(e);
+        }
+        let gas_remaining = vm.gas_remaining()?;
 
         {
-            let store = vm.store();
-            let memory_view = vm.memory.view(store.deref());
-            let slice = vm
-                .system_state_ptr
-                .slice(&memory_view, SystemState::Size as u32)
-                .expect("system state read");
-            slice
-                .read_slice(&mut state.state)
+            vm.read_system_state(&mut state.state)
                 .expect("system state read");
             apply_system_state(sim, handle, state);
 
+            if let Some(gas_remaining) = gas_remaining {
+                state.set(SystemState::GasRemaining, gas_remaining as f64);
+                sim.emit_gas_used(handle, (GAS_PER_TICK - gas_remaining) as u32);
+            }
+
             if state.get(SystemState::DebugTextLength) > 0.0 {
                 let offset = state.get(SystemState::DebugTextPointer) as u32;
                 let length = state.get(SystemState::DebugTextLength) as u32;
-                if let Some(s) = WasmVm::read_string(&memory_view, offset, length) {
+                if let Some(s) = vm.read_string(offset, length) {
                     sim.emit_debug_text(handle, s);
                 }
             }
@@ -167,7 +241,7 @@ impl TeamController {
                 let offset = state.get(SystemState::DebugLinesPointer) as u32;
                 let length = state.get(SystemState::DebugLinesLength) as u32;
                 if length <= 128 {
-                    if let Some(lines) = WasmVm::read_vec::<Line>(&memory_view, offset, length) {
+                    if let Some(lines) = vm.read_vec::<Line>(offset, length) {
                         if validate_lines(&lines) {
                             sim.emit_debug_lines(
                                 handle,
@@ -185,11 +259,45 @@ impl TeamController {
                 }
             }
 
+            if state.get(SystemState::DebugShapesLength) > 0.0 {
+                let offset = state.get(SystemState::DebugShapesPointer) as u32;
+                let length = state.get(SystemState::DebugShapesLength) as u32;
+                let layers = if state.get(SystemState::DebugLayersLength) > 0.0 {
+                    let offset = state.get(SystemState::DebugLayersPointer) as u32;
+                    let length = state.get(SystemState::DebugLayersLength) as u32;
+                    vm.read_string(offset, length.min(4096)).unwrap_or_default()
+                } else {
+                    String::new()
+                };
+                if length <= 128 {
+                    if let Some(shapes) = vm.read_vec::<Shape>(offset, length) {
+                        let layers: Vec<&str> = layers.lines().collect();
+                        if let Some(drawings) = shapes
+                            .iter()
+                            .map(|x| translate_shape(x, &layers))
+                            .collect::<Option<Vec<_>>>()
+                        {
+                            sim.emit_drawings(handle, drawings);
+                        }
+                    }
+                }
+            }
+
+            if state.get(SystemState::DebugLogLength) > 0.0 {
+                let offset = state.get(SystemState::DebugLogPointer) as u32;
+                let length = state.get(SystemState::DebugLogLength) as u32;
+                if length <= MAX_LOG_BYTES {
+                    if let Some(s) = vm.read_string(offset, length) {
+                        sim.emit_log(handle, parse_log(&s));
+                    }
+                }
+            }
+
             if state.get(SystemState::DrawnTextLength) > 0.0 {
                 let offset = state.get(SystemState::DrawnTextPointer) as u32;
                 let length = state.get(SystemState::DrawnTextLength) as u32;
                 if length <= 128 {
-                    if let Some(texts) = WasmVm::read_vec::<Text>(&memory_view, offset, length) {
+                    if let Some(texts) = vm.read_vec::<Text>(offset, length) {
                         if validate_texts(&texts) {
                             sim.emit_drawn_text(handle, &texts);
                         }
@@ -204,99 +312,154 @@ impl TeamController {
     pub fn update_environment(&mut self, environment: &Environment) -> Result<(), Error> {
         self.vm.update_environment(environment)
     }
+
+    /// The VM running this team's code.
+    pub fn vm(&self) -> &WasmVm {
+        &self.vm
+    }
+}
+
+#[derive(Clone)]
+enum Inner {
+    #[cfg(any(feature = "sys", feature = "js"))]
+    Wasmer(wasmer_backend::WasmerVm),
+    #[cfg(feature = "interpreter")]
+    Interpreter(interpreter::InterpreterVm),
+}
+
+macro_rules! dispatch {
+    ($self:expr, $vm:ident => $body:expr) => {
+        match &$self.inner {
+            #[cfg(any(feature = "sys", feature = "js"))]
+            Inner::Wasmer($vm) => $body,
+            #[cfg(feature = "interpreter")]
+            Inner::Interpreter($vm) => $body,
+        }
+    };
 }
 
 #[derive(Clone)]
 pub struct WasmVm {
-    store: Rc<RefCell<wasmer::Store>>,
-    memory: wasmer::Memory,
-    system_state_ptr: WasmPtr<f64>,
-    environment_ptr: WasmPtr<u8>,
-    tick_ship: wasmer::Function,
-    delete_ship: wasmer::Function,
-    reset_gas: wasmer::Function,
+    inner: Inner,
 }
 
 impl WasmVm {
     pub fn create(code: &Code) -> Result<WasmVm, Error> {
-        #[cfg(feature = "js")]
-        let mut store = Store::default();
-        #[cfg(feature = "sys")]
-        let mut store = Store::new(wasmer_compiler_singlepass::Singlepass::new());
-        let module = match code {
-            Code::Wasm(wasm) => {
-                let wasm = limiter::rewrite(wasm)?;
-                translate_error(Module::new(&store, wasm))?
+        Self::create_with_limits(code, &Limits::default())
+    }
+
+    pub fn create_with_limits(code: &Code, limits: &Limits) -> Result<WasmVm, Error> {
+        Self::create_with_backend(code, limits, Backend::default())
+    }
+
+    pub fn create_with_backend(
+        code: &Code,
+        limits: &Limits,
+        backend: Backend,
+    ) -> Result<WasmVm, Error> {
+        let inner = match backend {
+            #[cfg(any(feature = "sys", feature = "js"))]
+            Backend::Wasmer => Inner::Wasmer(wasmer_backend::WasmerVm::create(code, limits)?),
+            #[cfg(feature = "interpreter")]
+            Backend::Interpreter => {
+                Inner::Interpreter(interpreter::InterpreterVm::create(code, limits)?)
             }
-            #[cfg(feature = "precompile")]
-            Code::Precompiled(bytes) => {
-                translate_error(unsafe { Module::deserialize(&store, bytes.clone()) })?
+            #[allow(unreachable_patterns)]
+            _ => {
+                return Err(Error {
+                    msg: format!("VM backend {backend:?} is not enabled in this build"),
+                })
             }
-            _ => unreachable!(),
         };
-        let import_object = imports! {};
-        let instance = Instance::new(&mut store, &module, &import_object)?;
-
-        let memory = translate_error(instance.exports.get_memory("memory"))?.clone();
-        let system_state_offset: i32 =
-            translate_error(instance.exports.get_global("SYSTEM_STATE"))?
-                .get(&mut store)
-                .i32()
-                .unwrap();
-        let system_state_ptr: WasmPtr<f64> = WasmPtr::new(system_state_offset as u32);
-        let environment_offset: i32 = translate_error(instance.exports.get_global("ENVIRONMENT"))?
-            .get(&mut store)
-            .i32()
-            .unwrap();
-        let environment_ptr: WasmPtr<u8> = WasmPtr::new(environment_offset as u32);
-
-        let initialize =
-            translate_error(instance.exports.get_function("export_initialize"))?.clone();
-        let tick_ship = translate_error(instance.exports.get_function("export_tick_ship"))?.clone();
-        let delete_ship =
-            translate_error(instance.exports.get_function("export_delete_ship"))?.clone();
-        let reset_gas = translate_error(instance.exports.get_function("reset_gas"))?.clone();
-
-        translate_runtime_error(reset_gas.call(&mut store, &[GAS_PER_TICK.into()]))?;
-        translate_runtime_error(initialize.call(&mut store, &[]))?;
-
-        Ok(WasmVm {
-            store: Rc::new(RefCell::new(store)),
-            memory,
-            system_state_ptr,
-            environment_ptr,
-            tick_ship,
-            delete_ship,
-            reset_gas,
-        })
+        Ok(WasmVm { inner })
+    }
+
+    pub fn backend(&self) -> Backend {
+        match &self.inner {
+            #[cfg(any(feature = "sys", feature = "js"))]
+            Inner::Wasmer(_) => Backend::Wasmer,
+            #[cfg(feature = "interpreter")]
+            Inner::Interpreter(_) => Backend::Interpreter,
+        }
+    }
+
+    pub fn reset_gas(&self, gas: i32) -> Result<(), Error> {
+        dispatch!(self, vm => vm.reset_gas(gas))
+    }
+
+    /// Returns the gas left after the last call into the VM, or None if the
+    /// module predates the get_gas export.
+    pub fn gas_remaining(&self) -> Result<Option<i32>, Error> {
+        dispatch!(self, vm => vm.gas_remaining())
+    }
+
+    pub fn tick_ship(&self, index: i32) -> Result<(), Error> {
+        dispatch!(self, vm => vm.tick_ship(index))
+    }
+
+    pub fn delete_ship(&self, index: i32) -> Result<(), Error> {
+        dispatch!(self, vm => vm.delete_ship(index))
     }
 
-    fn store(&self) -> Ref<'_, Store> {
-        self.store.borrow()
+    /// Copies `buf.len()` bytes of guest memory starting at `offset`.
+    pub fn read_memory(&self, offset: u32, buf: &mut [u8]) -> Option<()> {
+        dispatch!(self, vm => vm.read_memory(offset, buf))
     }
 
-    fn store_mut(&self) -> RefMut<'_, Store> {
-        self.store.borrow_mut()
+    pub fn write_memory(&self, offset: u32, data: &[u8]) -> Option<()> {
+        dispatch!(self, vm => vm.write_memory(offset, data))
     }
 
-    fn read_string(memory_view: &MemoryView, offset: u32, length: u32) -> Option<String> {
-        let ptr: WasmPtr<u8> = WasmPtr::new(offset);
-        let mut bytes: Vec<u8> = Vec::new();
-        bytes.resize(length as usize, 0);
-        let slice = ptr.slice(memory_view, length).ok()?;
-        slice.read_slice(&mut bytes).ok()?;
+    fn system_state_offset(&self) -> u32 {
+        dispatch!(self, vm => vm.system_state_offset())
+    }
+
+    fn environment_offset(&self) -> u32 {
+        dispatch!(self, vm => vm.environment_offset())
+    }
+
+    fn loadout_offset(&self) -> Option<u32> {
+        dispatch!(self, vm => vm.loadout_offset())
+    }
+
+    fn read_system_state(&self, state: &mut [f64]) -> Option<()> {
+        let mut bytes = [0u8; SystemState::Size as usize * std::mem::size_of::<f64>()];
+        self.read_memory(self.system_state_offset(), &mut bytes)?;
+        for (v, chunk) in state.iter_mut().zip(bytes.chunks_exact(8)) {
+            *v = f64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        Some(())
+    }
+
+    fn write_system_state(&self, state: &[f64]) -> Option<()> {
+        let mut bytes = [0u8; SystemState::Size as usize * std::mem::size_of::<f64>()];
+        for (v, chunk) in state.iter().zip(bytes.chunks_exact_mut(8)) {
+            chunk.copy_from_slice(&v.to_le_bytes());
+        }
+        self.write_memory(self.system_state_offset(), &bytes)
+    }
+
+    fn write_loadout(&self, loadout: &[f64]) -> Option<()> {
+        let Some(offset) = self.loadout_offset() else {
+            return Some(());
+        };
+        let mut bytes = [0u8; loadout_internal::SIZE * std::mem::size_of::<f64>()];
+        for (v, chunk) in loadout.iter().zip(bytes.chunks_exact_mut(8)) {
+            chunk.copy_from_slice(&v.to_le_bytes());
+        }
+        self.write_memory(offset, &bytes)
+    }
+
+    fn read_string(&self, offset: u32, length: u32) -> Option<String> {
+        let mut bytes: Vec<u8> = vec![0; length as usize];
+        self.read_memory(offset, &mut bytes)?;
         String::from_utf8(bytes).ok()
     }
 
-    fn read_vec<T: Default + Clone>(
-        memory_view: &MemoryView,
-        offset: u32,
-        length: u32,
-    ) -> Option<Vec<T>> {
-        let ptr: WasmPtr<u8> = WasmPtr::new(offset);
-        let byte_length = length.saturating_mul(std::mem::size_of::<T>() as u32);
-        let slice = ptr.slice(memory_view, byte_length).ok()?;
-        let byte_vec = slice.read_to_vec().ok()?;
+    fn read_vec<T: Default + Clone>(&self, offset: u32, length: u32) -> Option<Vec<T>> {
+        let byte_length = length.checked_mul(std::mem::size_of::<T>() as u32)?;
+        let mut byte_vec = vec![0; byte_length as usize];
+        self.read_memory(offset, &mut byte_vec)?;
         let src_ptr = unsafe { std::mem::transmute::<*const u8, *const T>(byte_vec.as_ptr()) };
         let src_slice = unsafe { std::slice::from_raw_parts(src_ptr, length as usize) };
         Some(src_slice.to_vec())
@@ -313,18 +476,10 @@ impl WasmVm {
                 msg: "environment too large".to_string(),
             });
         }
-        let store = self.store_mut();
-        let view = self.memory.view(&store);
-        let slice = self
-            .environment_ptr
-            .slice(&view, environment_string.bytes().len() as u32)
-            .ok()
-            .unwrap();
-        slice
-            .write_slice(environment_string.as_bytes())
-            .ok()
-            .unwrap();
-        Ok(())
+        self.write_memory(self.environment_offset(), environment_string.as_bytes())
+            .ok_or_else(|| Error {
+                msg: "failed to write environment".to_string(),
+            })
     }
 }
 
@@ -354,6 +509,10 @@ impl LocalSystemState {
 }
 
 fn generate_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut LocalSystemState) {
+    // Values from the previous tick, compared below to generate events.
+    let previous_health = state.get(SystemState::Health);
+    let previous_contact_found = state.get(SystemState::RadarContactFound) > 0.0;
+
     state.set(
         SystemState::Class,
         translate_class(sim.ship(handle).data().class) as u32 as f64,
@@ -444,6 +603,60 @@ fn generate_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut L
         state.set(idxs.send, 0.0);
     }
 
+    {
+        use oort_api::events_internal::*;
+        let mut events = 0;
+        let health = state.get(SystemState::Health);
+        if previous_health > health {
+            events |= DAMAGED;
+            state.set(SystemState::DamageTaken, previous_health - health);
+        } else {
+            state.set(SystemState::DamageTaken, 0.0);
+        }
+        if previous_contact_found && state.get(SystemState::RadarContactFound) == 0.0 {
+            events |= CONTACT_LOST;
+        }
+        for i in 0..sim.ship(handle).data().radios.len() {
+            let idxs = oort_api::prelude::radio_internal::radio_indices(i);
+            if state.get(idxs.receive) > 0.0 {
+                events |= RADIO_RECEIVED << i;
+            }
+        }
+        for (i, idx) in [
+            SystemState::ReloadTicks0,
+            SystemState::ReloadTicks1,
+            SystemState::ReloadTicks2,
+            SystemState::ReloadTicks3,
+        ]
+        .iter()
+        .enumerate()
+        .take(MAX_LAUNCH_EVENTS)
+        {
+            // Launchers come after guns. A launcher can only fire with no
+            // reload remaining, so a higher count than last tick means it fired.
+            if i >= sim.ship(handle).data().guns.len()
+                && sim.ship(handle).get_reload_ticks(i) as f64 > state.get(*idx)
+            {
+                events |= MISSILE_LAUNCHED << i;
+            }
+        }
+        let previous_active = state.get(SystemState::ActiveAbilities) as u32;
+        let mut active = 0;
+        for ability in sim.ship(handle).data().abilities.iter() {
+            if ability.active_time_remaining > 0.0 {
+                active |= 1 << ability.ability as u32;
+            }
+        }
+        for ability in ABILITIES {
+            let bit = 1 << ability as u32;
+            if previous_active & bit != 0 && active & bit == 0 {
+                events |= ABILITY_ENDED << ability as u32;
+            }
+        }
+        state.set(SystemState::ActiveAbilities, active as f64);
+        state.set(SystemState::Events, events as f64);
+    }
+
     state.set(SystemState::CurrentTick, sim.tick() as f64);
 
     for (i, idx) in [
@@ -459,6 +672,66 @@ fn generate_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut L
     }
 }
 
+fn generate_loadout(sim: &Simulation, handle: ShipHandle) -> [f64; loadout_internal::SIZE] {
+    use loadout_internal::*;
+    let mut loadout = [0.0; SIZE];
+    let ship = sim.ship(handle);
+    let data = ship.data();
+
+    // Weapon indices match fire(): guns first, then missile launchers.
+    let num_guns = data.guns.len();
+    for (i, gun) in data.guns.iter().enumerate().take(MAX_WEAPONS) {
+        let mut set = |field, value| loadout[weapon_index(i, field)] = value;
+        set(KIND, KIND_GUN);
+        set(SPEED, gun.speed);
+        set(SPEED_ERROR, gun.speed_error);
+        set(OFFSET_X, gun.offset.x);
+        set(OFFSET_Y, gun.offset.y);
+        set(HEADING, gun.heading);
+        set(MIN_ANGLE, gun.min_angle);
+        set(MAX_ANGLE, gun.max_angle);
+        set(INACCURACY, gun.inaccuracy);
+        set(RELOAD_TICKS, gun.reload_ticks as f64);
+        set(MAGAZINE_SIZE, gun.magazine_size as f64);
+        set(MAGAZINE_REMAINING, gun.magazine_remaining as f64);
+        set(MAGAZINE_RELOAD_TICKS, gun.magazine_reload_ticks as f64);
+        set(BURST_SIZE, gun.burst_size as f64);
+        set(BULLET_TTL, gun.ttl as f64);
+        set(BULLET_MASS, gun.bullet_mass);
+    }
+    for (j, launcher) in data.missile_launchers.iter().enumerate() {
+        let i = num_guns + j;
+        if i >= MAX_WEAPONS {
+            break;
+        }
+        let mut set = |field, value| loadout[weapon_index(i, field)] = value;
+        set(KIND, KIND_LAUNCHER);
+        set(SPEED, launcher.initial_speed);
+        set(OFFSET_X, launcher.offset.x);
+        set(OFFSET_Y, launcher.offset.y);
+        set(HEADING, launcher.angle);
+        set(RELOAD_TICKS, launcher.reload_ticks as f64);
+        set(
+            LAUNCHER_CLASS,
+            translate_class(launcher.class) as u32 as f64,
+        );
+    }
+    let num_weapons = (num_guns + data.missile_launchers.len()).min(MAX_WEAPONS);
+    loadout[NUM_WEAPONS] = num_weapons as f64;
+
+    for (i, ability) in data.abilities.iter().take(MAX_ABILITIES).enumerate() {
+        let mut set = |field, value| loadout[ability_index(i, field)] = value;
+        set(ABILITY, ability.ability as u32 as f64);
+        set(ACTIVE_TIME, ability.active_time);
+        set(RELOAD_TIME, ability.reload_time);
+        set(ACTIVE_TIME_REMAINING, ability.active_time_remaining);
+        set(RELOAD_TIME_REMAINING, ability.reload_time_remaining);
+    }
+    loadout[NUM_ABILITIES] = data.abilities.len().min(MAX_ABILITIES) as f64;
+
+    loadout
+}
+
 fn apply_system_state(sim: &mut Simulation, handle: ShipHandle, state: &mut LocalSystemState) {
     sim.ship_mut(handle).accelerate(Vec2::new(
         state.get(SystemState::AccelerateX),
@@ -567,29 +840,87 @@ fn translate_ecm_mode(v: f64) -> EcmMode {
     }
 }
 
-fn translate_error<T, U>(err: Result<T, U>) -> Result<T, Error>
-where
-    U: std::fmt::Debug,
-{
-    match err {
-        Ok(val) => Ok(val),
-        Err(err) => Err(Error {
-            msg: format!("Wasmer error: {err:?}"),
-        }),
-    }
+fn validate_floats(vs: &[f64]) -> bool {
+    vs.iter().all(|v| v.is_finite())
 }
 
-fn translate_runtime_error<T>(err: Result<T, wasmer::RuntimeError>) -> Result<T, Error> {
-    match err {
-        Ok(val) => Ok(val),
-        Err(err) => Err(Error {
-            msg: format!("Ship runtime error: {err}"),
-        }),
-    }
+// Each entry is a level byte followed by the message and a NUL.
+fn parse_log(s: &str) -> Vec<(oort_api::LogLevel, String)> {
+    s.split_terminator('\0')
+        .filter_map(|record| {
+            let mut chars = record.chars();
+            let level = oort_api::LogLevel::from_u8(u8::try_from(chars.next()?).ok()?)?;
+            Some((
+                level,
+                chars.take(MAX_LOG_MESSAGE_LENGTH).collect::<String>(),
+            ))
+        })
+        .take(MAX_LOG_ENTRIES_PER_TICK)
+        .collect()
 }
 
-fn validate_floats(vs: &[f64]) -> bool {
-    vs.iter().all(|v| v.is_finite())
+// Returns the TTL and drawing, or None if the shape is invalid.
+fn translate_shape(shape: &Shape, layers: &[&str]) -> Option<(u32, debug::Drawing)> {
+    use debug::Shape as S;
+    if !validate_floats(&[
+        shape.x0,
+        shape.y0,
+        shape.x1,
+        shape.y1,
+        shape.angle0,
+        shape.angle1,
+    ]) {
+        return None;
+    }
+    let p0 = point![shape.x0, shape.y0];
+    let radius = shape.x1;
+    if shape.kind != Shape::LINE && !(0.0..=1e6).contains(&radius) {
+        return None;
+    }
+    let shape_ = match shape.kind {
+        Shape::LINE => S::Line {
+            a: p0,
+            b: point![shape.x1, shape.y1],
+        },
+        Shape::CIRCLE => S::Circle { center: p0, radius },
+        Shape::ARC => S::Arc {
+            center: p0,
+            radius,
+            start_angle: shape.angle0,
+            end_angle: shape.angle1,
+        },
+        Shape::POLYGON => S::Polygon {
+            center: p0,
+            radius,
+            sides: shape.y1.clamp(3.0, 64.0) as u32,
+            angle: shape.angle0,
+        },
+        Shape::TEXT => {
+            if shape.text_length as usize > shape.text.len() {
+                return None;
+            }
+            S::Text {
+                topleft: p0,
+                text: String::from_utf8_lossy(&shape.text[..shape.text_length as usize])
+                    .into_owned(),
+            }
+        }
+        _ => return None,
+    };
+    let layer = match shape.layer {
+        0 => String::new(),
+        i => layers.get(i as usize - 1)?.to_string(),
+    };
+    Some((
+        shape.ttl,
+        debug::Drawing {
+            shape: shape_,
+            color: color::from_u24(shape.color),
+            filled: shape.flags & Shape::FILLED != 0,
+            screen: shape.flags & Shape::SCREEN != 0,
+            layer,
+        },
+    ))
 }
 
 fn validate_lines(lines: &[Line]) -> bool {
@@ -604,14 +935,6 @@ fn validate_texts(texts: &[Text]) -> bool {
         .all(|t| validate_floats(&[t.x, t.y]) && t.length as usize <= t.text.len())
 }
 
-#[cfg(feature = "precompile")]
-pub fn precompile(code: &[u8]) -> Result<Code, Error> {
-    let code = limiter::rewrite(code)?;
-    let store = Store::default();
-    let module = translate_error(Module::new(&store, code))?;
-    Ok(Code::Precompiled(translate_error(module.serialize())?))
-}
-
 fn make_seed(sim_seed: u32, handle: ShipHandle) -> i64 {
     use std::collections::hash_map::DefaultHasher;
     use std::hash::Hasher;
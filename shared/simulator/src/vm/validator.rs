@@ -0,0 +1,424 @@
+// Checks that a Wasm module implements the ABI expected by TeamController.
+//
+// WasmVm::create only discovers problems one at a time with terse wasmer
+// errors. This reports everything at once so that AIs built with non-Rust
+// toolchains can be diagnosed.
+use super::Error;
+use oort_api::prelude::loadout_internal;
+use oort_api::{SystemState, MAX_ENVIRONMENT_SIZE};
+use serde::{Deserialize, Serialize};
+use walrus::{ExportItem, GlobalKind, InitExpr, ValType};
+
+const PAGE_SIZE: u64 = 65536;
+
+/// Names injected by the gas limiter. Modules must not export them.
+const RESERVED_EXPORTS: &[&str] = &["reset_gas", "get_gas"];
+
+const REQUIRED_FUNCTIONS: &[(&str, &[ValType])] = &[
+    ("export_initialize", &[]),
+    ("export_tick_ship", &[ValType::I32]),
+    ("export_delete_ship", &[ValType::I32]),
+];
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    InvalidModule {
+        msg: String,
+    },
+    MissingExport {
+        name: String,
+        kind: String,
+    },
+    WrongExportKind {
+        name: String,
+        expected: String,
+    },
+    WrongSignature {
+        name: String,
+        expected: String,
+        actual: String,
+    },
+    ReservedExport {
+        name: String,
+    },
+    Import {
+        module: String,
+        name: String,
+    },
+    UnsupportedGlobal {
+        name: String,
+    },
+    OutOfBounds {
+        name: String,
+        offset: u64,
+        size: u64,
+        memory_size: u64,
+    },
+}
+
+impl std::fmt::Display for Problem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Problem::InvalidModule { msg } => write!(f, "invalid Wasm module: {msg}"),
+            Problem::MissingExport { name, kind } => {
+                write!(f, "missing required {kind} export {name:?}")
+            }
+            Problem::WrongExportKind { name, expected } => {
+                write!(f, "export {name:?} must be a {expected}")
+            }
+            Problem::WrongSignature {
+                name,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "function {name:?} has signature {actual}, expected {expected}"
+            ),
+            Problem::ReservedExport { name } => {
+                write!(f, "export {name:?} is reserved by the simulator")
+            }
+            Problem::Import { module, name } => {
+                write!(f, "imports are not allowed, found \"{module}\".{name:?}")
+            }
+            Problem::UnsupportedGlobal { name } => write!(
+                f,
+                "global {name:?} must be an immutable i32 initialized with a constant"
+            ),
+            Problem::OutOfBounds {
+                name,
+                offset,
+                size,
+                memory_size,
+            } => write!(
+                f,
+                "{name} occupies bytes {offset}..{} but initial memory is only {memory_size} bytes",
+                offset + size
+            ),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub problems: Vec<Problem>,
+    pub memory_pages: Option<u32>,
+    pub system_state_offset: Option<u32>,
+    pub environment_offset: Option<u32>,
+    pub loadout_offset: Option<u32>,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+
+    pub fn into_result(self) -> Result<(), Error> {
+        if self.is_valid() {
+            return Ok(());
+        }
+        Err(Error {
+            msg: format!("Invalid AI module:\n{self}"),
+        })
+    }
+}
+
+impl std::fmt::Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.problems.is_empty() {
+            return write!(f, "ok");
+        }
+        for (i, problem) in self.problems.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "- {problem}")?;
+        }
+        Ok(())
+    }
+}
+
+pub fn validate(wasm: &[u8]) -> Report {
+    let mut report = Report::default();
+    let module = match walrus::Module::from_buffer(wasm) {
+        Ok(m) => m,
+        Err(e) => {
+            report.problems.push(Problem::InvalidModule {
+                msg: format!("{e:?}"),
+            });
+            return report;
+        }
+    };
+
+    for import in module.imports.iter() {
+        report.problems.push(Problem::Import {
+            module: import.module.clone(),
+            name: import.name.clone(),
+        });
+    }
+
+    for name in RESERVED_EXPORTS {
+        if module.exports.iter().any(|e| e.name == *name) {
+            report.problems.push(Problem::ReservedExport {
+                name: name.to_string(),
+            });
+        }
+    }
+
+    let find_export = |name: &str| module.exports.iter().find(|e| e.name == name);
+
+    let mut memory_size = None;
+    match find_export("memory").map(|e| e.item) {
+        Some(ExportItem::Memory(id)) => {
+            let pages = module.memories.get(id).initial;
+            report.memory_pages = Some(pages);
+            memory_size = Some(pages as u64 * PAGE_SIZE);
+        }
+        Some(_) => report.problems.push(Problem::WrongExportKind {
+            name: "memory".to_string(),
+            expected: "memory".to_string(),
+        }),
+        None => report.problems.push(Problem::MissingExport {
+            name: "memory".to_string(),
+            kind: "memory".to_string(),
+        }),
+    }
+
+    let mut check_buffer = |name: &str, size: u64| -> Option<u32> {
+        let offset = match find_export(name).map(|e| e.item) {
+            Some(ExportItem::Global(id)) => {
+                let global = module.globals.get(id);
+                match global.kind {
+                    GlobalKind::Local(InitExpr::Value(walrus::ir::Value::I32(v)))
+                        if !global.mutable && global.ty == ValType::I32 =>
+                    {
+                        v as u32
+                    }
+                    _ => {
+                        report.problems.push(Problem::UnsupportedGlobal {
+                            name: name.to_string(),
+                        });
+                        return None;
+                    }
+                }
+            }
+            Some(_) => {
+                report.problems.push(Problem::WrongExportKind {
+                    name: name.to_string(),
+                    expected: "global".to_string(),
+                });
+                return None;
+            }
+            None => {
+                report.problems.push(Problem::MissingExport {
+                    name: name.to_string(),
+                    kind: "global".to_string(),
+                });
+                return None;
+            }
+        };
+        if let Some(memory_size) = memory_size {
+            if offset as u64 + size > memory_size {
+                report.problems.push(Problem::OutOfBounds {
+                    name: name.to_string(),
+                    offset: offset as u64,
+                    size,
+                    memory_size,
+                });
+            }
+        }
+        Some(offset)
+    };
+
+    let system_state_size = SystemState::MaxSize as u64 * std::mem::size_of::<f64>() as u64;
+    let system_state_offset = check_buffer("SYSTEM_STATE", system_state_size);
+    let environment_offset = check_buffer("ENVIRONMENT", MAX_ENVIRONMENT_SIZE as u64);
+    // Optional, missing from AIs built against older versions of oort_api.
+    let loadout_offset = if find_export("LOADOUT").is_some() {
+        let loadout_size = (loadout_internal::SIZE * std::mem::size_of::<f64>()) as u64;
+        check_buffer("LOADOUT", loadout_size)
+    } else {
+        None
+    };
+    report.system_state_offset = system_state_offset;
+    report.environment_offset = environment_offset;
+    report.loadout_offset = loadout_offset;
+
+    for (name, params) in REQUIRED_FUNCTIONS {
+        match find_export(name).map(|e| e.item) {
+            Some(ExportItem::Function(id)) => {
+                let ty = module.types.get(module.funcs.get(id).ty());
+                if ty.params() != *params || !ty.results().is_empty() {
+                    report.problems.push(Problem::WrongSignature {
+                        name: name.to_string(),
+                        expected: format_signature(params, &[]),
+                        actual: format_signature(ty.params(), ty.results()),
+                    });
+                }
+            }
+            Some(_) => report.problems.push(Problem::WrongExportKind {
+                name: name.to_string(),
+                expected: "function".to_string(),
+            }),
+            None => report.problems.push(Problem::MissingExport {
+                name: name.to_string(),
+                kind: "function".to_string(),
+            }),
+        }
+    }
+
+    report
+}
+
+fn format_signature(params: &[ValType], results: &[ValType]) -> String {
+    let join = |xs: &[ValType]| {
+        xs.iter()
+            .map(|x| x.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    format!("({}) -> ({})", join(params), join(results))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wat2wasm(wat: &str) -> Vec<u8> {
+        wabt::Wat2Wasm::new()
+            .convert(wat)
+            .unwrap()
+            .as_ref()
+            .to_vec()
+    }
+
+    const VALID: &str = r#"
+(module
+    (memory (export "memory") 1)
+    (global (export "SYSTEM_STATE") i32 (i32.const 1024))
+    (global (export "ENVIRONMENT") i32 (i32.const 4096))
+    (func (export "export_initialize"))
+    (func (export "export_tick_ship") (param i32))
+    (func (export "export_delete_ship") (param i32))
+)
+"#;
+
+    #[test]
+    fn test_valid() {
+        let report = validate(&wat2wasm(VALID));
+        assert!(report.is_valid(), "{report}");
+        assert_eq!(report.memory_pages, Some(1));
+        assert_eq!(report.system_state_offset, Some(1024));
+        assert_eq!(report.environment_offset, Some(4096));
+        assert_eq!(report.loadout_offset, None);
+    }
+
+    #[test]
+    fn test_optional_loadout() {
+        let with_loadout = |offset: u32| {
+            VALID.replacen(
+                "(func",
+                &format!("(global (export \"LOADOUT\") i32 (i32.const {offset}))\n    (func"),
+                1,
+            )
+        };
+        let report = validate(&wat2wasm(&with_loadout(8192)));
+        assert!(report.is_valid(), "{report}");
+        assert_eq!(report.loadout_offset, Some(8192));
+
+        let report = validate(&wat2wasm(&with_loadout(65000)));
+        assert!(matches!(
+            report.problems.as_slice(),
+            [Problem::OutOfBounds { name, .. }] if name == "LOADOUT"
+        ));
+    }
+
+    #[test]
+    fn test_reports_all_missing_exports() {
+        let report = validate(&wat2wasm("(module)"));
+        let missing: Vec<_> = report
+            .problems
+            .iter()
+            .filter_map(|p| match p {
+                Problem::MissingExport { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            missing,
+            vec![
+                "memory",
+                "SYSTEM_STATE",
+                "ENVIRONMENT",
+                "export_initialize",
+                "export_tick_ship",
+                "export_delete_ship"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rejects_imports() {
+        let wat = VALID.replacen(
+            "(module",
+            r#"(module (import "env" "abort" (func (param i32)))"#,
+            1,
+        );
+        let report = validate(&wat2wasm(&wat));
+        assert_eq!(
+            report.problems,
+            vec![Problem::Import {
+                module: "env".to_string(),
+                name: "abort".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rejects_out_of_bounds() {
+        let wat = VALID.replace("(i32.const 4096)", "(i32.const 65000)");
+        let report = validate(&wat2wasm(&wat));
+        assert!(matches!(
+            report.problems.as_slice(),
+            [Problem::OutOfBounds { name, .. }] if name == "ENVIRONMENT"
+        ));
+    }
+
+    #[test]
+    fn test_rejects_wrong_signature() {
+        let wat = VALID.replace(
+            r#"(func (export "export_tick_ship") (param i32))"#,
+            r#"(func (export "export_tick_ship") (param i64) (result i32) i32.const 0)"#,
+        );
+        let report = validate(&wat2wasm(&wat));
+        assert_eq!(
+            report.problems,
+            vec![Problem::WrongSignature {
+                name: "export_tick_ship".to_string(),
+                expected: "(i32) -> ()".to_string(),
+                actual: "(i64) -> (i32)".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_rejects_reserved_export() {
+        let wat = VALID.replacen("(module", r#"(module (func (export "reset_gas"))"#, 1);
+        let report = validate(&wat2wasm(&wat));
+        assert_eq!(
+            report.problems,
+            vec![Problem::ReservedExport {
+                name: "reset_gas".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_invalid_module() {
+        let report = validate(b"not wasm");
+        assert!(matches!(
+            report.problems.as_slice(),
+            [Problem::InvalidModule { .. }]
+        ));
+        assert!(report.into_result().is_err());
+    }
+}
@@ -0,0 +1,195 @@
+use super::{limiter, Error, Limits};
+use crate::simulation::Code;
+use std::cell::RefCell;
+use std::ops::DerefMut;
+use std::rc::Rc;
+use wasmer::{imports, Instance, Module, Store};
+
+impl From<wasmer::InstantiationError> for Error {
+    fn from(err: wasmer::InstantiationError) -> Self {
+        Self {
+            msg: format!("Wasmer instantiation error: {err:?}"),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct WasmerVm {
+    store: Rc<RefCell<Store>>,
+    memory: wasmer::Memory,
+    system_state_offset: u32,
+    environment_offset: u32,
+    loadout_offset: Option<u32>,
+    tick_ship: wasmer::Function,
+    delete_ship: wasmer::Function,
+    reset_gas: wasmer::Function,
+    get_gas: Option<wasmer::Function>,
+}
+
+impl WasmerVm {
+    pub fn create(code: &Code, limits: &Limits) -> Result<WasmerVm, Error> {
+        let mut store = new_store(limits);
+        let module = match code {
+            Code::Wasm(wasm) => {
+                let wasm = limiter::rewrite(wasm, limits)?;
+                translate_error(Module::new(&store, wasm))?
+            }
+            #[cfg(feature = "precompile")]
+            Code::Precompiled(bytes) => {
+                translate_error(unsafe { Module::deserialize(&store, bytes.clone()) })?
+            }
+            _ => unreachable!(),
+        };
+        let import_object = imports! {};
+        let instance = Instance::new(&mut store, &module, &import_object)?;
+
+        let memory = translate_error(instance.exports.get_memory("memory"))?.clone();
+        let system_state_offset: i32 =
+            translate_error(instance.exports.get_global("SYSTEM_STATE"))?
+                .get(&mut store)
+                .i32()
+                .unwrap();
+        let environment_offset: i32 = translate_error(instance.exports.get_global("ENVIRONMENT"))?
+            .get(&mut store)
+            .i32()
+            .unwrap();
+        // Optional, missing from AIs built against older versions of oort_api.
+        let loadout_offset = instance
+            .exports
+            .get_global("LOADOUT")
+            .ok()
+            .and_then(|g| g.get(&mut store).i32())
+            .map(|v| v as u32);
+
+        let initialize =
+            translate_error(instance.exports.get_function("export_initialize"))?.clone();
+        let tick_ship = translate_error(instance.exports.get_function("export_tick_ship"))?.clone();
+        let delete_ship =
+            translate_error(instance.exports.get_function("export_delete_ship"))?.clone();
+        let reset_gas = translate_error(instance.exports.get_function("reset_gas"))?.clone();
+        // Optional, missing from artifacts precompiled before it was added.
+        let get_gas = instance.exports.get_function("get_gas").ok().cloned();
+
+        translate_runtime_error(reset_gas.call(&mut store, &[super::GAS_PER_TICK.into()]))?;
+        translate_runtime_error(initialize.call(&mut store, &[]))?;
+
+        Ok(WasmerVm {
+            store: Rc::new(RefCell::new(store)),
+            memory,
+            system_state_offset: system_state_offset as u32,
+            environment_offset: environment_offset as u32,
+            loadout_offset,
+            tick_ship,
+            delete_ship,
+            reset_gas,
+            get_gas,
+        })
+    }
+
+    pub fn system_state_offset(&self) -> u32 {
+        self.system_state_offset
+    }
+
+    pub fn environment_offset(&self) -> u32 {
+        self.environment_offset
+    }
+
+    pub fn loadout_offset(&self) -> Option<u32> {
+        self.loadout_offset
+    }
+
+    pub fn reset_gas(&self, gas: i32) -> Result<(), Error> {
+        translate_runtime_error(
+            self.reset_gas
+                .call(self.store.borrow_mut().deref_mut(), &[gas.into()]),
+        )?;
+        Ok(())
+    }
+
+    pub fn gas_remaining(&self) -> Result<Option<i32>, Error> {
+        let Some(get_gas) = self.get_gas.as_ref() else {
+            return Ok(None);
+        };
+        let result =
+            translate_runtime_error(get_gas.call(self.store.borrow_mut().deref_mut(), &[]))?;
+        Ok(result.first().and_then(|v| v.i32()))
+    }
+
+    pub fn tick_ship(&self, index: i32) -> Result<(), Error> {
+        translate_runtime_error(
+            self.tick_ship
+                .call(self.store.borrow_mut().deref_mut(), &[index.into()]),
+        )?;
+        Ok(())
+    }
+
+    pub fn delete_ship(&self, index: i32) -> Result<(), Error> {
+        translate_runtime_error(
+            self.delete_ship
+                .call(self.store.borrow_mut().deref_mut(), &[index.into()]),
+        )?;
+        Ok(())
+    }
+
+    pub fn read_memory(&self, offset: u32, buf: &mut [u8]) -> Option<()> {
+        let store = self.store.borrow();
+        self.memory.view(&*store).read(offset as u64, buf).ok()
+    }
+
+    pub fn write_memory(&self, offset: u32, data: &[u8]) -> Option<()> {
+        let store = self.store.borrow();
+        self.memory.view(&*store).write(offset as u64, data).ok()
+    }
+}
+
+fn translate_error<T, U>(err: Result<T, U>) -> Result<T, Error>
+where
+    U: std::fmt::Debug,
+{
+    match err {
+        Ok(val) => Ok(val),
+        Err(err) => Err(Error {
+            msg: format!("Wasmer error: {err:?}"),
+        }),
+    }
+}
+
+fn translate_runtime_error<T>(err: Result<T, wasmer::RuntimeError>) -> Result<T, Error> {
+    match err {
+        Ok(val) => Ok(val),
+        Err(err) => Err(Error {
+            msg: format!("Ship runtime error: {err}"),
+        }),
+    }
+}
+
+#[cfg(feature = "js")]
+pub(super) fn new_store(_limits: &Limits) -> Store {
+    // Limits are only enforced by the static checks and memory caps applied in
+    // limiter::rewrite.
+    Store::default()
+}
+
+#[cfg(feature = "sys")]
+pub(super) fn new_store(limits: &Limits) -> Store {
+    use wasmer::{BaseTunables, Engine, NativeEngineExt, Target};
+    let mut engine: Engine = wasmer_compiler_singlepass::Singlepass::new().into();
+    engine.set_tunables(super::limits::LimitingTunables::new(
+        BaseTunables::for_target(&Target::default()),
+        limits.clone(),
+    ));
+    Store::new(engine)
+}
+
+#[cfg(feature = "precompile")]
+pub fn precompile(code: &[u8]) -> Result<Code, Error> {
+    precompile_with_limits(code, &Limits::default())
+}
+
+#[cfg(feature = "precompile")]
+pub fn precompile_with_limits(code: &[u8], limits: &Limits) -> Result<Code, Error> {
+    let code = limiter::rewrite(code, limits)?;
+    let store = Store::default();
+    let module = translate_error(Module::new(&store, code))?;
+    Ok(Code::Precompiled(translate_error(module.serialize())?))
+}
@@ -1,9 +1,278 @@
-use nalgebra::vector;
-use oort_simulator::ship::{self, fighter, ShipHandle};
-use oort_simulator::simulation::{self, Code};
+use nalgebra::{point, vector};
+use oort_api::prelude::loadout_internal;
+use oort_api::prelude::{AbilityInfo, GunInfo, LauncherInfo, Weapon};
+use oort_api::{Ability, Class, Shape, SystemState};
+use oort_simulator::debug::{self, Drawing, LogEntry, LogLevel};
+use oort_simulator::ship::{self, cruiser, fighter, frigate, ShipData, ShipHandle};
+use oort_simulator::simulation::{self, Code, Simulation, MAX_PERSISTENT_DRAWINGS};
+use oort_simulator::vm::{MAX_LOG_BYTES, MAX_LOG_ENTRIES_PER_TICK, MAX_LOG_MESSAGE_LENGTH};
 use std::collections::BTreeMap;
 use test_log::test;
 
+// Memory layout of recorder_ai().
+const LOADOUT_OFFSET: u32 = 1024;
+const ENVIRONMENT_OFFSET: u32 = 2048;
+const COMMANDS_OFFSET: u32 = 4096;
+const BUFFER_OFFSET: u32 = 8192;
+const RECORD_OFFSET: u32 = 65536;
+const STATE_BYTES: u32 = SystemState::MaxSize as u32 * 8;
+// Each record is a copy of the system state and loadout.
+const RECORD_BYTES: u32 = ENVIRONMENT_OFFSET;
+
+/// An AI that records the system state and loadout each ship sees in a
+/// per-ship area of memory and applies one-shot writes queued with `command`,
+/// so tests can exercise the simulator's side of the ABI directly.
+fn recorder_ai() -> Code {
+    // Outputs are cleared each tick, as oort_api does.
+    let clear_outputs: String = [
+        SystemState::DebugTextLength,
+        SystemState::DebugLinesLength,
+        SystemState::DrawnTextLength,
+        SystemState::DebugShapesLength,
+        SystemState::DebugLayersLength,
+        SystemState::DebugLogLength,
+    ]
+    .iter()
+    .map(|&index| {
+        format!(
+            "(f64.store (i32.const {}) (f64.const 0))\n",
+            index as u32 * 8
+        )
+    })
+    .collect();
+    let wasm = wabt::wat2wasm(format!(
+        r#"
+(module
+    (memory (export "memory") 4)
+    (global (export "SYSTEM_STATE") i32 (i32.const 0))
+    (global (export "LOADOUT") i32 (i32.const {LOADOUT_OFFSET}))
+    (global (export "ENVIRONMENT") i32 (i32.const {ENVIRONMENT_OFFSET}))
+    (func (export "export_initialize"))
+    (func (export "export_tick_ship") (param $index i32)
+        (local $record i32)
+        (local $i i32)
+        (local.set $record (i32.add
+            (i32.const {RECORD_OFFSET})
+            (i32.mul (local.get $index) (i32.const {RECORD_BYTES}))))
+        (loop $copy
+            (f64.store
+                (i32.add (local.get $record) (local.get $i))
+                (f64.load (local.get $i)))
+            (local.set $i (i32.add (local.get $i) (i32.const 8)))
+            (br_if $copy (i32.lt_u (local.get $i) (i32.const {RECORD_BYTES}))))
+        {clear_outputs}
+        (local.set $i (i32.const 0))
+        (loop $apply
+            (if (f64.ne (f64.load offset={COMMANDS_OFFSET} (local.get $i)) (f64.const 0))
+                (then
+                    (f64.store (local.get $i) (f64.load offset={COMMANDS_OFFSET} (local.get $i)))
+                    (f64.store offset={COMMANDS_OFFSET} (local.get $i) (f64.const 0))))
+            (local.set $i (i32.add (local.get $i) (i32.const 8)))
+            (br_if $apply (i32.lt_u (local.get $i) (i32.const {STATE_BYTES})))))
+    (func (export "export_delete_ship") (param i32))
+)
+"#
+    ))
+    .unwrap();
+    Code::Wasm(wasm)
+}
+
+fn read_memory(sim: &mut Simulation, team: i32, offset: u32, length: usize) -> Vec<u8> {
+    let team_ctrl = sim
+        .get_team_controller(team)
+        .expect("Missing team controller");
+    let mut bytes = vec![0; length];
+    team_ctrl
+        .borrow()
+        .vm()
+        .read_memory(offset, &mut bytes)
+        .expect("Failed to read memory");
+    bytes
+}
+
+fn write_memory(sim: &mut Simulation, team: i32, offset: u32, data: &[u8]) {
+    let team_ctrl = sim
+        .get_team_controller(team)
+        .expect("Missing team controller");
+    team_ctrl
+        .borrow()
+        .vm()
+        .write_memory(offset, data)
+        .expect("Failed to write memory");
+}
+
+/// Reads `n` values from the ship's record of its last tick, starting at
+/// byte `offset` within the record.
+fn read_record(sim: &mut Simulation, ship: ShipHandle, offset: u32, n: usize) -> Vec<f64> {
+    let team = sim.ship(ship).data().team;
+    let (ship_index, _) = ship.0.into_raw_parts();
+    let offset = RECORD_OFFSET + ship_index * RECORD_BYTES + offset;
+    read_memory(sim, team, offset, n * 8)
+        .chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect()
+}
+
+/// Returns a system state value as the ship saw it on its last tick.
+fn seen(sim: &mut Simulation, ship: ShipHandle, index: SystemState) -> f64 {
+    read_record(sim, ship, index as u32 * 8, 1)[0]
+}
+
+/// Decodes the loadout the ship saw on its last tick with oort_api.
+fn seen_loadout(sim: &mut Simulation, ship: ShipHandle) -> (Vec<Weapon>, Vec<AbilityInfo>) {
+    let loadout = read_record(sim, ship, LOADOUT_OFFSET, loadout_internal::SIZE);
+    // Holds the lock on oort_api's globals.
+    let _mock = oort_api::mock::Mock::new(Class::Unknown);
+    for (i, value) in loadout.into_iter().enumerate() {
+        oort_api::sys::write_loadout(i, value);
+    }
+    (oort_api::prelude::weapons(), oort_api::prelude::abilities())
+}
+
+/// The weapons the ship should see, guns first.
+fn expected_weapons(data: &ShipData) -> Vec<Weapon> {
+    let guns = data.guns.iter().map(|gun| {
+        Weapon::Gun(GunInfo {
+            speed: gun.speed,
+            speed_error: gun.speed_error,
+            offset: oort_api::prelude::vec2(gun.offset.x, gun.offset.y),
+            heading: gun.heading,
+            min_angle: gun.min_angle,
+            max_angle: gun.max_angle,
+            inaccuracy: gun.inaccuracy,
+            reload_ticks: gun.reload_ticks,
+            magazine_size: gun.magazine_size as u32,
+            magazine_remaining: gun.magazine_remaining as u32,
+            magazine_reload_ticks: gun.magazine_reload_ticks,
+            burst_size: gun.burst_size as u32,
+            bullet_ttl: gun.ttl as f64,
+            bullet_mass: gun.bullet_mass,
+        })
+    });
+    let launchers = data.missile_launchers.iter().map(|launcher| {
+        Weapon::Launcher(LauncherInfo {
+            class: match launcher.class {
+                ship::ShipClass::Missile => Class::Missile,
+                ship::ShipClass::Torpedo => Class::Torpedo,
+                _ => Class::Unknown,
+            },
+            speed: launcher.initial_speed,
+            offset: oort_api::prelude::vec2(launcher.offset.x, launcher.offset.y),
+            heading: launcher.angle,
+            reload_ticks: launcher.reload_ticks,
+        })
+    });
+    guns.chain(launchers)
+        .take(loadout_internal::MAX_WEAPONS)
+        .collect()
+}
+
+/// Returns the event bits and damage taken the ship saw on its last tick.
+fn seen_events(sim: &mut Simulation, ship: ShipHandle) -> (u32, f64) {
+    (
+        seen(sim, ship, SystemState::Events) as u32,
+        seen(sim, ship, SystemState::DamageTaken),
+    )
+}
+
+/// Makes the next ship on `team` to tick write `value` to its system state.
+fn command(sim: &mut Simulation, team: i32, index: SystemState, value: f64) {
+    let offset = COMMANDS_OFFSET + index as u32 * 8;
+    write_memory(sim, team, offset, &value.to_le_bytes());
+}
+
+/// Passes shapes and a newline-separated list of layer names to the simulator
+/// as if the ship had drawn them on its next tick.
+fn draw(sim: &mut Simulation, team: i32, shapes: &[Shape], layers: &str) {
+    // Shape is repr(C) with no padding.
+    let shape_bytes = unsafe {
+        std::slice::from_raw_parts(shapes.as_ptr() as *const u8, std::mem::size_of_val(shapes))
+    };
+    let layers_offset = BUFFER_OFFSET + shape_bytes.len() as u32;
+    write_memory(sim, team, BUFFER_OFFSET, shape_bytes);
+    write_memory(sim, team, layers_offset, layers.as_bytes());
+    command(
+        sim,
+        team,
+        SystemState::DebugShapesPointer,
+        BUFFER_OFFSET as f64,
+    );
+    command(
+        sim,
+        team,
+        SystemState::DebugShapesLength,
+        shapes.len() as f64,
+    );
+    command(
+        sim,
+        team,
+        SystemState::DebugLayersPointer,
+        layers_offset as f64,
+    );
+    command(
+        sim,
+        team,
+        SystemState::DebugLayersLength,
+        layers.len() as f64,
+    );
+}
+
+/// Passes a log buffer to the simulator as if the ship had logged it on its
+/// next tick. Each record is a level byte followed by the message and a NUL.
+fn log(sim: &mut Simulation, team: i32, buffer: &[u8]) {
+    write_memory(sim, team, BUFFER_OFFSET, buffer);
+    command(
+        sim,
+        team,
+        SystemState::DebugLogPointer,
+        BUFFER_OFFSET as f64,
+    );
+    command(sim, team, SystemState::DebugLogLength, buffer.len() as f64);
+}
+
+/// Returns the drawings emitted for the ship on the last tick.
+fn drawings(sim: &Simulation, ship: ShipHandle) -> Vec<Drawing> {
+    sim.events()
+        .drawings
+        .get(&ship.into())
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn circle(radius: f64) -> Shape {
+    Shape {
+        x0: 1.0,
+        y0: 2.0,
+        x1: radius,
+        kind: Shape::CIRCLE,
+        ..Default::default()
+    }
+}
+
+/// The drawing expected for `circle(radius)`.
+fn circle_drawing(radius: f64) -> Drawing {
+    Drawing {
+        shape: debug::Shape::Circle {
+            center: point![1.0, 2.0],
+            radius,
+        },
+        color: oort_simulator::color::from_u24(0),
+        filled: false,
+        screen: false,
+        layer: String::new(),
+    }
+}
+
+fn text(text: &str, text_length: u8) -> Shape {
+    let mut shape = Shape {
+        kind: Shape::TEXT,
+        text_length,
+        ..Default::default()
+    };
+    shape.text[..text.len()].copy_from_slice(text.as_bytes());
+    shape
+}
+
 #[test]
 fn test_scenario_name() {
     let mut sim =
@@ -99,3 +368,560 @@ fn test_id() {
     check(ship_handles[1], 2);
     check(ship_handles[2], 1);
 }
+
+#[test]
+fn test_gas_used() {
+    let mut sim =
+        simulation::Simulation::new("test", 0, &[Code::Builtin("test".to_string()), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+    let gas = *sim
+        .events()
+        .gas_used
+        .get(&ship0.into())
+        .expect("Missing gas usage");
+    assert!(gas > 0, "gas: {gas}");
+    assert!(gas < oort_simulator::vm::GAS_PER_TICK as u32, "gas: {gas}");
+}
+
+#[test]
+fn test_gas_used_out_of_gas() {
+    let wasm = wabt::wat2wasm(
+        r#"
+(module
+    (memory (export "memory") 1)
+    (global (export "SYSTEM_STATE") i32 (i32.const 0))
+    (global (export "ENVIRONMENT") i32 (i32.const 32768))
+    (func (export "export_initialize"))
+    (func (export "export_tick_ship") (param i32)
+        (loop $loop
+        br $loop))
+    (func (export "export_delete_ship") (param i32))
+)
+"#,
+    )
+    .unwrap();
+    let mut sim = simulation::Simulation::new("test", 0, &[Code::Wasm(wasm), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+    let gas = *sim
+        .events()
+        .gas_used
+        .get(&ship0.into())
+        .expect("Missing gas usage");
+    assert_eq!(gas, oort_simulator::vm::GAS_PER_TICK as u32);
+}
+
+#[test]
+fn test_scenario_params() {
+    let codes = [Code::None, Code::None];
+    let overrides =
+        oort_simulator::scenario::ParamOverrides::from([("spawn_distance".to_string(), 4000.0)]);
+    let sim = simulation::Simulation::new_with_params(
+        "gunnery",
+        0,
+        &codes,
+        oort_simulator::vm::Backend::default(),
+        &overrides,
+    )
+    .unwrap();
+    assert_eq!(sim.params().get("spawn_distance"), 4000.0);
+
+    for (name, value) in [("spawn_distance", 1e6), ("unknown", 1.0)] {
+        let overrides = oort_simulator::scenario::ParamOverrides::from([(name.to_string(), value)]);
+        assert!(simulation::Simulation::new_with_params(
+            "gunnery",
+            0,
+            &codes,
+            oort_simulator::vm::Backend::default(),
+            &overrides,
+        )
+        .is_err());
+    }
+}
+
+#[test]
+fn test_event_damaged() {
+    use oort_api::events_internal::DAMAGED;
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (0, 0.0));
+
+    sim.ship_mut(ship0).data_mut().health -= 10.0;
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (DAMAGED, 10.0));
+
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (0, 0.0));
+}
+
+#[test]
+fn test_event_contact_lost() {
+    use oort_api::events_internal::CONTACT_LOST;
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    ship::create(
+        &mut sim,
+        vector![1000.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(1),
+    );
+    sim.step();
+    assert_eq!(seen(&mut sim, ship0, SystemState::RadarContactFound), 1.0);
+    assert_eq!(seen_events(&mut sim, ship0), (0, 0.0));
+
+    // Turn the radar away from the target.
+    command(&mut sim, 0, SystemState::RadarHeading, std::f64::consts::PI);
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (0, 0.0));
+
+    sim.step();
+    assert_eq!(seen(&mut sim, ship0, SystemState::RadarContactFound), 0.0);
+    assert_eq!(seen_events(&mut sim, ship0), (CONTACT_LOST, 0.0));
+
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (0, 0.0));
+}
+
+#[test]
+fn test_event_radio_received() {
+    use oort_api::events_internal::RADIO_RECEIVED;
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    let ship1 = ship::create(
+        &mut sim,
+        vector![0.0, 1000.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(1),
+    );
+    let msg = [1.0, 2.0, 3.0, 4.0];
+    sim.ship_mut(ship1).data_mut().radios[0].set_sent(Some(msg));
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (RADIO_RECEIVED, 0.0));
+    let idxs = oort_api::prelude::radio_internal::radio_indices(0);
+    for (i, &index) in idxs.data.iter().enumerate() {
+        assert_eq!(seen(&mut sim, ship0, index), msg[i]);
+    }
+
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (0, 0.0));
+}
+
+#[test]
+fn test_event_missile_launched() {
+    use oort_api::events_internal::MISSILE_LAUNCHED;
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+
+    // Fire the gun (index 0) and the missile launcher (index 1).
+    command(&mut sim, 0, SystemState::Fire0, 1.0);
+    command(&mut sim, 0, SystemState::Fire1, 1.0);
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (0, 0.0));
+
+    // Only the launcher generates an event.
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (MISSILE_LAUNCHED << 1, 0.0));
+
+    sim.step();
+    assert_eq!(seen_events(&mut sim, ship0), (0, 0.0));
+}
+
+#[test]
+fn test_event_ability_ended() {
+    use oort_api::events_internal::ABILITY_ENDED;
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    command(
+        &mut sim,
+        0,
+        SystemState::ActivateAbility,
+        Ability::Boost as u32 as f64,
+    );
+    sim.step();
+    assert!(sim.ship(ship0).is_ability_active(Ability::Boost));
+
+    while sim.ship(ship0).is_ability_active(Ability::Boost) {
+        sim.step();
+        assert_eq!(seen_events(&mut sim, ship0), (0, 0.0));
+    }
+
+    sim.step();
+    assert_eq!(
+        seen_events(&mut sim, ship0),
+        (ABILITY_ENDED << Ability::Boost as u32, 0.0)
+    );
+}
+
+#[test]
+fn test_loadout() {
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    // A frigate with a fifth weapon, more than fit in the loadout.
+    let mut data = frigate(0);
+    let torpedo_launcher = cruiser(0).missile_launchers[2].clone();
+    data.missile_launchers.push(torpedo_launcher);
+    let ship1 = ship::create(&mut sim, vector![0.0, 1000.0], vector![0.0, 0.0], 0.0, data);
+    // The loadout is written before the ship's tick, so compare against the
+    // data from before the step.
+    let expected0 = expected_weapons(sim.ship(ship0).data());
+    let expected1 = expected_weapons(sim.ship(ship1).data());
+    sim.step();
+
+    // The buffer is shared by the team, so it's rewritten for each ship.
+    let (weapons, abilities) = seen_loadout(&mut sim, ship0);
+    assert_eq!(weapons, expected0);
+    assert!(matches!(
+        weapons[..],
+        [
+            Weapon::Gun(_),
+            Weapon::Launcher(LauncherInfo {
+                class: Class::Missile,
+                ..
+            })
+        ]
+    ));
+    assert_eq!(
+        abilities,
+        vec![AbilityInfo {
+            ability: Ability::Boost,
+            active_time: 2.0,
+            reload_time: 10.0,
+            active_time_remaining: 0.0,
+            reload_time_remaining: 0.0,
+        }]
+    );
+
+    // Guns come first and the torpedo launcher doesn't fit.
+    let (weapons, abilities) = seen_loadout(&mut sim, ship1);
+    assert_eq!(weapons, expected1);
+    assert!(matches!(
+        weapons[..],
+        [
+            Weapon::Gun(_),
+            Weapon::Gun(_),
+            Weapon::Gun(_),
+            Weapon::Launcher(LauncherInfo {
+                class: Class::Missile,
+                ..
+            })
+        ]
+    ));
+    assert_eq!(abilities, vec![]);
+
+    // Firing the fighter's gun uses up a round of its magazine, which the
+    // next tick's loadout reflects.
+    command(&mut sim, 0, SystemState::Fire0, 1.0);
+    sim.step();
+    sim.step();
+    let (weapons, _) = seen_loadout(&mut sim, ship0);
+    assert_eq!(weapons, expected_weapons(sim.ship(ship0).data()));
+    match weapons[0] {
+        Weapon::Gun(gun) => assert_eq!(gun.magazine_remaining, gun.magazine_size - 1),
+        _ => panic!("Expected a gun"),
+    }
+    let (weapons, _) = seen_loadout(&mut sim, ship1);
+    assert_eq!(weapons, expected_weapons(sim.ship(ship1).data()));
+}
+
+#[test]
+fn test_draw_validation() {
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+
+    draw(&mut sim, 0, &[circle(10.0)], "");
+    sim.step();
+    assert_eq!(drawings(&sim, ship0), vec![circle_drawing(10.0)]);
+
+    // An invalid shape rejects the whole batch.
+    for bad in [
+        Shape {
+            y0: f64::NAN,
+            ..circle(10.0)
+        },
+        circle(f64::NAN),
+        circle(-1.0),
+        circle(1e6 + 1.0),
+        text("", 29),
+    ] {
+        draw(&mut sim, 0, &[circle(10.0), bad.clone()], "");
+        sim.step();
+        assert_eq!(drawings(&sim, ship0), vec![], "{bad:?}");
+    }
+
+    // Lines don't have a radius.
+    let line = Shape {
+        x1: 2e6,
+        kind: Shape::LINE,
+        ..Default::default()
+    };
+    draw(&mut sim, 0, &[circle(1e6), line], "");
+    sim.step();
+    assert_eq!(drawings(&sim, ship0).len(), 2);
+
+    draw(&mut sim, 0, &[text("0123456789012345678901234567", 28)], "");
+    sim.step();
+    assert_eq!(
+        drawings(&sim, ship0)[0].shape,
+        debug::Shape::Text {
+            topleft: point![0.0, 0.0],
+            text: "0123456789012345678901234567".to_string()
+        }
+    );
+}
+
+#[test]
+fn test_draw_layers() {
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+
+    let in_layer = |layer| Shape {
+        layer,
+        ..circle(10.0)
+    };
+    draw(
+        &mut sim,
+        0,
+        &[in_layer(0), in_layer(1), in_layer(2)],
+        "foo\nbar",
+    );
+    sim.step();
+    let layers: Vec<String> = drawings(&sim, ship0).into_iter().map(|x| x.layer).collect();
+    assert_eq!(layers, vec!["", "foo", "bar"]);
+
+    // A layer missing from the list rejects the whole batch.
+    draw(&mut sim, 0, &[in_layer(1), in_layer(3)], "foo\nbar");
+    sim.step();
+    assert_eq!(drawings(&sim, ship0), vec![]);
+
+    draw(&mut sim, 0, &[in_layer(1)], "");
+    sim.step();
+    assert_eq!(drawings(&sim, ship0), vec![]);
+}
+
+#[test]
+fn test_draw_ttl() {
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+
+    let with_ttl = |ttl| Shape {
+        ttl,
+        ..circle(ttl as f64)
+    };
+    draw(&mut sim, 0, &[with_ttl(0), with_ttl(1), with_ttl(3)], "");
+    sim.step();
+    assert_eq!(drawings(&sim, ship0).len(), 3);
+    sim.step();
+    assert_eq!(drawings(&sim, ship0), vec![circle_drawing(3.0)]);
+    sim.step();
+    assert_eq!(drawings(&sim, ship0), vec![circle_drawing(3.0)]);
+    sim.step();
+    assert_eq!(drawings(&sim, ship0), vec![]);
+}
+
+#[test]
+fn test_draw_persistent_limit() {
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+
+    // Once the limit is reached, new drawings only last a single tick.
+    let shapes = vec![
+        Shape {
+            ttl: 100,
+            ..circle(10.0)
+        };
+        128
+    ];
+    let batches = MAX_PERSISTENT_DRAWINGS / shapes.len();
+    for i in 0..batches {
+        draw(&mut sim, 0, &shapes, "");
+        sim.step();
+        assert_eq!(drawings(&sim, ship0).len(), (i + 1) * shapes.len());
+    }
+    draw(&mut sim, 0, &shapes, "");
+    sim.step();
+    assert_eq!(
+        drawings(&sim, ship0).len(),
+        MAX_PERSISTENT_DRAWINGS + shapes.len()
+    );
+    sim.step();
+    assert_eq!(drawings(&sim, ship0).len(), MAX_PERSISTENT_DRAWINGS);
+}
+
+#[test]
+fn test_log() {
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), recorder_ai()]);
+    let ship0 = ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    let ship1 = ship::create(
+        &mut sim,
+        vector![0.0, 1000.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(1),
+    );
+    sim.step();
+
+    let long_message = "x".repeat(MAX_LOG_MESSAGE_LENGTH + 10);
+    let mut buffer = vec![];
+    for (level, message) in [
+        (LogLevel::Info as u8, "hello"),
+        // Invalid levels.
+        (6, "six"),
+        (b'n', "o level"),
+        (LogLevel::Error as u8, long_message.as_str()),
+        (LogLevel::Trace as u8, ""),
+    ] {
+        buffer.push(level);
+        buffer.extend(message.as_bytes());
+        buffer.push(0);
+    }
+    // An empty record has no level.
+    buffer.push(0);
+    log(&mut sim, 0, &buffer);
+    log(&mut sim, 1, b"\x04warning\0");
+    let tick = sim.tick();
+    sim.step();
+
+    let entry = |ship: ShipHandle, team, level, message: &str| LogEntry {
+        tick,
+        ship: ship.into(),
+        team,
+        level,
+        message: message.to_string(),
+    };
+    assert_eq!(
+        sim.snapshot(0).log,
+        vec![
+            entry(ship0, 0, LogLevel::Info, "hello"),
+            entry(
+                ship0,
+                0,
+                LogLevel::Error,
+                &long_message[..MAX_LOG_MESSAGE_LENGTH]
+            ),
+            entry(ship0, 0, LogLevel::Trace, ""),
+            entry(ship1, 1, LogLevel::Warn, "warning"),
+        ]
+    );
+}
+
+#[test]
+fn test_log_limits() {
+    let mut sim = Simulation::new("test", 0, &[recorder_ai(), Code::None]);
+    ship::create(
+        &mut sim,
+        vector![0.0, 0.0],
+        vector![0.0, 0.0],
+        0.0,
+        fighter(0),
+    );
+    sim.step();
+
+    let buffer: Vec<u8> = (0..MAX_LOG_ENTRIES_PER_TICK + 10)
+        .flat_map(|i| format!("\x03{i}\0").into_bytes())
+        .collect();
+    log(&mut sim, 0, &buffer);
+    sim.step();
+    let messages: Vec<String> = sim.events().log.iter().map(|x| x.message.clone()).collect();
+    let expected: Vec<String> = (0..MAX_LOG_ENTRIES_PER_TICK)
+        .map(|i| i.to_string())
+        .collect();
+    assert_eq!(messages, expected);
+
+    // Oversized buffers and invalid UTF-8 are dropped.
+    let mut buffer = b"\x03hello\0".to_vec();
+    buffer.resize(MAX_LOG_BYTES as usize + 1, 0);
+    log(&mut sim, 0, &buffer);
+    sim.step();
+    assert_eq!(sim.events().log, vec![]);
+
+    log(&mut sim, 0, b"\x03hello\0\x03\xff\0");
+    sim.step();
+    assert_eq!(sim.events().log, vec![]);
+}
@@ -1,12 +1,24 @@
 use oort_simulator::scenario;
 use oort_simulator::simulation;
+use oort_simulator::vm::Backend;
 use test_log::test;
 
 fn run(scenario_name: &str) -> u64 {
+    let hash = run_with_backend(scenario_name, Backend::default());
+    #[cfg(all(feature = "interpreter", any(feature = "sys", feature = "js")))]
+    assert_eq!(
+        hash,
+        run_with_backend(scenario_name, Backend::Interpreter),
+        "{scenario_name} differs between the wasmer and interpreter backends"
+    );
+    hash
+}
+
+fn run_with_backend(scenario_name: &str, backend: Backend) -> u64 {
     let scenario = scenario::load(scenario_name);
     let codes = scenario.solution_codes();
     let seed = 0;
-    let mut sim = simulation::Simulation::new(scenario_name, seed, &codes);
+    let mut sim = simulation::Simulation::new_with_backend(scenario_name, seed, &codes, backend);
 
     while sim.status() == scenario::Status::Running {
         sim.step();
@@ -19,3 +31,21 @@ fn run(scenario_name: &str) -> u64 {
 fn test_frigate_vs_cruiser() {
     assert_eq!(run("frigate_vs_cruiser"), 4173106142651117365);
 }
+
+#[test]
+fn test_hash_trace() {
+    let scenario_name = "frigate_vs_cruiser";
+    let trace = || {
+        let codes = scenario::load(scenario_name).solution_codes();
+        let mut sim = simulation::Simulation::new(scenario_name, 0, &codes);
+        sim.enable_hash_trace();
+        for _ in 0..100 {
+            sim.step();
+        }
+        sim.hash_trace().unwrap().to_vec()
+    };
+    let a = trace();
+    assert_eq!(a.len(), 101);
+    assert_eq!(a[100].tick, 100);
+    assert_eq!(a, trace());
+}
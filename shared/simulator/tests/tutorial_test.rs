@@ -1,5 +1,6 @@
 use oort_simulator::scenario;
 use oort_simulator::simulation;
+use oort_simulator::vm::Backend;
 use rayon::prelude::*;
 use std::time::Instant;
 use test_log::test;
@@ -7,11 +8,12 @@ use test_log::test;
 fn check_solution(scenario_name: &str) {
     (0..10u32).into_par_iter().for_each(|seed| {
         let start_time = Instant::now();
-        let check_once = |seed: u32| -> u64 {
+        let check_once = |seed: u32, backend: Backend| -> u64 {
             let scenario = scenario::load(scenario_name);
             let mut codes = scenario.initial_code();
             codes[0] = scenario.solution();
-            let mut sim = simulation::Simulation::new(scenario_name, seed, &codes);
+            let mut sim =
+                simulation::Simulation::new_with_backend(scenario_name, seed, &codes, backend);
 
             let mut i = 0;
             while sim.status() == scenario::Status::Running && i < 10000 {
@@ -26,9 +28,15 @@ fn check_solution(scenario_name: &str) {
             );
             sim.hash()
         };
-        let hashes: Vec<u64> = (0..2usize)
-            .into_par_iter()
-            .map(|_| check_once(seed))
+        // With the interpreter enabled the second run also cross-checks it
+        // against wasmer.
+        #[cfg(feature = "interpreter")]
+        let backends = [Backend::default(), Backend::Interpreter];
+        #[cfg(not(feature = "interpreter"))]
+        let backends = [Backend::default(), Backend::default()];
+        let hashes: Vec<u64> = backends
+            .par_iter()
+            .map(|&backend| check_once(seed, backend))
             .collect();
         assert_eq!(
             hashes[0], hashes[1],
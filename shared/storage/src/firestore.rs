@@ -0,0 +1,395 @@
+use crate::*;
+use ::firestore::*;
+use async_trait::async_trait;
+use gcloud_sdk::google::firestore::v1::Document;
+use oort_proto::Telemetry;
+use serde::de::DeserializeOwned;
+
+pub struct FirestoreStorage {
+    db: FirestoreDb,
+}
+
+impl FirestoreStorage {
+    pub async fn new(project_id: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            db: FirestoreDb::new(project_id).await?,
+        })
+    }
+
+    async fn get<T: DeserializeOwned + Send>(
+        &self,
+        collection: &str,
+        docid: &str,
+    ) -> anyhow::Result<Option<T>> {
+        // Firestore reports a missing document as an error.
+        match self.db.get_obj::<T>(collection, docid).await {
+            Ok(obj) => Ok(Some(obj)),
+            Err(errors::FirestoreError::DataNotFoundError(_)) => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+fn equal(field: &str, value: FirestoreValue) -> FirestoreQueryFilter {
+    FirestoreQueryFilter::Compare(Some(FirestoreQueryFilterCompare::Equal(
+        field.into(),
+        value,
+    )))
+}
+
+fn filter(filters: Vec<FirestoreQueryFilter>) -> FirestoreQueryFilter {
+    FirestoreQueryFilter::Composite(FirestoreQueryFilterComposite::new(filters))
+}
+
+fn ascending(fields: &[&str]) -> Vec<FirestoreQueryOrder> {
+    fields
+        .iter()
+        .map(|field| {
+            FirestoreQueryOrder::new(field.to_string(), FirestoreQueryDirection::Ascending)
+        })
+        .collect()
+}
+
+fn extract_docid(docname: &str) -> String {
+    match docname.rsplit_once('/') {
+        Some((_, docid)) => docid.to_string(),
+        None => docname.to_string(),
+    }
+}
+
+fn deserialize_docs<T: DeserializeOwned>(docs: &[Document]) -> Vec<(String, T)> {
+    let mut result = vec![];
+    for doc in docs {
+        match FirestoreDb::deserialize_doc_to::<T>(doc) {
+            Ok(obj) => result.push((extract_docid(&doc.name), obj)),
+            Err(_) => log::error!("Failed to deserialize doc {}", doc.name),
+        }
+    }
+    result
+}
+
+#[async_trait]
+impl Storage for FirestoreStorage {
+    async fn list_leaderboard(
+        &self,
+        scenario_name: &str,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, LeaderboardSubmission)>> {
+        let mut params = FirestoreQueryParams::new(LEADERBOARD.into())
+            .with_filter(filter(vec![equal("scenario_name", scenario_name.into())]))
+            .with_order_by(ascending(&["time", "timestamp"]));
+        if let Some(limit) = limit {
+            params = params.with_limit(limit as u32);
+        }
+        let docs: Vec<Document> = self.db.query_doc(params).await?;
+        Ok(deserialize_docs(&docs))
+    }
+
+    async fn find_leaderboard_by_username(
+        &self,
+        scenario_name: &str,
+        username: &str,
+    ) -> anyhow::Result<Option<LeaderboardSubmission>> {
+        let docs: Vec<Document> = self
+            .db
+            .query_doc(
+                FirestoreQueryParams::new(LEADERBOARD.into())
+                    .with_filter(filter(vec![
+                        equal("scenario_name", scenario_name.into()),
+                        equal("username", username.into()),
+                    ]))
+                    .with_order_by(ascending(&["time", "timestamp"]))
+                    .with_limit(1),
+            )
+            .await?;
+        Ok(deserialize_docs(&docs).into_iter().next().map(|(_, x)| x))
+    }
+
+    async fn get_leaderboard(&self, docid: &str) -> anyhow::Result<Option<LeaderboardSubmission>> {
+        self.get(LEADERBOARD, docid).await
+    }
+
+    async fn put_leaderboard(
+        &self,
+        docid: &str,
+        obj: &LeaderboardSubmission,
+    ) -> anyhow::Result<()> {
+        self.db.update_obj(LEADERBOARD, docid, obj, None).await?;
+        Ok(())
+    }
+
+    async fn delete_leaderboard(&self, docid: &str) -> anyhow::Result<()> {
+        self.db.delete_by_id(LEADERBOARD, docid).await?;
+        Ok(())
+    }
+
+    async fn list_code_size_leaderboard(
+        &self,
+        scenario_name: &str,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, LeaderboardSubmission)>> {
+        let mut params = FirestoreQueryParams::new(CODE_SIZE_LEADERBOARD.into())
+            .with_filter(filter(vec![equal("scenario_name", scenario_name.into())]))
+            .with_order_by(ascending(&["code_size", "timestamp"]));
+        if let Some(limit) = limit {
+            params = params.with_limit(limit as u32);
+        }
+        let docs: Vec<Document> = self.db.query_doc(params).await?;
+        Ok(deserialize_docs(&docs))
+    }
+
+    async fn get_code_size_leaderboard(
+        &self,
+        docid: &str,
+    ) -> anyhow::Result<Option<LeaderboardSubmission>> {
+        self.get(CODE_SIZE_LEADERBOARD, docid).await
+    }
+
+    async fn put_code_size_leaderboard(
+        &self,
+        docid: &str,
+        obj: &LeaderboardSubmission,
+    ) -> anyhow::Result<()> {
+        self.db
+            .update_obj(CODE_SIZE_LEADERBOARD, docid, obj, None)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_code_size_leaderboard(&self, docid: &str) -> anyhow::Result<()> {
+        self.db.delete_by_id(CODE_SIZE_LEADERBOARD, docid).await?;
+        Ok(())
+    }
+
+    async fn get_leaderboard_verification(
+        &self,
+        docid: &str,
+    ) -> anyhow::Result<Option<LeaderboardVerificationJob>> {
+        self.get(LEADERBOARD_VERIFICATION, docid).await
+    }
+
+    async fn put_leaderboard_verification(
+        &self,
+        docid: &str,
+        obj: &LeaderboardVerificationJob,
+    ) -> anyhow::Result<()> {
+        self.db
+            .update_obj(LEADERBOARD_VERIFICATION, docid, obj, None)
+            .await?;
+        Ok(())
+    }
+
+    async fn list_pending_leaderboard_verifications(
+        &self,
+    ) -> anyhow::Result<Vec<(String, LeaderboardVerificationJob)>> {
+        let docs: Vec<Document> = self
+            .db
+            .query_doc(
+                FirestoreQueryParams::new(LEADERBOARD_VERIFICATION.into())
+                    .with_filter(filter(vec![equal("status.status", "Pending".into())])),
+            )
+            .await?;
+        // Sorted here to avoid needing a composite index.
+        let mut docs: Vec<(String, LeaderboardVerificationJob)> = deserialize_docs(&docs);
+        docs.sort_by_key(|(_, x)| x.submission.timestamp);
+        Ok(docs)
+    }
+
+    async fn get_shortcode(&self, docid: &str) -> anyhow::Result<Option<ShortcodeUpload>> {
+        self.get(SHORTCODE, docid).await
+    }
+
+    async fn create_shortcode(&self, docid: &str, obj: &ShortcodeUpload) -> anyhow::Result<()> {
+        self.db.create_obj(SHORTCODE, docid, obj).await?;
+        Ok(())
+    }
+
+    async fn get_telemetry(&self, docid: &str) -> anyhow::Result<Option<TelemetryMsg>> {
+        self.get(TELEMETRY, docid).await
+    }
+
+    async fn create_telemetry(&self, docid: &str, obj: &TelemetryMsg) -> anyhow::Result<()> {
+        self.db.create_obj(TELEMETRY, docid, obj).await?;
+        Ok(())
+    }
+
+    async fn list_telemetry(&self, limit: usize) -> anyhow::Result<Vec<(String, TelemetryMsg)>> {
+        let docs: Vec<Document> = self
+            .db
+            .query_doc(
+                FirestoreQueryParams::new(TELEMETRY.into())
+                    .with_order_by(vec![FirestoreQueryOrder::new(
+                        "timestamp".to_owned(),
+                        FirestoreQueryDirection::Descending,
+                    )])
+                    .with_limit(limit as u32),
+            )
+            .await?;
+        Ok(deserialize_docs(&docs))
+    }
+
+    async fn list_finished_telemetry(
+        &self,
+        scenario_name: &str,
+    ) -> anyhow::Result<Vec<(String, TelemetryMsg)>> {
+        let docs: Vec<Document> = self
+            .db
+            .query_doc(
+                FirestoreQueryParams::new(TELEMETRY.into()).with_filter(filter(vec![
+                    equal("type", "FinishScenario".into()),
+                    equal("success", true.into()),
+                    equal("scenario_name", scenario_name.into()),
+                ])),
+            )
+            .await?;
+        Ok(deserialize_docs::<TelemetryMsg>(&docs)
+            .into_iter()
+            .filter(|(_, msg)| matches!(msg.payload, Telemetry::FinishScenario { .. }))
+            .collect())
+    }
+
+    async fn put_tournament_submission(
+        &self,
+        docid: &str,
+        obj: &TournamentSubmission,
+    ) -> anyhow::Result<()> {
+        self.db.update_obj(TOURNAMENT, docid, obj, None).await?;
+        Ok(())
+    }
+
+    async fn find_tournament_by_username(
+        &self,
+        scenario_name: &str,
+        username: &str,
+    ) -> anyhow::Result<Option<TournamentSubmission>> {
+        let docs: Vec<Document> = self
+            .db
+            .query_doc(
+                FirestoreQueryParams::new(TOURNAMENT.into())
+                    .with_filter(filter(vec![
+                        equal("scenario_name", scenario_name.into()),
+                        equal("username", username.into()),
+                    ]))
+                    .with_order_by(ascending(&["timestamp"]))
+                    .with_limit(1),
+            )
+            .await?;
+        Ok(deserialize_docs(&docs).into_iter().next().map(|(_, x)| x))
+    }
+
+    async fn list_tournament_submissions(
+        &self,
+        scenario_name: &str,
+    ) -> anyhow::Result<Vec<TournamentSubmission>> {
+        let docs: Vec<Document> = self
+            .db
+            .query_doc(
+                FirestoreQueryParams::new(TOURNAMENT.into())
+                    .with_filter(filter(vec![equal("scenario_name", scenario_name.into())]))
+                    .with_order_by(ascending(&["username", "timestamp"])),
+            )
+            .await?;
+        Ok(deserialize_docs(&docs)
+            .into_iter()
+            .map(|(_, x)| x)
+            .collect())
+    }
+
+    async fn get_tournament_results(&self, id: &str) -> anyhow::Result<Option<TournamentResults>> {
+        self.get(TOURNAMENT_RESULTS, id).await
+    }
+
+    async fn create_tournament_results(
+        &self,
+        id: &str,
+        obj: &TournamentResults,
+    ) -> anyhow::Result<()> {
+        self.db.create_obj(TOURNAMENT_RESULTS, id, obj).await?;
+        Ok(())
+    }
+
+    async fn list_ladder(&self, scenario_name: &str) -> anyhow::Result<Vec<LadderRating>> {
+        let docs: Vec<Document> = self
+            .db
+            .query_doc(
+                FirestoreQueryParams::new(LADDER.into())
+                    .with_filter(filter(vec![equal("scenario_name", scenario_name.into())]))
+                    .with_order_by(vec![
+                        FirestoreQueryOrder::new(
+                            "rating".to_owned(),
+                            FirestoreQueryDirection::Descending,
+                        ),
+                        FirestoreQueryOrder::new(
+                            "timestamp".to_owned(),
+                            FirestoreQueryDirection::Ascending,
+                        ),
+                    ]),
+            )
+            .await?;
+        Ok(deserialize_docs(&docs)
+            .into_iter()
+            .map(|(_, x)| x)
+            .collect())
+    }
+
+    async fn get_ladder(&self, docid: &str) -> anyhow::Result<Option<LadderRating>> {
+        self.get(LADDER, docid).await
+    }
+
+    async fn put_ladder(&self, docid: &str, obj: &LadderRating) -> anyhow::Result<()> {
+        self.db.update_obj(LADDER, docid, obj, None).await?;
+        Ok(())
+    }
+
+    async fn get_ladder_job(&self, docid: &str) -> anyhow::Result<Option<LadderJob>> {
+        self.get(LADDER_JOBS, docid).await
+    }
+
+    async fn put_ladder_job(&self, docid: &str, obj: &LadderJob) -> anyhow::Result<()> {
+        self.db.update_obj(LADDER_JOBS, docid, obj, None).await?;
+        Ok(())
+    }
+
+    async fn delete_ladder_job(&self, docid: &str) -> anyhow::Result<()> {
+        self.db.delete_by_id(LADDER_JOBS, docid).await?;
+        Ok(())
+    }
+
+    async fn list_ladder_jobs(&self) -> anyhow::Result<Vec<(String, LadderJob)>> {
+        let docs: Vec<Document> = self
+            .db
+            .query_doc(FirestoreQueryParams::new(LADDER_JOBS.into()))
+            .await?;
+        let mut docs: Vec<(String, LadderJob)> = deserialize_docs(&docs);
+        docs.sort_by_key(|(_, x)| x.submission.timestamp);
+        Ok(docs)
+    }
+
+    async fn create_ladder_match(&self, id: &str, obj: &LadderMatch) -> anyhow::Result<()> {
+        self.db.create_obj(LADDER_MATCHES, id, obj).await?;
+        Ok(())
+    }
+
+    async fn list_ladder_matches(
+        &self,
+        scenario_name: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<LadderMatch>> {
+        let docs: Vec<Document> = self
+            .db
+            .query_doc(
+                FirestoreQueryParams::new(LADDER_MATCHES.into())
+                    .with_filter(filter(vec![equal("scenario_name", scenario_name.into())]))
+                    .with_order_by(vec![FirestoreQueryOrder::new(
+                        "timestamp".to_owned(),
+                        FirestoreQueryDirection::Descending,
+                    )])
+                    .with_limit(limit as u32),
+            )
+            .await?;
+        Ok(deserialize_docs(&docs)
+            .into_iter()
+            .map(|(_, x)| x)
+            .collect())
+    }
+}
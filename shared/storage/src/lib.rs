@@ -0,0 +1,189 @@
+//! Persistent storage for leaderboards, shortcodes, telemetry, tournaments and
+//! the ranked ladder.
+//!
+//! Production uses Firestore. [`LocalStorage`] keeps the same collections in
+//! an SQLite database so the backend and tools can run offline and in tests.
+mod firestore;
+mod local;
+
+pub use crate::firestore::FirestoreStorage;
+pub use crate::local::LocalStorage;
+
+use async_trait::async_trait;
+use oort_proto::{
+    LadderJob, LadderMatch, LadderRating, LeaderboardSubmission, LeaderboardVerificationJob,
+    ShortcodeUpload, TelemetryMsg, TournamentResults, TournamentSubmission,
+};
+use std::sync::Arc;
+
+pub const LEADERBOARD: &str = "leaderboard";
+pub const CODE_SIZE_LEADERBOARD: &str = "code_size_leaderboard";
+pub const LEADERBOARD_VERIFICATION: &str = "leaderboard_verification";
+pub const SHORTCODE: &str = "shortcode";
+pub const TELEMETRY: &str = "telemetry";
+pub const TOURNAMENT: &str = "tournament";
+pub const TOURNAMENT_RESULTS: &str = "tournament_results";
+pub const LADDER: &str = "ladder";
+pub const LADDER_MATCHES: &str = "ladder_matches";
+pub const LADDER_JOBS: &str = "ladder_jobs";
+
+/// Returns the document ID for a user's leaderboard entries, leaderboard
+/// verification job, tournament submission, ladder rating or ladder job.
+pub fn submission_docid(scenario_name: &str, userid: &str) -> String {
+    format!("{scenario_name}.{userid}")
+}
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    /// Returns leaderboard entries for a scenario, fastest first with ties
+    /// broken by submission time.
+    async fn list_leaderboard(
+        &self,
+        scenario_name: &str,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, LeaderboardSubmission)>>;
+
+    /// Returns the fastest leaderboard entry for a user, looked up by username.
+    async fn find_leaderboard_by_username(
+        &self,
+        scenario_name: &str,
+        username: &str,
+    ) -> anyhow::Result<Option<LeaderboardSubmission>>;
+
+    async fn get_leaderboard(&self, docid: &str) -> anyhow::Result<Option<LeaderboardSubmission>>;
+
+    /// Creates or replaces a leaderboard entry.
+    async fn put_leaderboard(&self, docid: &str, obj: &LeaderboardSubmission)
+        -> anyhow::Result<()>;
+
+    async fn delete_leaderboard(&self, docid: &str) -> anyhow::Result<()>;
+
+    /// Returns code size leaderboard entries for a scenario, smallest first
+    /// with ties broken by submission time.
+    async fn list_code_size_leaderboard(
+        &self,
+        scenario_name: &str,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, LeaderboardSubmission)>>;
+
+    async fn get_code_size_leaderboard(
+        &self,
+        docid: &str,
+    ) -> anyhow::Result<Option<LeaderboardSubmission>>;
+
+    /// Creates or replaces a code size leaderboard entry.
+    async fn put_code_size_leaderboard(
+        &self,
+        docid: &str,
+        obj: &LeaderboardSubmission,
+    ) -> anyhow::Result<()>;
+
+    async fn delete_code_size_leaderboard(&self, docid: &str) -> anyhow::Result<()>;
+
+    async fn get_leaderboard_verification(
+        &self,
+        docid: &str,
+    ) -> anyhow::Result<Option<LeaderboardVerificationJob>>;
+
+    /// Creates or replaces a leaderboard verification job.
+    async fn put_leaderboard_verification(
+        &self,
+        docid: &str,
+        obj: &LeaderboardVerificationJob,
+    ) -> anyhow::Result<()>;
+
+    /// Returns verification jobs that are still pending, oldest submission first.
+    async fn list_pending_leaderboard_verifications(
+        &self,
+    ) -> anyhow::Result<Vec<(String, LeaderboardVerificationJob)>>;
+
+    async fn get_shortcode(&self, docid: &str) -> anyhow::Result<Option<ShortcodeUpload>>;
+
+    /// Fails if the shortcode already exists.
+    async fn create_shortcode(&self, docid: &str, obj: &ShortcodeUpload) -> anyhow::Result<()>;
+
+    async fn get_telemetry(&self, docid: &str) -> anyhow::Result<Option<TelemetryMsg>>;
+
+    /// Fails if the document already exists.
+    async fn create_telemetry(&self, docid: &str, obj: &TelemetryMsg) -> anyhow::Result<()>;
+
+    /// Returns the most recent telemetry, newest first.
+    async fn list_telemetry(&self, limit: usize) -> anyhow::Result<Vec<(String, TelemetryMsg)>>;
+
+    /// Returns all successful `FinishScenario` telemetry for a scenario.
+    async fn list_finished_telemetry(
+        &self,
+        scenario_name: &str,
+    ) -> anyhow::Result<Vec<(String, TelemetryMsg)>>;
+
+    /// Creates or replaces a tournament submission.
+    async fn put_tournament_submission(
+        &self,
+        docid: &str,
+        obj: &TournamentSubmission,
+    ) -> anyhow::Result<()>;
+
+    /// Returns the earliest tournament submission for a user, looked up by username.
+    async fn find_tournament_by_username(
+        &self,
+        scenario_name: &str,
+        username: &str,
+    ) -> anyhow::Result<Option<TournamentSubmission>>;
+
+    /// Returns tournament submissions for a scenario ordered by username and
+    /// then submission time.
+    async fn list_tournament_submissions(
+        &self,
+        scenario_name: &str,
+    ) -> anyhow::Result<Vec<TournamentSubmission>>;
+
+    async fn get_tournament_results(&self, id: &str) -> anyhow::Result<Option<TournamentResults>>;
+
+    /// Fails if results with this ID already exist.
+    async fn create_tournament_results(
+        &self,
+        id: &str,
+        obj: &TournamentResults,
+    ) -> anyhow::Result<()>;
+
+    /// Returns ladder ratings for a scenario, highest rating first.
+    async fn list_ladder(&self, scenario_name: &str) -> anyhow::Result<Vec<LadderRating>>;
+
+    async fn get_ladder(&self, docid: &str) -> anyhow::Result<Option<LadderRating>>;
+
+    /// Creates or replaces a ladder rating.
+    async fn put_ladder(&self, docid: &str, obj: &LadderRating) -> anyhow::Result<()>;
+
+    async fn get_ladder_job(&self, docid: &str) -> anyhow::Result<Option<LadderJob>>;
+
+    /// Creates or replaces a ladder job.
+    async fn put_ladder_job(&self, docid: &str, obj: &LadderJob) -> anyhow::Result<()>;
+
+    async fn delete_ladder_job(&self, docid: &str) -> anyhow::Result<()>;
+
+    /// Returns all ladder jobs, oldest submission first.
+    async fn list_ladder_jobs(&self) -> anyhow::Result<Vec<(String, LadderJob)>>;
+
+    /// Fails if a match with this ID already exists.
+    async fn create_ladder_match(&self, id: &str, obj: &LadderMatch) -> anyhow::Result<()>;
+
+    /// Returns the most recent ladder matches for a scenario, newest first.
+    async fn list_ladder_matches(
+        &self,
+        scenario_name: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<LadderMatch>>;
+}
+
+/// Opens storage described by `spec`.
+///
+/// `local:PATH` opens an SQLite database at `PATH`, creating it if needed, and
+/// `local::memory:` opens a temporary in-memory database. Anything else is
+/// taken as a Firestore project ID.
+pub async fn open(spec: &str) -> anyhow::Result<Arc<dyn Storage>> {
+    if let Some(path) = spec.strip_prefix("local:") {
+        Ok(Arc::new(LocalStorage::open(path)?))
+    } else {
+        Ok(Arc::new(FirestoreStorage::new(spec).await?))
+    }
+}
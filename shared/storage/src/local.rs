@@ -0,0 +1,777 @@
+use crate::*;
+use async_trait::async_trait;
+use oort_proto::{LeaderboardVerification, Telemetry};
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Stores each collection as JSON documents in an SQLite database.
+///
+/// Collections are small enough outside production that queries load the
+/// whole collection and filter and sort in memory.
+pub struct LocalStorage {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl LocalStorage {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> anyhow::Result<Self> {
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS documents (
+                collection TEXT NOT NULL,
+                id TEXT NOT NULL,
+                data TEXT NOT NULL,
+                PRIMARY KEY (collection, id)
+            )",
+            [],
+        )?;
+        Ok(Self {
+            conn: Arc::new(Mutex::new(conn)),
+        })
+    }
+
+    /// Runs `f` on the blocking thread pool so SQLite calls don't stall the
+    /// async runtime.
+    async fn with_conn<T, F>(&self, f: F) -> anyhow::Result<T>
+    where
+        T: Send + 'static,
+        F: FnOnce(&Connection) -> anyhow::Result<T> + Send + 'static,
+    {
+        let conn = self.conn.clone();
+        tokio::task::spawn_blocking(move || f(&conn.lock().unwrap())).await?
+    }
+
+    async fn get<T: DeserializeOwned>(
+        &self,
+        collection: &'static str,
+        id: &str,
+    ) -> anyhow::Result<Option<T>> {
+        let id = id.to_string();
+        let data: Option<String> = self
+            .with_conn(move |conn| {
+                Ok(conn
+                    .query_row(
+                        "SELECT data FROM documents WHERE collection = ?1 AND id = ?2",
+                        params![collection, id],
+                        |row| row.get(0),
+                    )
+                    .optional()?)
+            })
+            .await?;
+        match data {
+            Some(data) => Ok(Some(serde_json::from_str(&data)?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn put<T: Serialize>(
+        &self,
+        collection: &'static str,
+        id: &str,
+        obj: &T,
+    ) -> anyhow::Result<()> {
+        let id = id.to_string();
+        let data = serde_json::to_string(obj)?;
+        self.with_conn(move |conn| {
+            conn.execute(
+                "INSERT OR REPLACE INTO documents (collection, id, data) VALUES (?1, ?2, ?3)",
+                params![collection, id, data],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn create<T: Serialize>(
+        &self,
+        collection: &'static str,
+        id: &str,
+        obj: &T,
+    ) -> anyhow::Result<()> {
+        let id = id.to_string();
+        let data = serde_json::to_string(obj)?;
+        self.with_conn(move |conn| {
+            let result = conn.execute(
+                "INSERT INTO documents (collection, id, data) VALUES (?1, ?2, ?3)",
+                params![collection, id, data],
+            );
+            match result {
+                Ok(_) => Ok(()),
+                Err(rusqlite::Error::SqliteFailure(e, _))
+                    if e.code == rusqlite::ErrorCode::ConstraintViolation =>
+                {
+                    anyhow::bail!("document {collection}/{id} already exists")
+                }
+                Err(e) => Err(e.into()),
+            }
+        })
+        .await
+    }
+
+    async fn delete(&self, collection: &'static str, id: &str) -> anyhow::Result<()> {
+        let id = id.to_string();
+        self.with_conn(move |conn| {
+            conn.execute(
+                "DELETE FROM documents WHERE collection = ?1 AND id = ?2",
+                params![collection, id],
+            )?;
+            Ok(())
+        })
+        .await
+    }
+
+    async fn list<T: DeserializeOwned>(
+        &self,
+        collection: &'static str,
+    ) -> anyhow::Result<Vec<(String, T)>> {
+        let rows: Vec<(String, String)> = self
+            .with_conn(move |conn| {
+                let mut stmt =
+                    conn.prepare("SELECT id, data FROM documents WHERE collection = ?1")?;
+                let rows = stmt.query_map(params![collection], |row| {
+                    Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+                })?;
+                Ok(rows.collect::<Result<_, _>>()?)
+            })
+            .await?;
+        let mut result = vec![];
+        for (id, data) in rows {
+            match serde_json::from_str(&data) {
+                Ok(obj) => result.push((id, obj)),
+                Err(e) => log::error!("Failed to deserialize doc {collection}/{id}: {e}"),
+            }
+        }
+        Ok(result)
+    }
+
+    async fn list_leaderboard_sorted(
+        &self,
+        scenario_name: &str,
+    ) -> anyhow::Result<Vec<(String, LeaderboardSubmission)>> {
+        let mut docs: Vec<(String, LeaderboardSubmission)> = self
+            .list(LEADERBOARD)
+            .await?
+            .into_iter()
+            .filter(|(_, x): &(String, LeaderboardSubmission)| x.scenario_name == scenario_name)
+            .collect();
+        docs.sort_by(|(_, a), (_, b)| {
+            a.time
+                .total_cmp(&b.time)
+                .then_with(|| a.timestamp.cmp(&b.timestamp))
+        });
+        Ok(docs)
+    }
+
+    async fn list_code_size_leaderboard_sorted(
+        &self,
+        scenario_name: &str,
+    ) -> anyhow::Result<Vec<(String, LeaderboardSubmission)>> {
+        let mut docs: Vec<(String, LeaderboardSubmission)> = self
+            .list(CODE_SIZE_LEADERBOARD)
+            .await?
+            .into_iter()
+            .filter(|(_, x): &(String, LeaderboardSubmission)| x.scenario_name == scenario_name)
+            .collect();
+        docs.sort_by_key(|(_, x)| (x.code_size, x.timestamp));
+        Ok(docs)
+    }
+
+    async fn list_tournament_sorted(
+        &self,
+        scenario_name: &str,
+    ) -> anyhow::Result<Vec<TournamentSubmission>> {
+        let mut docs: Vec<TournamentSubmission> = self
+            .list(TOURNAMENT)
+            .await?
+            .into_iter()
+            .map(|(_, x): (String, TournamentSubmission)| x)
+            .filter(|x| x.scenario_name == scenario_name)
+            .collect();
+        docs.sort_by(|a, b| {
+            a.username
+                .cmp(&b.username)
+                .then_with(|| a.timestamp.cmp(&b.timestamp))
+        });
+        Ok(docs)
+    }
+}
+
+#[async_trait]
+impl Storage for LocalStorage {
+    async fn list_leaderboard(
+        &self,
+        scenario_name: &str,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, LeaderboardSubmission)>> {
+        let mut docs = self.list_leaderboard_sorted(scenario_name).await?;
+        if let Some(limit) = limit {
+            docs.truncate(limit);
+        }
+        Ok(docs)
+    }
+
+    async fn find_leaderboard_by_username(
+        &self,
+        scenario_name: &str,
+        username: &str,
+    ) -> anyhow::Result<Option<LeaderboardSubmission>> {
+        Ok(self
+            .list_leaderboard_sorted(scenario_name)
+            .await?
+            .into_iter()
+            .map(|(_, x)| x)
+            .find(|x| x.username == username))
+    }
+
+    async fn get_leaderboard(&self, docid: &str) -> anyhow::Result<Option<LeaderboardSubmission>> {
+        self.get(LEADERBOARD, docid).await
+    }
+
+    async fn put_leaderboard(
+        &self,
+        docid: &str,
+        obj: &LeaderboardSubmission,
+    ) -> anyhow::Result<()> {
+        self.put(LEADERBOARD, docid, obj).await
+    }
+
+    async fn delete_leaderboard(&self, docid: &str) -> anyhow::Result<()> {
+        self.delete(LEADERBOARD, docid).await
+    }
+
+    async fn list_code_size_leaderboard(
+        &self,
+        scenario_name: &str,
+        limit: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, LeaderboardSubmission)>> {
+        let mut docs = self
+            .list_code_size_leaderboard_sorted(scenario_name)
+            .await?;
+        if let Some(limit) = limit {
+            docs.truncate(limit);
+        }
+        Ok(docs)
+    }
+
+    async fn get_code_size_leaderboard(
+        &self,
+        docid: &str,
+    ) -> anyhow::Result<Option<LeaderboardSubmission>> {
+        self.get(CODE_SIZE_LEADERBOARD, docid).await
+    }
+
+    async fn put_code_size_leaderboard(
+        &self,
+        docid: &str,
+        obj: &LeaderboardSubmission,
+    ) -> anyhow::Result<()> {
+        self.put(CODE_SIZE_LEADERBOARD, docid, obj).await
+    }
+
+    async fn delete_code_size_leaderboard(&self, docid: &str) -> anyhow::Result<()> {
+        self.delete(CODE_SIZE_LEADERBOARD, docid).await
+    }
+
+    async fn get_leaderboard_verification(
+        &self,
+        docid: &str,
+    ) -> anyhow::Result<Option<LeaderboardVerificationJob>> {
+        self.get(LEADERBOARD_VERIFICATION, docid).await
+    }
+
+    async fn put_leaderboard_verification(
+        &self,
+        docid: &str,
+        obj: &LeaderboardVerificationJob,
+    ) -> anyhow::Result<()> {
+        self.put(LEADERBOARD_VERIFICATION, docid, obj).await
+    }
+
+    async fn list_pending_leaderboard_verifications(
+        &self,
+    ) -> anyhow::Result<Vec<(String, LeaderboardVerificationJob)>> {
+        let mut docs: Vec<(String, LeaderboardVerificationJob)> = self
+            .list(LEADERBOARD_VERIFICATION)
+            .await?
+            .into_iter()
+            .filter(|(_, x): &(String, LeaderboardVerificationJob)| {
+                x.status == LeaderboardVerification::Pending
+            })
+            .collect();
+        docs.sort_by_key(|(_, x)| x.submission.timestamp);
+        Ok(docs)
+    }
+
+    async fn get_shortcode(&self, docid: &str) -> anyhow::Result<Option<ShortcodeUpload>> {
+        self.get(SHORTCODE, docid).await
+    }
+
+    async fn create_shortcode(&self, docid: &str, obj: &ShortcodeUpload) -> anyhow::Result<()> {
+        self.create(SHORTCODE, docid, obj).await
+    }
+
+    async fn get_telemetry(&self, docid: &str) -> anyhow::Result<Option<TelemetryMsg>> {
+        self.get(TELEMETRY, docid).await
+    }
+
+    async fn create_telemetry(&self, docid: &str, obj: &TelemetryMsg) -> anyhow::Result<()> {
+        self.create(TELEMETRY, docid, obj).await
+    }
+
+    async fn list_telemetry(&self, limit: usize) -> anyhow::Result<Vec<(String, TelemetryMsg)>> {
+        let mut docs: Vec<(String, TelemetryMsg)> = self.list(TELEMETRY).await?;
+        docs.sort_by_key(|(_, x)| std::cmp::Reverse(x.timestamp));
+        docs.truncate(limit);
+        Ok(docs)
+    }
+
+    async fn list_finished_telemetry(
+        &self,
+        scenario_name: &str,
+    ) -> anyhow::Result<Vec<(String, TelemetryMsg)>> {
+        let docs: Vec<(String, TelemetryMsg)> = self.list(TELEMETRY).await?;
+        Ok(docs
+            .into_iter()
+            .filter(|(_, msg)| {
+                matches!(&msg.payload, Telemetry::FinishScenario {
+                    scenario_name: name,
+                    success: true,
+                    ..
+                } if name == scenario_name)
+            })
+            .collect())
+    }
+
+    async fn put_tournament_submission(
+        &self,
+        docid: &str,
+        obj: &TournamentSubmission,
+    ) -> anyhow::Result<()> {
+        self.put(TOURNAMENT, docid, obj).await
+    }
+
+    async fn find_tournament_by_username(
+        &self,
+        scenario_name: &str,
+        username: &str,
+    ) -> anyhow::Result<Option<TournamentSubmission>> {
+        Ok(self
+            .list_tournament_sorted(scenario_name)
+            .await?
+            .into_iter()
+            .find(|x| x.username == username))
+    }
+
+    async fn list_tournament_submissions(
+        &self,
+        scenario_name: &str,
+    ) -> anyhow::Result<Vec<TournamentSubmission>> {
+        self.list_tournament_sorted(scenario_name).await
+    }
+
+    async fn get_tournament_results(&self, id: &str) -> anyhow::Result<Option<TournamentResults>> {
+        self.get(TOURNAMENT_RESULTS, id).await
+    }
+
+    async fn create_tournament_results(
+        &self,
+        id: &str,
+        obj: &TournamentResults,
+    ) -> anyhow::Result<()> {
+        self.create(TOURNAMENT_RESULTS, id, obj).await
+    }
+
+    async fn list_ladder(&self, scenario_name: &str) -> anyhow::Result<Vec<LadderRating>> {
+        let mut docs: Vec<LadderRating> = self
+            .list(LADDER)
+            .await?
+            .into_iter()
+            .map(|(_, x): (String, LadderRating)| x)
+            .filter(|x| x.scenario_name == scenario_name)
+            .collect();
+        docs.sort_by(|a, b| {
+            b.rating
+                .total_cmp(&a.rating)
+                .then_with(|| a.timestamp.cmp(&b.timestamp))
+        });
+        Ok(docs)
+    }
+
+    async fn get_ladder(&self, docid: &str) -> anyhow::Result<Option<LadderRating>> {
+        self.get(LADDER, docid).await
+    }
+
+    async fn put_ladder(&self, docid: &str, obj: &LadderRating) -> anyhow::Result<()> {
+        self.put(LADDER, docid, obj).await
+    }
+
+    async fn get_ladder_job(&self, docid: &str) -> anyhow::Result<Option<LadderJob>> {
+        self.get(LADDER_JOBS, docid).await
+    }
+
+    async fn put_ladder_job(&self, docid: &str, obj: &LadderJob) -> anyhow::Result<()> {
+        self.put(LADDER_JOBS, docid, obj).await
+    }
+
+    async fn delete_ladder_job(&self, docid: &str) -> anyhow::Result<()> {
+        self.delete(LADDER_JOBS, docid).await
+    }
+
+    async fn list_ladder_jobs(&self) -> anyhow::Result<Vec<(String, LadderJob)>> {
+        let mut docs: Vec<(String, LadderJob)> = self.list(LADDER_JOBS).await?;
+        docs.sort_by_key(|(_, x)| x.submission.timestamp);
+        Ok(docs)
+    }
+
+    async fn create_ladder_match(&self, id: &str, obj: &LadderMatch) -> anyhow::Result<()> {
+        self.create(LADDER_MATCHES, id, obj).await
+    }
+
+    async fn list_ladder_matches(
+        &self,
+        scenario_name: &str,
+        limit: usize,
+    ) -> anyhow::Result<Vec<LadderMatch>> {
+        let mut docs: Vec<LadderMatch> = self
+            .list(LADDER_MATCHES)
+            .await?
+            .into_iter()
+            .map(|(_, x): (String, LadderMatch)| x)
+            .filter(|x| x.scenario_name == scenario_name)
+            .collect();
+        docs.sort_by_key(|x| std::cmp::Reverse(x.timestamp));
+        docs.truncate(limit);
+        Ok(docs)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn submission(username: &str, time: f64, timestamp: i64) -> LeaderboardSubmission {
+        LeaderboardSubmission {
+            scenario_name: "gunnery".to_string(),
+            userid: format!("id-{username}"),
+            username: username.to_string(),
+            timestamp: Utc.timestamp_millis_opt(timestamp).unwrap(),
+            time,
+            code_size: 0,
+            code: format!("// {username}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_leaderboard() {
+        let storage = LocalStorage::in_memory().unwrap();
+        for obj in [
+            submission("b", 2.0, 1),
+            submission("a", 1.0, 2),
+            submission("c", 2.0, 0),
+        ] {
+            let docid = submission_docid(&obj.scenario_name, &obj.userid);
+            storage.put_leaderboard(&docid, &obj).await.unwrap();
+        }
+
+        let names = |docs: Vec<(String, LeaderboardSubmission)>| {
+            docs.into_iter()
+                .map(|(_, x)| x.username)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(storage.list_leaderboard("gunnery", None).await.unwrap()),
+            vec!["a", "c", "b"]
+        );
+        assert_eq!(
+            names(storage.list_leaderboard("gunnery", Some(2)).await.unwrap()),
+            vec!["a", "c"]
+        );
+        assert!(storage
+            .list_leaderboard("tutorial01", None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let obj = submission("b", 0.5, 3);
+        storage.put_leaderboard("gunnery.id-b", &obj).await.unwrap();
+        assert_eq!(
+            storage.get_leaderboard("gunnery.id-b").await.unwrap(),
+            Some(obj.clone())
+        );
+        assert_eq!(
+            storage
+                .find_leaderboard_by_username("gunnery", "b")
+                .await
+                .unwrap(),
+            Some(obj)
+        );
+
+        storage.delete_leaderboard("gunnery.id-b").await.unwrap();
+        assert_eq!(storage.get_leaderboard("gunnery.id-b").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_code_size_leaderboard() {
+        let storage = LocalStorage::in_memory().unwrap();
+        for (username, code_size, timestamp) in [("a", 300, 0), ("b", 100, 1), ("c", 100, 0)] {
+            let mut obj = submission(username, 1.0, timestamp);
+            obj.code_size = code_size;
+            let docid = submission_docid(&obj.scenario_name, &obj.userid);
+            storage
+                .put_code_size_leaderboard(&docid, &obj)
+                .await
+                .unwrap();
+        }
+        let names: Vec<_> = storage
+            .list_code_size_leaderboard("gunnery", Some(2))
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, x)| x.username)
+            .collect();
+        assert_eq!(names, vec!["c", "b"]);
+        // The time leaderboard is a separate collection.
+        assert!(storage
+            .list_leaderboard("gunnery", None)
+            .await
+            .unwrap()
+            .is_empty());
+
+        storage
+            .delete_code_size_leaderboard("gunnery.id-a")
+            .await
+            .unwrap();
+        assert_eq!(
+            storage
+                .get_code_size_leaderboard("gunnery.id-a")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_leaderboard_verification() {
+        let storage = LocalStorage::in_memory().unwrap();
+        let job = |username: &str, timestamp: i64, status| LeaderboardVerificationJob {
+            submission: submission(username, 1.0, timestamp),
+            status,
+            claimed: None,
+        };
+        let rejected = LeaderboardVerification::Rejected {
+            reason: "reason".to_string(),
+        };
+        for obj in [
+            job("a", 1, LeaderboardVerification::Pending),
+            job("b", 0, LeaderboardVerification::Pending),
+            job("c", 2, rejected.clone()),
+        ] {
+            let docid = submission_docid(&obj.submission.scenario_name, &obj.submission.userid);
+            storage
+                .put_leaderboard_verification(&docid, &obj)
+                .await
+                .unwrap();
+        }
+
+        let pending: Vec<_> = storage
+            .list_pending_leaderboard_verifications()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(docid, _)| docid)
+            .collect();
+        assert_eq!(pending, vec!["gunnery.id-b", "gunnery.id-a"]);
+        assert_eq!(
+            storage
+                .get_leaderboard_verification("gunnery.id-c")
+                .await
+                .unwrap()
+                .map(|x| x.status),
+            Some(rejected)
+        );
+        assert_eq!(
+            storage
+                .get_leaderboard_verification("gunnery.id-d")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn test_create_existing() {
+        let storage = LocalStorage::in_memory().unwrap();
+        let obj = ShortcodeUpload {
+            userid: "id".to_string(),
+            username: "user".to_string(),
+            timestamp: Utc.timestamp_millis_opt(0).unwrap(),
+            code: "code".to_string(),
+        };
+        storage.create_shortcode("abc", &obj).await.unwrap();
+        assert!(storage.create_shortcode("abc", &obj).await.is_err());
+        assert_eq!(storage.get_shortcode("abc").await.unwrap(), Some(obj));
+        assert_eq!(storage.get_shortcode("def").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_telemetry() {
+        let storage = LocalStorage::in_memory().unwrap();
+        let msg = |payload, timestamp| TelemetryMsg {
+            payload,
+            build: "build".to_string(),
+            userid: "id".to_string(),
+            username: "user".to_string(),
+            timestamp: Utc.timestamp_millis_opt(timestamp).unwrap(),
+        };
+        let finish = |scenario_name: &str, success| Telemetry::FinishScenario {
+            scenario_name: scenario_name.to_string(),
+            code: "".to_string(),
+            ticks: 0,
+            code_size: 0,
+            success,
+            time: Some(1.0),
+        };
+        storage
+            .create_telemetry("1", &msg(finish("gunnery", true), 1))
+            .await
+            .unwrap();
+        storage
+            .create_telemetry("2", &msg(finish("gunnery", false), 2))
+            .await
+            .unwrap();
+        storage
+            .create_telemetry("3", &msg(finish("tutorial01", true), 3))
+            .await
+            .unwrap();
+
+        let ids = |docs: Vec<(String, TelemetryMsg)>| {
+            docs.into_iter().map(|(id, _)| id).collect::<Vec<_>>()
+        };
+        assert_eq!(
+            ids(storage.list_telemetry(2).await.unwrap()),
+            vec!["3", "2"]
+        );
+        assert_eq!(
+            ids(storage.list_finished_telemetry("gunnery").await.unwrap()),
+            vec!["1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ladder() {
+        let storage = LocalStorage::in_memory().unwrap();
+        let rating = |username: &str, rating| LadderRating {
+            scenario_name: "fighter_duel".to_string(),
+            userid: format!("id-{username}"),
+            username: username.to_string(),
+            timestamp: Utc.timestamp_millis_opt(0).unwrap(),
+            rating,
+            deviation: 350.0,
+            volatility: 0.06,
+            wins: 0,
+            losses: 0,
+            draws: 0,
+        };
+        for obj in [
+            rating("a", 1400.0),
+            rating("b", 1600.0),
+            rating("c", 1500.0),
+        ] {
+            let docid = submission_docid(&obj.scenario_name, &obj.userid);
+            storage.put_ladder(&docid, &obj).await.unwrap();
+        }
+        let names: Vec<_> = storage
+            .list_ladder("fighter_duel")
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|x| x.username)
+            .collect();
+        assert_eq!(names, vec!["b", "c", "a"]);
+        assert_eq!(
+            storage.get_ladder("fighter_duel.id-a").await.unwrap(),
+            Some(rating("a", 1400.0))
+        );
+
+        let game = |timestamp| LadderMatch {
+            scenario_name: "fighter_duel".to_string(),
+            timestamp: Utc.timestamp_millis_opt(timestamp).unwrap(),
+            seed: 0,
+            usernames: vec!["a".to_string(), "b".to_string()],
+            winner: None,
+        };
+        for timestamp in 0..3 {
+            storage
+                .create_ladder_match(&timestamp.to_string(), &game(timestamp))
+                .await
+                .unwrap();
+        }
+        assert!(storage.create_ladder_match("0", &game(0)).await.is_err());
+        assert_eq!(
+            storage
+                .list_ladder_matches("fighter_duel", 2)
+                .await
+                .unwrap(),
+            vec![game(2), game(1)]
+        );
+
+        let job = |username: &str, timestamp| LadderJob {
+            submission: TournamentSubmission {
+                scenario_name: "fighter_duel".to_string(),
+                userid: format!("id-{username}"),
+                username: username.to_string(),
+                timestamp: Utc.timestamp_millis_opt(timestamp).unwrap(),
+                code: "".to_string(),
+            },
+            claimed: None,
+        };
+        storage
+            .put_ladder_job("fighter_duel.id-a", &job("a", 1))
+            .await
+            .unwrap();
+        storage
+            .put_ladder_job("fighter_duel.id-b", &job("b", 0))
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.list_ladder_jobs().await.unwrap(),
+            vec![
+                ("fighter_duel.id-b".to_string(), job("b", 0)),
+                ("fighter_duel.id-a".to_string(), job("a", 1))
+            ]
+        );
+        storage
+            .delete_ladder_job("fighter_duel.id-b")
+            .await
+            .unwrap();
+        assert_eq!(
+            storage.get_ladder_job("fighter_duel.id-b").await.unwrap(),
+            None
+        );
+        assert_eq!(storage.list_ladder_jobs().await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_open_file() {
+        let path = std::env::temp_dir().join(format!("oort_storage_{}.db", std::process::id()));
+        let obj = submission("a", 1.0, 0);
+        {
+            let storage = LocalStorage::open(&path).unwrap();
+            storage.put_leaderboard("x", &obj).await.unwrap();
+        }
+        let storage = LocalStorage::open(&path).unwrap();
+        assert_eq!(storage.get_leaderboard("x").await.unwrap(), Some(obj));
+        drop(storage);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,297 @@
+//! Regression test harness for Oort AIs.
+//!
+//! Runs an AI against the real simulator over a list of seeds and checks the
+//! results from an ordinary `cargo test`:
+//!
+//! ```no_run
+//! use oort_testing::{builtin, compile_file, Harness};
+//!
+//! #[test]
+//! fn beats_reference() {
+//!     Harness::new("fighter_duel")
+//!         .team(0, compile_file("src/user.rs"))
+//!         .team(1, builtin("reference"))
+//!         .seeds(0..10)
+//!         .run()
+//!         .assert_no_errors()
+//!         .assert_win_rate(0, 0.8);
+//! }
+//! ```
+//!
+//! AIs run in the same Wasm VM as the game rather than natively. The API
+//! keeps each ship's state in process globals, so native code can only be
+//! driven one ship at a time (see `oort_api::mock`), and running in the VM
+//! means the results include gas limits, traps and the exact floating point
+//! behavior players see in the game. The cost is a compile to
+//! `wasm32-unknown-unknown` for Rust source.
+use oort_simulator::scenario::{self, Status};
+use oort_simulator::simulation::{Code, Simulation};
+use oort_simulator::vm;
+use rayon::prelude::*;
+use std::path::Path;
+
+/// Compiles Rust source code for a `Ship` to Wasm.
+///
+/// Uses the same toolchain setup as the compiler service, so it needs
+/// `cargo` and the `wasm32-unknown-unknown` target installed locally.
+pub fn compile(source: &str) -> Code {
+    let mut compiler = oort_compiler::Compiler::new();
+    match compiler.compile(source) {
+        Ok(wasm) => Code::Wasm(wasm),
+        Err(e) => panic!("Failed to compile AI: {e}"),
+    }
+}
+
+/// Compiles a Rust source file containing a `Ship`.
+pub fn compile_file(path: impl AsRef<Path>) -> Code {
+    let path = path.as_ref();
+    let source = std::fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display()));
+    compile(&source)
+}
+
+/// Loads a prebuilt Wasm AI.
+pub fn load_wasm(path: impl AsRef<Path>) -> Code {
+    let path = path.as_ref();
+    Code::Wasm(
+        std::fs::read(path).unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display())),
+    )
+}
+
+/// Selects one of the AIs built into the game, e.g. "reference".
+pub fn builtin(name: &str) -> Code {
+    Code::Builtin(name.to_string())
+}
+
+/// Returns the scenario's own solution, where it has one.
+pub fn solution(scenario_name: &str) -> Code {
+    scenario::load(scenario_name).solution()
+}
+
+/// Configures a set of simulations of one scenario.
+#[derive(Clone)]
+pub struct Harness {
+    scenario_name: String,
+    codes: Vec<Code>,
+    seeds: Vec<u32>,
+    max_ticks: u32,
+    backend: vm::Backend,
+}
+
+impl Harness {
+    /// Starts with the scenario's initial code for every team and seeds 0 to 9.
+    pub fn new(scenario_name: &str) -> Harness {
+        Harness {
+            scenario_name: scenario_name.to_string(),
+            codes: scenario::load(scenario_name).initial_code(),
+            seeds: (0..10).collect(),
+            max_ticks: scenario::MAX_TICKS,
+            backend: vm::Backend::default(),
+        }
+    }
+
+    /// Sets the code for a team. Rust source is compiled with [`compile`].
+    pub fn team(mut self, team: usize, code: Code) -> Harness {
+        let code = match code {
+            Code::Rust(source) => compile(&source),
+            code => code,
+        };
+        if self.codes.len() <= team {
+            self.codes.resize(team + 1, Code::None);
+        }
+        self.codes[team] = code;
+        self
+    }
+
+    /// Sets the seeds to simulate, one simulation per seed.
+    pub fn seeds(mut self, seeds: impl IntoIterator<Item = u32>) -> Harness {
+        self.seeds = seeds.into_iter().collect();
+        self
+    }
+
+    /// Stops each simulation after this many ticks.
+    pub fn max_ticks(mut self, max_ticks: u32) -> Harness {
+        self.max_ticks = max_ticks;
+        self
+    }
+
+    /// Selects the VM backend used to run the AIs.
+    pub fn backend(mut self, backend: vm::Backend) -> Harness {
+        self.backend = backend;
+        self
+    }
+
+    /// Runs the simulations in parallel.
+    pub fn run(&self) -> Results {
+        let mut matches: Vec<MatchResult> = self
+            .seeds
+            .par_iter()
+            .map(|&seed| self.run_one(seed))
+            .collect();
+        matches.sort_by_key(|m| m.seed);
+        Results {
+            scenario_name: self.scenario_name.clone(),
+            matches,
+        }
+    }
+
+    fn run_one(&self, seed: u32) -> MatchResult {
+        let mut sim =
+            Simulation::new_with_backend(&self.scenario_name, seed, &self.codes, self.backend);
+        let mut errors: Vec<vm::Error> = sim.events().errors.clone();
+        while sim.status() == Status::Running && sim.tick() < self.max_ticks {
+            sim.step();
+            errors.extend(sim.events().errors.iter().cloned());
+        }
+        MatchResult {
+            seed,
+            status: sim.status(),
+            ticks: sim.tick(),
+            errors,
+            hash: sim.hash(),
+        }
+    }
+}
+
+/// The outcome of one simulation.
+#[derive(Clone, Debug)]
+pub struct MatchResult {
+    pub seed: u32,
+    pub status: Status,
+    pub ticks: u32,
+    pub errors: Vec<vm::Error>,
+    pub hash: u64,
+}
+
+/// The outcomes of every simulation run by a [`Harness`].
+///
+/// The `assert_*` methods panic with a per-seed summary on failure and
+/// return `self` so they can be chained.
+#[derive(Clone, Debug)]
+pub struct Results {
+    pub scenario_name: String,
+    pub matches: Vec<MatchResult>,
+}
+
+impl Results {
+    /// Fraction of simulations won by `team`.
+    pub fn win_rate(&self, team: i32) -> f64 {
+        if self.matches.is_empty() {
+            return 0.0;
+        }
+        let wins = self
+            .matches
+            .iter()
+            .filter(|m| m.status == Status::Victory { team })
+            .count();
+        wins as f64 / self.matches.len() as f64
+    }
+
+    /// Seeds where `team` did not win.
+    pub fn losses(&self, team: i32) -> Vec<u32> {
+        self.matches
+            .iter()
+            .filter(|m| m.status != Status::Victory { team })
+            .map(|m| m.seed)
+            .collect()
+    }
+
+    /// Panics unless `team` won at least `min_win_rate` (0 to 1) of the simulations.
+    pub fn assert_win_rate(&self, team: i32, min_win_rate: f64) -> &Results {
+        let win_rate = self.win_rate(team);
+        assert!(
+            win_rate >= min_win_rate,
+            "{}: team {team} won {:.0}% of simulations, expected at least {:.0}%\n{}",
+            self.scenario_name,
+            win_rate * 100.0,
+            min_win_rate * 100.0,
+            self.summary()
+        );
+        self
+    }
+
+    /// Panics unless `team` won every simulation before reaching `tick`.
+    pub fn assert_victory_before(&self, team: i32, tick: u32) -> &Results {
+        let failures: Vec<u32> = self
+            .matches
+            .iter()
+            .filter(|m| m.status != Status::Victory { team } || m.ticks >= tick)
+            .map(|m| m.seed)
+            .collect();
+        assert!(
+            failures.is_empty(),
+            "{}: team {team} did not win before tick {tick} with seeds {failures:?}\n{}",
+            self.scenario_name,
+            self.summary()
+        );
+        self
+    }
+
+    /// Panics if any AI reported a [`vm::Error`], such as a trap or running out of gas.
+    pub fn assert_no_errors(&self) -> &Results {
+        let errors: Vec<String> = self
+            .matches
+            .iter()
+            .flat_map(|m| {
+                m.errors
+                    .iter()
+                    .map(move |e| format!("seed {}: {}", m.seed, e.msg))
+            })
+            .collect();
+        assert!(
+            errors.is_empty(),
+            "{}: simulations reported errors:\n{}",
+            self.scenario_name,
+            errors.join("\n")
+        );
+        self
+    }
+
+    /// One line per seed with its outcome.
+    pub fn summary(&self) -> String {
+        self.matches
+            .iter()
+            .map(|m| {
+                format!(
+                    "seed {}: {:?} at tick {}{}",
+                    m.seed,
+                    m.status,
+                    m.ticks,
+                    if m.errors.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" ({} errors)", m.errors.len())
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use test_log::test;
+
+    #[test]
+    fn test_tutorial_solution() {
+        Harness::new("tutorial_guns")
+            .team(0, solution("tutorial_guns"))
+            .seeds(0..2)
+            .run()
+            .assert_no_errors()
+            .assert_win_rate(0, 1.0)
+            .assert_victory_before(0, scenario::MAX_TICKS);
+    }
+
+    #[test]
+    fn test_empty_ai_loses() {
+        let results = Harness::new("tutorial_guns")
+            .seeds(0..1)
+            .max_ticks(60)
+            .run();
+        assert_eq!(results.win_rate(0), 0.0);
+        assert_eq!(results.losses(0), vec![0]);
+    }
+}
@@ -1,5 +1,5 @@
 use oort_simulator::simulation::Code;
-use oort_simulator::{scenario, simulation};
+use oort_simulator::{scenario, simulation, vm};
 use rayon::prelude::*;
 use std::default::Default;
 
@@ -9,11 +9,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         .init();
 
     let args: Vec<String> = std::env::args().collect();
-    if args.len() != 4 {
-        panic!("Expected arguments: SCENARIO PATH PATH");
+    if args.len() < 4 {
+        panic!("Expected arguments: SCENARIO PATH PATH [NAME=VALUE...]");
     }
     let scenario_name = args[1].clone();
-    let srcs = args[2..].to_vec();
+    let srcs = args[2..4].to_vec();
+    let params = args[4..]
+        .iter()
+        .map(|x| scenario::parse_param_override(x))
+        .collect::<Result<scenario::ParamOverrides, String>>()?;
+    scenario::Params::resolve(&scenario::load(&scenario_name).params(), &params)?;
 
     let mut compiler = oort_compiler::Compiler::new();
     let mut codes = vec![];
@@ -24,7 +29,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     }
 
     log::info!("Running simulations");
-    let results = run_simulations(&scenario_name, codes);
+    let results = run_simulations(&scenario_name, &params, codes)?;
     log::info!("Results: {:?}", results);
     match results.team0_wins.len().cmp(&results.team1_wins.len()) {
         std::cmp::Ordering::Greater => log::info!("Team 0 ({:?}) wins", srcs[0]),
@@ -41,11 +46,20 @@ struct Results {
     draws: Vec<u32>,
 }
 
-fn run_simulations(scenario_name: &str, codes: Vec<Code>) -> Results {
+fn run_simulations(
+    scenario_name: &str,
+    params: &scenario::ParamOverrides,
+    codes: Vec<Code>,
+) -> Result<Results, String> {
     let seed_statuses: Vec<(u32, scenario::Status)> = (0..10u32)
         .into_par_iter()
-        .map(|seed| (seed, run_simulation(scenario_name, seed, codes.clone())))
-        .collect();
+        .map(|seed| {
+            Ok((
+                seed,
+                run_simulation(scenario_name, params, seed, codes.clone())?,
+            ))
+        })
+        .collect::<Result<_, String>>()?;
     let mut results: Results = Default::default();
     for (seed, status) in seed_statuses {
         match status {
@@ -55,13 +69,46 @@ fn run_simulations(scenario_name: &str, codes: Vec<Code>) -> Results {
             _ => unreachable!(),
         }
     }
-    results
+    Ok(results)
 }
 
-fn run_simulation(scenario_name: &str, seed: u32, codes: Vec<Code>) -> scenario::Status {
-    let mut sim = simulation::Simulation::new(scenario_name, seed, &codes);
+fn run_simulation(
+    scenario_name: &str,
+    params: &scenario::ParamOverrides,
+    seed: u32,
+    codes: Vec<Code>,
+) -> Result<scenario::Status, String> {
+    let mut sim = simulation::Simulation::new_with_params(
+        scenario_name,
+        seed,
+        &codes,
+        vm::Backend::default(),
+        params,
+    )?;
     while sim.status() == scenario::Status::Running && sim.tick() < scenario::MAX_TICKS {
         sim.step();
+        for entry in sim.events().log.iter() {
+            log::log!(
+                target: "battle::ai",
+                log_level(entry.level),
+                "seed={} tick={} ship={} team={}: {}",
+                seed,
+                entry.tick,
+                entry.ship,
+                entry.team,
+                entry.message
+            );
+        }
+    }
+    Ok(sim.status())
+}
+
+fn log_level(level: oort_api::LogLevel) -> log::Level {
+    match level {
+        oort_api::LogLevel::Trace => log::Level::Trace,
+        oort_api::LogLevel::Debug => log::Level::Debug,
+        oort_api::LogLevel::Info => log::Level::Info,
+        oort_api::LogLevel::Warn => log::Level::Warn,
+        oort_api::LogLevel::Error => log::Level::Error,
     }
-    sim.status()
 }
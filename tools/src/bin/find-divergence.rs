@@ -0,0 +1,285 @@
+use clap::{Parser, Subcommand};
+use oort_simulator::ship::ShipHandle;
+use oort_simulator::simulation::{Code, Simulation};
+use oort_simulator::trace::{self, TickHash};
+use oort_simulator::vm::Backend;
+use oort_simulator::{scenario, simulation};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Parser, Debug)]
+#[clap()]
+/// Finds the first tick where two simulations diverge.
+struct Arguments {
+    #[clap(subcommand)]
+    cmd: SubCommand,
+}
+
+#[derive(Subcommand, Debug)]
+enum SubCommand {
+    /// Runs two configurations side by side in this process.
+    Compare {
+        scenario: String,
+
+        #[clap(short, long, default_value_t = 0)]
+        seed: u32,
+
+        /// Team 0 code for the left simulation (.rs or .wasm). Defaults to the scenario solution.
+        #[clap(long)]
+        left_code: Option<String>,
+
+        /// Team 0 code for the right simulation (.rs or .wasm). Defaults to the scenario solution.
+        #[clap(long)]
+        right_code: Option<String>,
+
+        #[clap(long, value_parser = parse_backend, default_value = "wasmer")]
+        left_backend: Backend,
+
+        #[clap(long, value_parser = parse_backend, default_value = "wasmer")]
+        right_backend: Backend,
+
+        #[clap(long, default_value_t = scenario::MAX_TICKS)]
+        max_ticks: u32,
+    },
+    /// Records a hash trace so that it can be compared with one from another build.
+    Trace {
+        scenario: String,
+        output: String,
+
+        #[clap(short, long, default_value_t = 0)]
+        seed: u32,
+
+        /// Team 0 code (.rs or .wasm). Defaults to the scenario solution.
+        #[clap(long)]
+        code: Option<String>,
+
+        #[clap(long, value_parser = parse_backend, default_value = "wasmer")]
+        backend: Backend,
+
+        #[clap(long, default_value_t = scenario::MAX_TICKS)]
+        max_ticks: u32,
+
+        /// Also record the full state of each ship at this tick.
+        #[clap(long)]
+        ships_at: Option<u32>,
+    },
+    /// Compares two traces written by the trace subcommand.
+    Diff { left: String, right: String },
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+struct Trace {
+    scenario: String,
+    seed: u32,
+    ticks: Vec<TickHash>,
+    ships_tick: Option<u32>,
+    ships: BTreeMap<String, Vec<String>>,
+}
+
+fn main() -> anyhow::Result<()> {
+    env_logger::Builder::from_env(
+        env_logger::Env::default().default_filter_or("find_divergence=info"),
+    )
+    .init();
+
+    let args = Arguments::parse();
+    let diverged = match args.cmd {
+        SubCommand::Compare {
+            scenario,
+            seed,
+            left_code,
+            right_code,
+            left_backend,
+            right_backend,
+            max_ticks,
+        } => {
+            let mut left = create_simulation(&scenario, seed, left_code.as_deref(), left_backend)?;
+            let mut right =
+                create_simulation(&scenario, seed, right_code.as_deref(), right_backend)?;
+            compare(&mut left, &mut right, max_ticks)
+        }
+        SubCommand::Trace {
+            scenario,
+            output,
+            seed,
+            code,
+            backend,
+            max_ticks,
+            ships_at,
+        } => {
+            let mut sim = create_simulation(&scenario, seed, code.as_deref(), backend)?;
+            sim.enable_hash_trace();
+            let mut ships = BTreeMap::new();
+            loop {
+                if Some(sim.tick()) == ships_at {
+                    ships = describe_ships(&sim);
+                }
+                if sim.status() != scenario::Status::Running || sim.tick() >= max_ticks {
+                    break;
+                }
+                sim.step();
+            }
+            let trace = Trace {
+                scenario,
+                seed,
+                ticks: sim.hash_trace().unwrap().to_vec(),
+                ships_tick: ships_at,
+                ships,
+            };
+            std::fs::write(&output, serde_json::to_string(&trace)?)?;
+            log::info!("Wrote {} ticks to {:?}", trace.ticks.len(), output);
+            false
+        }
+        SubCommand::Diff { left, right } => {
+            let left: Trace = serde_json::from_str(&std::fs::read_to_string(left)?)?;
+            let right: Trace = serde_json::from_str(&std::fs::read_to_string(right)?)?;
+            diff_traces(&left, &right)
+        }
+    };
+
+    if diverged {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+fn parse_backend(s: &str) -> Result<Backend, String> {
+    match s {
+        "wasmer" => Ok(Backend::Wasmer),
+        "interpreter" => Ok(Backend::Interpreter),
+        _ => Err(format!("unknown backend {s:?}")),
+    }
+}
+
+fn create_simulation(
+    scenario_name: &str,
+    seed: u32,
+    code: Option<&str>,
+    backend: Backend,
+) -> anyhow::Result<Box<Simulation>> {
+    let mut codes = scenario::load(scenario_name).solution_codes();
+    if let Some(path) = code {
+        codes[0] = if path.ends_with(".wasm") {
+            Code::Wasm(std::fs::read(path)?)
+        } else {
+            log::info!("Compiling {:?}", path);
+            let src_code = std::fs::read_to_string(path)?;
+            Code::Wasm(oort_compiler::Compiler::new().compile(&src_code)?)
+        };
+    }
+    let sim = simulation::Simulation::new_with_backend(scenario_name, seed, &codes, backend);
+    if let Some(e) = sim.events().errors.first() {
+        anyhow::bail!("Failed to create simulation: {}", e.msg);
+    }
+    Ok(sim)
+}
+
+fn compare(left: &mut Simulation, right: &mut Simulation, max_ticks: u32) -> bool {
+    loop {
+        let left_hash = TickHash::compute(left);
+        let right_hash = TickHash::compute(right);
+        let components = left_hash.diff(&right_hash);
+        if !components.is_empty() {
+            println!(
+                "Diverged at tick {} in {}",
+                left.tick(),
+                components.join(", ")
+            );
+            print_ship_diff(&describe_ships(left), &describe_ships(right));
+            return true;
+        }
+
+        let running = |sim: &Simulation| sim.status() == scenario::Status::Running;
+        if left.tick() >= max_ticks || !running(left) || !running(right) {
+            if left.status() != right.status() {
+                println!(
+                    "Status differs at tick {}: {:?} vs {:?}",
+                    left.tick(),
+                    left.status(),
+                    right.status()
+                );
+                return true;
+            }
+            println!("No divergence in {} ticks", left.tick());
+            return false;
+        }
+
+        left.step();
+        right.step();
+    }
+}
+
+fn diff_traces(left: &Trace, right: &Trace) -> bool {
+    if (&left.scenario, left.seed) != (&right.scenario, right.seed) {
+        println!(
+            "Warning: comparing {} seed {} with {} seed {}",
+            left.scenario, left.seed, right.scenario, right.seed
+        );
+    }
+
+    for (a, b) in left.ticks.iter().zip(right.ticks.iter()) {
+        let components = a.diff(b);
+        if components.is_empty() {
+            continue;
+        }
+        println!("Diverged at tick {} in {}", a.tick, components.join(", "));
+        if left.ships_tick == Some(a.tick) && right.ships_tick == Some(a.tick) {
+            print_ship_diff(&left.ships, &right.ships);
+        } else {
+            println!(
+                "Rerun both traces with --ships-at {} for a field-level diff",
+                a.tick
+            );
+        }
+        return true;
+    }
+
+    if left.ticks.len() != right.ticks.len() {
+        println!(
+            "Traces have different lengths: {} vs {} ticks",
+            left.ticks.len(),
+            right.ticks.len()
+        );
+        return true;
+    }
+    println!("No divergence in {} ticks", left.ticks.len());
+    false
+}
+
+fn handle_name(handle: ShipHandle) -> String {
+    let (index, generation) = handle.0.into_raw_parts();
+    format!("{index}v{generation}")
+}
+
+fn describe_ships(sim: &Simulation) -> BTreeMap<String, Vec<String>> {
+    sim.ships
+        .iter()
+        .map(|&handle| (handle_name(handle), trace::describe_ship(sim, handle)))
+        .collect()
+}
+
+fn print_ship_diff(left: &BTreeMap<String, Vec<String>>, right: &BTreeMap<String, Vec<String>>) {
+    let names: std::collections::BTreeSet<&String> = left.keys().chain(right.keys()).collect();
+    for name in names {
+        match (left.get(name), right.get(name)) {
+            (Some(a), Some(b)) if a == b => {}
+            (Some(a), Some(b)) => {
+                println!("Ship {name}:");
+                for i in 0..a.len().max(b.len()) {
+                    let (x, y) = (a.get(i), b.get(i));
+                    if x != y {
+                        if let Some(x) = x {
+                            println!("- {x}");
+                        }
+                        if let Some(y) = y {
+                            println!("+ {y}");
+                        }
+                    }
+                }
+            }
+            (Some(_), None) => println!("Ship {name} only exists on the left"),
+            (None, Some(_)) => println!("Ship {name} only exists on the right"),
+            (None, None) => unreachable!(),
+        }
+    }
+}
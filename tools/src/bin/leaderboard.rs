@@ -1,21 +1,32 @@
 use chrono::prelude::*;
-use clap::{Parser, Subcommand};
+use clap::{Parser, Subcommand, ValueEnum};
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::Table;
-use firestore::*;
-use gcloud_sdk::google::firestore::v1::Document;
 use oort_proto::LeaderboardSubmission;
+use oort_storage::Storage;
 
 #[derive(Parser, Debug)]
 #[clap()]
 struct Arguments {
+    /// Firestore project ID, or local:PATH for an SQLite database
     #[clap(short, long, value_parser, default_value_t = String::from("oort-319301"))]
     project_id: String,
 
+    #[clap(short, long, value_enum, default_value_t = Board::Time)]
+    board: Board,
+
     #[clap(subcommand)]
     cmd: SubCommand,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Board {
+    /// Lowest time
+    Time,
+    /// Smallest code
+    CodeSize,
+}
+
 #[derive(Subcommand, Debug)]
 enum SubCommand {
     List {
@@ -41,62 +52,58 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         .init();
 
     let args = Arguments::parse();
+    let storage = oort_storage::open(&args.project_id).await?;
     match args.cmd {
-        SubCommand::List { scenario, limit } => cmd_list(&args.project_id, &scenario, limit).await,
+        SubCommand::List { scenario, limit } => {
+            cmd_list(storage.as_ref(), args.board, &scenario, limit).await
+        }
         SubCommand::Download {
             scenario,
             limit,
             out_dir,
-        } => cmd_download(&args.project_id, &scenario, limit, &out_dir).await,
-        SubCommand::Get { docid } => cmd_get(&args.project_id, docid).await,
+        } => cmd_download(storage.as_ref(), args.board, &scenario, limit, &out_dir).await,
+        SubCommand::Get { docid } => cmd_get(storage.as_ref(), args.board, docid).await,
+    }
+}
+
+async fn list(
+    storage: &dyn Storage,
+    board: Board,
+    scenario_name: &str,
+    limit: usize,
+) -> anyhow::Result<Vec<(String, LeaderboardSubmission)>> {
+    match board {
+        Board::Time => storage.list_leaderboard(scenario_name, Some(limit)).await,
+        Board::CodeSize => {
+            storage
+                .list_code_size_leaderboard(scenario_name, Some(limit))
+                .await
+        }
     }
 }
 
 async fn cmd_list(
-    project_id: &str,
+    storage: &dyn Storage,
+    board: Board,
     scenario_name: &str,
     limit: usize,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let db = FirestoreDb::new(project_id).await?;
-
-    let docs: Vec<Document> = db
-        .query_doc(
-            FirestoreQueryParams::new("leaderboard".into())
-                .with_filter(FirestoreQueryFilter::Composite(
-                    FirestoreQueryFilterComposite::new(vec![FirestoreQueryFilter::Compare(Some(
-                        FirestoreQueryFilterCompare::Equal(
-                            "scenario_name".into(),
-                            scenario_name.into(),
-                        ),
-                    ))]),
-                ))
-                .with_order_by(vec![
-                    FirestoreQueryOrder::new("time".to_owned(), FirestoreQueryDirection::Ascending),
-                    FirestoreQueryOrder::new(
-                        "timestamp".to_owned(),
-                        FirestoreQueryDirection::Ascending,
-                    ),
-                ])
-                .with_limit(limit as u32),
-        )
-        .await?;
+    let docs = list(storage, board, scenario_name, limit).await?;
 
     let mut table = Table::new();
     table.load_preset(UTF8_FULL);
-    table.set_header(vec!["Rank", "User", "Time", "Docid", "Created"]);
-
-    for (i, doc) in docs.iter().enumerate() {
-        let (_, docid) = doc.name.rsplit_once('/').unwrap();
-        if let Ok(msg) = FirestoreDb::deserialize_doc_to::<LeaderboardSubmission>(doc) {
-            let datetime: DateTime<Local> = DateTime::from(msg.timestamp);
-            table.add_row(vec![
-                format!("{}", i + 1),
-                msg.username.to_owned(),
-                format!("{:.2}s", msg.time),
-                docid.to_owned(),
-                datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
-            ]);
-        }
+    table.set_header(vec!["Rank", "User", "Time", "Size", "Docid", "Created"]);
+
+    for (i, (docid, msg)) in docs.iter().enumerate() {
+        let datetime: DateTime<Local> = DateTime::from(msg.timestamp);
+        table.add_row(vec![
+            format!("{}", i + 1),
+            msg.username.to_owned(),
+            format!("{:.2}s", msg.time),
+            format!("{}", msg.code_size),
+            docid.to_owned(),
+            datetime.format("%Y-%m-%d %H:%M:%S").to_string(),
+        ]);
     }
 
     println!("Scenario: {scenario_name}");
@@ -106,56 +113,34 @@ async fn cmd_list(
 }
 
 async fn cmd_download(
-    project_id: &str,
+    storage: &dyn Storage,
+    board: Board,
     scenario_name: &str,
     limit: usize,
     out_dir: &str,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let db = FirestoreDb::new(project_id).await?;
-
-    let docs: Vec<Document> = db
-        .query_doc(
-            FirestoreQueryParams::new("leaderboard".into())
-                .with_filter(FirestoreQueryFilter::Composite(
-                    FirestoreQueryFilterComposite::new(vec![FirestoreQueryFilter::Compare(Some(
-                        FirestoreQueryFilterCompare::Equal(
-                            "scenario_name".into(),
-                            scenario_name.into(),
-                        ),
-                    ))]),
-                ))
-                .with_order_by(vec![
-                    FirestoreQueryOrder::new("time".to_owned(), FirestoreQueryDirection::Ascending),
-                    FirestoreQueryOrder::new(
-                        "timestamp".to_owned(),
-                        FirestoreQueryDirection::Ascending,
-                    ),
-                ])
-                .with_limit(limit as u32),
-        )
-        .await?;
+    let docs = list(storage, board, scenario_name, limit).await?;
 
     std::fs::create_dir_all(out_dir).unwrap();
-    for doc in docs.iter() {
-        if let Ok(msg) = FirestoreDb::deserialize_doc_to::<LeaderboardSubmission>(doc) {
-            let filename = format!("{}/{}.rs", &out_dir, msg.username);
-            std::fs::write(&filename, &msg.code).unwrap();
-            println!("Wrote {filename}");
-        }
+    for (_, msg) in docs.iter() {
+        let filename = format!("{}/{}.rs", &out_dir, msg.username);
+        std::fs::write(&filename, &msg.code).unwrap();
+        println!("Wrote {filename}");
     }
 
     Ok(())
 }
 
 async fn cmd_get(
-    project_id: &str,
+    storage: &dyn Storage,
+    board: Board,
     docid: String,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let db = FirestoreDb::new(project_id).await?;
-    if let Ok(msg) = db
-        .get_obj::<LeaderboardSubmission>("leaderboard", &docid)
-        .await
-    {
+    let msg = match board {
+        Board::Time => storage.get_leaderboard(&docid).await?,
+        Board::CodeSize => storage.get_code_size_leaderboard(&docid).await?,
+    };
+    if let Some(msg) = msg {
         let datetime: DateTime<Local> = DateTime::from(msg.timestamp);
         println!("// User: {}", msg.username);
         println!("// Scenario: {}", msg.scenario_name);
@@ -163,8 +148,7 @@ async fn cmd_get(
         println!("// Time: {:.2}s Size: {}", msg.time, msg.code_size);
         println!("{}", msg.code.trim());
     } else {
-        let doc = db.get_doc_by_id("", "leaderboard", &docid).await?;
-        println!("Failed to parse {doc:?}");
+        println!("Document {docid} not found");
     }
 
     Ok(())
@@ -458,6 +458,21 @@ async fn main() -> anyhow::Result<()> {
                     progress.set_message("pushing image");
                     sync_cmd_ok(&["docker", "push", &container_image]).await?;
 
+                    let compiler_url = sync_cmd_ok(&[
+                        "gcloud",
+                        "--project",
+                        &project,
+                        "run",
+                        "services",
+                        "describe",
+                        "oort-compiler-service",
+                        "--format=value(status.url)",
+                    ])
+                    .await?
+                    .stdout_string()
+                    .trim()
+                    .to_string();
+
                     progress.set_message("deploying");
                     sync_cmd_ok(&[
                         "gcloud",
@@ -476,8 +491,10 @@ async fn main() -> anyhow::Result<()> {
                         "--timeout=20s",
                         "--concurrency=1",
                         "--max-instances=3",
+                        // Leaderboard verification continues after the response is sent.
+                        "--no-cpu-throttling",
                         &format!("--service-account=oort-backend-service@{project}.iam.gserviceaccount.com"),
-                        &format!("--set-env-vars=PROJECT_ID={project}"),
+                        &format!("--set-env-vars=PROJECT_ID={project},COMPILER_URL={compiler_url}"),
                     ])
                     .await?;
                 }
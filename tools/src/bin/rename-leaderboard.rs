@@ -1,11 +1,10 @@
 use clap::Parser;
-use firestore::*;
-use gcloud_sdk::google::firestore::v1::Document;
-use oort_proto::LeaderboardSubmission;
+use oort_storage::submission_docid;
 
 #[derive(Parser, Debug)]
 #[clap()]
 struct Arguments {
+    /// Firestore project ID, or local:PATH for an SQLite database
     #[clap(short, long, value_parser, default_value_t = String::from("oort-319301"))]
     project_id: String,
     src_scenario: String,
@@ -26,40 +25,13 @@ async fn run(
     src_scenario_name: &str,
     dst_scenario_name: &str,
 ) -> anyhow::Result<()> {
-    let db = FirestoreDb::new(project_id).await?;
+    let storage = oort_storage::open(project_id).await?;
 
-    let docs: Vec<Document> = db
-        .query_doc(
-            FirestoreQueryParams::new("leaderboard".into())
-                .with_filter(FirestoreQueryFilter::Composite(
-                    FirestoreQueryFilterComposite::new(vec![FirestoreQueryFilter::Compare(Some(
-                        FirestoreQueryFilterCompare::Equal(
-                            "scenario_name".into(),
-                            src_scenario_name.into(),
-                        ),
-                    ))]),
-                ))
-                .with_order_by(vec![
-                    FirestoreQueryOrder::new("time".to_owned(), FirestoreQueryDirection::Ascending),
-                    FirestoreQueryOrder::new(
-                        "timestamp".to_owned(),
-                        FirestoreQueryDirection::Ascending,
-                    ),
-                ]),
-        )
-        .await?;
-
-    for doc in &docs {
-        if let Ok(mut msg) = FirestoreDb::deserialize_doc_to::<LeaderboardSubmission>(doc) {
-            let (_, docid) = doc.name.rsplit_once('/').unwrap();
-            let (_, userid) = docid.rsplit_once('.').unwrap();
-            let new_docid = format!("{dst_scenario_name}.{userid}");
-            log::info!("copying {} to {}", docid, new_docid);
-            msg.scenario_name = dst_scenario_name.into();
-            db.update_obj("leaderboard", &new_docid, &msg, None).await?;
-        } else {
-            log::error!("Failed to deserialize doc {}", doc.name);
-        }
+    for (docid, mut msg) in storage.list_leaderboard(src_scenario_name, None).await? {
+        let new_docid = submission_docid(dst_scenario_name, &msg.userid);
+        log::info!("copying {} to {}", docid, new_docid);
+        msg.scenario_name = dst_scenario_name.into();
+        storage.put_leaderboard(&new_docid, &msg).await?;
     }
 
     Ok(())
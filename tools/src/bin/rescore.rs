@@ -1,8 +1,6 @@
 use clap::Parser;
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::Table;
-use firestore::*;
-use gcloud_sdk::google::firestore::v1::Document;
 use oort_proto::LeaderboardSubmission;
 use oort_simulator::{scenario, simulation};
 use rayon::prelude::*;
@@ -12,6 +10,7 @@ use std::collections::HashMap;
 #[derive(Parser, Debug)]
 #[clap()]
 struct Arguments {
+    /// Firestore project ID, or local:PATH for an SQLite database
     #[clap(long, value_parser, default_value_t = String::from("oort-319301"))]
     project_id: String,
 
@@ -25,6 +24,12 @@ struct Arguments {
     limit: usize,
 }
 
+#[derive(Clone, Copy, Debug)]
+enum Board {
+    Time,
+    CodeSize,
+}
+
 #[derive(Serialize, Deserialize)]
 struct JsonMap {
     #[serde(flatten)]
@@ -38,10 +43,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
 
     let args = Arguments::parse();
 
-    let db = FirestoreDb::new(&args.project_id).await?;
+    let storage = oort_storage::open(&args.project_id).await?;
     let mut compiler = oort_compiler::Compiler::new();
-    let mut updates: Vec<(String, LeaderboardSubmission, Option<LeaderboardSubmission>)> =
-        Vec::new();
+    let mut updates: Vec<(
+        Board,
+        String,
+        LeaderboardSubmission,
+        Option<LeaderboardSubmission>,
+    )> = Vec::new();
 
     let mut scenario_names = vec![];
     if let Some(scenario) = args.scenario.as_ref() {
@@ -56,80 +65,76 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     for scenario_name in &scenario_names {
         log::info!("Processing scenario {}", scenario_name);
 
-        let docs: Vec<Document> = db
-            .query_doc(
-                FirestoreQueryParams::new("leaderboard".into())
-                    .with_filter(FirestoreQueryFilter::Composite(
-                        FirestoreQueryFilterComposite::new(vec![FirestoreQueryFilter::Compare(
-                            Some(FirestoreQueryFilterCompare::Equal(
-                                "scenario_name".into(),
-                                scenario_name.into(),
-                            )),
-                        )]),
-                    ))
-                    .with_order_by(vec![
-                        FirestoreQueryOrder::new(
-                            "time".to_owned(),
-                            FirestoreQueryDirection::Ascending,
-                        ),
-                        FirestoreQueryOrder::new(
-                            "timestamp".to_owned(),
-                            FirestoreQueryDirection::Ascending,
-                        ),
-                    ])
-                    .with_limit(args.limit as u32),
-            )
-            .await?;
-
-        for doc in docs {
-            let docid = extract_docid(&doc.name);
-            if let Ok(msg) = FirestoreDb::deserialize_doc_to::<LeaderboardSubmission>(&doc) {
-                log::info!(
-                    "Running simulations for username={} scenario={} old_time={} docid={}",
-                    msg.username,
-                    msg.scenario_name,
-                    msg.time,
-                    docid
-                );
-
-                let wasm = match compiler.compile(&msg.code) {
-                    Ok(wasm) => wasm,
-                    Err(e) => {
-                        log::warn!(
-                            "Compilation failed for userid={} scenario_name={} docid={}: {}",
-                            msg.username,
-                            msg.scenario_name,
-                            docid,
-                            e
-                        );
-                        updates.push((doc.name.to_string(), msg.clone(), None));
-                        continue;
-                    }
-                };
-
-                log::info!("Successfully compiled to WASM");
-                let status = run_simulations(&msg.scenario_name, wasm);
-                match status {
-                    Some(new_time) => {
-                        if (msg.time - new_time).abs() >= 0.001 {
-                            log::info!("Updating time from {} to {}", msg.time, new_time);
-                            let mut new_msg = msg.clone();
-                            new_msg.time = new_time;
-                            updates.push((doc.name.to_string(), msg.clone(), Some(new_msg)));
-                        } else {
-                            log::info!("Time unchanged");
-                        }
-                    }
-                    None => {
-                        log::warn!(
-                            "Simulation failed for userid={} scenario_name={} docid={}",
-                            msg.username,
-                            msg.scenario_name,
-                            docid,
+        let mut docs: Vec<(Board, String, LeaderboardSubmission)> = vec![];
+        for (docid, msg) in storage
+            .list_leaderboard(scenario_name, Some(args.limit))
+            .await?
+        {
+            docs.push((Board::Time, docid, msg));
+        }
+        for (docid, msg) in storage
+            .list_code_size_leaderboard(scenario_name, Some(args.limit))
+            .await?
+        {
+            docs.push((Board::CodeSize, docid, msg));
+        }
+
+        for (board, docid, msg) in docs {
+            log::info!(
+                "Running simulations for board={:?} username={} scenario={} old_time={} old_code_size={} docid={}",
+                board,
+                msg.username,
+                msg.scenario_name,
+                msg.time,
+                msg.code_size,
+                docid
+            );
+
+            let wasm = match compiler.compile(&msg.code) {
+                Ok(wasm) => wasm,
+                Err(e) => {
+                    log::warn!(
+                        "Compilation failed for userid={} scenario_name={} docid={}: {}",
+                        msg.username,
+                        msg.scenario_name,
+                        docid,
+                        e
+                    );
+                    updates.push((board, docid.clone(), msg.clone(), None));
+                    continue;
+                }
+            };
+
+            log::info!("Successfully compiled to WASM");
+            let status = run_simulations(&msg.scenario_name, wasm);
+            match status {
+                Some(new_time) => {
+                    let new_code_size = oort_code_size::calculate(&msg.code);
+                    if (msg.time - new_time).abs() >= 0.001 || msg.code_size != new_code_size {
+                        log::info!(
+                            "Updating time from {} to {} and code size from {} to {}",
+                            msg.time,
+                            new_time,
+                            msg.code_size,
+                            new_code_size
                         );
-                        updates.push((doc.name.to_string(), msg.clone(), None));
+                        let mut new_msg = msg.clone();
+                        new_msg.time = new_time;
+                        new_msg.code_size = new_code_size;
+                        updates.push((board, docid.clone(), msg.clone(), Some(new_msg)));
+                    } else {
+                        log::info!("Time and code size unchanged");
                     }
                 }
+                None => {
+                    log::warn!(
+                        "Simulation failed for userid={} scenario_name={} docid={}",
+                        msg.username,
+                        msg.scenario_name,
+                        docid,
+                    );
+                    updates.push((board, docid.clone(), msg.clone(), None));
+                }
             }
         }
     }
@@ -137,14 +142,18 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     log::info!("Applying {} updates:", updates.len());
     let mut table = Table::new();
     table.load_preset(UTF8_FULL);
-    table.set_header(vec!["Scenario", "User", "Old Time", "New Time", "Docid"]);
-    for (docname, old_msg, new_msg) in &updates {
-        let docid = extract_docid(docname);
+    table.set_header(vec![
+        "Board", "Scenario", "User", "Old Time", "New Time", "Old Size", "New Size", "Docid",
+    ]);
+    for (board, docid, old_msg, new_msg) in &updates {
         table.add_row(vec![
+            format!("{board:?}"),
             old_msg.scenario_name.clone(),
             old_msg.username.clone(),
             format!("{:.2}", old_msg.time),
             format!("{:.2?}", new_msg.as_ref().map(|x| x.time)),
+            format!("{}", old_msg.code_size),
+            format!("{:?}", new_msg.as_ref().map(|x| x.code_size)),
             docid.clone(),
         ]);
     }
@@ -155,12 +164,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         return Ok(());
     }
 
-    for (docname, _old_msg, new_msg) in &updates {
-        let docid = extract_docid(docname);
-        if let Some(new_msg) = new_msg {
-            db.update_obj("leaderboard", &docid, new_msg, None).await?;
-        } else {
-            db.delete_by_id("leaderboard", &docid).await?;
+    for (board, docid, _old_msg, new_msg) in &updates {
+        match (board, new_msg) {
+            (Board::Time, Some(new_msg)) => storage.put_leaderboard(docid, new_msg).await?,
+            (Board::Time, None) => storage.delete_leaderboard(docid).await?,
+            (Board::CodeSize, Some(new_msg)) => {
+                storage.put_code_size_leaderboard(docid, new_msg).await?
+            }
+            (Board::CodeSize, None) => storage.delete_code_size_leaderboard(docid).await?,
         }
     }
 
@@ -192,8 +203,3 @@ fn run_simulation(scenario_name: &str, seed: u32, wasm: Vec<u8>) -> Option<f64>
         _ => None,
     }
 }
-
-fn extract_docid(docname: &str) -> String {
-    let (_, docid) = docname.rsplit_once('/').unwrap();
-    docid.to_string()
-}
@@ -2,16 +2,13 @@ use chrono::prelude::*;
 use clap::{Parser, Subcommand};
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::Table;
-use firestore::*;
-use gcloud_sdk::google::firestore::v1::Document;
 use oort_proto::{Telemetry, TelemetryMsg};
 use std::collections::HashMap;
 
-const COLLECTION_NAME: &str = "telemetry";
-
 #[derive(Parser, Debug)]
 #[clap()]
 struct Arguments {
+    /// Firestore project ID, or local:PATH for an SQLite database
     #[clap(short, long, value_parser, default_value_t = String::from("oort-319301"))]
     project_id: String,
 
@@ -55,58 +52,43 @@ async fn cmd_list(
     user_filter: Option<String>,
     limit: usize,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let db = FirestoreDb::new(project_id).await?;
+    let storage = oort_storage::open(project_id).await?;
 
-    let mut docs: Vec<Document> = db
-        .query_doc(
-            FirestoreQueryParams::new(COLLECTION_NAME.into())
-                .with_order_by(vec![FirestoreQueryOrder::new(
-                    "timestamp".to_owned(),
-                    FirestoreQueryDirection::Descending,
-                )])
-                .with_limit(limit as u32)
-                .clone(),
-        )
-        .await?;
-    docs.sort_by_key(|doc| doc.create_time.clone().map(|x| x.seconds).unwrap_or(0));
-    for doc in &docs {
-        if let Ok(msg) = FirestoreDb::deserialize_doc_to::<TelemetryMsg>(doc) {
-            let (_, docid) = doc.name.rsplit_once('/').unwrap();
-            let user = &msg.username;
-            if let Some(u) = user_filter.as_ref() {
-                if user != u {
-                    continue;
-                }
+    let mut docs = storage.list_telemetry(limit).await?;
+    docs.reverse();
+    for (docid, msg) in &docs {
+        let user = &msg.username;
+        if let Some(u) = user_filter.as_ref() {
+            if user != u {
+                continue;
             }
-            let datetime: DateTime<Local> = DateTime::from(msg.timestamp);
-            let prefix = format!("{docid} {}", datetime.format("%Y-%m-%d %H:%M:%S"));
-            match &msg.payload {
-                Telemetry::StartScenario { scenario_name, .. } => {
-                    println!("{prefix} StartScenario user={user} scenario={scenario_name}")
-                }
-                Telemetry::FinishScenario {
-                    scenario_name,
-                    success,
-                    time,
-                    ..
-                } => {
-                    let time = if *success {
-                        format!("{:.2}s", time.unwrap_or_default())
-                    } else {
-                        "failed".to_string()
-                    };
-                    println!(
-                        "{prefix} FinishScenario user={user} scenario={scenario_name} time={time}"
-                    );
-                }
-                Telemetry::Crash { .. } => println!("{prefix} Crash user={user}"),
-                Telemetry::SubmitToTournament { scenario_name, .. } => {
-                    println!("{prefix} SubmitToTournament user={user} scenario={scenario_name}")
-                }
-                Telemetry::Feedback { .. } => println!("{prefix} Feedback user={user}"),
+        }
+        let datetime: DateTime<Local> = DateTime::from(msg.timestamp);
+        let prefix = format!("{docid} {}", datetime.format("%Y-%m-%d %H:%M:%S"));
+        match &msg.payload {
+            Telemetry::StartScenario { scenario_name, .. } => {
+                println!("{prefix} StartScenario user={user} scenario={scenario_name}")
+            }
+            Telemetry::FinishScenario {
+                scenario_name,
+                success,
+                time,
+                ..
+            } => {
+                let time = if *success {
+                    format!("{:.2}s", time.unwrap_or_default())
+                } else {
+                    "failed".to_string()
+                };
+                println!(
+                    "{prefix} FinishScenario user={user} scenario={scenario_name} time={time}"
+                );
+            }
+            Telemetry::Crash { .. } => println!("{prefix} Crash user={user}"),
+            Telemetry::SubmitToTournament { scenario_name, .. } => {
+                println!("{prefix} SubmitToTournament user={user} scenario={scenario_name}")
             }
-        } else {
-            log::error!("Failed to deserialize doc {}", doc.name);
+            Telemetry::Feedback { .. } => println!("{prefix} Feedback user={user}"),
         }
     }
 
@@ -117,8 +99,8 @@ async fn cmd_get(
     project_id: &str,
     docid: String,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let db = FirestoreDb::new(project_id).await?;
-    if let Ok(msg) = db.get_obj::<TelemetryMsg>(COLLECTION_NAME, &docid).await {
+    let storage = oort_storage::open(project_id).await?;
+    if let Some(msg) = storage.get_telemetry(&docid).await? {
         let user = &msg.username;
         match msg.payload {
             Telemetry::StartScenario {
@@ -165,8 +147,7 @@ async fn cmd_get(
             }
         }
     } else {
-        let doc = db.get_doc_by_id("", COLLECTION_NAME, &docid).await?;
-        println!("Failed to parse {doc:?}");
+        println!("Failed to find {docid}");
     }
 
     Ok(())
@@ -177,60 +158,34 @@ async fn cmd_top(
     scenario: String,
     out_dir: Option<String>,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let db = FirestoreDb::new(project_id).await?;
-
-    let docs: Vec<Document> = db
-        .query_doc(
-            FirestoreQueryParams::new(COLLECTION_NAME.into()).with_filter(
-                FirestoreQueryFilter::Composite(FirestoreQueryFilterComposite::new(vec![
-                    FirestoreQueryFilter::Compare(Some(FirestoreQueryFilterCompare::Equal(
-                        "type".into(),
-                        "FinishScenario".into(),
-                    ))),
-                    FirestoreQueryFilter::Compare(Some(FirestoreQueryFilterCompare::Equal(
-                        "success".into(),
-                        true.into(),
-                    ))),
-                    FirestoreQueryFilter::Compare(Some(FirestoreQueryFilterCompare::Equal(
-                        "scenario_name".into(),
-                        scenario.clone().into(),
-                    ))),
-                ])),
-            ),
-        )
-        .await?;
+    let storage = oort_storage::open(project_id).await?;
+    let docs = storage.list_finished_telemetry(&scenario).await?;
 
     // userid -> (time, timestamp, docid, msg)
     let mut best_times: HashMap<String, (f64, DateTime<Utc>, String, TelemetryMsg)> =
         HashMap::new();
 
-    for doc in &docs {
-        if let Ok(msg) = FirestoreDb::deserialize_doc_to::<TelemetryMsg>(doc) {
-            let (_, docid) = doc.name.rsplit_once('/').unwrap();
-            match &msg.payload {
-                Telemetry::FinishScenario { time, .. } => {
-                    let insert = if let Some((ref old_time, _, _, _)) = best_times.get(&msg.userid)
-                    {
-                        *old_time > time.unwrap_or_default()
-                    } else {
-                        true
-                    };
-                    if insert {
-                        best_times.insert(
-                            msg.userid.clone(),
-                            (
-                                time.unwrap_or_default(),
-                                msg.timestamp,
-                                docid.to_owned(),
-                                msg.clone(),
-                            ),
-                        );
-                    }
+    for (docid, msg) in &docs {
+        match &msg.payload {
+            Telemetry::FinishScenario { time, .. } => {
+                let insert = if let Some((ref old_time, _, _, _)) = best_times.get(&msg.userid) {
+                    *old_time > time.unwrap_or_default()
+                } else {
+                    true
+                };
+                if insert {
+                    best_times.insert(
+                        msg.userid.clone(),
+                        (
+                            time.unwrap_or_default(),
+                            msg.timestamp,
+                            docid.to_owned(),
+                            msg.clone(),
+                        ),
+                    );
                 }
-                _ => unreachable!(),
             }
-        } else {
-            log::error!("Failed to deserialize doc {}", doc.name);
+            _ => unreachable!(),
         }
     }
 
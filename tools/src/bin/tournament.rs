@@ -1,24 +1,33 @@
 use chrono::Utc;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use comfy_table::presets::UTF8_FULL;
 use comfy_table::Table;
-use firestore::*;
-use itertools::Itertools;
-use oort_proto::{ShortcodeUpload, TournamentCompetitor, TournamentResults, TournamentSubmission};
+use oort_proto::{
+    ShortcodeUpload, TournamentCompetitor, TournamentFailure, TournamentResults,
+    TournamentScenarioResults, TournamentSubmission,
+};
+use oort_simulator::simulation::Code;
 use oort_simulator::{scenario, simulation};
-use oort_tools::AI;
+use oort_storage::Storage;
+use oort_tools::{isolate, parse_pool, PoolEntry, AI};
 use rand::Rng;
 use rayon::prelude::*;
 use skillratings::{
     glicko2::{glicko2, Glicko2Config, Glicko2Rating},
     Outcomes,
 };
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::default::Default;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Mutex;
+use std::time::Duration;
+use tempdir::TempDir;
 
 #[derive(Parser, Debug)]
 #[clap()]
 struct Arguments {
+    /// Firestore project ID, or local:PATH for an SQLite database
     #[clap(short, long, value_parser, default_value_t = String::from("oort-319301"))]
     project_id: String,
 
@@ -29,29 +38,103 @@ struct Arguments {
 #[derive(Subcommand, Debug)]
 enum SubCommand {
     Run {
+        /// Scenario or pool of scenarios, e.g. "fighter_duel,frigate_duel:2,belt".
+        /// Entrants are taken from submissions to the first scenario.
         scenario: String,
         usernames: Vec<String>,
 
-        #[clap(short, long)]
-        rounds: i32,
+        #[clap(flatten)]
+        options: TournamentOptions,
 
         #[clap(short, long)]
         dry_run: bool,
     },
     RunUnofficial {
+        /// Scenario or pool of scenarios, e.g. "fighter_duel,frigate_duel:2,belt".
         scenario: String,
         shortcodes: Vec<String>,
 
-        #[clap(short, long)]
-        rounds: i32,
+        #[clap(flatten)]
+        options: TournamentOptions,
 
         #[clap(short, long)]
         dev: bool,
     },
+    /// Runs a tournament between the .rs and .wasm files in a directory
+    /// without any network access.
+    RunLocal {
+        /// Scenario or pool of scenarios, e.g. "fighter_duel,frigate_duel:2,belt".
+        scenario: String,
+        dir: String,
+
+        #[clap(flatten)]
+        options: TournamentOptions,
+
+        /// Directory to write results.json and results.html to.
+        #[clap(short, long, default_value = ".")]
+        out_dir: String,
+    },
     Fetch {
         scenario: String,
         out_dir: String,
     },
+    /// Plays a single game and prints "win", "loss" or "draw" for team 0.
+    /// Used by --isolate to run each game in a child process.
+    #[clap(hide = true)]
+    PlayGame {
+        scenario: String,
+        seed: u32,
+        /// JSON-serialized compiled code for each team.
+        codes: Vec<String>,
+    },
+}
+
+#[derive(Args, Debug, Clone)]
+struct TournamentOptions {
+    /// Games on each side per scenario in every match.
+    #[clap(short, long, value_parser = clap::value_parser!(i32).range(1..))]
+    rounds: i32,
+
+    #[clap(short, long, value_enum, default_value_t = Format::RoundRobin)]
+    format: Format,
+
+    /// Number of Swiss rounds. Defaults to log2 of the number of entrants, rounded up.
+    #[clap(long)]
+    swiss_rounds: Option<usize>,
+
+    /// Run each game in a child process with time and memory limits. Games
+    /// that crash or exceed a limit are scored as forfeits.
+    #[clap(long)]
+    isolate: bool,
+
+    /// Wall-clock and CPU time limit for each game with --isolate.
+    #[clap(long, default_value_t = 600)]
+    game_timeout_secs: u64,
+
+    /// Address space limit for each game with --isolate. The Wasmer backend
+    /// reserves about 6 GB for each AI up front, so this needs to be well
+    /// above the memory actually used.
+    #[clap(long, default_value_t = 16384)]
+    game_memory_limit_mb: u64,
+
+    /// Number of times to retry a game with --isolate if the child process
+    /// couldn't be run.
+    #[clap(long, default_value_t = 2)]
+    retries: usize,
+
+    /// Number of games to play in parallel. Defaults to the number of CPUs.
+    #[clap(long)]
+    workers: Option<usize>,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// Every entrant plays every other entrant.
+    RoundRobin,
+    /// Entrants with similar scores are paired each round, without rematches.
+    Swiss,
+    /// Entrants are knocked out after losing two matches.
+    DoubleElimination,
 }
 
 #[derive(Debug, Clone)]
@@ -70,33 +153,53 @@ async fn main() -> anyhow::Result<()> {
         SubCommand::Run {
             scenario,
             usernames,
-            rounds,
+            options,
             dry_run,
-        } => cmd_run(&args.project_id, &scenario, &usernames, rounds, dry_run).await,
+        } => {
+            cmd_run(
+                &args.project_id,
+                &parse_pool(&scenario)?,
+                &usernames,
+                &options,
+                dry_run,
+            )
+            .await
+        }
         SubCommand::RunUnofficial {
             scenario,
             shortcodes,
-            rounds,
+            options,
             dev,
-        } => cmd_run_unofficial(&scenario, &shortcodes, rounds, dev).await,
+        } => cmd_run_unofficial(&parse_pool(&scenario)?, &shortcodes, &options, dev).await,
+        SubCommand::RunLocal {
+            scenario,
+            dir,
+            options,
+            out_dir,
+        } => cmd_run_local(&parse_pool(&scenario)?, &dir, &options, &out_dir),
         SubCommand::Fetch { scenario, out_dir } => {
             cmd_fetch(&args.project_id, &scenario, &out_dir).await
         }
+        SubCommand::PlayGame {
+            scenario,
+            seed,
+            codes,
+        } => cmd_play_game(&scenario, seed, &codes),
     }
 }
 
 async fn cmd_run(
     project_id: &str,
-    scenario_name: &str,
+    pool: &[PoolEntry],
     usernames: &[String],
-    rounds: i32,
+    options: &TournamentOptions,
     dry_run: bool,
 ) -> anyhow::Result<()> {
-    let db = FirestoreDb::new(project_id).await?;
-    scenario::load_safe(scenario_name).expect("Unknown scenario");
+    let storage = oort_storage::open(project_id).await?;
+    let scenario_name = &pool[0].scenario_name;
 
     let mut compiler = oort_compiler::Compiler::new();
-    let entrants = get_entrants(&db, scenario_name, usernames).await?;
+    let entrants = get_entrants(storage.as_ref(), scenario_name, usernames).await?;
     let results: Vec<anyhow::Result<AI>> = entrants
         .iter()
         .map(|entrant| {
@@ -113,120 +216,769 @@ async fn cmd_run(
     let ais: Vec<AI> = results.into_iter().collect::<anyhow::Result<Vec<AI>>>()?;
 
     log::info!("Running tournament");
-    let results = run_tournament(scenario_name, &ais, rounds);
+    let results = run_tournament(pool, &ais, options)?;
 
     display_results(&results);
 
     if !dry_run {
-        upload_results(&db, project_id, &entrants, &results).await?;
+        upload_results(storage.as_ref(), project_id, &entrants, &results).await?;
     }
 
     Ok(())
 }
 
 async fn cmd_run_unofficial(
-    scenario_name: &str,
+    pool: &[PoolEntry],
     shortcodes: &[String],
-    rounds: i32,
+    options: &TournamentOptions,
     dev: bool,
 ) -> anyhow::Result<()> {
-    scenario::load_safe(scenario_name).expect("Unknown scenario");
-
     let http = reqwest::Client::new();
     let ais = oort_tools::fetch_and_compile_multiple(&http, shortcodes, dev).await?;
 
     log::info!("Running tournament");
-    let results = run_tournament(scenario_name, &ais, rounds);
+    let results = run_tournament(pool, &ais, options)?;
 
     display_results(&results);
 
     Ok(())
 }
 
-fn run_tournament(scenario_name: &str, ais: &[AI], rounds: i32) -> TournamentResults {
-    let mut pairings: HashMap<(String, String), f64> = HashMap::new();
-    let config = Glicko2Config::new();
-    let mut ratings: Vec<Glicko2Rating> = Vec::new();
-    ratings.resize_with(ais.len(), Default::default);
-    let pairs: Vec<(i32, Vec<_>)> = (0..rounds)
-        .flat_map(|round| (0..(ais.len())).permutations(2).map(move |x| (round, x)))
+fn cmd_run_local(
+    pool: &[PoolEntry],
+    dir: &str,
+    options: &TournamentOptions,
+    out_dir: &str,
+) -> anyhow::Result<()> {
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|x| x.path()))
+        .collect::<Result<_, _>>()?;
+    paths.retain(|path| {
+        matches!(
+            path.extension().and_then(|x| x.to_str()),
+            Some("rs" | "wasm")
+        )
+    });
+    paths.sort();
+    if paths.len() < 2 {
+        anyhow::bail!("Expected at least two .rs or .wasm files in {dir}");
+    }
+
+    let mut compiler = oort_compiler::Compiler::new();
+    let ais: Vec<AI> = paths
+        .iter()
+        .map(|path| oort_tools::compile_local(&mut compiler, path))
+        .collect::<anyhow::Result<_>>()?;
+
+    let mut filenames: HashMap<String, String> = HashMap::new();
+    for (path, ai) in paths.iter().zip(ais.iter()) {
+        let filename = path.file_name().unwrap().to_string_lossy().to_string();
+        if let Some(other) = filenames.insert(ai.name.clone(), filename.clone()) {
+            anyhow::bail!("{other} and {filename} have the same name");
+        }
+    }
+
+    log::info!("Running tournament");
+    let mut results = run_tournament(pool, &ais, options)?;
+    // There are no shortcodes offline, so point at the source file instead.
+    for competitor in results.competitors.iter_mut().chain(
+        results
+            .scenarios
+            .iter_mut()
+            .flat_map(|x| x.results.competitors.iter_mut()),
+    ) {
+        competitor.shortcode = filenames[&competitor.username].clone();
+    }
+
+    display_results(&results);
+
+    std::fs::create_dir_all(out_dir)?;
+    let json_path = Path::new(out_dir).join("results.json");
+    std::fs::write(&json_path, serde_json::to_string_pretty(&results)?)?;
+    println!("Wrote {}", json_path.display());
+    let html_path = Path::new(out_dir).join("results.html");
+    std::fs::write(&html_path, render_html(&results))?;
+    println!("Wrote {}", html_path.display());
+
+    Ok(())
+}
+
+fn run_tournament(
+    pool: &[PoolEntry],
+    ais: &[AI],
+    options: &TournamentOptions,
+) -> anyhow::Result<TournamentResults> {
+    if ais.len() < 2 {
+        anyhow::bail!("Expected at least two entrants");
+    }
+    // Zero threads means one per CPU.
+    let thread_pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(options.workers.unwrap_or(0))
+        .build()?;
+    let runner = Runner::new(ais, options)?;
+    let (games, standings) = thread_pool.install(|| match options.format {
+        Format::RoundRobin => {
+            let pairs: Vec<(usize, usize)> = (0..ais.len())
+                .flat_map(|i| ((i + 1)..ais.len()).map(move |j| (i, j)))
+                .collect();
+            let games: Vec<(Game, Outcomes)> =
+                play_matches(pool, &runner, options.rounds, 0, &pairs)
+                    .into_iter()
+                    .flatten()
+                    .collect();
+            (games, None)
+        }
+        Format::Swiss => {
+            let (games, points) = run_swiss(pool, ais, &runner, options);
+            (games, Some(points))
+        }
+        Format::DoubleElimination => {
+            let (games, placements) = run_double_elimination(pool, ais, &runner, options);
+            (games, Some(placements))
+        }
+    });
+    let mut results = summarize(pool, ais, &games, standings);
+    results.failures = runner.failures.into_inner().unwrap();
+    Ok(results)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Game {
+    /// Index into the scenario pool.
+    scenario: usize,
+    seed: u32,
+    /// Entrants controlling team 0 and team 1.
+    players: [usize; 2],
+}
+
+/// Plays each pair on every scenario in the pool, `rounds` times on each side.
+///
+/// Returns the games of each match in the same order as `pairs`.
+fn play_matches(
+    pool: &[PoolEntry],
+    runner: &dyn Play,
+    rounds: i32,
+    seed_base: u32,
+    pairs: &[(usize, usize)],
+) -> Vec<Vec<(Game, Outcomes)>> {
+    let games: Vec<Game> = pairs
+        .iter()
+        .flat_map(|&(a, b)| {
+            (0..pool.len()).flat_map(move |scenario| {
+                (0..rounds).flat_map(move |round| {
+                    let seed = seed_base + round as u32;
+                    [[a, b], [b, a]].map(|players| Game {
+                        scenario,
+                        seed,
+                        players,
+                    })
+                })
+            })
+        })
         .collect();
-    let outcomes: Vec<(i32, Vec<_>, Outcomes)> = pairs
+    let outcomes: Vec<(Game, Outcomes)> = games
         .par_iter()
-        .map(|(round, indices)| {
-            let seed = *round as u32;
-            let ai0: &AI = &ais[indices[0]];
-            let ai1: &AI = &ais[indices[1]];
-            (
-                *round,
-                indices.clone(),
-                run_simulation(scenario_name, seed, &[ai0, ai1]),
-            )
-        })
+        .map(|game| (*game, runner.play(&pool[game.scenario].scenario_name, game)))
         .collect();
+    let games_per_match = (pool.len() * rounds as usize * 2).max(1);
+    outcomes
+        .chunks(games_per_match)
+        .map(|x| x.to_vec())
+        .collect()
+}
+
+/// Returns the weighted fraction of points won by `player` in a match.
+fn match_score(pool: &[PoolEntry], games: &[(Game, Outcomes)], player: usize) -> f64 {
+    let mut points = 0.0;
+    let mut total = 0.0;
+    for (game, outcome) in games {
+        let weight = pool[game.scenario].weight;
+        let team0_points = match outcome {
+            Outcomes::WIN => 1.0,
+            Outcomes::LOSS => 0.0,
+            Outcomes::DRAW => 0.5,
+        };
+        points += weight
+            * if game.players[0] == player {
+                team0_points
+            } else {
+                1.0 - team0_points
+            };
+        total += weight;
+    }
+    if total > 0.0 {
+        points / total
+    } else {
+        0.5
+    }
+}
+
+fn run_swiss(
+    pool: &[PoolEntry],
+    ais: &[AI],
+    runner: &dyn Play,
+    options: &TournamentOptions,
+) -> (Vec<(Game, Outcomes)>, Vec<f64>) {
+    let n = ais.len();
+    let num_rounds = options
+        .swiss_rounds
+        .unwrap_or_else(|| (n as f64).log2().ceil() as usize)
+        .max(1);
+    let mut points: Vec<f64> = vec![0.0; n];
+    let mut had_bye = vec![false; n];
+    let mut played: HashSet<(usize, usize)> = HashSet::new();
+    let mut all_games = vec![];
+
+    for round in 0..num_rounds {
+        let mut order: Vec<usize> = (0..n).collect();
+        order.sort_by(|&a, &b| points[b].total_cmp(&points[a]).then(a.cmp(&b)));
+
+        if n % 2 == 1 {
+            let bye = order
+                .iter()
+                .rev()
+                .copied()
+                .find(|&i| !had_bye[i])
+                .unwrap_or(order[n - 1]);
+            log::info!("Swiss round {}: {} has a bye", round + 1, ais[bye].name);
+            had_bye[bye] = true;
+            points[bye] += 1.0;
+            order.retain(|&i| i != bye);
+        }
+
+        let pairs = swiss_pairs(&order, &played);
+        for &(a, b) in &pairs {
+            played.insert((a.min(b), a.max(b)));
+        }
+
+        let results = play_matches(pool, runner, options.rounds, 0, &pairs);
+        for (&(a, b), games) in pairs.iter().zip(results) {
+            let score = match_score(pool, &games, a);
+            let a_points = if score > 0.5 {
+                1.0
+            } else if score < 0.5 {
+                0.0
+            } else {
+                0.5
+            };
+            log::info!(
+                "Swiss round {}: {} vs {}: {}",
+                round + 1,
+                ais[a].name,
+                ais[b].name,
+                a_points
+            );
+            points[a] += a_points;
+            points[b] += 1.0 - a_points;
+            all_games.extend(games);
+        }
+    }
+
+    (all_games, points)
+}
+
+/// Largest number of partial pairings to try before allowing rematches.
+const MAX_PAIRING_STEPS: usize = 100_000;
+
+/// Pairs each player, in ranking order, with the highest-ranked remaining
+/// player they haven't met yet. Backtracks when that would force a rematch
+/// later, and falls back to pairing with rematches if none can be avoided.
+fn swiss_pairs(order: &[usize], played: &HashSet<(usize, usize)>) -> Vec<(usize, usize)> {
+    fn search(
+        remaining: &mut Vec<usize>,
+        played: &HashSet<(usize, usize)>,
+        pairs: &mut Vec<(usize, usize)>,
+        steps: &mut usize,
+    ) -> bool {
+        if remaining.is_empty() {
+            return true;
+        }
+        *steps += 1;
+        if *steps > MAX_PAIRING_STEPS {
+            return false;
+        }
+        let a = remaining.remove(0);
+        for k in 0..remaining.len() {
+            let b = remaining[k];
+            if played.contains(&(a.min(b), a.max(b))) {
+                continue;
+            }
+            remaining.remove(k);
+            pairs.push((a, b));
+            if search(remaining, played, pairs, steps) {
+                return true;
+            }
+            pairs.pop();
+            remaining.insert(k, b);
+        }
+        remaining.insert(0, a);
+        false
+    }
+
+    let mut pairs = vec![];
+    if search(&mut order.to_vec(), played, &mut pairs, &mut 0) {
+        return pairs;
+    }
 
-    for (round, indices, outcome) in outcomes {
-        let i0 = indices[0];
-        let i1 = indices[1];
+    let mut order = order.to_vec();
+    let mut pairs = vec![];
+    while !order.is_empty() {
+        let a = order.remove(0);
+        let k = order
+            .iter()
+            .position(|&b| !played.contains(&(a.min(b), a.max(b))))
+            .unwrap_or(0);
+        pairs.push((a, order.remove(k)));
+    }
+    pairs
+}
+
+/// Pairs the first entrant with the last, the second with the second to last,
+/// and so on. The middle entrant gets a bye if there is an odd number.
+fn bracket_pairs(entrants: &[usize]) -> (Vec<(usize, usize)>, Option<usize>) {
+    let n = entrants.len();
+    let pairs = (0..n / 2)
+        .map(|k| (entrants[k], entrants[n - 1 - k]))
+        .collect();
+    let bye = (n % 2 == 1).then(|| entrants[n / 2]);
+    (pairs, bye)
+}
+
+/// Returns the winner and loser of a match. Ties go to the first player.
+fn decide(pool: &[PoolEntry], games: &[(Game, Outcomes)], a: usize, b: usize) -> (usize, usize) {
+    if match_score(pool, games, a) >= 0.5 {
+        (a, b)
+    } else {
+        (b, a)
+    }
+}
+
+/// Returns all games played and a placement score for each entrant, higher
+/// being better.
+fn run_double_elimination(
+    pool: &[PoolEntry],
+    ais: &[AI],
+    runner: &dyn Play,
+    options: &TournamentOptions,
+) -> (Vec<(Game, Outcomes)>, Vec<f64>) {
+    let n = ais.len();
+    let mut all_games = vec![];
+    let mut winners: Vec<usize> = (0..n).collect();
+    let mut losers: Vec<usize> = vec![];
+    let mut eliminated: Vec<usize> = vec![];
+
+    // Winners and losers bracket matches in the same round are played together.
+    while winners.len() > 1 || losers.len() > 1 {
+        let (winner_pairs, winner_bye) = bracket_pairs(&winners);
+        let (loser_pairs, loser_bye) = bracket_pairs(&losers);
+        let pairs: Vec<(usize, usize)> = winner_pairs
+            .iter()
+            .chain(loser_pairs.iter())
+            .copied()
+            .collect();
+        let results = play_matches(pool, runner, options.rounds, 0, &pairs);
+
+        let mut next_winners: Vec<usize> = winner_bye.into_iter().collect();
+        let mut next_losers: Vec<usize> = loser_bye.into_iter().collect();
+        let mut dropped = vec![];
+        for (k, (&(a, b), games)) in pairs.iter().zip(results).enumerate() {
+            let (winner, loser) = decide(pool, &games, a, b);
+            all_games.extend(games);
+            if k < winner_pairs.len() {
+                log::info!(
+                    "Winners bracket: {} beat {}",
+                    ais[winner].name,
+                    ais[loser].name
+                );
+                next_winners.push(winner);
+                dropped.push(loser);
+            } else {
+                log::info!(
+                    "Losers bracket: {} beat {}, who is eliminated",
+                    ais[winner].name,
+                    ais[loser].name
+                );
+                next_losers.push(winner);
+                eliminated.push(loser);
+            }
+        }
+        next_losers.extend(dropped);
+        winners = next_winners;
+        losers = next_losers;
+    }
+
+    // Grand final. The losers bracket champion has to win twice, so a reset
+    // match is played on different seeds if they win the first.
+    let (mut champion, mut runner_up) = (winners[0], losers[0]);
+    let games = play_matches(pool, runner, options.rounds, 0, &[(champion, runner_up)]).remove(0);
+    let (winner, loser) = decide(pool, &games, champion, runner_up);
+    all_games.extend(games);
+    if winner != champion {
+        log::info!("Grand final: {} forces a reset", ais[winner].name);
+        let seed_base = options.rounds.max(0) as u32;
+        let games = play_matches(
+            pool,
+            runner,
+            options.rounds,
+            seed_base,
+            &[(champion, runner_up)],
+        )
+        .remove(0);
+        (champion, runner_up) = decide(pool, &games, champion, runner_up);
+        all_games.extend(games);
+    } else {
+        (champion, runner_up) = (winner, loser);
+    }
+    log::info!(
+        "Grand final: {} beat {}",
+        ais[champion].name,
+        ais[runner_up].name
+    );
+
+    let mut placements = vec![0.0; n];
+    let order = [champion, runner_up]
+        .into_iter()
+        .chain(eliminated.into_iter().rev());
+    for (place, i) in order.enumerate() {
+        placements[i] = (n - place) as f64;
+    }
+    (all_games, placements)
+}
+
+/// Computes per-scenario Glicko-2 ratings and win matrices, and combines them
+/// using the scenario weights.
+///
+/// Competitors are ranked by `standings` if given (Swiss points or bracket
+/// placement) and then by combined rating.
+fn summarize(
+    pool: &[PoolEntry],
+    ais: &[AI],
+    games: &[(Game, Outcomes)],
+    standings: Option<Vec<f64>>,
+) -> TournamentResults {
+    let n = ais.len();
+    let config = Glicko2Config::new();
+    let mut ratings: Vec<Vec<Glicko2Rating>> = vec![vec![Default::default(); n]; pool.len()];
+    let mut wins: Vec<Vec<f64>> = vec![vec![0.0; n * n]; pool.len()];
+    let mut counts: Vec<Vec<f64>> = vec![vec![0.0; n * n]; pool.len()];
+
+    for (game, outcome) in games {
+        let s = game.scenario;
+        let [i0, i1] = game.players;
         log::debug!(
-            "{} vs {} seed {}: {:?}",
+            "{} vs {} on {} seed {}: {:?}",
             ais[i0].name,
             ais[i1].name,
-            round,
+            pool[s].scenario_name,
+            game.seed,
             outcome
         );
-        let (r0, r1) = glicko2(&ratings[i0], &ratings[i1], &outcome, &config);
-        ratings[i0] = r0;
-        ratings[i1] = r1;
-
-        let increment = 1.0 / (2.0 * rounds as f64);
-        if outcome == Outcomes::WIN {
-            *pairings
-                .entry((ais[i0].name.clone(), ais[i1].name.clone()))
-                .or_default() += increment;
-        } else if outcome == Outcomes::LOSS {
-            *pairings
-                .entry((ais[i1].name.clone(), ais[i0].name.clone()))
-                .or_default() += increment;
+        let (r0, r1) = glicko2(&ratings[s][i0], &ratings[s][i1], outcome, &config);
+        ratings[s][i0] = r0;
+        ratings[s][i1] = r1;
+
+        counts[s][i0 * n + i1] += 1.0;
+        counts[s][i1 * n + i0] += 1.0;
+        if *outcome == Outcomes::WIN {
+            wins[s][i0 * n + i1] += 1.0;
+        } else if *outcome == Outcomes::LOSS {
+            wins[s][i1 * n + i0] += 1.0;
         }
     }
 
-    let mut competitors: Vec<_> = ais
-        .iter()
-        .enumerate()
-        .map(|(i, x)| TournamentCompetitor {
-            username: x.name.clone(),
-            shortcode: "".to_string(),
-            rating: ratings[i].rating,
+    let total_weight: f64 = pool.iter().map(|x| x.weight).sum();
+    let combined_ratings: Vec<f64> = (0..n)
+        .map(|i| {
+            pool.iter()
+                .enumerate()
+                .map(|(s, entry)| entry.weight * ratings[s][i].rating)
+                .sum::<f64>()
+                / total_weight
         })
         .collect();
-    competitors.sort_by_key(|c| (-c.rating * 1e6) as i64);
-
-    let mut win_matrix: Vec<f64> = vec![];
-    for competitor in &competitors {
-        for other_competitor in &competitors {
-            win_matrix.push(
-                pairings
-                    .get(&(
-                        competitor.username.clone(),
-                        other_competitor.username.clone(),
-                    ))
-                    .copied()
-                    .unwrap_or_default(),
+    let fraction = |wins: f64, count: f64| if count > 0.0 { wins / count } else { 0.0 };
+    let combined_win_matrix: Vec<f64> = (0..n * n)
+        .map(|k| {
+            let weighted = |x: &[Vec<f64>]| -> f64 {
+                pool.iter()
+                    .enumerate()
+                    .map(|(s, entry)| entry.weight * x[s][k])
+                    .sum()
+            };
+            fraction(weighted(&wins), weighted(&counts))
+        })
+        .collect();
+
+    let mut order: Vec<usize> = (0..n).collect();
+    order.sort_by(|&a, &b| {
+        let by_standings = match standings.as_ref() {
+            Some(standings) => standings[b].total_cmp(&standings[a]),
+            None => std::cmp::Ordering::Equal,
+        };
+        by_standings.then(combined_ratings[b].total_cmp(&combined_ratings[a]))
+    });
+
+    let make_results =
+        |scenario_name: &str, ratings: &[f64], win_matrix: &[f64]| TournamentResults {
+            scenario_name: scenario_name.to_string(),
+            competitors: order
+                .iter()
+                .map(|&i| TournamentCompetitor {
+                    username: ais[i].name.clone(),
+                    shortcode: "".to_string(),
+                    rating: ratings[i],
+                })
+                .collect(),
+            win_matrix: order
+                .iter()
+                .flat_map(|&i| order.iter().map(move |&j| win_matrix[i * n + j]))
+                .collect(),
+            scenarios: vec![],
+            failures: vec![],
+        };
+
+    let mut results = make_results(
+        &pool[0].scenario_name,
+        &combined_ratings,
+        &combined_win_matrix,
+    );
+    if pool.len() > 1 {
+        results.scenarios = pool
+            .iter()
+            .enumerate()
+            .map(|(s, entry)| {
+                let scenario_ratings: Vec<f64> = ratings[s].iter().map(|x| x.rating).collect();
+                let win_matrix: Vec<f64> = (0..n * n)
+                    .map(|k| fraction(wins[s][k], counts[s][k]))
+                    .collect();
+                TournamentScenarioResults {
+                    weight: entry.weight,
+                    results: make_results(&entry.scenario_name, &scenario_ratings, &win_matrix),
+                }
+            })
+            .collect();
+    }
+    results
+}
+
+/// Plays a single game and returns the outcome for team 0.
+trait Play: Sync {
+    fn play(&self, scenario_name: &str, game: &Game) -> Outcomes;
+}
+
+/// Plays games in this process or, with --isolate, in child processes.
+struct Runner<'a> {
+    ais: &'a [AI],
+    isolation: Option<Isolation>,
+    failures: Mutex<Vec<TournamentFailure>>,
+}
+
+struct Isolation {
+    /// Holds the compiled code of each entrant for child processes to load.
+    dir: TempDir,
+    limits: isolate::Limits,
+    retries: usize,
+}
+
+impl<'a> Runner<'a> {
+    fn new(ais: &'a [AI], options: &TournamentOptions) -> anyhow::Result<Self> {
+        let isolation = if options.isolate {
+            let dir = TempDir::new("tournament")?;
+            for (i, ai) in ais.iter().enumerate() {
+                std::fs::write(
+                    dir.path().join(format!("{i}.json")),
+                    serde_json::to_vec(&ai.compiled_code)?,
+                )?;
+            }
+            Some(Isolation {
+                dir,
+                limits: isolate::Limits {
+                    timeout: Duration::from_secs(options.game_timeout_secs),
+                    cpu_limit: Duration::from_secs(options.game_timeout_secs),
+                    memory_limit_bytes: options.game_memory_limit_mb << 20,
+                },
+                retries: options.retries,
+            })
+        } else {
+            None
+        };
+        Ok(Self {
+            ais,
+            isolation,
+            failures: Mutex::new(vec![]),
+        })
+    }
+
+    /// Runs a game in a child process, retrying infrastructure failures with
+    /// exponential backoff.
+    fn run_isolated(
+        &self,
+        isolation: &Isolation,
+        scenario_name: &str,
+        seed: u32,
+        paths: &[PathBuf],
+    ) -> Result<Outcomes, isolate::Error> {
+        let exe = std::env::current_exe()
+            .map_err(|e| isolate::Error::Infrastructure(format!("current_exe: {e}")))?;
+        let mut attempt = 0;
+        loop {
+            let mut cmd = Command::new(&exe);
+            cmd.arg("play-game")
+                .arg(scenario_name)
+                .arg(seed.to_string())
+                .args(paths);
+            let result =
+                isolate::run(&mut cmd, &isolation.limits).and_then(|stdout| match stdout.trim() {
+                    "win" => Ok(Outcomes::WIN),
+                    "loss" => Ok(Outcomes::LOSS),
+                    "draw" => Ok(Outcomes::DRAW),
+                    x => Err(isolate::Error::Infrastructure(format!(
+                        "unexpected output {x:?}"
+                    ))),
+                });
+            match result {
+                Err(isolate::Error::Infrastructure(msg)) if attempt < isolation.retries => {
+                    log::warn!("Retrying {} seed {}: {}", scenario_name, seed, msg);
+                    std::thread::sleep(Duration::from_secs(1 << attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Play for Runner<'_> {
+    fn play(&self, scenario_name: &str, game: &Game) -> Outcomes {
+        let isolation = match self.isolation.as_ref() {
+            Some(isolation) => isolation,
+            None => {
+                let codes: Vec<Code> = game
+                    .players
+                    .iter()
+                    .map(|&i| self.ais[i].compiled_code.clone())
+                    .collect();
+                return run_simulation(scenario_name, game.seed, &codes);
+            }
+        };
+
+        let [i0, i1] = game.players;
+        let paths = game
+            .players
+            .map(|i| isolation.dir.path().join(format!("{i}.json")));
+        let (outcome, forfeited_by, reason) =
+            match self.run_isolated(isolation, scenario_name, game.seed, &paths) {
+                Ok(outcome) => return outcome,
+                // The child reports the team whose AI was running when it failed.
+                Err(isolate::Error::Crashed { reason, stdout }) => match crashed_team(&stdout) {
+                    Some(0) => (Outcomes::LOSS, Some(i0), reason),
+                    Some(1) => (Outcomes::WIN, Some(i1), reason),
+                    _ => (Outcomes::DRAW, None, reason),
+                },
+                Err(e) => (Outcomes::DRAW, None, e.to_string()),
+            };
+
+        let usernames: Vec<String> = game
+            .players
+            .iter()
+            .map(|&i| self.ais[i].name.clone())
+            .collect();
+        let forfeited_by = forfeited_by.map(|i| self.ais[i].name.clone());
+        log::warn!(
+            "{} vs {} on {} seed {} failed: {} (forfeited by {:?})",
+            usernames[0],
+            usernames[1],
+            scenario_name,
+            game.seed,
+            reason,
+            forfeited_by
+        );
+        self.failures.lock().unwrap().push(TournamentFailure {
+            scenario_name: scenario_name.to_string(),
+            seed: game.seed,
+            usernames,
+            forfeited_by,
+            reason,
+        });
+        outcome
+    }
+}
+
+/// Parses the team reported by [`report_executing_team_on_crash`] from a child's output.
+fn crashed_team(stdout: &str) -> Option<i32> {
+    stdout
+        .lines()
+        .find_map(|line| line.strip_prefix("team ")?.parse().ok())
+}
+
+fn cmd_play_game(scenario_name: &str, seed: u32, paths: &[String]) -> anyhow::Result<()> {
+    report_executing_team_on_crash();
+    let codes: anyhow::Result<Vec<Code>> = paths
+        .iter()
+        .map(|path| Ok(serde_json::from_slice(&std::fs::read(path)?)?))
+        .collect();
+    let codes = match codes {
+        Ok(codes) => codes,
+        Err(e) => {
+            eprintln!("Failed to load code: {e}");
+            std::process::exit(isolate::EXIT_INFRASTRUCTURE);
+        }
+    };
+    let outcome = match run_simulation(scenario_name, seed, &codes) {
+        Outcomes::WIN => "win",
+        Outcomes::LOSS => "loss",
+        Outcomes::DRAW => "draw",
+    };
+    println!("{outcome}");
+    Ok(())
+}
+
+/// Makes the process print "team N" if it panics or is stopped by a resource
+/// limit while team N's AI is running, so the parent knows who to blame.
+///
+/// The executing team is tracked per thread, so the game must run on the
+/// thread that calls this.
+fn report_executing_team_on_crash() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        if let Some(team) = oort_simulator::vm::executing_team() {
+            println!("team {team}");
+        }
+        default_hook(info);
+    }));
+    // SIGXCPU is the CPU time limit, SIGTERM the wall-clock limit and SIGABRT
+    // an allocation failing at the address space limit.
+    #[cfg(unix)]
+    for signal in [libc::SIGXCPU, libc::SIGTERM, libc::SIGABRT] {
+        // SAFETY: the handler only makes async-signal-safe calls.
+        unsafe {
+            libc::signal(
+                signal,
+                report_executing_team as *const () as libc::sighandler_t,
             );
         }
     }
+}
 
-    TournamentResults {
-        scenario_name: scenario_name.to_string(),
-        competitors,
-        win_matrix,
+#[cfg(unix)]
+extern "C" fn report_executing_team(signal: libc::c_int) {
+    if let Some(team @ 0..=9) = oort_simulator::vm::executing_team() {
+        let msg = [b't', b'e', b'a', b'm', b' ', b'0' + team as u8, b'\n'];
+        // SAFETY: write is async-signal-safe and msg outlives the call.
+        unsafe {
+            libc::write(1, msg.as_ptr() as *const libc::c_void, msg.len());
+        }
+    }
+    // Die from the original signal so the parent sees why the child stopped.
+    // SAFETY: signal and raise are async-signal-safe.
+    unsafe {
+        libc::signal(signal, libc::SIG_DFL);
+        libc::raise(signal);
     }
 }
 
-fn run_simulation(scenario_name: &str, seed: u32, ais: &[&AI]) -> Outcomes {
-    let codes: Vec<_> = ais.iter().map(|x| x.compiled_code.clone()).collect();
-    let mut sim = simulation::Simulation::new(scenario_name, seed, &codes);
+fn run_simulation(scenario_name: &str, seed: u32, codes: &[Code]) -> Outcomes {
+    let mut sim = simulation::Simulation::new(scenario_name, seed, codes);
     while sim.status() == scenario::Status::Running && sim.tick() < scenario::MAX_TICKS {
         sim.step();
     }
@@ -248,7 +1000,16 @@ fn display_results(results: &TournamentResults) {
             format!("{:.0}", competitor.rating),
         ]);
     }
-    println!("Scenario: {}", results.scenario_name);
+    if results.scenarios.is_empty() {
+        println!("Scenario: {}", results.scenario_name);
+    } else {
+        let pool: Vec<String> = results
+            .scenarios
+            .iter()
+            .map(|x| format!("{} (weight {})", x.results.scenario_name, x.weight))
+            .collect();
+        println!("Combined: {}", pool.join(", "));
+    }
     println!("{table}");
     println!();
 
@@ -277,10 +1038,124 @@ fn display_results(results: &TournamentResults) {
         table.add_row(row);
     }
     println!("{table}");
+
+    for scenario in &results.scenarios {
+        println!();
+        display_results(&scenario.results);
+    }
+
+    if !results.failures.is_empty() {
+        println!();
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec![
+            "Scenario",
+            "Seed",
+            "Players",
+            "Forfeited By",
+            "Reason",
+        ]);
+        for failure in &results.failures {
+            table.add_row(vec![
+                failure.scenario_name.clone(),
+                failure.seed.to_string(),
+                failure.usernames.join(" vs "),
+                failure.forfeited_by.clone().unwrap_or_default(),
+                failure.reason.clone(),
+            ]);
+        }
+        println!("Failed games:");
+        println!("{table}");
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Renders the ratings and win matrix as a standalone HTML page, followed by
+/// the breakdown for each scenario in the pool.
+fn render_html(results: &TournamentResults) -> String {
+    let scenario_name = escape_html(&results.scenario_name);
+    let mut html = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>Tournament results: {scenario_name}</title>\n\
+         <style>table {{ border-collapse: collapse; margin-bottom: 2em; }} \
+         th, td {{ border: 1px solid #999; padding: 4px 12px; }}</style>\n\
+         </head>\n<body>\n<h1>Tournament results: {scenario_name}</h1>\n"
+    );
+    render_html_tables(&mut html, results);
+    for scenario in &results.scenarios {
+        html.push_str(&format!(
+            "<h2>{} (weight {})</h2>\n",
+            escape_html(&scenario.results.scenario_name),
+            scenario.weight
+        ));
+        render_html_tables(&mut html, &scenario.results);
+    }
+    if !results.failures.is_empty() {
+        html.push_str(
+            "<h2>Failed games</h2>\n<table>\n<tr><th>Scenario</th><th>Seed</th>\
+             <th>Players</th><th>Forfeited by</th><th>Reason</th></tr>\n",
+        );
+        for failure in &results.failures {
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&failure.scenario_name),
+                failure.seed,
+                escape_html(&failure.usernames.join(" vs ")),
+                escape_html(failure.forfeited_by.as_deref().unwrap_or("")),
+                escape_html(&failure.reason)
+            ));
+        }
+        html.push_str("</table>\n");
+    }
+    html.push_str("</body>\n</html>\n");
+    html
+}
+
+fn render_html_tables(html: &mut String, results: &TournamentResults) {
+    html.push_str("<table>\n<tr><th>Rank</th><th>Name</th><th>Rating</th><th>File</th></tr>\n");
+    for (i, competitor) in results.competitors.iter().enumerate() {
+        html.push_str(&format!(
+            "<tr><td>{}</td><td>{}</td><td>{:.0}</td><td>{}</td></tr>\n",
+            i + 1,
+            escape_html(&competitor.username),
+            competitor.rating,
+            escape_html(&competitor.shortcode)
+        ));
+    }
+    html.push_str("</table>\n");
+
+    html.push_str("<table>\n<tr><th>Winner / Loser</th>");
+    for competitor in &results.competitors {
+        html.push_str(&format!("<th>{}</th>", escape_html(&competitor.username)));
+    }
+    html.push_str("</tr>\n");
+    let n = results.competitors.len();
+    for (i, competitor) in results.competitors.iter().enumerate() {
+        html.push_str(&format!(
+            "<tr><th>{}</th>",
+            escape_html(&competitor.username)
+        ));
+        for j in 0..n {
+            if i == j {
+                html.push_str("<td></td>");
+            } else {
+                let frac = results.win_matrix[i * n + j];
+                html.push_str(&format!("<td>{}</td>", (frac * 100.0).round()));
+            }
+        }
+        html.push_str("</tr>\n");
+    }
+    html.push_str("</table>\n");
 }
 
 async fn upload_results(
-    db: &FirestoreDb,
+    storage: &dyn Storage,
     project_id: &str,
     entrants: &[Entrant],
     results: &TournamentResults,
@@ -307,10 +1182,23 @@ async fn upload_results(
             code: entrant.source_code.clone(),
         };
         let shortcode = format!("{tournament_id}.{}", competitor.username);
-        db.create_obj("shortcode", &shortcode, &obj).await?;
+        storage.create_shortcode(&shortcode, &obj).await?;
         competitor.shortcode = shortcode;
     }
-    db.create_obj("tournament_results", &tournament_id, &results)
+    let shortcodes: HashMap<String, String> = results
+        .competitors
+        .iter()
+        .map(|x| (x.username.clone(), x.shortcode.clone()))
+        .collect();
+    for competitor in results
+        .scenarios
+        .iter_mut()
+        .flat_map(|x| x.results.competitors.iter_mut())
+    {
+        competitor.shortcode = shortcodes[&competitor.username].clone();
+    }
+    storage
+        .create_tournament_results(&tournament_id, &results)
         .await?;
     println!();
     if project_id == "oort-dev" {
@@ -322,33 +1210,11 @@ async fn upload_results(
 }
 
 async fn get_entrants(
-    db: &FirestoreDb,
+    storage: &dyn Storage,
     scenario_name: &str,
     usernames: &[String],
 ) -> anyhow::Result<Vec<Entrant>> {
-    let msgs: Vec<TournamentSubmission> = db
-        .query_obj(
-            FirestoreQueryParams::new("tournament".into())
-                .with_filter(FirestoreQueryFilter::Composite(
-                    FirestoreQueryFilterComposite::new(vec![FirestoreQueryFilter::Compare(Some(
-                        FirestoreQueryFilterCompare::Equal(
-                            "scenario_name".into(),
-                            scenario_name.into(),
-                        ),
-                    ))]),
-                ))
-                .with_order_by(vec![
-                    FirestoreQueryOrder::new(
-                        "username".to_owned(),
-                        FirestoreQueryDirection::Ascending,
-                    ),
-                    FirestoreQueryOrder::new(
-                        "timestamp".to_owned(),
-                        FirestoreQueryDirection::Ascending,
-                    ),
-                ]),
-        )
-        .await?;
+    let msgs = storage.list_tournament_submissions(scenario_name).await?;
 
     let mut map: HashMap<String, TournamentSubmission> = HashMap::new();
     for msg in msgs {
@@ -369,8 +1235,8 @@ async fn get_entrants(
 }
 
 async fn cmd_fetch(project_id: &str, scenario_name: &str, out_dir: &str) -> anyhow::Result<()> {
-    let db = FirestoreDb::new(project_id).await?;
-    let entrants = get_entrants(&db, scenario_name, &[]).await?;
+    let storage = oort_storage::open(project_id).await?;
+    let entrants = get_entrants(storage.as_ref(), scenario_name, &[]).await?;
     std::fs::create_dir_all(out_dir).unwrap();
     for entrant in entrants {
         let filename = format!("{}/{}.rs", &out_dir, entrant.username);
@@ -381,3 +1247,168 @@ async fn cmd_fetch(project_id: &str, scenario_name: &str, out_dir: &str) -> anyh
 
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Decides each match with `winner`, which is given the pair of entrants
+    /// (lowest first) and how many times they have met before.
+    struct Scripted {
+        winner: fn(usize, usize, usize) -> usize,
+        games: Mutex<Vec<[usize; 2]>>,
+    }
+
+    impl Scripted {
+        fn new(winner: fn(usize, usize, usize) -> usize) -> Self {
+            Self {
+                winner,
+                games: Mutex::new(vec![]),
+            }
+        }
+
+        /// Returns each match played, lowest entrant first, in sorted order
+        /// since matches within a round are played in parallel.
+        fn matches(&self) -> Vec<(usize, usize)> {
+            // Each match is one game on each side.
+            let mut matches: Vec<(usize, usize)> = self
+                .games
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|[a, b]| a < b)
+                .map(|&[a, b]| (a, b))
+                .collect();
+            matches.sort();
+            matches
+        }
+    }
+
+    impl Play for Scripted {
+        fn play(&self, _scenario_name: &str, game: &Game) -> Outcomes {
+            let [a, b] = game.players;
+            let (lo, hi) = (a.min(b), a.max(b));
+            let mut games = self.games.lock().unwrap();
+            let meetings = games
+                .iter()
+                .filter(|&&[x, y]| (x.min(y), x.max(y)) == (lo, hi))
+                .count()
+                / 2;
+            games.push(game.players);
+            if (self.winner)(lo, hi, meetings) == a {
+                Outcomes::WIN
+            } else {
+                Outcomes::LOSS
+            }
+        }
+    }
+
+    fn lower_wins(a: usize, _b: usize, _meetings: usize) -> usize {
+        a
+    }
+
+    fn pool() -> Vec<PoolEntry> {
+        vec![PoolEntry {
+            scenario_name: "fighter_duel".to_string(),
+            weight: 1.0,
+        }]
+    }
+
+    fn ais(n: usize) -> Vec<AI> {
+        (0..n)
+            .map(|i| AI {
+                name: format!("ai{i}"),
+                source_code: String::new(),
+                compiled_code: Code::None,
+            })
+            .collect()
+    }
+
+    fn options(format: Format) -> TournamentOptions {
+        TournamentOptions {
+            rounds: 1,
+            format,
+            swiss_rounds: None,
+            isolate: false,
+            game_timeout_secs: 600,
+            game_memory_limit_mb: 4096,
+            retries: 0,
+            workers: None,
+        }
+    }
+
+    #[test]
+    fn test_bracket_pairs() {
+        assert_eq!(bracket_pairs(&[0, 1, 2, 3]), (vec![(0, 3), (1, 2)], None));
+        assert_eq!(bracket_pairs(&[4, 5, 6]), (vec![(4, 6)], Some(5)));
+        assert_eq!(bracket_pairs(&[7]), (vec![], Some(7)));
+    }
+
+    #[test]
+    fn test_swiss_no_repeat_pairings() {
+        let runner = Scripted::new(lower_wins);
+        let (games, points) = run_swiss(&pool(), &ais(6), &runner, &options(Format::Swiss));
+        assert_eq!(games.len(), 3 * 3 * 2);
+        let matches = runner.matches();
+        assert_eq!(matches.len(), 9);
+        let unique: HashSet<_> = matches.iter().collect();
+        assert_eq!(unique.len(), matches.len(), "{matches:?}");
+        assert_eq!(points.iter().sum::<f64>(), 9.0);
+        assert_eq!(points[0], 3.0);
+    }
+
+    #[test]
+    fn test_swiss_byes() {
+        let runner = Scripted::new(lower_wins);
+        let (_, points) = run_swiss(&pool(), &ais(5), &runner, &options(Format::Swiss));
+        let matches = runner.matches();
+        // Three rounds of two matches, with a different entrant sitting out each round.
+        assert_eq!(matches.len(), 6);
+        for i in 0..5 {
+            let played = matches.iter().filter(|&&(a, b)| a == i || b == i).count();
+            assert!(played >= 2, "entrant {i} had more than one bye");
+        }
+        let unique: HashSet<_> = matches.iter().collect();
+        assert_eq!(unique.len(), matches.len(), "{matches:?}");
+        // A bye is worth a win.
+        assert_eq!(points.iter().sum::<f64>(), 6.0 + 3.0);
+    }
+
+    #[test]
+    fn test_double_elimination_bye() {
+        let runner = Scripted::new(lower_wins);
+        let (_, placements) = run_double_elimination(
+            &pool(),
+            &ais(3),
+            &runner,
+            &options(Format::DoubleElimination),
+        );
+        assert_eq!(runner.matches(), vec![(0, 1), (0, 1), (0, 2), (1, 2)]);
+        assert_eq!(placements, vec![3.0, 2.0, 1.0]);
+    }
+
+    #[test]
+    fn test_double_elimination_losers_bracket_champion() {
+        // Entrant 0 beats 1 in the winners bracket final, then loses to them
+        // in the grand final and the reset.
+        let runner = Scripted::new(|a, b, meetings| {
+            if (a, b) == (0, 1) && meetings > 0 {
+                1
+            } else {
+                a
+            }
+        });
+        let (games, placements) = run_double_elimination(
+            &pool(),
+            &ais(4),
+            &runner,
+            &options(Format::DoubleElimination),
+        );
+        assert_eq!(
+            runner.matches(),
+            vec![(0, 1), (0, 1), (0, 1), (0, 3), (1, 2), (1, 2), (2, 3)]
+        );
+        assert_eq!(games.len(), 14);
+        assert_eq!(placements, vec![3.0, 4.0, 2.0, 1.0]);
+    }
+}
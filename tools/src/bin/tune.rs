@@ -1,15 +1,61 @@
-use clap::Parser as _;
+use clap::{Parser as _, ValueEnum};
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::Table;
 use metaheuristics_nature::utility::prelude::*;
-use metaheuristics_nature::{Bounded, ObjFunc, Solver};
+use metaheuristics_nature::{Bounded, De, Fa, ObjFunc, Pso, Rga, Solver, Tlbo};
 use oort_simulator::simulation::Code;
-use oort_simulator::{scenario, simulation};
+use oort_simulator::{scenario, simulation, vm};
+use oort_tools::PoolEntry;
+use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use std::default::Default;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, RwLock};
 
 thread_local! {
   static COMPILERS: std::cell::RefCell<oort_compiler::Compiler> = RefCell::new(oort_compiler::Compiler::new());
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug)]
+enum Algorithm {
+    /// Real-coded genetic algorithm
+    Rga,
+    /// Differential evolution
+    De,
+    /// Particle swarm optimization
+    Pso,
+    /// Firefly algorithm
+    Fa,
+    /// Teaching-learning-based optimization
+    Tlbo,
+}
+
+/// Population saved to disk so that a long run can be resumed.
+#[derive(Serialize, Deserialize, Debug)]
+struct Checkpoint {
+    /// Number of generations completed.
+    generation: u64,
+    best: Vec<f64>,
+    best_fitness: f64,
+    /// Candidates in the population, starting with the best.
+    population: Vec<Vec<f64>>,
+    /// Champions found so far with --self-play.
+    #[serde(default)]
+    champions: Vec<Champion>,
+}
+
+/// A parameter set that beat the previous champion with --self-play.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct Champion {
+    generation: u64,
+    params: Vec<f64>,
+    /// Rating relative to the initial parameters, which start at INITIAL_ELO.
+    elo: f64,
+}
+
+const INITIAL_ELO: f64 = 1000.0;
+
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("tune=info")).init();
@@ -28,35 +74,207 @@ async fn main() -> anyhow::Result<()> {
         #[clap(short, long)]
         dry_run: bool,
 
+        #[clap(short, long, value_enum, default_value_t = Algorithm::Rga)]
+        algorithm: Algorithm,
+
+        /// Objective penalty in seconds for losing every game. The objective is
+        /// this times the fraction of games not won, plus the mean score time of
+        /// won games, weighted across scenarios and enemies.
+        #[clap(long, value_parser, default_value = "1000")]
+        loss_penalty: f64,
+
+        /// Weight of each enemy, in the same order. Defaults to 1 for all.
+        #[clap(long = "enemy-weight", value_parser)]
+        enemy_weights: Vec<f64>,
+
+        /// Save the population to this file as JSON.
+        #[clap(long, value_parser)]
+        checkpoint: Option<PathBuf>,
+
+        /// Number of generations between checkpoints.
+        #[clap(long, value_parser, default_value = "1")]
+        checkpoint_interval: u64,
+
+        /// Resume from a checkpoint written by --checkpoint.
+        #[clap(long, value_parser)]
+        resume: Option<PathBuf>,
+
+        /// Append the best and mean fitness of each generation to this CSV file.
+        #[clap(long, value_parser)]
+        csv: Option<PathBuf>,
+
+        /// Evaluate against the enemies plus snapshots of the champions found
+        /// so far in this run. A candidate becomes champion when it beats the
+        /// current one head-to-head. The enemies default to builtin:reference.
+        #[clap(long)]
+        self_play: bool,
+
+        /// Number of recent champions kept in the opponent pool with --self-play.
+        #[clap(long, value_parser, default_value = "4")]
+        snapshots: usize,
+
+        /// Score against the current champion needed to replace it with --self-play.
+        #[clap(long, value_parser, default_value = "0.55")]
+        champion_threshold: f64,
+
+        /// Override a scenario parameter, e.g. --param num_targets=8.
+        #[clap(long = "param", value_parser = scenario::parse_param_override)]
+        params: Vec<(String, f64)>,
+
+        /// Scenario or pool of scenarios, e.g. "fighter_duel,frigate_duel:2".
         scenario_name: String,
         player_code: String,
-        enemy_code: String,
+        /// Shortcodes or files to tune against. Builtin AIs can be given as
+        /// e.g. "builtin:reference" or "builtin:tutorial/tutorial_frigate_enemy".
+        enemy_codes: Vec<String>,
     }
 
-    let args = Arguments::parse();
+    let mut args = Arguments::parse();
+    if args.enemy_codes.is_empty() {
+        if !args.self_play {
+            anyhow::bail!("Expected at least one enemy");
+        }
+        args.enemy_codes.push("builtin:reference".to_string());
+    }
+    let pool = oort_tools::parse_pool(&args.scenario_name)?;
+    let params: scenario::ParamOverrides = args.params.iter().cloned().collect();
+    for entry in &pool {
+        scenario::Params::resolve(&scenario::load(&entry.scenario_name).params(), &params)
+            .map_err(anyhow::Error::msg)?;
+    }
+    let enemy_weights = if args.enemy_weights.is_empty() {
+        vec![1.0; args.enemy_codes.len()]
+    } else if args.enemy_weights.len() == args.enemy_codes.len() {
+        args.enemy_weights.clone()
+    } else {
+        anyhow::bail!(
+            "Got {} enemy weights for {} enemies",
+            args.enemy_weights.len(),
+            args.enemy_codes.len()
+        );
+    };
 
     let player_src_code = std::fs::read_to_string(&args.player_code).unwrap();
     let (initial_values, bounds) = extract_tunables(&player_src_code);
     assert!(!initial_values.is_empty());
 
-    let ais = oort_tools::fetch_and_compile_multiple(
-        &reqwest::Client::new(),
-        &[args.player_code.clone(), args.enemy_code.clone()],
-        false,
-    )
-    .await?;
-    let codes = ais
-        .into_iter()
-        .map(|ai| ai.compiled_code)
-        .collect::<Vec<_>>();
+    let checkpoint: Option<Checkpoint> = match args.resume.as_ref() {
+        Some(path) => {
+            let checkpoint: Checkpoint = serde_json::from_slice(&std::fs::read(path)?)?;
+            if checkpoint.best.len() != initial_values.len() {
+                anyhow::bail!(
+                    "Checkpoint has {} parameters but the player code has {}",
+                    checkpoint.best.len(),
+                    initial_values.len()
+                );
+            }
+            if checkpoint.generation >= args.generations {
+                anyhow::bail!(
+                    "Checkpoint already completed {} generations",
+                    checkpoint.generation
+                );
+            }
+            log::info!(
+                "Resuming from generation {} with best fitness {}",
+                checkpoint.generation,
+                checkpoint.best_fitness
+            );
+            Some(checkpoint)
+        }
+        None => None,
+    };
+    let start_generation = checkpoint.as_ref().map(|x| x.generation).unwrap_or(0);
+
+    let http = reqwest::Client::new();
+    let player_code = oort_tools::fetch_and_compile(&http, &args.player_code, false)
+        .await?
+        .compiled_code;
+    let mut fixed = vec![];
+    for (shortcode, weight) in args.enemy_codes.iter().zip(enemy_weights.iter()) {
+        let code = match shortcode.strip_prefix("builtin:") {
+            Some(name) => scenario::builtin(name),
+            None => {
+                oort_tools::fetch_and_compile(&http, shortcode, false)
+                    .await?
+                    .compiled_code
+            }
+        };
+        fixed.push(Opponent {
+            name: shortcode.clone(),
+            code,
+            weight: *weight,
+        });
+    }
+    let opponents = Arc::new(RwLock::new(OpponentPool {
+        fixed,
+        snapshots: vec![],
+        max_snapshots: args.snapshots.max(1),
+    }));
+
+    let self_play = if args.self_play {
+        let champions = match checkpoint.as_ref() {
+            Some(checkpoint) if !checkpoint.champions.is_empty() => checkpoint.champions.clone(),
+            _ => vec![Champion {
+                generation: start_generation,
+                params: initial_values.clone(),
+                elo: INITIAL_ELO,
+            }],
+        };
+        let mut self_play = SelfPlay {
+            scenarios: pool.clone(),
+            params: params.clone(),
+            num_seeds: args.num_seeds,
+            player_src_code: player_src_code.clone(),
+            opponents: opponents.clone(),
+            threshold: args.champion_threshold,
+            champions: vec![],
+            champion_code: player_code.clone(),
+        };
+        let skip = champions.len().saturating_sub(args.snapshots.max(1));
+        for (i, champion) in champions.into_iter().enumerate() {
+            if i >= skip {
+                let code = compile_params(&player_src_code, &champion.params)
+                    .ok_or_else(|| anyhow::anyhow!("Failed to compile checkpointed champion"))?;
+                self_play.add_champion(champion, code);
+            } else {
+                self_play.champions.push(champion);
+            }
+        }
+        Some(Mutex::new(self_play))
+    } else {
+        None
+    };
+
+    let objective_function = ObjectiveFunction {
+        scenarios: pool.clone(),
+        opponents,
+        params: params.clone(),
+        player_src_code: player_src_code.clone(),
+        bounds: bounds.to_vec(),
+        num_seeds: args.num_seeds,
+        loss_penalty: args.loss_penalty,
+    };
 
     log::info!("Running initial simulations");
-    let initial_fitness = run_simulations(&args.scenario_name, codes.clone(), args.num_seeds);
+    let initial_results = objective_function.evaluate(&player_code);
+    for (matchup, results) in &initial_results {
+        log::info!(
+            "Initial results on {} against {}: win rate {:.2}, mean score time {:.2}",
+            matchup.scenario_name,
+            matchup.enemy_name,
+            results.win_rate(),
+            results.mean_time()
+        );
+    }
+    let mut initial_fitness = objective_function.combine(&initial_results);
     log::info!(
         "Initial fitness: {} for {:?}",
         initial_fitness,
         initial_values
     );
+    if let Some(checkpoint) = checkpoint.as_ref() {
+        initial_fitness = initial_fitness.min(checkpoint.best_fitness);
+    }
 
     {
         let test_src_code = rewrite_tunables(&player_src_code, &initial_values);
@@ -65,37 +283,120 @@ async fn main() -> anyhow::Result<()> {
         assert_eq!(test_bounds, bounds);
     }
 
-    let objective_function = ObjectiveFunction {
-        scenario_name: args.scenario_name.to_string(),
-        player_src_code: player_src_code.clone(),
-        bounds: bounds.to_vec(),
-        enemy_code: codes[1].clone(),
-        num_seeds: args.num_seeds,
-    };
+    if let Some(path) = args.csv.as_ref() {
+        if std::fs::metadata(path)
+            .map(|x| x.len() == 0)
+            .unwrap_or(true)
+        {
+            let mut header = vec![
+                "generation".to_string(),
+                "best_fitness".to_string(),
+                "mean_fitness".to_string(),
+            ];
+            if args.self_play {
+                header.push("champion_elo".to_string());
+            }
+            header.extend((0..initial_values.len()).map(|i| format!("param{i}")));
+            append_line(path, &header.join(","))?;
+        }
+    }
 
-    let pool = generate_pool(&initial_values);
+    let population = checkpoint.map(|x| x.population);
 
-    let s = Solver::build(metaheuristics_nature::Rga::default(), objective_function)
-        .pop_num(args.population)
-        .pool(pool)
-        .task(|ctx| ctx.gen + 1 == args.generations)
-        .callback(|ctx| {
-            log::info!(
-                "Generation {}/{}. Best fitness {} for {:?}",
-                ctx.gen + 1,
-                args.generations,
-                ctx.best_f,
-                ctx.best.iter().cloned().collect::<Vec<f64>>()
-            );
-            if ctx.best_f < initial_fitness {
-                log::info!("Writing back to {}", args.player_code);
-                let new_src_code =
-                    rewrite_tunables(&player_src_code, ctx.best.slice(s![..]).as_slice().unwrap());
-                std::fs::write(&args.player_code, new_src_code).unwrap();
-            }
-        })
-        .solve()
-        .unwrap();
+    // Each optimizer has its own settings type, so the builder chain is
+    // repeated for each.
+    macro_rules! solve {
+        ($setting:expr) => {
+            Solver::build($setting, objective_function)
+                .pop_num(args.population)
+                .pool(generate_pool(&initial_values, population))
+                .task(|ctx| start_generation + ctx.gen + 1 >= args.generations)
+                .callback(|ctx| {
+                    let generation = start_generation + ctx.gen + 1;
+                    let best: Vec<f64> = ctx.best.to_vec();
+                    let mean_fitness =
+                        ctx.pool_f.iter().sum::<f64>() / ctx.pool_f.len().max(1) as f64;
+                    log::info!(
+                        "Generation {}/{}. Best fitness {} (mean {}) for {:?}",
+                        generation,
+                        args.generations,
+                        ctx.best_f,
+                        mean_fitness,
+                        best
+                    );
+                    if let Some(self_play) = self_play.as_ref() {
+                        // ctx.best may have been scored against an older, weaker
+                        // pool, so the challenger is the best of the current
+                        // population instead.
+                        let challenger = ctx
+                            .pool_f
+                            .iter()
+                            .enumerate()
+                            .min_by(|a, b| a.1.total_cmp(b.1))
+                            .map(|(i, _)| ctx.pool.row(i).to_vec())
+                            .unwrap_or_else(|| best.clone());
+                        if self_play.lock().unwrap().challenge(generation, &challenger) {
+                            log::info!("Writing back to {}", args.player_code);
+                            let new_src_code = rewrite_tunables(&player_src_code, &challenger);
+                            std::fs::write(&args.player_code, new_src_code).unwrap();
+                        }
+                    } else if ctx.best_f < initial_fitness {
+                        log::info!("Writing back to {}", args.player_code);
+                        let new_src_code = rewrite_tunables(&player_src_code, &best);
+                        std::fs::write(&args.player_code, new_src_code).unwrap();
+                    }
+                    let champions: Vec<Champion> = self_play
+                        .as_ref()
+                        .map(|x| x.lock().unwrap().champions.clone())
+                        .unwrap_or_default();
+                    if let Some(path) = args.csv.as_ref() {
+                        let mut row = vec![
+                            generation.to_string(),
+                            ctx.best_f.to_string(),
+                            mean_fitness.to_string(),
+                        ];
+                        if let Some(champion) = champions.last() {
+                            row.push(champion.elo.to_string());
+                        }
+                        row.extend(best.iter().map(|x| x.to_string()));
+                        if let Err(e) = append_line(path, &row.join(",")) {
+                            log::warn!("Failed to write {}: {}", path.display(), e);
+                        }
+                    }
+                    if let Some(path) = args.checkpoint.as_ref() {
+                        if generation % args.checkpoint_interval.max(1) == 0
+                            || generation == args.generations
+                        {
+                            let mut population = vec![best.clone()];
+                            population.extend(ctx.pool.outer_iter().map(|x| x.to_vec()));
+                            let checkpoint = Checkpoint {
+                                generation,
+                                best: best.clone(),
+                                best_fitness: ctx.best_f,
+                                population,
+                                champions,
+                            };
+                            match write_checkpoint(path, &checkpoint) {
+                                Ok(()) => log::info!("Wrote checkpoint to {}", path.display()),
+                                Err(e) => {
+                                    log::warn!("Failed to write {}: {}", path.display(), e)
+                                }
+                            }
+                        }
+                    }
+                })
+                .solve()
+                .unwrap()
+        };
+    }
+
+    let s = match args.algorithm {
+        Algorithm::Rga => solve!(Rga::default()),
+        Algorithm::De => solve!(De::default()),
+        Algorithm::Pso => solve!(Pso::default()),
+        Algorithm::Fa => solve!(Fa::default()),
+        Algorithm::Tlbo => solve!(Tlbo::default()),
+    };
 
     log::info!(
         "Result: fitness={:?} parameters={:?}",
@@ -103,15 +404,191 @@ async fn main() -> anyhow::Result<()> {
         s.best_parameters()
     );
 
+    if let Some(self_play) = self_play.as_ref() {
+        let self_play = self_play.lock().unwrap();
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        table.set_header(vec!["Generation", "Elo", "Parameters"]);
+        for champion in &self_play.champions {
+            table.add_row(vec![
+                champion.generation.to_string(),
+                format!("{:.0}", champion.elo),
+                format!("{:?}", champion.params),
+            ]);
+        }
+        println!("Champion progression:");
+        println!("{table}");
+    }
+
     Ok(())
 }
 
-struct ObjectiveFunction {
+/// A scenario and enemy that candidates are evaluated against.
+struct Matchup {
     scenario_name: String,
+    enemy_name: String,
+    enemy_code: Code,
+    weight: f64,
+}
+
+struct Opponent {
+    name: String,
+    code: Code,
+    weight: f64,
+}
+
+/// Opponents that candidates are evaluated against. With --self-play the
+/// most recent champions rotate through alongside the fixed opponents.
+struct OpponentPool {
+    fixed: Vec<Opponent>,
+    snapshots: Vec<Opponent>,
+    max_snapshots: usize,
+}
+
+impl OpponentPool {
+    fn add_snapshot(&mut self, opponent: Opponent) {
+        self.snapshots.push(opponent);
+        if self.snapshots.len() > self.max_snapshots {
+            self.snapshots.remove(0);
+        }
+    }
+}
+
+struct ObjectiveFunction {
+    scenarios: Vec<PoolEntry>,
+    opponents: Arc<RwLock<OpponentPool>>,
+    params: scenario::ParamOverrides,
     player_src_code: String,
     bounds: Vec<[f64; 2]>,
-    enemy_code: Code,
     num_seeds: u32,
+    loss_penalty: f64,
+}
+
+impl ObjectiveFunction {
+    /// Returns every combination of scenario and opponent in the current pool.
+    fn matchups(&self) -> Vec<Matchup> {
+        let opponents = self.opponents.read().unwrap();
+        let mut matchups = vec![];
+        for entry in &self.scenarios {
+            for opponent in opponents.fixed.iter().chain(opponents.snapshots.iter()) {
+                matchups.push(Matchup {
+                    scenario_name: entry.scenario_name.clone(),
+                    enemy_name: opponent.name.clone(),
+                    enemy_code: opponent.code.clone(),
+                    weight: entry.weight * opponent.weight,
+                });
+            }
+        }
+        matchups
+    }
+
+    /// Plays the player against each matchup.
+    fn evaluate(&self, player_code: &Code) -> Vec<(Matchup, Results)> {
+        self.matchups()
+            .into_iter()
+            .map(|matchup| {
+                let results = run_simulations(
+                    &matchup.scenario_name,
+                    &self.params,
+                    vec![player_code.clone(), matchup.enemy_code.clone()],
+                    self.num_seeds,
+                );
+                (matchup, results)
+            })
+            .collect()
+    }
+
+    /// Combines the results of each matchup into a fitness, lower being better.
+    fn combine(&self, results: &[(Matchup, Results)]) -> f64 {
+        let total_weight: f64 = results.iter().map(|(matchup, _)| matchup.weight).sum();
+        results
+            .iter()
+            .map(|(matchup, results)| matchup.weight * results.fitness(self.loss_penalty))
+            .sum::<f64>()
+            / total_weight
+    }
+}
+
+/// State for --self-play: the champions found so far and everything needed
+/// to challenge the current one.
+struct SelfPlay {
+    scenarios: Vec<PoolEntry>,
+    params: scenario::ParamOverrides,
+    num_seeds: u32,
+    player_src_code: String,
+    opponents: Arc<RwLock<OpponentPool>>,
+    threshold: f64,
+    champions: Vec<Champion>,
+    champion_code: Code,
+}
+
+impl SelfPlay {
+    fn add_champion(&mut self, champion: Champion, code: Code) {
+        self.opponents.write().unwrap().add_snapshot(Opponent {
+            name: format!("champion from generation {}", champion.generation),
+            code: code.clone(),
+            weight: 1.0,
+        });
+        self.champions.push(champion);
+        self.champion_code = code;
+    }
+
+    /// Plays `challenger` against the current champion on every scenario and
+    /// makes it the new champion if it scores at least the threshold.
+    ///
+    /// The challenger's Elo is the champion's plus the rating difference
+    /// implied by its score.
+    fn challenge(&mut self, generation: u64, challenger: &[f64]) -> bool {
+        let champion = self.champions.last().unwrap();
+        if champion.params == challenger {
+            return false;
+        }
+        let code = match compile_params(&self.player_src_code, challenger) {
+            Some(code) => code,
+            None => return false,
+        };
+        let total_weight: f64 = self.scenarios.iter().map(|x| x.weight).sum();
+        let score = self
+            .scenarios
+            .iter()
+            .map(|entry| {
+                let results = run_simulations(
+                    &entry.scenario_name,
+                    &self.params,
+                    vec![code.clone(), self.champion_code.clone()],
+                    self.num_seeds,
+                );
+                entry.weight * results.score()
+            })
+            .sum::<f64>()
+            / total_weight;
+        log::info!(
+            "Challenger scored {:.2} against the champion from generation {}",
+            score,
+            champion.generation
+        );
+        if score < self.threshold {
+            return false;
+        }
+
+        let p = score.clamp(0.01, 0.99);
+        let elo = champion.elo + 400.0 * (p / (1.0 - p)).log10();
+        log::info!(
+            "New champion at generation {} with Elo {:.0}: {:?}",
+            generation,
+            elo,
+            challenger
+        );
+        self.add_champion(
+            Champion {
+                generation,
+                params: challenger.to_vec(),
+                elo,
+            },
+            code,
+        );
+        true
+    }
 }
 
 impl Bounded for ObjectiveFunction {
@@ -126,22 +603,17 @@ impl ObjFunc for ObjectiveFunction {
     fn fitness(&self, x: &[f64]) -> Self::Fitness {
         log::debug!("Evaluating candidate {:?}", x);
         let start_time = std::time::Instant::now();
-        let player_src_code = rewrite_tunables(&self.player_src_code, x);
 
         let compile_start_time = std::time::Instant::now();
-        let player_code = if let Some(wasm) = compile("player code".to_string(), player_src_code) {
-            oort_simulator::vm::precompile(&wasm).unwrap()
+        let player_code = if let Some(code) = compile_params(&self.player_src_code, x) {
+            code
         } else {
             panic!("Failed to compile player source code");
         };
         let compile_duration = std::time::Instant::now() - compile_start_time;
 
         let sim_start_time = std::time::Instant::now();
-        let fitness = run_simulations(
-            &self.scenario_name,
-            vec![player_code, self.enemy_code.clone()],
-            self.num_seeds,
-        );
+        let fitness = self.combine(&self.evaluate(&player_code));
         let sim_duration = std::time::Instant::now() - sim_start_time;
 
         log::debug!(
@@ -155,9 +627,25 @@ impl ObjFunc for ObjectiveFunction {
     }
 }
 
-fn generate_pool<F: ObjFunc>(initial_values: &[f64]) -> impl Fn(&Ctx<F>, &Rng) -> Array2<f64> {
+/// Generates the initial population: perturbations of the values in the
+/// player code, or the population from a checkpoint when resuming.
+fn generate_pool<F: ObjFunc>(
+    initial_values: &[f64],
+    population: Option<Vec<Vec<f64>>>,
+) -> impl Fn(&Ctx<F>, &Rng) -> Array2<f64> {
     let initial_values = initial_values.to_owned();
     move |ctx, rng| {
+        if let Some(population) = population.as_ref() {
+            // Fill any extra slots with perturbed copies.
+            return Array2::from_shape_fn(ctx.pool_size(), |(i, j)| {
+                let x = population[i % population.len()][j];
+                if i < population.len() {
+                    ctx.clamp(j, x)
+                } else {
+                    ctx.clamp(j, rng.normal(x, ctx.bound_width(j) / 32.0))
+                }
+            });
+        }
         let mut pool = Array2::from_shape_fn(ctx.pool_size(), |(_, s)| initial_values[s]);
         for i in 0..(ctx.pool_size()[0] - 1) {
             let s = i % initial_values.len();
@@ -210,27 +698,121 @@ fn compile(name: String, code: String) -> Option<Vec<u8>> {
     })
 }
 
-fn run_simulations(scenario_name: &str, codes: Vec<Code>, num_seeds: u32) -> f64 {
+/// Compiles the player code with its tunables set to `values`.
+fn compile_params(player_src_code: &str, values: &[f64]) -> Option<Code> {
+    let src_code = rewrite_tunables(player_src_code, values);
+    let wasm = compile("player code".to_string(), src_code)?;
+    Some(oort_simulator::vm::precompile(&wasm).unwrap())
+}
+
+fn append_line(path: &Path, line: &str) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{line}")
+}
+
+/// Writes to a temporary file first so an interrupted write doesn't destroy
+/// the previous checkpoint.
+fn write_checkpoint(path: &Path, checkpoint: &Checkpoint) -> anyhow::Result<()> {
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, serde_json::to_vec_pretty(checkpoint)?)?;
+    std::fs::rename(&tmp_path, path)?;
+    Ok(())
+}
+
+/// Outcomes of the player's games in one matchup.
+#[derive(Debug, Default, Clone)]
+struct Results {
+    games: u32,
+    wins: u32,
+    draws: u32,
+    /// Sum of the score time of won games.
+    win_time: f64,
+}
+
+impl Results {
+    fn win_rate(&self) -> f64 {
+        if self.games == 0 {
+            0.0
+        } else {
+            self.wins as f64 / self.games as f64
+        }
+    }
+
+    fn mean_time(&self) -> f64 {
+        if self.wins == 0 {
+            0.0
+        } else {
+            self.win_time / self.wins as f64
+        }
+    }
+
+    /// Fraction of points won, counting draws as half a win.
+    fn score(&self) -> f64 {
+        if self.games == 0 {
+            0.5
+        } else {
+            (self.wins as f64 + 0.5 * self.draws as f64) / self.games as f64
+        }
+    }
+
+    fn fitness(&self, loss_penalty: f64) -> f64 {
+        (1.0 - self.win_rate()) * loss_penalty + self.mean_time()
+    }
+}
+
+/// Plays the player (the first code) on both sides of each seed.
+fn run_simulations(
+    scenario_name: &str,
+    params: &scenario::ParamOverrides,
+    codes: Vec<Code>,
+    num_seeds: u32,
+) -> Results {
     let reverse_codes = codes.iter().rev().cloned().collect::<Vec<_>>();
-    (0..num_seeds)
+    let outcomes: Vec<(scenario::Status, i32, f64)> = (0..num_seeds)
         .into_par_iter()
         .flat_map(|seed| [(seed, false), (seed, true)])
         .map(|(seed, reverse)| {
             let codes = if reverse { &reverse_codes } else { &codes };
             let player_team = if reverse { 1 } else { 0 };
-            let (status, time) = run_simulation(scenario_name, seed, codes.clone());
-            match status {
-                scenario::Status::Victory { team } if team == player_team => time,
-                _ => 1e6,
-            }
+            let (status, time) = run_simulation(scenario_name, params, seed, codes.clone());
+            (status, player_team, time)
         })
-        .sum()
+        .collect();
+    let mut results = Results::default();
+    for (status, player_team, time) in outcomes {
+        results.games += 1;
+        match status {
+            scenario::Status::Victory { team } if team == player_team => {
+                results.wins += 1;
+                results.win_time += time;
+            }
+            scenario::Status::Victory { .. } => {}
+            _ => results.draws += 1,
+        }
+    }
+    results
 }
 
-fn run_simulation(scenario_name: &str, seed: u32, codes: Vec<Code>) -> (scenario::Status, f64) {
-    let mut sim = simulation::Simulation::new(scenario_name, seed, &codes);
+fn run_simulation(
+    scenario_name: &str,
+    params: &scenario::ParamOverrides,
+    seed: u32,
+    codes: Vec<Code>,
+) -> (scenario::Status, f64) {
+    // The overrides were checked against every scenario in the pool at startup.
+    let mut sim = simulation::Simulation::new_with_params(
+        scenario_name,
+        seed,
+        &codes,
+        vm::Backend::default(),
+        params,
+    )
+    .expect("invalid scenario parameters");
     while sim.status() == scenario::Status::Running && sim.tick() < scenario::MAX_TICKS {
         sim.step();
     }
-    (sim.status(), sim.time())
+    (sim.status(), sim.score_time())
 }
@@ -0,0 +1,38 @@
+use clap::Parser;
+use oort_simulator::vm::validator;
+
+#[derive(Parser, Debug)]
+#[clap()]
+/// Checks that Wasm modules implement the AI ABI.
+struct Arguments {
+    /// Wasm files to check.
+    #[clap(required = true)]
+    paths: Vec<String>,
+
+    /// Print the reports as JSON.
+    #[clap(short, long)]
+    json: bool,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Arguments::parse();
+
+    let mut valid = true;
+    for path in &args.paths {
+        let wasm = std::fs::read(path)?;
+        let report = validator::validate(&wasm);
+        valid &= report.is_valid();
+        if args.json {
+            println!("{}", serde_json::to_string(&report)?);
+        } else if report.is_valid() {
+            println!("{path}: ok");
+        } else {
+            println!("{path}: invalid\n{report}");
+        }
+    }
+
+    if !valid {
+        std::process::exit(1);
+    }
+    Ok(())
+}
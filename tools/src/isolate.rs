@@ -0,0 +1,292 @@
+//! Runs commands in child processes with time and memory limits.
+//!
+//! Used to keep a single misbehaving AI from taking down a long-running job.
+//! CPU time and address space are limited with `setrlimit` on Unix, where the
+//! kernel enforces them; elsewhere only the wall-clock limit applies.
+use std::io::Read;
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::time::{Duration, Instant};
+
+/// Exit code a child uses to report a problem with its environment, as
+/// opposed to a problem with the code it was running.
+pub const EXIT_INFRASTRUCTURE: i32 = 3;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a child has to exit after being asked to stop at the time limit.
+const TERMINATE_GRACE_PERIOD: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Wall-clock limit.
+    pub timeout: Duration,
+    /// CPU time limit, summed over all threads.
+    pub cpu_limit: Duration,
+    /// Address space limit. This counts memory that is reserved but never
+    /// used, so it has to allow for guard regions as well as real usage.
+    pub memory_limit_bytes: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The child could not be run or exited with [`EXIT_INFRASTRUCTURE`]. Worth retrying.
+    Infrastructure(String),
+    /// The child crashed or exceeded a limit. `stdout` is whatever the child
+    /// printed before it stopped.
+    Crashed { reason: String, stdout: String },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Infrastructure(msg) => write!(f, "infrastructure failure: {msg}"),
+            Error::Crashed { reason, .. } => write!(f, "{reason}"),
+        }
+    }
+}
+
+/// Runs `cmd`, killing it if it exceeds `limits`, and returns its stdout.
+///
+/// At the wall-clock limit the child is sent SIGTERM, so it can report what
+/// it was doing, and killed if it hasn't exited after a short grace period.
+pub fn run(cmd: &mut Command, limits: &Limits) -> Result<String, Error> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    #[cfg(unix)]
+    set_rlimits(cmd, limits);
+    let mut child = cmd
+        .spawn()
+        .map_err(|e| Error::Infrastructure(format!("failed to start process: {e}")))?;
+
+    // Drain the pipes on other threads so the child can't block on a full pipe.
+    let mut stdout = child.stdout.take().unwrap();
+    let stdout_thread = std::thread::spawn(move || {
+        let mut s = String::new();
+        let _ = stdout.read_to_string(&mut s);
+        s
+    });
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr_thread = std::thread::spawn(move || {
+        let mut s = String::new();
+        let _ = stderr.read_to_string(&mut s);
+        s
+    });
+
+    let start = Instant::now();
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break Some(status),
+            Ok(None) => {}
+            Err(e) => {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Error::Infrastructure(format!("failed to wait: {e}")));
+            }
+        }
+        if start.elapsed() > limits.timeout {
+            terminate(&mut child);
+            break None;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    };
+
+    let stdout = stdout_thread.join().unwrap_or_default();
+    let stderr = stderr_thread.join().unwrap_or_default();
+    let Some(status) = status else {
+        return Err(Error::Crashed {
+            reason: format!("exceeded time limit of {}s", limits.timeout.as_secs_f64()),
+            stdout,
+        });
+    };
+    let last_line = stderr.lines().last().unwrap_or_default().to_string();
+    if status.success() {
+        Ok(stdout)
+    } else if status.code() == Some(EXIT_INFRASTRUCTURE) {
+        Err(Error::Infrastructure(last_line))
+    } else {
+        Err(Error::Crashed {
+            reason: crash_reason(status, &stderr, &last_line, limits),
+            stdout,
+        })
+    }
+}
+
+/// Applies the CPU time and address space limits in the child before it execs.
+#[cfg(unix)]
+fn set_rlimits(cmd: &mut Command, limits: &Limits) {
+    use std::os::unix::process::CommandExt;
+
+    // The hard CPU limit is a second higher so the child gets SIGXCPU, which
+    // it can catch, before it is killed outright.
+    let cpu_secs = limits.cpu_limit.as_secs().max(1) as libc::rlim_t;
+    let cpu = libc::rlimit {
+        rlim_cur: cpu_secs,
+        rlim_max: cpu_secs + 1,
+    };
+    let memory = libc::rlimit {
+        rlim_cur: limits.memory_limit_bytes as libc::rlim_t,
+        rlim_max: limits.memory_limit_bytes as libc::rlim_t,
+    };
+    // SAFETY: setrlimit is async-signal-safe and the closure doesn't allocate.
+    unsafe {
+        cmd.pre_exec(move || {
+            if libc::setrlimit(libc::RLIMIT_CPU, &cpu) != 0
+                || libc::setrlimit(libc::RLIMIT_AS, &memory) != 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+            Ok(())
+        });
+    }
+}
+
+#[cfg(unix)]
+fn terminate(child: &mut Child) {
+    // SAFETY: the child hasn't been reaped, so its pid still refers to it.
+    unsafe {
+        libc::kill(child.id() as libc::pid_t, libc::SIGTERM);
+    }
+    let start = Instant::now();
+    while start.elapsed() < TERMINATE_GRACE_PERIOD {
+        if let Ok(Some(_)) = child.try_wait() {
+            return;
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+#[cfg(not(unix))]
+fn terminate(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Describes why a child failed, recognizing the ways the rlimits stop it.
+fn crash_reason(status: ExitStatus, stderr: &str, last_line: &str, limits: &Limits) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if status.signal() == Some(libc::SIGXCPU) {
+            return format!(
+                "exceeded CPU time limit of {}s",
+                limits.cpu_limit.as_secs_f64()
+            );
+        }
+    }
+    // Printed by the Rust runtime when an allocation fails.
+    if stderr.contains("memory allocation of") {
+        return format!(
+            "exceeded memory limit of {} MB",
+            limits.memory_limit_bytes / (1 << 20)
+        );
+    }
+    format!("crashed ({status}): {last_line}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn limits() -> Limits {
+        Limits {
+            timeout: Duration::from_secs(10),
+            cpu_limit: Duration::from_secs(10),
+            memory_limit_bytes: 1 << 30,
+        }
+    }
+
+    fn sh(script: &str) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(script);
+        cmd
+    }
+
+    #[test]
+    fn test_success() {
+        assert_eq!(run(&mut sh("echo win"), &limits()), Ok("win\n".to_string()));
+    }
+
+    #[test]
+    fn test_crash() {
+        match run(&mut sh("echo oops >&2; exit 101"), &limits()) {
+            Err(Error::Crashed { reason, .. }) => assert!(reason.contains("oops"), "{reason}"),
+            r => panic!("unexpected result {r:?}"),
+        }
+    }
+
+    #[test]
+    fn test_infrastructure() {
+        assert_eq!(
+            run(&mut sh("echo missing file >&2; exit 3"), &limits()),
+            Err(Error::Infrastructure("missing file".to_string()))
+        );
+        assert!(matches!(
+            run(&mut Command::new("/nonexistent"), &limits()),
+            Err(Error::Infrastructure(_))
+        ));
+    }
+
+    #[test]
+    fn test_timeout() {
+        let limits = Limits {
+            timeout: Duration::from_millis(200),
+            ..limits()
+        };
+        let start = Instant::now();
+        assert!(matches!(
+            run(&mut sh("exec sleep 10"), &limits),
+            Err(Error::Crashed { .. })
+        ));
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_timeout_reports() {
+        let limits = Limits {
+            timeout: Duration::from_millis(200),
+            ..limits()
+        };
+        assert_eq!(
+            run(
+                &mut sh("trap 'echo stopped; exit 1' TERM; while :; do sleep 0.01; done"),
+                &limits
+            ),
+            Err(Error::Crashed {
+                reason: "exceeded time limit of 0.2s".to_string(),
+                stdout: "stopped\n".to_string(),
+            })
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rlimits() {
+        let limits = Limits {
+            cpu_limit: Duration::from_secs(5),
+            memory_limit_bytes: 512 << 20,
+            ..limits()
+        };
+        assert_eq!(
+            run(&mut sh("ulimit -t; ulimit -v"), &limits),
+            Ok("5\n524288\n".to_string())
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cpu_limit() {
+        let limits = Limits {
+            cpu_limit: Duration::from_secs(1),
+            ..limits()
+        };
+        let start = Instant::now();
+        match run(&mut sh("while :; do :; done"), &limits) {
+            Err(Error::Crashed { reason, .. }) => assert!(reason.contains("CPU"), "{reason}"),
+            r => panic!("unexpected result {r:?}"),
+        }
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,4 +1,8 @@
+pub mod isolate;
+
+use oort_simulator::scenario;
 use oort_simulator::simulation::Code;
+use std::path::Path;
 
 pub struct AI {
     pub name: String,
@@ -56,3 +60,60 @@ pub async fn fetch_and_compile_multiple(
     let results = futures::future::join_all(futures).await;
     results.into_iter().collect()
 }
+
+/// Loads an AI from a local `.rs` or `.wasm` file, compiling Rust source with
+/// the local toolchain. The AI is named after the file stem.
+pub fn compile_local(compiler: &mut oort_compiler::Compiler, path: &Path) -> anyhow::Result<AI> {
+    let name = path
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .ok_or_else(|| anyhow::anyhow!("invalid filename {:?}", path))?
+        .to_string();
+    let (source_code, wasm) = match path.extension().and_then(|x| x.to_str()) {
+        Some("wasm") => (String::new(), std::fs::read(path)?),
+        Some("rs") => {
+            log::info!("Compiling {:?}", path);
+            let source_code = std::fs::read_to_string(path)?;
+            let wasm = compiler.compile(&source_code)?;
+            (source_code, wasm)
+        }
+        _ => anyhow::bail!("expected a .rs or .wasm file, got {:?}", path),
+    };
+    let compiled_code = oort_simulator::vm::precompile(&wasm)
+        .map_err(|e| anyhow::anyhow!("failed to load {:?}: {}", path, e.msg))?;
+    Ok(AI {
+        name,
+        source_code,
+        compiled_code,
+    })
+}
+
+/// A weighted scenario in a tournament or tuning pool.
+#[derive(Debug, Clone)]
+pub struct PoolEntry {
+    pub scenario_name: String,
+    pub weight: f64,
+}
+
+/// Parses a comma-separated list of scenarios with optional weights, like
+/// "fighter_duel,frigate_duel:2,belt". Weights default to 1.
+pub fn parse_pool(s: &str) -> anyhow::Result<Vec<PoolEntry>> {
+    let mut pool = vec![];
+    for item in s.split(',') {
+        let (scenario_name, weight) = match item.split_once(':') {
+            Some((name, weight)) => (name, weight.parse::<f64>()?),
+            None => (item, 1.0),
+        };
+        if scenario::load_safe(scenario_name).is_none() {
+            anyhow::bail!("Unknown scenario {scenario_name:?}");
+        }
+        if !weight.is_finite() || weight <= 0.0 {
+            anyhow::bail!("Invalid weight {weight} for scenario {scenario_name}");
+        }
+        pool.push(PoolEntry {
+            scenario_name: scenario_name.to_string(),
+            weight,
+        });
+    }
+    Ok(pool)
+}